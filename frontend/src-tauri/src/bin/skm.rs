@@ -0,0 +1,678 @@
+//! `skm` —— Skills Manager 命令行版本。
+//!
+//! 与桌面端共用同一个数据库（`~/.skills-manager/db/skills.db`）和同一套命令实现，
+//! 适合在 CI、dotfiles 脚本或无图形界面的机器上使用。所有结果以 JSON 输出到 stdout。
+
+use clap::{Args, Parser, Subcommand};
+use serde::Serialize;
+
+use skills_manager_lib::commands::{
+    catalog, catalog_snapshot, deployments, git, git_engine, git_sync, ignore_rules, lockfile, plan,
+    projects, registry, scanner, search, settings, skills, staging, static_registry,
+};
+use skills_manager_lib::db::{pool, DbPool};
+use skills_manager_lib::error::AppError;
+use skills_manager_lib::models::DeployTarget;
+
+#[derive(Parser)]
+#[command(name = "skm", version, about = "Skills Manager 命令行工具")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 项目管理
+    #[command(subcommand)]
+    Projects(ProjectsCmd),
+    /// Skill 库管理
+    #[command(subcommand)]
+    Skills(SkillsCmd),
+    /// 部署 Skill 到项目或全局目录
+    Deploy(DeployArgs),
+    /// 列出所有部署
+    Deployments,
+    /// 将库中内容同步到指定部署
+    Sync { deployment_id: String },
+    /// 对账：检查所有部署与磁盘的一致性
+    Reconcile {
+        /// 只输出计划，不写入
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 执行之前 --dry-run 输出的计划（文件路径，`-` 表示 stdin）
+    ApplyPlan { file: String },
+    /// Git 导入导出
+    #[command(subcommand)]
+    Git(GitCmd),
+    /// Skill 市场
+    #[command(subcommand)]
+    Catalog(CatalogCmd),
+    /// Skill 注册源
+    #[command(subcommand)]
+    Registry(RegistryCmd),
+}
+
+#[derive(Subcommand)]
+enum ProjectsCmd {
+    /// 列出所有项目
+    List,
+    /// 添加项目
+    Add { path: String },
+    /// 移除项目
+    Remove { project_id: String },
+    /// 扫描项目目录并导入其中的 Skill
+    Scan { path: String },
+    /// 扫描全局工具目录
+    ScanGlobal,
+    /// 按项目根目录的 skills.lock 安装并部署缺失的 Skill
+    Bootstrap {
+        path: String,
+        #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SkillsCmd {
+    /// 列出所有 Skill
+    List,
+    /// 查看 Skill 详情（ID 或名称）
+    Show { skill: String },
+    /// 全文搜索名称、描述和文件内容
+    Search {
+        query: String,
+        #[arg(long)]
+        limit: Option<usize>,
+    },
+    /// 列出 Skill 的文件
+    Files { skill: String },
+    /// 列出 Skill 的备份
+    Backups { skill: String },
+    /// 删除 Skill
+    Delete { skill: String },
+    /// 列出 Skill 的版本历史
+    Revisions { skill: String },
+    /// 用库中内容刷新 Skill，可选同步到部署
+    Update {
+        skill: String,
+        /// 同步到部署
+        #[arg(long)]
+        sync: bool,
+        /// 只同步这些项目的部署（可重复）
+        #[arg(long = "project")]
+        projects: Vec<String>,
+        /// 只同步这些工具的部署（可重复）
+        #[arg(long = "tool")]
+        tools: Vec<String>,
+        /// 只输出计划，不写入
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 恢复到指定版本
+    Restore {
+        skill: String,
+        revision: i64,
+        /// 恢复后同步到所有部署
+        #[arg(long)]
+        sync: bool,
+    },
+    /// 库与部署三向合并：默认只输出预览，--commit 时写回库
+    Merge {
+        skill: String,
+        deployment_id: String,
+        /// 以该历史版本为 base，默认取部署上次同步时的版本
+        #[arg(long)]
+        base_revision: Option<i64>,
+        /// 以该备份为 base
+        #[arg(long = "base-backup")]
+        base_backup_id: Option<String>,
+        /// 冲突文件的最终内容：`相对路径=本地文件`（可重复）
+        #[arg(long = "resolve")]
+        resolves: Vec<String>,
+        /// 写回库并关闭该部署的冲突
+        #[arg(long)]
+        commit: bool,
+        /// 写回后用新的库内容重写这些部署（可重复）
+        #[arg(long = "sync")]
+        sync_deployment_ids: Vec<String>,
+    },
+}
+
+#[derive(Args)]
+struct DeployArgs {
+    /// Skill ID 或名称
+    skill: String,
+    /// 目标工具（如 claude-code、cursor）
+    #[arg(long)]
+    tool: String,
+    /// 目标项目 ID；与 --global 二选一
+    #[arg(long, conflicts_with = "global", required_unless_present = "global")]
+    project: Option<String>,
+    /// 部署到工具的全局目录
+    #[arg(long)]
+    global: bool,
+    /// 目标已存在时强制覆盖
+    #[arg(long)]
+    force: bool,
+    /// 部署模式：copy | symlink | hardlink
+    #[arg(long, default_value = "copy")]
+    mode: String,
+}
+
+#[derive(Subcommand)]
+enum GitCmd {
+    /// 列出 Git 导出配置
+    Configs,
+    /// 按配置导出所有 Skill 到远程仓库
+    Export { config_id: String },
+    /// 克隆远程仓库并导入其中的 Skill
+    Import {
+        url: String,
+        #[arg(long)]
+        branch: Option<String>,
+        /// 只导入指定 Skill（可重复），默认全部
+        #[arg(long = "skill")]
+        skills: Vec<String>,
+        /// 覆盖本地同名 Skill
+        #[arg(long)]
+        overwrite: bool,
+    },
+    /// 与远程仓库双向同步
+    Sync {
+        config_id: String,
+        /// 两侧都改过的 Skill 的取舍，形如 name=local|remote|merged（可重复）
+        #[arg(long = "keep")]
+        keep: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CatalogCmd {
+    /// 搜索市场
+    Search {
+        query: String,
+        #[arg(long)]
+        category: Option<String>,
+    },
+    /// 搜索 skills.sh
+    SearchSkillsSh { query: String },
+    /// 从 skills.sh 安装（source 形如 owner/repo）
+    Install {
+        source: String,
+        skill_id: String,
+        /// 安装后全局部署到这些工具（可重复）
+        #[arg(long = "tool")]
+        tools: Vec<String>,
+        #[arg(long)]
+        force: bool,
+        #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// 把已安装的 Skill 更新到上游新版本（本地改过时三向合并）
+    Update {
+        skill: String,
+        /// 目标版本，默认最新
+        #[arg(long)]
+        revision: Option<String>,
+        /// 冲突文件的最终内容：`相对路径=本地文件`（可重复）
+        #[arg(long = "resolve")]
+        resolves: Vec<String>,
+        #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// 查看本地 catalog 快照（拉取时间、来源镜像）
+    Status,
+    /// 立即刷新 catalog 快照
+    Refresh,
+}
+
+#[derive(Subcommand)]
+enum RegistryCmd {
+    /// 列出已配置的注册源
+    List,
+    /// 添加或更新注册源
+    Add {
+        /// catalog | skills_sh | github | git | local
+        kind: String,
+        /// catalog.json 地址、owner/repo、git 地址或本地目录
+        url: String,
+        #[arg(long)]
+        id: Option<String>,
+        #[arg(long)]
+        name: Option<String>,
+        /// 附加配置（JSON），如 '{"ref":"main","path":"skills"}'
+        #[arg(long)]
+        options: Option<String>,
+        #[arg(long)]
+        priority: Option<i64>,
+        #[arg(long)]
+        disabled: bool,
+    },
+    /// 删除注册源
+    Remove { registry_id: String },
+    /// 在注册源中搜索（默认所有已启用的注册源）
+    Search {
+        query: String,
+        /// 只搜索这些注册源（可重复）
+        #[arg(long = "registry")]
+        registries: Vec<String>,
+        #[arg(long)]
+        limit: Option<usize>,
+        #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// 从注册源安装
+    Install {
+        registry_id: String,
+        skill_id: String,
+        #[arg(long)]
+        revision: Option<String>,
+        /// 安装后全局部署到这些工具（可重复）
+        #[arg(long = "tool")]
+        tools: Vec<String>,
+        #[arg(long)]
+        force: bool,
+        #[arg(long, env = "GITHUB_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+    },
+    /// 把库中的 Skill 导出为自托管静态注册源（index.json + 清单 + 文件）
+    Generate {
+        output_dir: String,
+        /// 只导出这些 Skill（ID 或名称，可重复），默认全部
+        #[arg(long = "skill")]
+        skills: Vec<String>,
+        #[arg(long)]
+        provider: Option<String>,
+    },
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("错误: {}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), AppError> {
+    let pool = pool::create_pool(&pool::get_db_path())?;
+    ignore_rules::load_user_patterns(&*pool.get()?);
+    // 与 GUI 启动时相同：收尾上次中断的部署替换
+    if let Err(e) = staging::cleanup_stale_staging(&pool) {
+        log::warn!("[skm] 清理部署暂存目录失败: {}", e);
+    }
+
+    match cli.command {
+        Command::Projects(cmd) => match cmd {
+            ProjectsCmd::List => print(&projects::get_projects_internal(&pool).await?),
+            ProjectsCmd::Add { path } => print(&projects::add_project_internal(&pool, path).await?),
+            ProjectsCmd::Remove { project_id } => {
+                projects::remove_project_internal(&pool, project_id).await?;
+                Ok(())
+            }
+            ProjectsCmd::Scan { path } => {
+                print(&scanner::scan_and_import_project_internal(&pool, path).await?)
+            }
+            ProjectsCmd::ScanGlobal => print(&scanner::scan_global_skills_internal(&pool).await?),
+            ProjectsCmd::Bootstrap { path, token } => print(
+                &lockfile::bootstrap_project_from_lock_internal(&pool, path, token).await?,
+            ),
+        },
+        Command::Skills(cmd) => match cmd {
+            SkillsCmd::List => print(&skills::get_skills_internal(&pool).await?),
+            SkillsCmd::Search { query, limit } => {
+                print(&search::search_skills_internal(&pool, query, limit)?)
+            }
+            SkillsCmd::Show { skill } => {
+                let id = resolve_skill_id(&pool, &skill)?;
+                print(&skills::get_skill_by_id_internal(&pool, id).await?)
+            }
+            SkillsCmd::Files { skill } => {
+                let id = resolve_skill_id(&pool, &skill)?;
+                print(&skills::list_skill_files_internal(&pool, id).await?)
+            }
+            SkillsCmd::Backups { skill } => {
+                let id = resolve_skill_id(&pool, &skill)?;
+                print(&skills::get_skill_backups_internal(&pool, id).await?)
+            }
+            SkillsCmd::Delete { skill } => {
+                let id = resolve_skill_id(&pool, &skill)?;
+                skills::delete_skill_internal(&pool, id).await?;
+                Ok(())
+            }
+            SkillsCmd::Revisions { skill } => {
+                let id = resolve_skill_id(&pool, &skill)?;
+                print(&skills::get_skill_revisions_internal(&pool, id).await?)
+            }
+            SkillsCmd::Update {
+                skill,
+                sync,
+                projects,
+                tools,
+                dry_run,
+            } => {
+                let id = resolve_skill_id(&pool, &skill)?;
+                print(
+                    &skills::update_skill_from_library_internal(
+                        &pool,
+                        id,
+                        sync,
+                        Some(projects),
+                        Some(tools),
+                        Some(dry_run),
+                    )
+                    .await?,
+                )
+            }
+            SkillsCmd::Restore {
+                skill,
+                revision,
+                sync,
+            } => {
+                let id = resolve_skill_id(&pool, &skill)?;
+                print(&skills::restore_skill_revision_internal(&pool, id, revision, sync).await?)
+            }
+            SkillsCmd::Merge {
+                skill,
+                deployment_id,
+                base_revision,
+                base_backup_id,
+                resolves,
+                commit,
+                sync_deployment_ids,
+            } => {
+                let id = resolve_skill_id(&pool, &skill)?;
+                if commit {
+                    print(&skills::commit_skill_merge_internal(
+                        &pool,
+                        id,
+                        parse_resolutions(&resolves)?,
+                        Some(deployment_id),
+                        base_revision,
+                        base_backup_id,
+                        Some(sync_deployment_ids),
+                    )?)
+                } else {
+                    print(&skills::merge_skill_by_id_internal(
+                        &pool,
+                        id,
+                        deployment_id,
+                        base_revision,
+                        base_backup_id,
+                    )?)
+                }
+            }
+        },
+        Command::Deploy(args) => {
+            let id = resolve_skill_id(&pool, &args.skill)?;
+            let force = Some(args.force);
+            let result = match args.project {
+                Some(project_id) => {
+                    deployments::deploy_skill_to_project_internal(
+                        &pool,
+                        id,
+                        project_id,
+                        args.tool,
+                        force,
+                        Some(args.mode),
+                    )
+                    .await?
+                }
+                None => {
+                    deployments::deploy_skill_global_internal(
+                        &pool,
+                        id,
+                        args.tool,
+                        force,
+                        Some(args.mode),
+                    )
+                    .await?
+                }
+            };
+            print(&result)
+        }
+        Command::Deployments => print(&deployments::get_deployments_internal(&pool).await?),
+        Command::Sync { deployment_id } => {
+            print(&deployments::sync_deployment_internal(&pool, deployment_id).await?)
+        }
+        Command::Reconcile { dry_run } => print(
+            &deployments::reconcile_all_deployments_internal(&pool, Some(dry_run)).await?,
+        ),
+        Command::ApplyPlan { file } => {
+            let text = if file == "-" {
+                std::io::read_to_string(std::io::stdin())?
+            } else {
+                std::fs::read_to_string(&file)?
+            };
+            // 接受计划本身，也接受 --dry-run 的完整输出
+            let value: serde_json::Value = serde_json::from_str(&text)?;
+            let plan_value = match value.get("plan") {
+                Some(plan) if !plan.is_null() => plan.clone(),
+                _ => value,
+            };
+            let plan: plan::DeploymentPlan = serde_json::from_value(plan_value)?;
+            print(&plan::apply_plan_internal(&pool, plan).await?)
+        }
+        Command::Git(cmd) => match cmd {
+            GitCmd::Configs => print(&settings::get_git_export_configs_internal(&pool).await?),
+            GitCmd::Export { config_id } => {
+                let op = git_engine::GitOperation::new("export");
+                print(&git::export_skills_to_git_internal(&pool, config_id, &op).await?)
+            }
+            GitCmd::Import {
+                url,
+                branch,
+                skills,
+                overwrite,
+            } => {
+                let op = git_engine::GitOperation::new("clone");
+                let cloned = git::clone_git_repo_internal(&pool, url.clone(), branch, &op).await?;
+                let names = if skills.is_empty() {
+                    cloned.skills_found.iter().map(|s| s.name.clone()).collect()
+                } else {
+                    skills
+                };
+                print(
+                    &git::import_from_git_repo_internal(
+                        &pool,
+                        cloned.clone_path,
+                        names,
+                        overwrite,
+                        Some(url),
+                    )
+                    .await?,
+                )
+            }
+            GitCmd::Sync { config_id, keep } => {
+                let resolutions = keep
+                    .iter()
+                    .map(|k| match k.split_once('=') {
+                        Some((name, side)) => Ok(git_sync::GitSyncResolution {
+                            skill_name: name.to_string(),
+                            keep: side.to_string(),
+                        }),
+                        None => Err(AppError::Validation(format!("--keep 需要 name=side 格式: {}", k))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let op = git_engine::GitOperation::new("sync");
+                print(&git_sync::sync_with_git_internal(&pool, config_id, Some(resolutions), &op).await?)
+            }
+        },
+        Command::Catalog(cmd) => match cmd {
+            CatalogCmd::Search { query, category } => {
+                print(&catalog::search_catalog_internal(&pool, query, category).await?)
+            }
+            CatalogCmd::SearchSkillsSh { query } => {
+                print(&catalog::search_skills_sh_internal(&pool, query).await?)
+            },
+            CatalogCmd::Install {
+                source,
+                skill_id,
+                tools,
+                force,
+                token,
+            } => {
+                let targets = tools
+                    .into_iter()
+                    .map(|tool| DeployTarget {
+                        project_id: None,
+                        tool,
+                    })
+                    .collect();
+                print(
+                    &catalog::install_from_skills_sh_internal(
+                        &pool,
+                        source,
+                        skill_id,
+                        targets,
+                        Some(force),
+                        token,
+                    )
+                    .await?,
+                )
+            }
+            CatalogCmd::Update {
+                skill,
+                revision,
+                resolves,
+                token,
+            } => {
+                let skill_id = resolve_skill_id(&pool, &skill)?;
+                let resolutions = parse_resolutions(&resolves)?;
+                print(
+                    &catalog::apply_catalog_update_internal(
+                        &pool,
+                        skill_id,
+                        revision,
+                        Some(resolutions),
+                        token,
+                    )
+                    .await?,
+                )
+            }
+            CatalogCmd::Status => print(&catalog_snapshot::get_catalog_status_internal(&pool)?),
+            CatalogCmd::Refresh => print(&catalog_snapshot::refresh_catalog_internal(&pool).await?),
+        },
+        Command::Registry(cmd) => match cmd {
+            RegistryCmd::List => print(&registry::list_registries_internal(&pool)?),
+            RegistryCmd::Add {
+                kind,
+                url,
+                id,
+                name,
+                options,
+                priority,
+                disabled,
+            } => print(&registry::save_registry_internal(
+                &pool,
+                registry::RegistryInput {
+                    id,
+                    name: name.unwrap_or_default(),
+                    kind,
+                    url,
+                    options,
+                    enabled: Some(!disabled),
+                    priority,
+                },
+            )?),
+            RegistryCmd::Remove { registry_id } => {
+                registry::delete_registry_internal(&pool, &registry_id)
+            }
+            RegistryCmd::Search {
+                query,
+                registries,
+                limit,
+                token,
+            } => print(
+                &registry::search_registries_internal(&pool, query, Some(registries), limit, token)
+                    .await?,
+            ),
+            RegistryCmd::Install {
+                registry_id,
+                skill_id,
+                revision,
+                tools,
+                force,
+                token,
+            } => {
+                let targets = tools
+                    .into_iter()
+                    .map(|tool| DeployTarget {
+                        project_id: None,
+                        tool,
+                    })
+                    .collect();
+                print(
+                    &registry::install_from_registry_internal(
+                        &pool,
+                        registry_id,
+                        skill_id,
+                        revision,
+                        targets,
+                        Some(force),
+                        token,
+                    )
+                    .await?,
+                )
+            }
+            RegistryCmd::Generate {
+                output_dir,
+                skills,
+                provider,
+            } => {
+                let skill_ids = skills
+                    .iter()
+                    .map(|s| resolve_skill_id(&pool, s))
+                    .collect::<Result<Vec<_>, _>>()?;
+                print(&static_registry::generate_static_registry_internal(
+                    &pool,
+                    output_dir,
+                    Some(skill_ids),
+                    provider,
+                )?)
+            }
+        },
+    }
+}
+
+/// `--resolve 相对路径=本地文件`：读取本地文件作为冲突文件的最终内容
+fn parse_resolutions(resolves: &[String]) -> Result<Vec<skills::MergeResolution>, AppError> {
+    resolves
+        .iter()
+        .map(|r| {
+            let (path, file) = r
+                .split_once('=')
+                .ok_or_else(|| AppError::Validation(format!("--resolve 应为 路径=文件: {}", r)))?;
+            Ok(skills::MergeResolution {
+                path: path.to_string(),
+                content: Some(std::fs::read_to_string(file)?),
+            })
+        })
+        .collect()
+}
+
+/// 命令行里允许直接写 Skill 名称，先按 ID 匹配，再按名称匹配
+fn resolve_skill_id(pool: &DbPool, skill: &str) -> Result<String, AppError> {
+    let conn = pool.get()?;
+    conn.query_row(
+        "SELECT id FROM skills WHERE id = ?1 OR name = ?1 ORDER BY id = ?1 DESC LIMIT 1",
+        [skill],
+        |row| row.get(0),
+    )
+    .map_err(|e| match e {
+        rusqlite::Error::QueryReturnedNoRows => {
+            AppError::NotFound(format!("Skill 不存在: {}", skill))
+        }
+        other => other.into(),
+    })
+}
+
+fn print<T: Serialize>(value: &T) -> Result<(), AppError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
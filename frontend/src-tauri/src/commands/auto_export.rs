@@ -0,0 +1,276 @@
+//! 后台自动导出：按频率把库同步到各个 git 配置。
+//!
+//! 配置的 `auto_export` 为 hourly / daily / on-change 时按配置执行，为 manual 时沿用全局设置
+//! `auto_export_frequency`；两者都是 manual 则只能手动导出。
+//! - hourly / daily：距上次推送或拉取超过间隔即执行，时间持久化，重启后接着算
+//! - on-change：库指纹与上次导出时不同、且连续两轮检查都没再变化时执行
+//!
+//! 定时执行走双向同步而不是手动导出：远程在两次执行之间的修改会拉回库中，
+//! 两侧都改过的 Skill 记为冲突留给用户处理，不会被库的内容覆盖。
+//! 失败或有冲突时按指数退避重试，结果写入 sync_history 并通过 `git-auto-export` 事件通知前端。
+
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use super::git_sync::sync_with_git_internal;
+use super::git_engine::{self, GitOperation};
+use crate::db::DbPool;
+use crate::error::AppError;
+
+pub const AUTO_EXPORT_EVENT: &str = "git-auto-export";
+
+/// 检查间隔，也是 on-change 的防抖窗口
+const TICK: Duration = Duration::from_secs(30);
+/// 首次失败后的重试等待，之后每次翻倍
+const BACKOFF_BASE: Duration = Duration::from_secs(60);
+const BACKOFF_MAX: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Manual,
+    Hourly,
+    Daily,
+    OnChange,
+}
+
+impl Frequency {
+    fn parse(value: &str) -> Self {
+        match value.trim().trim_matches('"') {
+            "hourly" => Self::Hourly,
+            "daily" => Self::Daily,
+            "on-change" => Self::OnChange,
+            _ => Self::Manual,
+        }
+    }
+
+    /// 配置自己的频率优先，manual 时沿用全局设置
+    pub fn effective(config: &str, global: &str) -> Self {
+        match Self::parse(config) {
+            Self::Manual => Self::parse(global),
+            f => f,
+        }
+    }
+
+    fn interval_secs(self) -> Option<i64> {
+        match self {
+            Self::Hourly => Some(3600),
+            Self::Daily => Some(86400),
+            _ => None,
+        }
+    }
+}
+
+/// 推送给前端的单次自动导出结果
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoExportEvent {
+    pub config_id: String,
+    pub remote_url: String,
+    /// success | conflict（有两侧都改过的 Skill 待处理） | error
+    pub status: String,
+    pub message: String,
+    pub commit_hash: Option<String>,
+    /// 失败时距下次重试的秒数
+    pub retry_in_secs: Option<u64>,
+}
+
+/// 库中所有 Skill 的名称、校验和与修改时间的摘要；任何增删改都会改变它
+pub fn library_fingerprint(conn: &Connection) -> Result<String, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT name, COALESCE(checksum, ''), updated_at FROM skills ORDER BY name",
+    )?;
+    let mut rows = stmt.query([])?;
+    let mut hasher = Sha256::new();
+    while let Some(row) = rows.next()? {
+        for i in 0..3 {
+            hasher.update(row.get::<_, String>(i)?.as_bytes());
+            hasher.update([0]);
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+struct Backoff {
+    failures: u32,
+    retry_at: Instant,
+}
+
+struct ConfigRow {
+    id: String,
+    remote_url: String,
+    auto_export: String,
+    /// 距上次推送或拉取的秒数，从未同步为 None
+    synced_secs_ago: Option<i64>,
+    export_fingerprint: Option<String>,
+}
+
+/// 调度状态只在内存中：退避计数和 on-change 上一轮看到的指纹
+#[derive(Default)]
+pub struct AutoExportScheduler {
+    backoff: HashMap<String, Backoff>,
+    seen: HashMap<String, String>,
+}
+
+impl AutoExportScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 检查一轮，依次执行到期的导出；返回本轮每个导出的结果
+    pub async fn tick(&mut self, pool: &DbPool, now: Instant) -> Result<Vec<AutoExportEvent>, AppError> {
+        let (configs, global, fingerprint) = {
+            let conn = pool.get()?;
+            let global: String = conn
+                .query_row(
+                    "SELECT value FROM app_settings WHERE key = 'auto_export_frequency'",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap_or_default();
+            let mut stmt = conn.prepare(
+                "SELECT id, remote_url, auto_export,
+                        CAST((julianday('now') - julianday(MAX(COALESCE(last_push_at, last_pull_at),
+                                                               COALESCE(last_pull_at, last_push_at)))) * 86400 AS INTEGER),
+                        export_fingerprint
+                 FROM git_export_config ORDER BY created_at",
+            )?;
+            let configs = stmt
+                .query_map([], |row| {
+                    Ok(ConfigRow {
+                        id: row.get(0)?,
+                        remote_url: row.get(1)?,
+                        auto_export: row.get(2)?,
+                        synced_secs_ago: row.get(3)?,
+                        export_fingerprint: row.get(4)?,
+                    })
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            (configs, global, library_fingerprint(&conn)?)
+        };
+
+        // 已删除的配置不再保留状态
+        self.backoff.retain(|id, _| configs.iter().any(|c| &c.id == id));
+        self.seen.retain(|id, _| configs.iter().any(|c| &c.id == id));
+
+        let mut events = Vec::new();
+        for config in configs {
+            let frequency = Frequency::effective(&config.auto_export, &global);
+            let stable = self.seen.insert(config.id.clone(), fingerprint.clone()).as_deref()
+                == Some(fingerprint.as_str());
+            let due = match frequency {
+                Frequency::Manual => false,
+                Frequency::OnChange => {
+                    stable && config.export_fingerprint.as_deref() != Some(fingerprint.as_str())
+                }
+                f => config
+                    .synced_secs_ago
+                    .is_none_or(|secs| secs >= f.interval_secs().unwrap_or(i64::MAX)),
+            };
+            if !due {
+                if frequency == Frequency::Manual {
+                    self.backoff.remove(&config.id);
+                }
+                continue;
+            }
+            if self.backoff.get(&config.id).is_some_and(|b| b.retry_at > now) {
+                continue;
+            }
+            events.push(self.export(pool, config, frequency, now).await);
+        }
+        Ok(events)
+    }
+
+    async fn export(
+        &mut self,
+        pool: &DbPool,
+        config: ConfigRow,
+        frequency: Frequency,
+        now: Instant,
+    ) -> AutoExportEvent {
+        info!("[auto_export] {} 开始自动同步 ({:?})", config.id, frequency);
+        let op = GitOperation::new("auto_export");
+        let outcome = sync_with_git_internal(pool, config.id.clone(), None, &op)
+            .await
+            .map(|result| (result.diverged.is_empty(), result));
+        match outcome {
+            Ok((true, result)) => {
+                self.backoff.remove(&config.id);
+                info!("[auto_export] {} 完成: {}", config.id, result.message);
+                AutoExportEvent {
+                    config_id: config.id,
+                    remote_url: git_engine::redact_url(&config.remote_url),
+                    status: "success".into(),
+                    message: result.message,
+                    commit_hash: result.commit_hash,
+                    retry_in_secs: None,
+                }
+            }
+            // 冲突已记录在 git_sync_conflicts，等用户处理；期间按退避间隔再检查，避免每轮重复提示
+            Ok((false, result)) => {
+                let delay = self.back_off(&config.id, now);
+                info!("[auto_export] {} 有冲突待处理: {}", config.id, result.message);
+                AutoExportEvent {
+                    config_id: config.id,
+                    remote_url: git_engine::redact_url(&config.remote_url),
+                    status: "conflict".into(),
+                    message: result.message,
+                    commit_hash: result.commit_hash,
+                    retry_in_secs: Some(delay.as_secs()),
+                }
+            }
+            Err(e) => {
+                let delay = self.back_off(&config.id, now);
+                warn!("[auto_export] {} 失败，{} 秒后重试: {}", config.id, delay.as_secs(), e);
+                if let Ok(conn) = pool.get() {
+                    let _ = conn.execute(
+                        "INSERT INTO sync_history (id, skill_id, action, status, error_message, created_at)
+                         VALUES (?1, NULL, 'export', 'error', ?2, datetime('now'))",
+                        params![Uuid::new_v4().to_string(), e.to_string()],
+                    );
+                }
+                AutoExportEvent {
+                    config_id: config.id,
+                    remote_url: git_engine::redact_url(&config.remote_url),
+                    status: "error".into(),
+                    message: e.to_string(),
+                    commit_hash: None,
+                    retry_in_secs: Some(delay.as_secs()),
+                }
+            }
+        }
+    }
+
+    /// 记一次失败，返回距下次重试的时间：首次 BACKOFF_BASE，之后每次翻倍
+    fn back_off(&mut self, config_id: &str, now: Instant) -> Duration {
+        let failures = self.backoff.get(config_id).map_or(0, |b| b.failures) + 1;
+        let delay = BACKOFF_BASE
+            .saturating_mul(1 << (failures - 1).min(10))
+            .min(BACKOFF_MAX);
+        self.backoff
+            .insert(config_id.to_string(), Backoff { failures, retry_at: now + delay });
+        delay
+    }
+}
+
+/// 在 Tauri 的异步运行时里启动调度循环，随应用退出
+pub fn start(pool: DbPool, app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut scheduler = AutoExportScheduler::new();
+        loop {
+            tokio::time::sleep(TICK).await;
+            match scheduler.tick(&pool, Instant::now()).await {
+                Ok(events) => {
+                    for event in events {
+                        let _ = app.emit(AUTO_EXPORT_EVENT, event);
+                    }
+                }
+                Err(e) => warn!("[auto_export] 检查失败: {}", e),
+            }
+        }
+    });
+}
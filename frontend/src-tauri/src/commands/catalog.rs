@@ -1,193 +1,72 @@
-use log::info;
+use log::{info, warn};
 use rusqlite::{params, OptionalExtension};
-use std::sync::Mutex;
+use std::collections::BTreeMap;
+use std::path::Path;
 use tauri::State;
 use uuid::Uuid;
 
-use super::skill_files::{compute_db_checksum, db_write_file};
+use super::credentials;
+use super::git::parse_skill_frontmatter;
+use super::ignore_rules::{IgnoreRules, SKILLIGNORE_FILE};
+use super::registry::{self, GitHubRegistry, SkillRegistry};
+use super::skill_files::{
+    db_export_to_dir, db_find_revision_by_checksum, db_list_files, db_read_file,
+    db_read_revision_file, db_replace_files, db_revision_manifest, has_db_files,
+    refresh_skill_checksum,
+};
+use super::skills::{merge_file_maps, MergeFileResult, MergeResolution, MergeResult};
+use super::skill_path::{skill_dir_under, SkillPath};
+use super::watcher::WatcherService;
 use crate::db::DbPool;
 use crate::error::AppError;
 use crate::models::*;
 
-// ── 内存缓存 ──
-
-struct CatalogCache {
-    skills: Vec<CatalogSkill>,
-    fetched_at: std::time::Instant,
-}
-
-static CATALOG_CACHE: Mutex<Option<CatalogCache>> = Mutex::new(None);
-
-const CATALOG_TTL_SECS: u64 = 86400; // 24 小时
 const INSTALLS_TTL_SECS: i64 = 604800; // 7 天（秒）
 
-// ── catalog.json 原始结构 ──
-
-#[derive(serde::Deserialize)]
-struct RawCatalog {
-    skills: Vec<RawCatalogSkill>,
-}
-
-#[derive(serde::Deserialize)]
-struct RawCatalogSkill {
-    #[serde(default)]
-    id: String,
-    #[serde(default)]
-    name: String,
-    description: Option<String>,
-    #[serde(default)]
-    provider: String,
-    #[serde(default)]
-    category: String,
-    license: Option<String>,
-    compatibility: Option<String>,
-    last_updated_at: Option<String>,
-    #[serde(default)]
-    has_scripts: bool,
-    #[serde(default)]
-    has_references: bool,
-    #[serde(default)]
-    has_assets: bool,
-    #[serde(default)]
-    tags: Vec<String>,
-    days_since_update: Option<u32>,
-    maintenance_status: Option<String>,
-    #[serde(default)]
-    quality_score: u32,
-    source: RawCatalogSource,
-}
-
-
-#[derive(serde::Deserialize)]
-struct RawCatalogSource {
-    #[serde(default)]
-    repo: String,
-    #[serde(default)]
-    path: String,
-    #[serde(default)]
-    skill_md_url: String,
-    #[serde(default)]
-    commit_sha: String,
-}
-
-fn raw_to_catalog(r: RawCatalogSkill) -> CatalogSkill {
-    CatalogSkill {
-        id: r.id,
-        name: r.name,
-        description: r.description,
-        provider: r.provider,
-        category: r.category,
-        license: r.license,
-        compatibility: r.compatibility,
-        last_updated_at: r.last_updated_at.unwrap_or_default(),
-        has_scripts: r.has_scripts,
-        has_references: r.has_references,
-        has_assets: r.has_assets,
-        tags: r.tags,
-        days_since_update: r.days_since_update.unwrap_or(0),
-        maintenance_status: r.maintenance_status.unwrap_or_else(|| "unknown".to_string()),
-        quality_score: r.quality_score,
-        source_repo: r.source.repo,
-        source_path: r.source.path,
-        skill_md_url: r.source.skill_md_url,
-        commit_sha: r.source.commit_sha,
-        installs: None,
-    }
-}
-
-// ── 内部 helper：加载全量 catalog（带内存缓存） ──
-
-async fn load_catalog_all() -> Result<Vec<CatalogSkill>, AppError> {
-    // 检查内存缓存
-    {
-        let guard = CATALOG_CACHE.lock().map_err(|_| AppError::Internal("锁污染".into()))?;
-        if let Some(ref cache) = *guard {
-            if cache.fetched_at.elapsed().as_secs() < CATALOG_TTL_SECS {
-                info!("[load_catalog_all] 命中内存缓存，共 {} 条", cache.skills.len());
-                return Ok(cache.skills.clone());
-            }
-        }
-    }
-
-    // 拉取 catalog，依次尝试多个镜像，任一成功即止
-    info!("[load_catalog_all] 拉取 dmgrok catalog.json...");
-    const CATALOG_URLS: &[&str] = &[
-        "https://cdn.jsdelivr.net/gh/dmgrok/agent_skills_directory@main/catalog.json",
-        "https://raw.githubusercontent.com/dmgrok/agent_skills_directory/main/catalog.json",
-        "https://ghproxy.com/https://raw.githubusercontent.com/dmgrok/agent_skills_directory/main/catalog.json",
-    ];
-
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
-        .build()
-        .unwrap_or_default();
-
-    let mut last_err = String::new();
-    let mut raw_opt: Option<RawCatalog> = None;
-
-    for url in CATALOG_URLS {
-        info!("[load_catalog_all] 尝试: {}", url);
-        match client
-            .get(*url)
-            .header("User-Agent", "shirehub-skills-manager")
-            .header("Accept-Encoding", "identity")
-            .send()
-            .await
-        {
-            Ok(resp) if resp.status().is_success() => {
-                match resp.json::<RawCatalog>().await {
-                    Ok(parsed) => {
-                        raw_opt = Some(parsed);
-                        break;
-                    }
-                    Err(e) => {
-                        last_err = format!("解析失败 ({}): {}", url, e);
-                        info!("[load_catalog_all] {}", last_err);
-                    }
-                }
-            }
-            Ok(resp) => {
-                last_err = format!("HTTP {} ({})", resp.status(), url);
-                info!("[load_catalog_all] {}", last_err);
-            }
+// ── 内部 helper：加载全量 catalog ──
+
+/// 合并所有已启用的 catalog 类注册源（dmgrok 与自托管静态注册源），按注册源优先级排列。
+/// 部分注册源失败时只记录日志，全部失败才报错
+async fn load_catalog_all(pool: &DbPool) -> Result<Vec<CatalogSkill>, AppError> {
+    let registries = registry::catalog_registries(pool)?;
+    let mut all = Vec::new();
+    let mut last_err = None;
+    for reg in &registries {
+        match reg.load_all().await {
+            Ok(skills) => all.extend(skills),
             Err(e) => {
-                last_err = format!("请求失败 ({}): {}", url, e);
-                info!("[load_catalog_all] {}", last_err);
+                warn!("[load_catalog_all] 注册源 {} 加载失败: {}", reg.id(), e);
+                last_err = Some(e);
             }
         }
     }
-
-    let raw = raw_opt.ok_or_else(|| AppError::Internal(format!("所有镜像均拉取失败，最后错误: {}", last_err)))?;
-
-    let mut skills: Vec<CatalogSkill> = raw.skills.into_iter().map(raw_to_catalog).collect();
-    skills.sort_by(|a, b| b.quality_score.cmp(&a.quality_score));
-
-    info!("[load_catalog_all] 拉取成功，共 {} 个 Skill", skills.len());
-
-    {
-        let mut guard = CATALOG_CACHE.lock().map_err(|_| AppError::Internal("锁污染".into()))?;
-        *guard = Some(CatalogCache {
-            skills: skills.clone(),
-            fetched_at: std::time::Instant::now(),
-        });
+    match last_err {
+        Some(e) if all.is_empty() => Err(e),
+        _ => Ok(all),
     }
-
-    Ok(skills)
 }
 
 // ── 1. fetch_catalog ──
 
-#[tauri::command]
-pub async fn fetch_catalog(
+pub async fn fetch_catalog_internal(
+    pool: &DbPool,
     category: Option<String>,
 ) -> Result<Vec<CatalogSkill>, AppError> {
     info!("[fetch_catalog] category={:?}", category);
-    let skills = load_catalog_all().await?;
+    let skills = load_catalog_all(pool).await?;
     let result = filter_by_category(&skills, &category);
     info!("[fetch_catalog] 返回 {} 条", result.len());
     Ok(result)
 }
 
+#[tauri::command]
+pub async fn fetch_catalog(
+    category: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<CatalogSkill>, AppError> {
+    fetch_catalog_internal(&pool, category).await
+}
+
 fn filter_by_category(skills: &[CatalogSkill], category: &Option<String>) -> Vec<CatalogSkill> {
     match category {
         None => skills.to_vec(),
@@ -202,15 +81,14 @@ fn filter_by_category(skills: &[CatalogSkill], category: &Option<String>) -> Vec
 
 // ── 2. search_catalog ──
 
-#[tauri::command]
-pub async fn search_catalog(
+pub async fn search_catalog_internal(
+    pool: &DbPool,
     query: String,
     category: Option<String>,
 ) -> Result<Vec<CatalogSkill>, AppError> {
     info!("[search_catalog] query={}, category={:?}", query, category);
 
-    // 先确保缓存已加载
-    let all = fetch_catalog(None).await?;
+    let all = load_catalog_all(pool).await?;
 
     let q = query.to_lowercase();
     let mut results: Vec<CatalogSkill> = all
@@ -235,11 +113,20 @@ pub async fn search_catalog(
         .collect();
 
     // 按质量分降序
-    results.sort_by(|a, b| b.quality_score.cmp(&a.quality_score));
+    results.sort_by_key(|s| std::cmp::Reverse(s.quality_score));
     info!("[search_catalog] 匹配到 {} 条", results.len());
     Ok(results)
 }
 
+#[tauri::command]
+pub async fn search_catalog(
+    query: String,
+    category: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<CatalogSkill>, AppError> {
+    search_catalog_internal(&pool, query, category).await
+}
+
 // ── 3. enrich_single_install ── （详情面板按需调用，带 SQLite 缓存）
 
 #[tauri::command]
@@ -278,38 +165,9 @@ pub async fn enrich_single_install(
     }
 
     // 调 skills.sh 搜索 API
-    let client = reqwest::Client::new();
-    let url = format!(
-        "https://skills.sh/api/search?q={}&limit=10",
-        urlencoding_encode(&skill_name)
-    );
-    info!("[enrich_single_install] 请求 skills.sh: {}", url);
-
-    let resp = client
-        .get(&url)
-        .header("User-Agent", "shirehub-skills-manager")
-        .header("Accept-Encoding", "identity")
-        .send()
-        .await
-        .map_err(|e| AppError::Internal(format!("skills.sh 请求失败: {}", e)))?;
-
-    if !resp.status().is_success() {
-        return Ok(None);
-    }
-
-    #[derive(serde::Deserialize)]
-    struct ApiResp {
-        skills: Option<Vec<SkillsShSearchResult>>,
-    }
-
-    let data: ApiResp = resp
-        .json()
-        .await
-        .map_err(|_| AppError::Internal("解析 skills.sh 响应失败".into()))?;
-
-    let installs = data
-        .skills
-        .unwrap_or_default()
+    let installs = registry::skills_sh(&pool)?
+        .search_raw(&skill_name, 10)
+        .await?
         .into_iter()
         .find(|s| {
             // 按 source 精确匹配（避免误匹配同名 Skill）
@@ -345,35 +203,9 @@ pub async fn enrich_batch_by_category(
 ) -> Result<(), AppError> {
     info!("[enrich_batch_by_category] keyword={}", category_keyword);
 
-    let client = reqwest::Client::new();
-    let url = format!(
-        "https://skills.sh/api/search?q={}&limit=50",
-        urlencoding_encode(&category_keyword)
-    );
-
-    let resp = client
-        .get(&url)
-        .header("User-Agent", "shirehub-skills-manager")
-        .header("Accept-Encoding", "identity")
-        .send()
-        .await
-        .map_err(|e| AppError::Internal(format!("skills.sh 请求失败: {}", e)))?;
-
-    if !resp.status().is_success() {
-        return Ok(());
-    }
-
-    #[derive(serde::Deserialize)]
-    struct ApiResp {
-        skills: Option<Vec<SkillsShSearchResult>>,
-    }
-
-    let data: ApiResp = match resp.json().await {
-        Ok(d) => d,
-        Err(_) => return Ok(()),
-    };
-
-    let skills = data.skills.unwrap_or_default();
+    let skills = registry::skills_sh(&pool)?
+        .search_raw(&category_keyword, 50)
+        .await?;
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap_or_default()
@@ -394,65 +226,72 @@ pub async fn enrich_batch_by_category(
     Ok(())
 }
 
-// ── 5. install_from_catalog ── （Contents API 获取文件列表 + Raw URL 下载）
+// ── 5. install_from_catalog ── （所有注册源共用的安装流程）
 
-#[derive(serde::Deserialize)]
-struct GhContentsEntry {
-    name: String,
-    #[serde(rename = "type")]
-    entry_type: String,
-    #[serde(default)]
-    download_url: Option<String>,
-}
+/// 下载注册源中 revision 版本的全部文件到内存，按 Skill 自带的 .skillignore 和全局规则过滤
+pub(crate) async fn download_skill_files(
+    registry: &dyn SkillRegistry,
+    skill_id: &str,
+    revision: &str,
+) -> Result<BTreeMap<String, Vec<u8>>, AppError> {
+    let paths = registry.list_files(skill_id, revision).await?;
+    let skillignore = if paths.iter().any(|p| p == SKILLIGNORE_FILE) {
+        let content = registry.fetch_file(skill_id, revision, SKILLIGNORE_FILE).await?;
+        Some(String::from_utf8_lossy(&content).into_owned())
+    } else {
+        None
+    };
+    let rules = IgnoreRules::new(skillignore.as_deref());
 
-fn parse_skill_md_frontmatter(content: &str) -> (Option<String>, Option<String>) {
-    let trimmed = content.trim();
-    if !trimmed.starts_with("---") {
-        return (None, None);
-    }
-    let rest = &trimmed[3..];
-    if let Some(end_idx) = rest.find("---") {
-        let frontmatter = &rest[..end_idx];
-        let mut description = None;
-        let mut version = None;
-        for line in frontmatter.lines() {
-            let line = line.trim();
-            if let Some(val) = line.strip_prefix("description:") {
-                description = Some(val.trim().trim_matches('"').trim_matches('\'').to_string());
-            } else if let Some(val) = line.strip_prefix("version:") {
-                version = Some(val.trim().trim_matches('"').trim_matches('\'').to_string());
-            }
+    let mut files: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    for rel in paths {
+        if rules.is_ignored(Path::new(&rel), false) {
+            continue;
         }
-        (description, version)
-    } else {
-        (None, None)
+        let content = registry.fetch_file(skill_id, revision, &rel).await?;
+        files.insert(rel, content);
     }
+    if files.is_empty() {
+        return Err(AppError::NotFound(format!("Skill {} 中没有可安装的文件", skill_id)));
+    }
+    Ok(files)
 }
 
-#[tauri::command]
-pub async fn install_from_catalog(
-    source_repo: String,
-    source_path: String,
-    skill_name: String,
-    commit_sha: String,
-    deploy_targets: Vec<DeployTarget>,
-    force_overwrite: Option<bool>,
-    token: Option<String>,
-    pool: State<'_, DbPool>,
+/// 写入 skill_sources 的来源信息
+pub struct InstallSource<'a> {
+    pub source_type: &'a str,
+    pub url: String,
+    pub registry_id: Option<&'a str>,
+}
+
+/// 从注册源下载 revision 版本的全部文件（按忽略规则过滤），以 skill_name 写入 DB 并部署。
+/// 同名 Skill 已存在且未指定 force 时只返回冲突信息
+#[allow(clippy::too_many_arguments)]
+pub async fn install_from_source(
+    pool: &DbPool,
+    registry: &dyn SkillRegistry,
+    skill_id: &str,
+    revision: &str,
+    skill_name: &str,
+    source: InstallSource<'_>,
+    deploy_targets: &[DeployTarget],
+    force: bool,
 ) -> Result<SkillsShInstallResult, AppError> {
     info!(
-        "[install_from_catalog] skill={}, repo={}, path={}, sha={}",
-        skill_name, source_repo, source_path, commit_sha
+        "[install_from_source] skill={}, registry={}, id={}, revision={}",
+        skill_name,
+        registry.id(),
+        skill_id,
+        revision
     );
 
     if skill_name.trim().is_empty() {
         return Err(AppError::Validation("skill_name 不能为空".into()));
     }
-
-    let force = force_overwrite.unwrap_or(false);
+    SkillPath::name(skill_name)?;
 
     // Step 1: 冲突检查
-    let pre_skill_id: String = {
+    let existing_id: Option<String> = {
         let conn = pool.get()?;
         let existing: Option<(String, Option<String>, Option<String>)> = conn
             .query_row(
@@ -462,8 +301,8 @@ pub async fn install_from_catalog(
             )
             .optional()?;
 
-        if let Some((existing_id, existing_version, existing_checksum)) = existing {
-            if !force {
+        match existing {
+            Some((existing_id, existing_version, existing_checksum)) if !force => {
                 let original_checksum: Option<String> = conn
                     .query_row(
                         "SELECT original_checksum FROM skill_sources WHERE skill_id = ?1",
@@ -490,543 +329,299 @@ pub async fn install_from_catalog(
                     }),
                 });
             }
-            existing_id
-        } else {
-            Uuid::new_v4().to_string()
-        }
-    };
-
-    // 强制覆盖时清空旧文件；新安装时先插入占位记录（FK 约束要求 skill_id 存在）
-    {
-        let conn = pool.get()?;
-        if force {
-            let _ = conn.execute(
-                "DELETE FROM skill_files WHERE skill_id = ?1",
-                params![pre_skill_id],
-            );
-        } else {
-            // 新安装：只有 pre_skill_id 不在 skills 表时才插占位
-            let exists: bool = conn
-                .query_row(
-                    "SELECT COUNT(1) FROM skills WHERE id = ?1",
-                    params![pre_skill_id],
-                    |r| r.get::<_, i64>(0),
-                )
-                .unwrap_or(0) > 0;
-            if !exists {
-                conn.execute(
-                    "INSERT INTO skills (id, name) VALUES (?1, ?2)",
-                    params![pre_skill_id, skill_name],
-                )?;
-            }
+            other => other.map(|(id, _, _)| id),
         }
-    }
-
-    // Step 2: Contents API 获取文件列表（含 download_url）
-    // source_repo 可能是完整 GitHub URL（如 https://github.com/openai/skills），需提取 owner/repo
-    let owner_repo = extract_owner_repo(&source_repo)
-        .unwrap_or_else(|| source_repo.trim_start_matches("https://github.com/").to_string());
-    let contents_url = if source_path.is_empty() {
-        format!(
-            "https://api.github.com/repos/{}/contents?ref={}",
-            owner_repo, commit_sha
-        )
-    } else {
-        format!(
-            "https://api.github.com/repos/{}/contents/{}?ref={}",
-            owner_repo, source_path, commit_sha
-        )
     };
 
-    info!("[install_from_catalog] Contents API: {}", contents_url);
-    let client = reqwest::Client::new();
-    let mut req = client
-        .get(&contents_url)
-        .header("Accept", "application/vnd.github.v3+json")
-        .header("User-Agent", "shirehub-skills-manager")
-        .header("Accept-Encoding", "identity");
-
-    if let Some(ref t) = token {
-        if !t.is_empty() {
-            req = req.header("Authorization", format!("Bearer {}", t));
-        }
-    }
-
-    let resp = req
-        .send()
-        .await
-        .map_err(|e| AppError::Internal(format!("Contents API 请求失败: {}", e)))?;
-
-    if !resp.status().is_success() {
-        return Err(AppError::Internal(format!(
-            "Contents API 返回 HTTP {}",
-            resp.status()
-        )));
-    }
+    // Step 2: 先下载到内存，全部成功后再写 DB，避免半途失败留下残缺的 Skill
+    let files = download_skill_files(registry, skill_id, revision).await?;
+    let files_downloaded = files.len();
+    info!("[install_from_source] 下载完成: {} 个文件", files_downloaded);
 
-    let entries: Vec<GhContentsEntry> = resp
-        .json()
-        .await
-        .map_err(|e| AppError::Internal(format!("解析 Contents API 响应失败: {}", e)))?;
-
-    // Step 3: 逐文件用 download_url（Raw URL）下载
-    let mut files_downloaded = 0usize;
-    let mut skill_md_content: Option<String> = None;
+    // Step 3: 解析 SKILL.md frontmatter
+    let (_, description, version) = files
+        .get("SKILL.md")
+        .map(|c| parse_skill_frontmatter(&String::from_utf8_lossy(c)))
+        .unwrap_or_default();
 
-    {
+    // Step 4: 一个事务内写 skills + skill_files + skill_sources
+    let skill_id_db = {
         let conn = pool.get()?;
-        for entry in &entries {
-            if entry.entry_type != "file" {
-                continue;
-            }
-
-            // 跳过无关文件
-            let fname = &entry.name;
-            if fname == "README.md"
-                || fname == "metadata.json"
-                || fname.starts_with('_')
-                || fname.starts_with('.')
-            {
-                continue;
-            }
-
-            let download_url = match &entry.download_url {
-                Some(u) if !u.is_empty() => u.clone(),
-                _ => {
-                    info!(
-                        "[install_from_catalog] 跳过 {} (无 download_url)",
-                        fname
-                    );
-                    continue;
-                }
-            };
-
-            // Raw URL 直接下载，不消耗 GitHub API 配额
-            let mut raw_req = client
-                .get(&download_url)
-                .header("User-Agent", "shirehub-skills-manager");
-
-            if let Some(ref t) = token {
-                if !t.is_empty() {
-                    raw_req = raw_req.header("Authorization", format!("Bearer {}", t));
-                }
-            }
-
-            let file_resp = raw_req.send().await.map_err(|e| {
-                AppError::Internal(format!("下载文件 {} 失败: {}", fname, e))
-            })?;
+        let tx = conn.unchecked_transaction()?;
 
-            if !file_resp.status().is_success() {
-                info!(
-                    "[install_from_catalog] 跳过 {} (HTTP {})",
-                    fname,
-                    file_resp.status()
-                );
-                continue;
+        let sid = match existing_id {
+            Some(eid) => {
+                tx.execute(
+                    "UPDATE skills SET description = ?1, version = ?2,
+                            last_modified = datetime('now'), updated_at = datetime('now')
+                     WHERE id = ?3",
+                    params![description, version, eid],
+                )?;
+                eid
             }
-
-            let bytes = file_resp.bytes().await.map_err(|e| {
-                AppError::Internal(format!("读取文件 {} 失败: {}", fname, e))
-            })?;
-
-            db_write_file(&conn, &pre_skill_id, fname, &bytes)?;
-            files_downloaded += 1;
-
-            if fname == "SKILL.md" {
-                skill_md_content = String::from_utf8(bytes.to_vec()).ok();
+            None => {
+                let sid = Uuid::new_v4().to_string();
+                tx.execute(
+                    "INSERT INTO skills (id, name, description, version, last_modified)
+                     VALUES (?1, ?2, ?3, ?4, datetime('now'))",
+                    params![sid, skill_name, description, version],
+                )?;
+                sid
             }
-        }
-    }
-
-    info!(
-        "[install_from_catalog] 下载完成: {} 个文件写入 DB",
-        files_downloaded
-    );
-
-    // Step 4: 解析 SKILL.md frontmatter
-    let (description, version) = if let Some(ref content) = skill_md_content {
-        parse_skill_md_frontmatter(content)
-    } else {
-        (None, None)
-    };
-
-    // Step 5: 计算 checksum，写 skills + skill_sources
-    let checksum = {
-        let conn = pool.get()?;
-        compute_db_checksum(&conn, &pre_skill_id)
-    };
-
-    let source_url = format!("https://github.com/{}/tree/{}/{}", source_repo, commit_sha, source_path);
-
-    let skill_id = {
-        let conn = pool.get()?;
-        let tx = conn.unchecked_transaction()?;
-
-        let existing_id: Option<String> = tx
-            .query_row(
-                "SELECT id FROM skills WHERE name = ?1",
-                params![skill_name],
-                |row| row.get(0),
-            )
-            .optional()?;
+        };
 
-        let sid = if let Some(eid) = existing_id {
-            tx.execute(
-                "UPDATE skills SET description = ?1, version = ?2, checksum = ?3,
-                        last_modified = datetime('now'), updated_at = datetime('now')
-                 WHERE id = ?4",
-                params![description, version, checksum, eid],
-            )?;
-            tx.execute(
-                "UPDATE skill_sources SET source_type = 'skills-sh', url = ?1,
-                        installed_version = ?2, original_checksum = ?3,
-                        remote_sha = ?4, skill_path = ?5,
-                        updated_at = datetime('now')
-                 WHERE skill_id = ?6",
-                params![source_url, version, checksum, commit_sha, source_path, eid],
-            )?;
-            eid
-        } else {
-            let source_id = Uuid::new_v4().to_string();
-            tx.execute(
-                "INSERT INTO skills (id, name, description, version, checksum, last_modified)
-                 VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))",
-                params![pre_skill_id, skill_name, description, version, checksum],
-            )?;
+        db_replace_files(&tx, &sid, &files)?;
+        let checksum = refresh_skill_checksum(&tx, &sid)?;
+
+        let updated = tx.execute(
+            "UPDATE skill_sources SET source_type = ?1, url = ?2,
+                    installed_version = ?3, original_checksum = ?4,
+                    remote_sha = ?5, skill_path = ?6, registry_id = ?7,
+                    updated_at = datetime('now')
+             WHERE skill_id = ?8",
+            params![
+                source.source_type,
+                source.url,
+                version,
+                checksum,
+                revision,
+                skill_id,
+                source.registry_id,
+                sid
+            ],
+        )?;
+        if updated == 0 {
             tx.execute(
                 "INSERT INTO skill_sources
                     (id, skill_id, source_type, url, installed_version,
-                     original_checksum, remote_sha, skill_path)
-                 VALUES (?1, ?2, 'skills-sh', ?3, ?4, ?5, ?6, ?7)",
+                     original_checksum, remote_sha, skill_path, registry_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
                 params![
-                    source_id,
-                    pre_skill_id,
-                    source_url,
+                    Uuid::new_v4().to_string(),
+                    sid,
+                    source.source_type,
+                    source.url,
                     version,
                     checksum,
-                    commit_sha,
-                    source_path
+                    revision,
+                    skill_id,
+                    source.registry_id
                 ],
             )?;
-            pre_skill_id.clone()
-        };
+        }
 
         tx.commit()?;
         sid
     };
 
-    // Step 6: 部署到目标
+    // Step 5: 部署到目标
     let mut deployments_created = 0usize;
-    for target in &deploy_targets {
-        let deploy_result = deploy_skill_internal(
-            &pool,
-            &skill_id,
-            &skill_name,
-            target,
-            std::path::Path::new(""),
-        )
-        .await;
-        match deploy_result {
+    for target in deploy_targets {
+        match deploy_skill_internal(pool, &skill_id_db, skill_name, target, Path::new("")).await {
             Ok(_) => deployments_created += 1,
             Err(e) => info!(
-                "[install_from_catalog] 部署失败 {:?}/{}: {}",
+                "[install_from_source] 部署失败 {:?}/{}: {}",
                 target.project_id, target.tool, e
             ),
         }
     }
 
-    // Step 7: 写 sync_history
+    // Step 6: 写 sync_history
     {
         let conn = pool.get()?;
         conn.execute(
             "INSERT INTO sync_history (id, skill_id, action, status, created_at)
              VALUES (?1, ?2, 'import', 'success', datetime('now'))",
-            params![Uuid::new_v4().to_string(), skill_id],
+            params![Uuid::new_v4().to_string(), skill_id_db],
         )?;
     }
 
     info!(
-        "[install_from_catalog] 安装完成: skill_id={}, files={}, deploys={}",
-        skill_id, files_downloaded, deployments_created
+        "[install_from_source] 安装完成: skill_id={}, files={}, deploys={}",
+        skill_id_db, files_downloaded, deployments_created
     );
 
     Ok(SkillsShInstallResult {
-        skill_id,
+        skill_id: skill_id_db,
         files_downloaded,
         deployments_created,
         conflict: None,
     })
 }
 
-// ── 6. search_skills_sh ── （直接查询 skills.sh 搜索 API）
-
-#[tauri::command]
-pub async fn search_skills_sh(query: String) -> Result<Vec<SkillsShSearchResult>, AppError> {
-    info!("[search_skills_sh] query={}", query);
-
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(15))
-        .build()
-        .unwrap_or_default();
-
-    let url = format!(
-        "https://skills.sh/api/search?q={}&limit=50",
-        urlencoding_encode(&query)
+#[allow(clippy::too_many_arguments)]
+pub async fn install_from_catalog_internal(
+    pool: &DbPool,
+    source_repo: String,
+    source_path: String,
+    skill_name: String,
+    commit_sha: String,
+    registry_id: Option<String>,
+    deploy_targets: Vec<DeployTarget>,
+    force_overwrite: Option<bool>,
+    token: Option<String>,
+) -> Result<SkillsShInstallResult, AppError> {
+    info!(
+        "[install_from_catalog] skill={}, repo={}, path={}, sha={}, registry={:?}",
+        skill_name, source_repo, source_path, commit_sha, registry_id
     );
-    info!("[search_skills_sh] url={}", url);
-
-    let resp = client
-        .get(&url)
-        .header("User-Agent", "shirehub-skills-manager")
-        .header("Accept-Encoding", "identity")
-        .send()
-        .await
-        .map_err(|e| AppError::Internal(format!("skills.sh 请求失败: {}", e)))?;
-
-    if !resp.status().is_success() {
-        info!("[search_skills_sh] HTTP {}", resp.status());
-        return Ok(vec![]);
-    }
 
-    #[derive(serde::Deserialize)]
-    struct ApiResp {
-        skills: Option<Vec<SkillsShSearchResult>>,
+    // 自托管静态注册源的条目按清单安装，dmgrok 条目仍从 GitHub 仓库下载
+    if let Some(registry_id) = registry_id.as_deref() {
+        let catalog = registry::catalog_registry(pool, registry_id, token.clone())?;
+        let entry = catalog
+            .load_all()
+            .await?
+            .into_iter()
+            .find(|c| c.source_path == source_path);
+        if let Some(CatalogSkill { id, manifest_url: Some(manifest_url), .. }) = entry {
+            return install_from_source(
+                pool,
+                &catalog,
+                &id,
+                &commit_sha,
+                &skill_name,
+                InstallSource {
+                    source_type: "registry",
+                    url: manifest_url,
+                    registry_id: Some(registry_id),
+                },
+                &deploy_targets,
+                force_overwrite.unwrap_or(false),
+            )
+            .await;
+        }
     }
 
-    let data: ApiResp = resp
-        .json()
-        .await
-        .map_err(|_| AppError::Internal("解析 skills.sh 响应失败".into()))?;
+    // source_repo 可能是完整 GitHub URL（如 https://github.com/openai/skills），需提取 owner/repo
+    let owner_repo = extract_owner_repo(&source_repo)
+        .unwrap_or_else(|| source_repo.trim_start_matches("https://github.com/").to_string());
+    let github = GitHubRegistry::for_repo(&owner_repo, credentials::github_token(pool, token));
 
-    let skills = data.skills.unwrap_or_default();
-    info!("[search_skills_sh] 返回 {} 条", skills.len());
-    Ok(skills)
+    install_from_source(
+        pool,
+        &github,
+        &source_path,
+        &commit_sha,
+        &skill_name,
+        InstallSource {
+            source_type: "skills-sh",
+            url: format!("https://github.com/{}/tree/{}/{}", source_repo, commit_sha, source_path),
+            registry_id: None,
+        },
+        &deploy_targets,
+        force_overwrite.unwrap_or(false),
+    )
+    .await
 }
 
-// ── 7. install_from_skills_sh ── （从 skills.sh 安装：先发现路径，再委托 install_from_catalog）
-
-fn extract_frontmatter_name(content: &str) -> Option<String> {
-    let trimmed = content.trim();
-    if !trimmed.starts_with("---") {
-        return None;
-    }
-    let rest = &trimmed[3..];
-    let end_idx = rest.find("---")?;
-    let frontmatter = &rest[..end_idx];
-    for line in frontmatter.lines() {
-        let line = line.trim();
-        if let Some(val) = line.strip_prefix("name:") {
-            return Some(
-                val.trim()
-                    .trim_matches('"')
-                    .trim_matches('\'')
-                    .to_string(),
-            );
-        }
-    }
-    None
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn install_from_catalog(
+    source_repo: String,
+    source_path: String,
+    skill_name: String,
+    commit_sha: String,
+    registry_id: Option<String>,
+    deploy_targets: Vec<DeployTarget>,
+    force_overwrite: Option<bool>,
+    token: Option<String>,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<SkillsShInstallResult, AppError> {
+    let result = install_from_catalog_internal(
+        &pool,
+        source_repo,
+        source_path,
+        skill_name,
+        commit_sha,
+        registry_id,
+        deploy_targets,
+        force_overwrite,
+        token,
+    )
+    .await?;
+    watcher.resync(&pool);
+    Ok(result)
 }
 
-#[derive(serde::Deserialize)]
-struct GitTreeEntry {
-    path: String,
-    #[serde(rename = "type")]
-    entry_type: String,
-}
+// ── 6. search_skills_sh ── （直接查询 skills.sh 搜索 API）
 
-#[derive(serde::Deserialize)]
-struct GitTree {
-    tree: Vec<GitTreeEntry>,
+pub async fn search_skills_sh_internal(
+    pool: &DbPool,
+    query: String,
+) -> Result<Vec<SkillsShSearchResult>, AppError> {
+    info!("[search_skills_sh] query={}", query);
+    let skills = registry::skills_sh(pool)?.search_raw(&query, 50).await?;
+    info!("[search_skills_sh] 返回 {} 条", skills.len());
+    Ok(skills)
 }
 
-async fn discover_skill_path_in_repo(
-    client: &reqwest::Client,
-    owner_repo: &str,
-    skill_id: &str,
-    token: &Option<String>,
-) -> Result<(String, String), AppError> {
-    info!(
-        "[discover_skill_path] owner_repo={}, skill_id={}",
-        owner_repo, skill_id
-    );
-
-    // Step 1: 获取最新 commit SHA
-    #[derive(serde::Deserialize)]
-    struct CommitInfo {
-        sha: String,
-    }
-
-    let commits_url = format!("https://api.github.com/repos/{}/commits/HEAD", owner_repo);
-    let mut commits_req = client
-        .get(&commits_url)
-        .header("User-Agent", "shirehub-skills-manager")
-        .header("Accept", "application/vnd.github.v3+json");
-    if let Some(ref t) = token {
-        if !t.is_empty() {
-            commits_req = commits_req.header("Authorization", format!("Bearer {}", t));
-        }
-    }
-    let commit_sha = match commits_req.send().await {
-        Ok(r) if r.status().is_success() => r
-            .json::<CommitInfo>()
-            .await
-            .map(|c| c.sha)
-            .unwrap_or_else(|_| "HEAD".to_string()),
-        _ => "HEAD".to_string(),
-    };
-    info!("[discover_skill_path] commit_sha={}", commit_sha);
-
-    // Step 2: 递归获取文件树
-    let tree_url = format!(
-        "https://api.github.com/repos/{}/git/trees/{}?recursive=1",
-        owner_repo, commit_sha
-    );
-    let mut tree_req = client
-        .get(&tree_url)
-        .header("User-Agent", "shirehub-skills-manager")
-        .header("Accept", "application/vnd.github.v3+json");
-    if let Some(ref t) = token {
-        if !t.is_empty() {
-            tree_req = tree_req.header("Authorization", format!("Bearer {}", t));
-        }
-    }
-    let tree: GitTree = tree_req
-        .send()
-        .await
-        .map_err(|e| AppError::Internal(format!("获取仓库文件树失败: {}", e)))?
-        .json()
-        .await
-        .map_err(|e| AppError::Internal(format!("解析文件树失败: {}", e)))?;
-
-    // Step 3: 找所有 SKILL.md 路径
-    let skill_md_paths: Vec<String> = tree
-        .tree
-        .into_iter()
-        .filter(|e| {
-            e.entry_type == "blob"
-                && (e.path.ends_with("/SKILL.md") || e.path == "SKILL.md")
-        })
-        .map(|e| e.path)
-        .collect();
-
-    if skill_md_paths.is_empty() {
-        return Err(AppError::Internal(format!(
-            "仓库 {} 中未找到任何 SKILL.md 文件",
-            owner_repo
-        )));
-    }
-
-    info!(
-        "[discover_skill_path] 找到 {} 个 SKILL.md",
-        skill_md_paths.len()
-    );
-
-    let skill_id_lower = skill_id.to_lowercase();
-
-    // Step 4: 优先按父目录名匹配
-    for path in &skill_md_paths {
-        let parent = std::path::Path::new(path)
-            .parent()
-            .map(|p| p.to_string_lossy().to_string())
-            .unwrap_or_default();
-
-        let dir_name = std::path::Path::new(path)
-            .parent()
-            .and_then(|p| p.file_name())
-            .map(|n| n.to_string_lossy().to_lowercase())
-            .unwrap_or_default();
-
-        if dir_name == skill_id_lower {
-            info!("[discover_skill_path] 按目录名匹配: {} → {}", skill_id, parent);
-            return Ok((parent, commit_sha));
-        }
-    }
-
-    // Step 5: 兜底：读取 SKILL.md 检查 frontmatter name
-    for path in &skill_md_paths {
-        let raw_url = format!(
-            "https://raw.githubusercontent.com/{}/{}/{}",
-            owner_repo, commit_sha, path
-        );
-
-        let mut raw_req = client
-            .get(&raw_url)
-            .header("User-Agent", "shirehub-skills-manager");
-        if let Some(ref t) = token {
-            if !t.is_empty() {
-                raw_req = raw_req.header("Authorization", format!("Bearer {}", t));
-            }
-        }
-        if let Ok(resp) = raw_req.send().await {
-            if resp.status().is_success() {
-                if let Ok(content) = resp.text().await {
-                    if let Some(name) = extract_frontmatter_name(&content) {
-                        if name.to_lowercase() == skill_id_lower {
-                            let parent = std::path::Path::new(path)
-                                .parent()
-                                .map(|p| p.to_string_lossy().to_string())
-                                .unwrap_or_default();
-                            info!(
-                                "[discover_skill_path] 按 frontmatter name 匹配: {} → {}",
-                                skill_id, parent
-                            );
-                            return Ok((parent, commit_sha));
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    Err(AppError::Internal(format!(
-        "在仓库 {} 中未找到名为 '{}' 的 Skill",
-        owner_repo, skill_id
-    )))
+#[tauri::command]
+pub async fn search_skills_sh(
+    query: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SkillsShSearchResult>, AppError> {
+    search_skills_sh_internal(&pool, query).await
 }
 
-#[tauri::command]
-pub async fn install_from_skills_sh(
+// ── 7. install_from_skills_sh ── （从 skills.sh 安装：先发现路径，再委托 install_from_catalog）
+
+pub async fn install_from_skills_sh_internal(
+    pool: &DbPool,
     source: String,
     skill_id: String,
     deploy_targets: Vec<DeployTarget>,
     force_overwrite: Option<bool>,
     token: Option<String>,
-    pool: State<'_, DbPool>,
 ) -> Result<SkillsShInstallResult, AppError> {
     info!(
         "[install_from_skills_sh] source={}, skill_id={}",
         source, skill_id
     );
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .unwrap_or_default();
-
-    let (source_path, commit_sha) =
-        discover_skill_path_in_repo(&client, &source, &skill_id, &token).await?;
+    let token = credentials::github_token(pool, token);
+    let (source_path, commit_sha) = GitHubRegistry::for_repo(&source, token.clone())
+        .find_skill(&skill_id)
+        .await?;
 
     info!(
         "[install_from_skills_sh] 发现路径: path={}, sha={}",
         source_path, commit_sha
     );
 
-    install_from_catalog(
+    install_from_catalog_internal(
+        pool,
         source,
         source_path,
         skill_id,
         commit_sha,
+        None,
         deploy_targets,
         force_overwrite,
         token,
-        pool,
     )
     .await
 }
 
+#[tauri::command]
+pub async fn install_from_skills_sh(
+    source: String,
+    skill_id: String,
+    deploy_targets: Vec<DeployTarget>,
+    force_overwrite: Option<bool>,
+    token: Option<String>,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<SkillsShInstallResult, AppError> {
+    let result = install_from_skills_sh_internal(&pool, source, skill_id, deploy_targets, force_overwrite, token).await?;
+    watcher.resync(&pool);
+    Ok(result)
+}
+
 // ── 内部工具函数 ──
 
-fn urlencoding_encode(input: &str) -> String {
+pub(crate) fn urlencoding_encode(input: &str) -> String {
     let mut result = String::new();
     for byte in input.bytes() {
         match byte {
@@ -1048,7 +643,7 @@ async fn deploy_skill_internal(
     target: &DeployTarget,
     _source_dir: &std::path::Path,
 ) -> Result<(), AppError> {
-    use super::skill_files::db_export_to_dir;
+    use super::staging::StagedDir;
     use super::utils::compute_dir_checksum;
 
     let tool_cfg = crate::tools::get_tool(&target.tool)
@@ -1063,31 +658,25 @@ async fn deploy_skill_internal(
                 |row| row.get(0),
             )
             .map_err(|_| AppError::NotFound(format!("项目不存在: {}", pid)))?;
-        std::path::Path::new(&project_path)
-            .join(tool_cfg.project_dir)
-            .join(skill_name)
+        skill_dir_under(&std::path::Path::new(&project_path).join(tool_cfg.project_dir), skill_name)?
     } else {
         let home = dirs::home_dir().expect("Cannot find home directory");
-        home.join(tool_cfg.global_dir).join(skill_name)
+        skill_dir_under(&home.join(tool_cfg.global_dir), skill_name)?
     };
 
     if skill_name.trim().is_empty() {
         return Err(AppError::Validation("skill_name 不能为空，拒绝部署".into()));
     }
 
-    if deploy_path.exists() {
-        let _ = std::fs::remove_dir_all(&deploy_path);
-    }
-    {
-        let conn = pool.get()?;
-        db_export_to_dir(&conn, skill_id, &deploy_path)?;
-    }
+    let conn = pool.get()?;
+    let mut staged = StagedDir::prepare(&deploy_path)?;
+    db_export_to_dir(&conn, skill_id, staged.path())?;
+    staged.swap()?;
 
     let deploy_checksum = compute_dir_checksum(&deploy_path);
     let deploy_path_str = deploy_path.to_string_lossy().to_string();
     let dep_id = Uuid::new_v4().to_string();
 
-    let conn = pool.get()?;
     conn.execute(
         "INSERT OR REPLACE INTO skill_deployments
             (id, skill_id, project_id, tool, path, checksum, status, last_synced)
@@ -1101,6 +690,7 @@ async fn deploy_skill_internal(
             deploy_checksum
         ],
     )?;
+    staged.commit();
 
     Ok(())
 }
@@ -1108,7 +698,7 @@ async fn deploy_skill_internal(
 // ── 6. check_catalog_updates ──
 
 /// 从 URL "https://github.com/{owner}/{repo}/tree/{sha}/{path}" 中提取 "owner/repo"
-fn extract_owner_repo(github_url: &str) -> Option<String> {
+pub(crate) fn extract_owner_repo(github_url: &str) -> Option<String> {
     // 去掉前缀 "https://github.com/"
     let rest = github_url.strip_prefix("https://github.com/")?;
     // 取前两段 "owner/repo"
@@ -1128,7 +718,7 @@ pub async fn check_catalog_updates(
     info!("[check_catalog_updates] 开始检查 catalog 更新");
 
     // 1. 从 DB 查询所有通过 catalog 安装的 Skill
-    //    catalog 安装的 URL 格式：https://github.com/...
+    //    dmgrok 条目的 URL 格式：https://github.com/...；静态注册源条目记录 registry_id，skill_path 为条目 ID
     #[derive(Debug)]
     struct InstalledSkill {
         skill_id: String,
@@ -1140,6 +730,7 @@ pub async fn check_catalog_updates(
         checksum: Option<String>,
         original_checksum: Option<String>,
         deploy_count: i64,
+        registry_id: Option<String>,
     }
 
     let installed: Vec<InstalledSkill> = {
@@ -1147,11 +738,12 @@ pub async fn check_catalog_updates(
         let mut stmt = conn.prepare(
             "SELECT s.id, s.name, s.version, ss.url, ss.remote_sha, ss.skill_path,
                     s.checksum, ss.original_checksum,
-                    (SELECT COUNT(*) FROM skill_deployments sd WHERE sd.skill_id = s.id)
+                    (SELECT COUNT(*) FROM skill_deployments sd WHERE sd.skill_id = s.id),
+                    ss.registry_id
              FROM skills s
              JOIN skill_sources ss ON ss.skill_id = s.id
-             WHERE ss.source_type = 'skills-sh'
-               AND ss.url LIKE 'https://github.com/%'
+             WHERE ((ss.source_type = 'skills-sh' AND ss.url LIKE 'https://github.com/%')
+                    OR (ss.source_type = 'registry' AND ss.registry_id IS NOT NULL))
                AND ss.skill_path IS NOT NULL"
         )?;
         let rows: Vec<InstalledSkill> = stmt.query_map([], |row| {
@@ -1165,6 +757,7 @@ pub async fn check_catalog_updates(
                 checksum: row.get(6)?,
                 original_checksum: row.get(7)?,
                 deploy_count: row.get(8)?,
+                registry_id: row.get(9)?,
             })
         })?.collect::<Result<Vec<_>, _>>()?;
         rows
@@ -1176,7 +769,7 @@ pub async fn check_catalog_updates(
     }
 
     // 2. 获取最新 catalog（利用内存缓存）
-    let catalog_skills = load_catalog_all().await?;
+    let catalog_skills = load_catalog_all(&pool).await?;
 
     // 3. 逐一对比 commit_sha
     let mut results = Vec::new();
@@ -1185,10 +778,14 @@ pub async fn check_catalog_updates(
         let owner_repo = skill.url.as_deref().and_then(extract_owner_repo);
         let sp = skill.skill_path.as_deref().unwrap_or("");
 
-        // 优先按 source_path 精确匹配，再用 source_repo 辅助验证
-        let catalog_entry = catalog_skills.iter().find(|c| {
-            c.source_path == sp
-                && owner_repo.as_deref().map(|r| r == c.source_repo).unwrap_or(true)
+        // 注册源安装的按 (registry_id, 条目 ID) 匹配；
+        // 其余优先按 source_path 精确匹配，再用 source_repo 辅助验证
+        let catalog_entry = catalog_skills.iter().find(|c| match &skill.registry_id {
+            Some(rid) => c.registry_id.as_ref() == Some(rid) && c.id == sp,
+            None => {
+                c.source_path == sp
+                    && owner_repo.as_deref().map(|r| r == c.source_repo).unwrap_or(true)
+            }
         });
 
         if let Some(entry) = catalog_entry {
@@ -1210,7 +807,11 @@ pub async fn check_catalog_updates(
                 skill_name: skill.skill_name.clone(),
                 current_version: skill.version.clone(),
                 source_url: skill.url.clone(),
-                owner_repo: entry.source_repo.clone(),
+                owner_repo: if entry.source_repo.is_empty() {
+                    entry.registry_id.clone().unwrap_or_default()
+                } else {
+                    entry.source_repo.clone()
+                },
                 skill_path: entry.source_path.clone(),
                 local_sha,
                 remote_sha,
@@ -1235,3 +836,433 @@ pub async fn check_catalog_updates(
 
     Ok(results)
 }
+// ── 7. apply_catalog_update ── （下载新版本；本地改过时与原安装版本三向合并）
+
+#[derive(serde::Serialize)]
+pub struct CatalogUpdateResult {
+    pub skill_id: String,
+    /// 'up_to_date' | 'updated' | 'merged' | 'conflict'
+    pub status: String,
+    pub from_revision: Option<String>,
+    pub to_revision: String,
+    pub backup_id: Option<String>,
+    pub files_written: usize,
+    pub deployments_synced: usize,
+    /// 有本地修改或未处理冲突、因此没有用新版本覆盖的部署
+    pub deployments_skipped: Vec<String>,
+    /// 本地改过时的合并详情；status 为 'conflict' 时未写入任何内容，
+    /// 为冲突文件提供 resolutions 后重新调用即可
+    pub merge: Option<MergeResult>,
+}
+
+struct InstalledSource {
+    skill_name: String,
+    checksum: Option<String>,
+    source_type: String,
+    url: Option<String>,
+    remote_sha: Option<String>,
+    skill_path: Option<String>,
+    registry_id: Option<String>,
+    original_checksum: Option<String>,
+}
+
+/// 已安装 Skill 对应的注册源与其中的 Skill ID
+fn open_installed_source(
+    pool: &DbPool,
+    src: &InstalledSource,
+    token: Option<String>,
+) -> Result<(Box<dyn SkillRegistry>, String), AppError> {
+    let upstream_id = src
+        .skill_path
+        .clone()
+        .ok_or_else(|| AppError::Validation(format!("{} 缺少来源路径，无法更新", src.skill_name)))?;
+    if let Some(registry_id) = &src.registry_id {
+        let config = registry::get_registry_config(&*pool.get()?, registry_id)?;
+        return Ok((registry::open_registry(pool, &config, token)?, upstream_id));
+    }
+    let owner_repo = match (src.source_type.as_str(), src.url.as_deref().and_then(extract_owner_repo)) {
+        ("skills-sh", Some(owner_repo)) => owner_repo,
+        _ => {
+            return Err(AppError::Validation(format!(
+                "{} 不是从市场或注册源安装的，无法更新",
+                src.skill_name
+            )));
+        }
+    };
+    Ok((Box::new(GitHubRegistry::for_repo(&owner_repo, credentials::github_token(pool, token))), upstream_id))
+}
+
+/// 原安装版本：优先取库中 checksum 等于 original_checksum 的历史版本，否则从注册源下载 remote_sha
+async fn load_update_base(
+    pool: &DbPool,
+    skill_id: &str,
+    src: &InstalledSource,
+    registry: &dyn SkillRegistry,
+    upstream_id: &str,
+) -> Option<BTreeMap<String, Vec<u8>>> {
+    if let Some(checksum) = &src.original_checksum {
+        let conn = pool.get().ok()?;
+        if let Ok(Some(rev)) = db_find_revision_by_checksum(&conn, skill_id, checksum) {
+            let mut files = BTreeMap::new();
+            for (rel_path, _) in db_revision_manifest(&conn, skill_id, rev).ok()? {
+                let content = db_read_revision_file(&conn, skill_id, rev, &rel_path).ok()?;
+                files.insert(rel_path, content);
+            }
+            return Some(files);
+        }
+    }
+    let remote_sha = src.remote_sha.as_deref()?;
+    match download_skill_files(registry, upstream_id, remote_sha).await {
+        Ok(files) => Some(files),
+        Err(e) => {
+            warn!("[apply_catalog_update] 无法获取原安装版本 {}: {}", remote_sha, e);
+            None
+        }
+    }
+}
+
+fn as_text(content: Option<&Vec<u8>>) -> Option<String> {
+    content.map(|c| String::from_utf8_lossy(c).into_owned())
+}
+
+/// 本地（left）与上游新版本（right）的三向合并。
+/// 一侧删除而另一侧未改动时接受删除；二进制文件只有一侧改动时取改动的一侧，否则算冲突。
+/// 返回无冲突文件的合并内容（冲突文件暂取本地内容）和合并详情
+pub(crate) fn merge_update(
+    base: Option<&BTreeMap<String, Vec<u8>>>,
+    local: &BTreeMap<String, Vec<u8>>,
+    upstream: &BTreeMap<String, Vec<u8>>,
+) -> (BTreeMap<String, Vec<u8>>, MergeResult) {
+    let empty = BTreeMap::new();
+    let base_map = base.unwrap_or(&empty);
+    let is_binary = |p: &String| {
+        [local.get(p), upstream.get(p), base_map.get(p)]
+            .into_iter()
+            .flatten()
+            .any(|c| std::str::from_utf8(c).is_err())
+    };
+    let texts = |m: &BTreeMap<String, Vec<u8>>| -> BTreeMap<String, String> {
+        m.iter()
+            .filter(|(p, _)| !is_binary(p))
+            .map(|(p, c)| (p.clone(), String::from_utf8_lossy(c).into_owned()))
+            .collect()
+    };
+    let base_texts = base.map(texts);
+    let mut result = merge_file_maps(base_texts.as_ref(), &texts(local), &texts(upstream));
+
+    let mut merged: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    let mut conflict_count = 0usize;
+    for f in &mut result.files {
+        let conflicted = match f.status.as_str() {
+            "conflict" => true,
+            "deleted_right" => f.left_content != f.base_content,
+            "deleted_left" => f.right_content != f.base_content,
+            _ => false,
+        };
+        if conflicted {
+            conflict_count += 1;
+            if let Some(c) = local.get(&f.path) {
+                merged.insert(f.path.clone(), c.clone());
+            }
+            continue;
+        }
+        if f.status.starts_with("deleted_") {
+            f.merged_content = None;
+        }
+        if let Some(c) = &f.merged_content {
+            merged.insert(f.path.clone(), c.clone().into_bytes());
+        }
+    }
+
+    let binaries: std::collections::BTreeSet<&String> = local
+        .keys()
+        .chain(upstream.keys())
+        .chain(base_map.keys())
+        .filter(|p| is_binary(p))
+        .collect();
+    for path in binaries {
+        let (l, r, b) = (local.get(path), upstream.get(path), base_map.get(path));
+        let (status, pick) = if l == r {
+            ("unchanged", l)
+        } else if l == b {
+            (if r.is_some() { "auto_merged" } else { "deleted_right" }, r)
+        } else if r == b {
+            (if l.is_some() { "auto_merged" } else { "deleted_left" }, l)
+        } else {
+            conflict_count += 1;
+            ("conflict", l)
+        };
+        if let Some(c) = pick {
+            merged.insert(path.clone(), c.clone());
+        }
+        let conflict = status == "conflict";
+        result.files.push(MergeFileResult {
+            path: path.clone(),
+            status: status.to_string(),
+            merged_content: None,
+            left_content: as_text(l.filter(|_| conflict)),
+            right_content: as_text(r.filter(|_| conflict)),
+            base_content: as_text(b.filter(|_| conflict)),
+        });
+    }
+
+    result.total_files = result.files.len();
+    result.conflict_count = conflict_count;
+    result.auto_merged_count = result.total_files - conflict_count;
+    (merged, result)
+}
+
+/// 把已安装的 Skill 更新到注册源的 revision（缺省为最新版本）。
+/// 本地未改动时直接替换；改动过时与原安装版本做三向合并，冲突需通过 resolutions 给出最终内容。
+/// 写入前备份，写入后重新同步该 Skill 的部署（有本地修改的部署跳过，见 deployments_skipped）
+pub async fn apply_catalog_update_internal(
+    pool: &DbPool,
+    skill_id: String,
+    revision: Option<String>,
+    resolutions: Option<Vec<MergeResolution>>,
+    token: Option<String>,
+) -> Result<CatalogUpdateResult, AppError> {
+    info!("[apply_catalog_update] skill={}, revision={:?}", skill_id, revision);
+    let resolutions = resolutions.unwrap_or_default();
+    for r in &resolutions {
+        SkillPath::parse(&r.path)?;
+        if r.has_conflict_markers() {
+            return Err(AppError::Validation(format!("文件仍包含冲突标记: {}", r.path)));
+        }
+    }
+
+    let src: InstalledSource = pool
+        .get()?
+        .query_row(
+            "SELECT s.name, s.checksum, ss.source_type, ss.url, ss.remote_sha, ss.skill_path,
+                    ss.registry_id, ss.original_checksum
+             FROM skills s JOIN skill_sources ss ON ss.skill_id = s.id
+             WHERE s.id = ?1",
+            params![skill_id],
+            |row| {
+                Ok(InstalledSource {
+                    skill_name: row.get(0)?,
+                    checksum: row.get(1)?,
+                    source_type: row.get(2)?,
+                    url: row.get(3)?,
+                    remote_sha: row.get(4)?,
+                    skill_path: row.get(5)?,
+                    registry_id: row.get(6)?,
+                    original_checksum: row.get(7)?,
+                })
+            },
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("Skill 不存在或没有来源记录: {}", skill_id)))?;
+
+    let (registry, upstream_id) = open_installed_source(pool, &src, token)?;
+    let to_revision = match revision {
+        Some(rev) => rev,
+        None => registry.latest_revision(&upstream_id).await?,
+    };
+    let mut result = CatalogUpdateResult {
+        skill_id: skill_id.clone(),
+        status: "up_to_date".to_string(),
+        from_revision: src.remote_sha.clone(),
+        to_revision: to_revision.clone(),
+        backup_id: None,
+        files_written: 0,
+        deployments_synced: 0,
+        deployments_skipped: Vec::new(),
+        merge: None,
+    };
+    if src.remote_sha.as_deref() == Some(to_revision.as_str()) && resolutions.is_empty() {
+        info!("[apply_catalog_update] {} 已是最新版本", src.skill_name);
+        return Ok(result);
+    }
+
+    let upstream = download_skill_files(registry.as_ref(), &upstream_id, &to_revision).await?;
+    let locally_modified = src.checksum.is_some() && src.checksum != src.original_checksum;
+
+    // 本地改过：合并。冲突未全部给出 resolutions 时只返回合并详情
+    let merged = if locally_modified {
+        let base = load_update_base(pool, &skill_id, &src, registry.as_ref(), &upstream_id).await;
+        if base.is_none() {
+            warn!("[apply_catalog_update] 找不到原安装版本，按两向合并处理");
+        }
+        let local = read_db_files(&*pool.get()?, &skill_id)?;
+        let (mut merged, merge) = merge_update(base.as_ref(), &local, &upstream);
+
+        let unresolved: Vec<&str> = merge
+            .files
+            .iter()
+            .filter(|f| merge_is_conflict(f) && !resolutions.iter().any(|r| r.path == f.path))
+            .map(|f| f.path.as_str())
+            .collect();
+        if !unresolved.is_empty() {
+            info!("[apply_catalog_update] {} 个文件冲突待解决: {:?}", unresolved.len(), unresolved);
+            result.status = "conflict".to_string();
+            result.merge = Some(merge);
+            return Ok(result);
+        }
+        for r in resolutions {
+            let rel = SkillPath::parse(&r.path)?.to_string();
+            match r.content {
+                Some(content) => merged.insert(rel, content.into_bytes()),
+                None => merged.remove(&rel),
+            };
+        }
+        result.status = "merged".to_string();
+        result.merge = Some(merge);
+        Some(merged)
+    } else {
+        result.status = "updated".to_string();
+        None
+    };
+
+    let (_, description, version) = merged
+        .as_ref()
+        .unwrap_or(&upstream)
+        .get("SKILL.md")
+        .map(|c| parse_skill_frontmatter(&String::from_utf8_lossy(c)))
+        .unwrap_or_default();
+
+    let conn = pool.get()?;
+    result.backup_id = backup_before_update(&conn, &skill_id, &src)?;
+
+    // 先写上游版本（作为下次合并的 base 留在版本历史里），本地改过时再写合并结果
+    let tx = conn.unchecked_transaction()?;
+    result.files_written = db_replace_files(&tx, &skill_id, &upstream)?;
+    let upstream_checksum = refresh_skill_checksum(&tx, &skill_id)?;
+    if let Some(merged) = &merged {
+        result.files_written = db_replace_files(&tx, &skill_id, merged)?;
+        refresh_skill_checksum(&tx, &skill_id)?;
+    }
+    tx.execute(
+        "UPDATE skills SET description = COALESCE(?1, description), version = ?2,
+                last_modified = datetime('now'), updated_at = datetime('now')
+         WHERE id = ?3",
+        params![description, version, skill_id],
+    )?;
+    tx.execute(
+        "UPDATE skill_sources SET remote_sha = ?1, original_checksum = ?2, installed_version = ?3,
+                updated_at = datetime('now')
+         WHERE skill_id = ?4",
+        params![to_revision, upstream_checksum, version, skill_id],
+    )?;
+    tx.execute(
+        "INSERT INTO sync_history (id, skill_id, action, from_checksum, to_checksum, status, created_at)
+         VALUES (?1, ?2, 'update', ?3, ?4, 'success', datetime('now'))",
+        params![Uuid::new_v4().to_string(), skill_id, src.checksum, upstream_checksum],
+    )?;
+    tx.commit()?;
+
+    // 重新同步部署；本地改过的部署不覆盖，由用户按 ID 合并
+    (result.deployments_synced, result.deployments_skipped) =
+        super::deployments::redeploy_unmodified(&conn, &skill_id)?;
+
+    info!(
+        "[apply_catalog_update] 完成: {} {:?} → {}, status={}, files={}, synced={}, skipped={}",
+        src.skill_name,
+        result.from_revision,
+        to_revision,
+        result.status,
+        result.files_written,
+        result.deployments_synced,
+        result.deployments_skipped.len()
+    );
+    Ok(result)
+}
+
+/// 合并详情中需要用户处理的文件
+pub(crate) fn merge_is_conflict(f: &MergeFileResult) -> bool {
+    f.status == "conflict" || (f.status.starts_with("deleted_") && f.merged_content.is_some())
+}
+
+pub(crate) fn read_db_files(conn: &rusqlite::Connection, skill_id: &str) -> Result<BTreeMap<String, Vec<u8>>, AppError> {
+    db_list_files(conn, skill_id)?
+        .into_iter()
+        .map(|p| db_read_file(conn, skill_id, &p).map(|c| (p, c)))
+        .collect()
+}
+
+fn backup_before_update(
+    conn: &rusqlite::Connection,
+    skill_id: &str,
+    src: &InstalledSource,
+) -> Result<Option<String>, AppError> {
+    if !has_db_files(conn, skill_id) {
+        return Ok(None);
+    }
+    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
+    let backup_path = dirs::home_dir()
+        .unwrap_or_default()
+        .join(".skills-manager")
+        .join("backups")
+        .join(SkillPath::name(&src.skill_name)?.as_str())
+        .join(format!("update-{}", timestamp));
+    db_export_to_dir(conn, skill_id, &backup_path)?;
+    let bid = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO skill_backups (id, skill_id, version_label, backup_path, checksum, reason, metadata)
+         VALUES (?1, ?2, ?3, ?4, ?5, 'before_update', ?6)",
+        params![
+            bid,
+            skill_id,
+            timestamp,
+            backup_path.to_string_lossy(),
+            src.checksum.clone().unwrap_or_default(),
+            serde_json::json!({ "remote_sha": src.remote_sha }).to_string()
+        ],
+    )?;
+    info!("[apply_catalog_update] 备份完成: {}", backup_path.display());
+    Ok(Some(bid))
+}
+
+#[tauri::command]
+pub async fn apply_catalog_update(
+    skill_id: String,
+    revision: Option<String>,
+    resolutions: Option<Vec<MergeResolution>>,
+    token: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<CatalogUpdateResult, AppError> {
+    apply_catalog_update_internal(&pool, skill_id, revision, resolutions, token).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(entries: &[(&str, &str)]) -> BTreeMap<String, Vec<u8>> {
+        entries
+            .iter()
+            .map(|(p, c)| (p.to_string(), c.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn merge_update_keeps_local_edits_and_upstream_changes() {
+        let base = files(&[("SKILL.md", "a\nb\nc\nd\ne\n"), ("old.md", "x\n")]);
+        let local = files(&[("SKILL.md", "A\nb\nc\nd\ne\n"), ("old.md", "x\n")]);
+        let upstream = files(&[("SKILL.md", "a\nb\nc\nd\nE\n"), ("new.md", "y\n")]);
+
+        let (merged, result) = merge_update(Some(&base), &local, &upstream);
+        assert_eq!(result.conflict_count, 0);
+        assert_eq!(merged.get("SKILL.md").unwrap(), b"A\nb\nc\nd\nE\n");
+        assert_eq!(merged.get("new.md").unwrap(), b"y\n");
+        assert!(!merged.contains_key("old.md"));
+    }
+
+    #[test]
+    fn merge_update_reports_overlapping_edits_and_binary_conflicts() {
+        let base = files(&[("SKILL.md", "a\n")]);
+        let local = files(&[("SKILL.md", "local\n")]);
+        let upstream = files(&[("SKILL.md", "upstream\n")]);
+        let (merged, result) = merge_update(Some(&base), &local, &upstream);
+        assert_eq!(result.conflict_count, 1);
+        assert_eq!(merged.get("SKILL.md").unwrap(), b"local\n");
+
+        let bin = |b: u8| BTreeMap::from([("logo.png".to_string(), vec![0xff, b])]);
+        let (merged, result) = merge_update(Some(&bin(0)), &bin(0), &bin(1));
+        assert_eq!(result.conflict_count, 0);
+        assert_eq!(merged.get("logo.png").unwrap(), &vec![0xff, 1]);
+        let (_, result) = merge_update(Some(&bin(0)), &bin(2), &bin(1));
+        assert_eq!(result.conflict_count, 1);
+    }
+}
@@ -0,0 +1,261 @@
+//! catalog 类注册源的离线快照。
+//!
+//! 解析后的条目连同拉取时间、命中的镜像地址和 ETag / Last-Modified 保存在 `catalog_snapshots` 表。
+//! 进程启动后浏览和搜索直接读快照；快照过期时在后台用条件请求刷新，断网时继续使用旧快照。
+
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use tauri::State;
+
+use super::registry::{self, SkillRegistry, USER_AGENT};
+use super::static_registry;
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::models::CatalogSkill;
+
+/// 快照超过这个时间（秒）视为过期，下次读取时触发后台刷新
+pub const SNAPSHOT_TTL_SECS: u64 = 86400; // 24 小时
+
+// ── 存储 ──
+
+pub struct CatalogSnapshot {
+    pub registry_id: String,
+    /// 本次内容来自哪个地址（主地址或镜像）
+    pub source_url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub skills: Vec<CatalogSkill>,
+    /// 距上次成功拉取（或服务端确认未变）的秒数
+    pub age_secs: u64,
+}
+
+pub fn load_snapshot(conn: &Connection, registry_id: &str) -> Result<Option<CatalogSnapshot>, AppError> {
+    let row = conn
+        .query_row(
+            "SELECT source_url, etag, last_modified, skills_json,
+                    CAST((julianday('now') - julianday(fetched_at)) * 86400 AS INTEGER)
+             FROM catalog_snapshots WHERE registry_id = ?1",
+            params![registry_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                ))
+            },
+        )
+        .optional()?;
+    let Some((source_url, etag, last_modified, skills_json, age)) = row else {
+        return Ok(None);
+    };
+    // 旧版本写入的快照结构不兼容时当作没有快照，重新拉取
+    let skills = match serde_json::from_str::<Vec<CatalogSkill>>(&skills_json) {
+        Ok(skills) => skills,
+        Err(e) => {
+            warn!("[catalog_snapshot] {} 的快照无法解析，忽略: {}", registry_id, e);
+            return Ok(None);
+        }
+    };
+    Ok(Some(CatalogSnapshot {
+        registry_id: registry_id.to_string(),
+        source_url,
+        etag,
+        last_modified,
+        skills,
+        age_secs: age.max(0) as u64,
+    }))
+}
+
+pub fn save_snapshot(conn: &Connection, snapshot: &CatalogSnapshot) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO catalog_snapshots
+            (registry_id, source_url, etag, last_modified, skills_json, skill_count)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(registry_id) DO UPDATE SET
+            source_url = ?2, etag = ?3, last_modified = ?4, skills_json = ?5, skill_count = ?6,
+            fetched_at = datetime('now'), updated_at = datetime('now')",
+        params![
+            snapshot.registry_id,
+            snapshot.source_url,
+            snapshot.etag,
+            snapshot.last_modified,
+            serde_json::to_string(&snapshot.skills)?,
+            snapshot.skills.len() as i64
+        ],
+    )?;
+    Ok(())
+}
+
+/// 服务端返回 304：内容没变，只刷新拉取时间
+pub fn touch_snapshot(conn: &Connection, registry_id: &str) -> Result<(), AppError> {
+    conn.execute(
+        "UPDATE catalog_snapshots SET fetched_at = datetime('now') WHERE registry_id = ?1",
+        params![registry_id],
+    )?;
+    Ok(())
+}
+
+pub fn delete_snapshot(conn: &Connection, registry_id: &str) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM catalog_snapshots WHERE registry_id = ?1",
+        params![registry_id],
+    )?;
+    Ok(())
+}
+
+// ── 条件请求 ──
+
+pub enum Fetched {
+    NotModified,
+    Body {
+        bytes: Vec<u8>,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+/// 拉取 catalog；prev 为同一地址上次的 (ETag, Last-Modified)，服务端确认未变时返回 NotModified。
+/// file:// 与本地路径没有缓存校验，总是直接读取
+pub async fn fetch_conditional(
+    client: &reqwest::Client,
+    url: &str,
+    prev: Option<(Option<&str>, Option<&str>)>,
+) -> Result<Fetched, AppError> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Ok(Fetched::Body {
+            bytes: static_registry::fetch_url(client, url).await?,
+            etag: None,
+            last_modified: None,
+        });
+    }
+
+    let mut req = client
+        .get(url)
+        .header("User-Agent", USER_AGENT)
+        .header("Accept-Encoding", "identity");
+    if let Some((etag, last_modified)) = prev {
+        if let Some(etag) = etag {
+            req = req.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = last_modified {
+            req = req.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let resp = req
+        .send()
+        .await
+        .map_err(|e| AppError::Internal(format!("请求失败 ({}): {}", url, e)))?;
+    if resp.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !resp.status().is_success() {
+        return Err(AppError::Internal(format!("HTTP {} ({})", resp.status(), url)));
+    }
+    let header = |name: reqwest::header::HeaderName| {
+        resp.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let etag = header(reqwest::header::ETAG);
+    let last_modified = header(reqwest::header::LAST_MODIFIED);
+    let bytes = resp
+        .bytes()
+        .await
+        .map_err(|e| AppError::Internal(format!("读取 {} 失败: {}", url, e)))?;
+    Ok(Fetched::Body {
+        bytes: bytes.to_vec(),
+        etag,
+        last_modified,
+    })
+}
+
+// ── 命令 ──
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CatalogSnapshotInfo {
+    pub registry_id: String,
+    pub source_url: Option<String>,
+    pub skill_count: usize,
+    /// 最近一次成功拉取的时间（UTC），从未拉取过为 None
+    pub fetched_at: Option<String>,
+    pub stale: bool,
+    /// 刷新失败的原因；此时浏览与搜索继续使用旧快照
+    pub error: Option<String>,
+}
+
+fn snapshot_info(conn: &Connection, registry_id: &str, error: Option<String>) -> Result<CatalogSnapshotInfo, AppError> {
+    let row = conn
+        .query_row(
+            "SELECT source_url, skill_count, fetched_at,
+                    CAST((julianday('now') - julianday(fetched_at)) * 86400 AS INTEGER)
+             FROM catalog_snapshots WHERE registry_id = ?1",
+            params![registry_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            },
+        )
+        .optional()?;
+    Ok(match row {
+        Some((source_url, count, fetched_at, age)) => CatalogSnapshotInfo {
+            registry_id: registry_id.to_string(),
+            source_url: Some(source_url),
+            skill_count: count.max(0) as usize,
+            fetched_at: Some(fetched_at),
+            stale: age.max(0) as u64 >= SNAPSHOT_TTL_SECS,
+            error,
+        },
+        None => CatalogSnapshotInfo {
+            registry_id: registry_id.to_string(),
+            source_url: None,
+            skill_count: 0,
+            fetched_at: None,
+            stale: true,
+            error,
+        },
+    })
+}
+
+/// 各 catalog 注册源的快照状态，市场页据此提示"离线 / 上次更新于"
+pub fn get_catalog_status_internal(pool: &DbPool) -> Result<Vec<CatalogSnapshotInfo>, AppError> {
+    let conn = pool.get()?;
+    registry::catalog_registries(pool)?
+        .iter()
+        .map(|reg| snapshot_info(&conn, reg.id(), None))
+        .collect()
+}
+
+#[tauri::command]
+pub async fn get_catalog_status(pool: State<'_, DbPool>) -> Result<Vec<CatalogSnapshotInfo>, AppError> {
+    get_catalog_status_internal(&pool)
+}
+
+/// 立即刷新所有 catalog 注册源的快照（仍使用条件请求），单个失败只记录在结果中
+pub async fn refresh_catalog_internal(pool: &DbPool) -> Result<Vec<CatalogSnapshotInfo>, AppError> {
+    info!("[refresh_catalog] 开始刷新");
+    let mut result = Vec::new();
+    for reg in registry::catalog_registries(pool)? {
+        let error = match reg.refresh().await {
+            Ok(_) => None,
+            Err(e) => {
+                warn!("[refresh_catalog] {} 刷新失败: {}", reg.id(), e);
+                Some(e.to_string())
+            }
+        };
+        result.push(snapshot_info(&*pool.get()?, reg.id(), error)?);
+    }
+    Ok(result)
+}
+
+#[tauri::command]
+pub async fn refresh_catalog(pool: State<'_, DbPool>) -> Result<Vec<CatalogSnapshotInfo>, AppError> {
+    refresh_catalog_internal(&pool).await
+}
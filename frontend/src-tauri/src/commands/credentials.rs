@@ -0,0 +1,152 @@
+//! 私有远程的凭据：git 导出配置各自保存的 HTTPS token / SSH 私钥，以及访问 GitHub API 的 token。
+//!
+//! 凭据只存放在本地数据库，读取配置时只返回是否已设置；日志中不出现任何密钥。
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use super::git_engine::GitCredential;
+use crate::db::DbPool;
+use crate::error::AppError;
+
+/// app_settings 中 GitHub token 的键（设置页「GitHub Token」）
+pub const GITHUB_TOKEN_KEY: &str = "github_token";
+
+/// 保存在 app_settings 中的密钥：只写不读，不返回给前端，也不写进日志
+pub fn is_secret_setting(key: &str) -> bool {
+    key == GITHUB_TOKEN_KEY
+}
+
+/// 前端提交的凭据。字段为 None 时保留已保存的值，空串表示清除
+#[derive(Clone, Default, Deserialize)]
+pub struct GitCredentialInput {
+    pub username: Option<String>,
+    pub token: Option<String>,
+    pub ssh_key_path: Option<String>,
+    pub ssh_passphrase: Option<String>,
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rel), Some(home)) => home.join(rel),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// 按 auth_type 组装凭据：token 需要令牌；ssh 未指定私钥时交给 ssh-agent；none 不使用凭据
+pub fn credential_from(auth_type: &str, input: GitCredentialInput) -> Result<Option<GitCredential>, AppError> {
+    match auth_type {
+        "token" => {
+            let token = non_empty(input.token)
+                .ok_or_else(|| AppError::Validation("HTTPS Token 认证需要填写 Token".into()))?;
+            Ok(Some(GitCredential::Token {
+                username: non_empty(input.username),
+                token,
+            }))
+        }
+        "ssh" => Ok(non_empty(input.ssh_key_path).map(|path| GitCredential::SshKey {
+            path: expand_home(path.trim()),
+            passphrase: non_empty(input.ssh_passphrase),
+        })),
+        _ => Ok(None),
+    }
+}
+
+/// 保存配置的凭据。auth_type 为 none 时删除；input 为 None 时保留原有凭据
+pub fn save(
+    conn: &Connection,
+    config_id: &str,
+    auth_type: &str,
+    input: Option<&GitCredentialInput>,
+) -> Result<(), AppError> {
+    if auth_type == "none" {
+        conn.execute("DELETE FROM git_credentials WHERE config_id = ?1", params![config_id])?;
+        return Ok(());
+    }
+    let Some(input) = input else {
+        return Ok(());
+    };
+    conn.execute(
+        "INSERT INTO git_credentials (config_id, username, token, ssh_key_path, ssh_passphrase)
+         VALUES (?1, NULLIF(?2, ''), NULLIF(?3, ''), NULLIF(?4, ''), NULLIF(?5, ''))
+         ON CONFLICT(config_id) DO UPDATE SET
+             username       = CASE WHEN ?2 IS NULL THEN username       ELSE NULLIF(?2, '') END,
+             token          = CASE WHEN ?3 IS NULL THEN token          ELSE NULLIF(?3, '') END,
+             ssh_key_path   = CASE WHEN ?4 IS NULL THEN ssh_key_path   ELSE NULLIF(?4, '') END,
+             ssh_passphrase = CASE WHEN ?5 IS NULL THEN ssh_passphrase ELSE NULLIF(?5, '') END,
+             updated_at     = datetime('now')",
+        params![
+            config_id,
+            input.username,
+            input.token,
+            input.ssh_key_path,
+            input.ssh_passphrase
+        ],
+    )?;
+    Ok(())
+}
+
+/// 配置保存的凭据；未保存或 auth_type 为 none 时为 None
+pub fn load_for_config(conn: &Connection, config_id: &str) -> Result<Option<GitCredential>, AppError> {
+    let row = conn
+        .query_row(
+            "SELECT c.auth_type, g.username, g.token, g.ssh_key_path, g.ssh_passphrase
+             FROM git_export_config c JOIN git_credentials g ON g.config_id = c.id
+             WHERE c.id = ?1",
+            params![config_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    GitCredentialInput {
+                        username: row.get(1)?,
+                        token: row.get(2)?,
+                        ssh_key_path: row.get(3)?,
+                        ssh_passphrase: row.get(4)?,
+                    },
+                ))
+            },
+        )
+        .optional()?;
+    match row {
+        // 保存的凭据与 auth_type 不匹配（例如切换了认证方式但未填写）时不使用
+        Some((auth_type, input)) => Ok(credential_from(&auth_type, input).ok().flatten()),
+        None => Ok(None),
+    }
+}
+
+/// 与某个导出配置地址相同的远程（导入、连接测试）使用该配置的凭据
+pub fn load_for_url(conn: &Connection, remote_url: &str) -> Result<Option<GitCredential>, AppError> {
+    let config_id: Option<String> = conn
+        .query_row(
+            "SELECT id FROM git_export_config WHERE remote_url = ?1",
+            params![remote_url],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match config_id {
+        Some(id) => load_for_config(conn, &id),
+        None => Ok(None),
+    }
+}
+
+/// 访问 GitHub API 的 token：调用方显式传入的优先，否则用设置中保存的
+pub fn github_token(pool: &DbPool, token: Option<String>) -> Option<String> {
+    non_empty(token).or_else(|| {
+        let value: Option<String> = pool
+            .get()
+            .ok()?
+            .query_row(
+                "SELECT value FROM app_settings WHERE key = ?1",
+                params![GITHUB_TOKEN_KEY],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten();
+        non_empty(value.map(|v| v.trim().trim_matches('"').to_string()))
+    })
+}
@@ -1,23 +1,33 @@
 use log::info;
-use rusqlite::params;
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use tauri::State;
 use uuid::Uuid;
-
-use super::skill_files::{compute_db_checksum, db_export_to_dir, db_import_from_dir, has_db_files};
-use super::utils::compute_dir_checksum;
+use walkdir::WalkDir;
+
+use super::skill_files::{
+    compute_db_checksum, db_clear_files, db_export_to_dir, db_import_from_dir, db_list_files,
+    db_read_file, has_db_files,
+};
+use super::lockfile::record_project_deployment;
+use super::skill_path::{skill_dir_under, SkillPath};
+use super::plan::{diff_dir_against_db, plan_redeploy, DeploymentPlan, PlanAction, PLAN_RECONCILE};
+use super::staging::{is_staging_path, StagedDir};
+use super::utils::{compute_dir_checksum, get_skills_lib_path};
+use super::watcher::WatcherService;
+use super::write_journal;
 use crate::db::DbPool;
 use crate::error::AppError;
 use crate::models::SkillDeployment;
 use crate::tools::ALL_TOOLS;
 
-#[tauri::command]
-pub async fn get_deployments(pool: State<'_, DbPool>) -> Result<Vec<SkillDeployment>, AppError> {
+pub async fn get_deployments_internal(pool: &DbPool) -> Result<Vec<SkillDeployment>, AppError> {
     info!("[get_deployments] 查询所有部署");
     let conn = pool.get()?;
     let mut stmt = conn.prepare(
         "SELECT id, skill_id, project_id, tool, path, checksum, status,
-                last_synced, created_at, updated_at
+                last_synced, created_at, updated_at, mode
          FROM skill_deployments ORDER BY tool, path"
     )?;
 
@@ -33,12 +43,18 @@ pub async fn get_deployments(pool: State<'_, DbPool>) -> Result<Vec<SkillDeploym
             last_synced: row.get(7)?,
             created_at: row.get(8)?,
             updated_at: row.get(9)?,
+            mode: row.get(10)?,
         })
     })?.collect::<Result<Vec<_>, _>>()?;
 
     Ok(deployments)
 }
 
+#[tauri::command]
+pub async fn get_deployments(pool: State<'_, DbPool>) -> Result<Vec<SkillDeployment>, AppError> {
+    get_deployments_internal(&pool).await
+}
+
 #[tauri::command]
 pub async fn get_skill_deployments(
     skill_id: String,
@@ -48,7 +64,7 @@ pub async fn get_skill_deployments(
     let conn = pool.get()?;
     let mut stmt = conn.prepare(
         "SELECT id, skill_id, project_id, tool, path, checksum, status,
-                last_synced, created_at, updated_at
+                last_synced, created_at, updated_at, mode
          FROM skill_deployments WHERE skill_id = ?1
          ORDER BY tool, path"
     )?;
@@ -65,6 +81,7 @@ pub async fn get_skill_deployments(
             last_synced: row.get(7)?,
             created_at: row.get(8)?,
             updated_at: row.get(9)?,
+            mode: row.get(10)?,
         })
     })?.collect::<Result<Vec<_>, _>>()?;
 
@@ -100,7 +117,7 @@ pub async fn create_deployment(
 
     let deployment = conn.query_row(
         "SELECT id, skill_id, project_id, tool, path, checksum, status,
-                last_synced, created_at, updated_at
+                last_synced, created_at, updated_at, mode
          FROM skill_deployments WHERE id = ?1",
         params![id],
         |row| Ok(SkillDeployment {
@@ -114,16 +131,16 @@ pub async fn create_deployment(
             last_synced: row.get(7)?,
             created_at: row.get(8)?,
             updated_at: row.get(9)?,
+            mode: row.get(10)?,
         }),
     )?;
 
     Ok(deployment)
 }
 
-#[tauri::command]
-pub async fn delete_deployment(
+pub async fn delete_deployment_internal(
+    pool: &DbPool,
     deployment_id: String,
-    pool: State<'_, DbPool>,
 ) -> Result<(), AppError> {
     info!("[delete_deployment] 删除部署: {}", deployment_id);
     let conn = pool.get()?;
@@ -141,10 +158,10 @@ pub async fn delete_deployment(
         return Err(AppError::NotFound(format!("部署记录不存在: {}", deployment_id)));
     };
 
-    // 删除磁盘上的部署目录
+    // 删除磁盘上的部署目录（符号链接部署只删链接，不动库目录）
     let deploy_dir = Path::new(&deploy_path);
-    if deploy_dir.exists() {
-        std::fs::remove_dir_all(deploy_dir)?;
+    if deploy_dir.symlink_metadata().is_ok() {
+        remove_deploy_target(deploy_dir)?;
         info!("[delete_deployment] 已删除磁盘目录: {}", deploy_path);
     } else {
         info!("[delete_deployment] 磁盘目录不存在，跳过: {}", deploy_path);
@@ -160,6 +177,17 @@ pub async fn delete_deployment(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn delete_deployment(
+    deployment_id: String,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<(), AppError> {
+    delete_deployment_internal(&pool, deployment_id).await?;
+    watcher.resync(&pool);
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn update_deployment_status(
     deployment_id: String,
@@ -185,7 +213,7 @@ pub async fn get_diverged_deployments(
     let conn = pool.get()?;
     let mut stmt = conn.prepare(
         "SELECT sd.id, sd.skill_id, sd.project_id, sd.tool, sd.path, sd.checksum,
-                sd.status, sd.last_synced, sd.created_at, sd.updated_at
+                sd.status, sd.last_synced, sd.created_at, sd.updated_at, sd.mode
          FROM skill_deployments sd
          JOIN skills s ON sd.skill_id = s.id
          WHERE sd.checksum != s.checksum OR sd.checksum IS NULL OR sd.status != 'synced'
@@ -204,12 +232,316 @@ pub async fn get_diverged_deployments(
             last_synced: row.get(7)?,
             created_at: row.get(8)?,
             updated_at: row.get(9)?,
+            mode: row.get(10)?,
         })
     })?.collect::<Result<Vec<_>, _>>()?;
 
     Ok(deployments)
 }
 
+// ── 部署模式 ──
+//
+// copy：从 DB 导出一份独立副本（默认）。
+// symlink：部署目录是指向库目录 `{skills_lib_path}/{skill_name}` 的符号链接。
+// hardlink：部署目录是真实目录，其中每个文件都硬链接到库目录中的同名文件。
+// 链接部署共享库目录的内容，库更新后它们随之变化，这不算偏离。
+
+pub const DEPLOY_MODES: &[&str] = &["copy", "symlink", "hardlink"];
+
+fn normalize_deploy_mode(mode: Option<String>) -> Result<String, AppError> {
+    let mode = mode.unwrap_or_else(|| "copy".to_string());
+    if DEPLOY_MODES.contains(&mode.as_str()) {
+        Ok(mode)
+    } else {
+        Err(AppError::Validation(format!(
+            "不支持的部署模式: {}（可选: {}）",
+            mode,
+            DEPLOY_MODES.join(", ")
+        )))
+    }
+}
+
+/// 把 DB 中的 Skill 文件物化到库目录并返回该目录。
+/// 文件原地覆盖（保留 inode，硬链接部署能直接看到新内容），DB 中已删除的文件同步删除。
+pub fn materialize_library_dir(
+    conn: &Connection,
+    skill_id: &str,
+    skill_name: &str,
+) -> Result<PathBuf, AppError> {
+    let lib_dir = skill_dir_under(&get_skills_lib_path(conn), skill_name)?;
+    let files = db_list_files(conn, skill_id)?;
+    if files.is_empty() {
+        return Err(AppError::Validation(format!(
+            "Skill 在 DB 中没有文件，请重新导入。skill_id={}",
+            skill_id
+        )));
+    }
+
+    std::fs::create_dir_all(&lib_dir)?;
+    let mut keep: HashSet<PathBuf> = HashSet::new();
+    for rel_path in &files {
+        let content = db_read_file(conn, skill_id, rel_path)?;
+        let target = SkillPath::parse(rel_path)?.join_under(&lib_dir)?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if std::fs::read(&target).ok().as_deref() != Some(content.as_slice()) {
+            std::fs::write(&target, &content)?;
+        }
+        keep.insert(target);
+    }
+
+    for entry in WalkDir::new(&lib_dir)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+    {
+        let path = entry.path();
+        if entry.file_type().is_dir() {
+            if path != lib_dir {
+                // 非空目录删除失败是预期行为
+                let _ = std::fs::remove_dir(path);
+            }
+        } else if !keep.contains(path) {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    Ok(lib_dir)
+}
+
+/// 删除部署目标：符号链接只删链接本身，真实目录递归删除，不存在时什么都不做
+pub fn remove_deploy_target(dst: &Path) -> std::io::Result<()> {
+    if !is_staging_path(dst) {
+        write_journal::record_tree_removal(dst);
+    }
+    match std::fs::symlink_metadata(dst) {
+        Ok(meta) if meta.file_type().is_symlink() || meta.is_file() => {
+            std::fs::remove_file(dst).or_else(|_| std::fs::remove_dir(dst))
+        }
+        Ok(_) => std::fs::remove_dir_all(dst),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(unix)]
+fn symlink_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(src, dst)
+}
+
+#[cfg(windows)]
+fn symlink_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_dir(src, dst)
+}
+
+fn count_files(dir: &Path) -> u64 {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .count() as u64
+}
+
+/// 在空路径 dst 上建立指向库目录的链接，返回文件数
+fn link_into(lib_dir: &Path, dst: &Path, mode: &str) -> Result<u64, AppError> {
+    match mode {
+        "symlink" => {
+            symlink_dir(lib_dir, dst).map_err(|e| {
+                AppError::Validation(format!("创建符号链接失败: {} — {}", dst.display(), e))
+            })?;
+            Ok(count_files(lib_dir))
+        }
+        "hardlink" => {
+            std::fs::create_dir_all(dst)?;
+            let mut count = 0u64;
+            for entry in WalkDir::new(lib_dir).into_iter().filter_map(|e| e.ok()) {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                let rel = entry.path().strip_prefix(lib_dir).unwrap_or(entry.path());
+                let target = dst.join(rel);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::hard_link(entry.path(), &target).map_err(|e| {
+                    AppError::Validation(format!(
+                        "创建硬链接失败（库目录与部署目录需位于同一文件系统）: {} — {}",
+                        target.display(),
+                        e
+                    ))
+                })?;
+                count += 1;
+            }
+            Ok(count)
+        }
+        _ => Err(AppError::Internal(format!("不是链接部署模式: {}", mode))),
+    }
+}
+
+/// 按模式把 DB 内容写到 dst 旁的暂存目录并换入，返回 (待提交的替换, 文件数)。
+/// 调用方写好 DB 记录后再 `commit()`，旧目录在此之前一直保留。
+fn stage_deployment(
+    conn: &Connection,
+    skill_id: &str,
+    lib_dir: Option<&Path>,
+    dst: &Path,
+    mode: &str,
+) -> Result<(StagedDir, u64), AppError> {
+    let mut staged = StagedDir::prepare(dst)?;
+    let files = match lib_dir {
+        Some(lib_dir) if mode != "copy" => link_into(lib_dir, staged.path(), mode)?,
+        _ => db_export_to_dir(conn, skill_id, staged.path())? as u64,
+    };
+    staged.swap()?;
+    Ok((staged, files))
+}
+
+/// 重新物化库目录，并把该 Skill 的所有链接部署重新指向它、标记为已同步。
+/// 返回 (库目录, 文件数)
+pub fn refresh_linked_deployments(
+    conn: &Connection,
+    skill_id: &str,
+    skill_name: &str,
+) -> Result<(PathBuf, u64), AppError> {
+    let lib_dir = materialize_library_dir(conn, skill_id, skill_name)?;
+
+    let linked: Vec<(String, String, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, path, mode FROM skill_deployments WHERE skill_id = ?1 AND mode != 'copy'",
+        )?;
+        let rows = stmt
+            .query_map(params![skill_id], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    for (dep_id, path, mode) in &linked {
+        let dst = Path::new(path);
+        // 已指向库目录的符号链接无需重建
+        let staged = if mode == "symlink" && std::fs::read_link(dst).ok().as_deref() == Some(lib_dir.as_path()) {
+            None
+        } else {
+            Some(stage_deployment(conn, skill_id, Some(&lib_dir), dst, mode)?.0)
+        };
+        conn.execute(
+            "UPDATE skill_deployments SET checksum = ?1, status = 'synced',
+                    last_synced = datetime('now'), updated_at = datetime('now')
+             WHERE id = ?2",
+            params![compute_dir_checksum(dst), dep_id],
+        )?;
+        if let Some(staged) = staged {
+            staged.commit();
+        }
+        // 符号链接未重建时，目录内容随库目录变化，也要登记
+        write_journal::record_tree(dst);
+    }
+    info!(
+        "[deploy] 已刷新库目录及 {} 个链接部署: {}",
+        linked.len(),
+        lib_dir.display()
+    );
+
+    let count = count_files(&lib_dir);
+    Ok((lib_dir, count))
+}
+
+/// 新建部署：按模式暂存并换入 dst。链接模式会先刷新库目录（连带已有的链接部署）
+fn write_deployment(
+    conn: &Connection,
+    skill_id: &str,
+    skill_name: &str,
+    dst: &Path,
+    mode: &str,
+) -> Result<(StagedDir, u64), AppError> {
+    if mode == "copy" {
+        return stage_deployment(conn, skill_id, None, dst, mode);
+    }
+    let (lib_dir, _) = refresh_linked_deployments(conn, skill_id, skill_name)?;
+    stage_deployment(conn, skill_id, Some(&lib_dir), dst, mode)
+}
+
+/// 用 DB 中的当前内容重写一个已有部署并标记为已同步，返回 (文件数, 新 checksum)。
+/// 链接部署共享库目录，因此会连带刷新同一 Skill 的其他链接部署。
+pub fn redeploy_from_db(
+    conn: &Connection,
+    deployment_id: &str,
+) -> Result<(u64, Option<String>), AppError> {
+    let (skill_id, skill_name, deploy_path, mode): (String, String, String, String) = conn
+        .query_row(
+            "SELECT sd.skill_id, s.name, sd.path, sd.mode
+             FROM skill_deployments sd
+             JOIN skills s ON s.id = sd.skill_id
+             WHERE sd.id = ?1",
+            params![deployment_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(|_| AppError::NotFound(format!("部署记录不存在: {}", deployment_id)))?;
+
+    let dst = Path::new(&deploy_path);
+    if mode != "copy" {
+        let (_, files) = refresh_linked_deployments(conn, &skill_id, &skill_name)?;
+        return Ok((files, compute_dir_checksum(dst)));
+    }
+
+    let (staged, files) = stage_deployment(conn, &skill_id, None, dst, &mode)?;
+    let checksum = compute_dir_checksum(dst);
+    conn.execute(
+        "UPDATE skill_deployments SET checksum = ?1, status = 'synced',
+                last_synced = datetime('now'), updated_at = datetime('now')
+         WHERE id = ?2",
+        params![checksum, deployment_id],
+    )?;
+    staged.commit();
+
+    Ok((files, checksum))
+}
+
+/// 库内容更新后重写 Skill 的所有部署。上次同步后被本地改过（`plan_redeploy` 判为 conflict）
+/// 或有未处理 watcher 冲突的部署不覆盖，留给用户合并；链接部署共享库目录，其中一个被跳过时全部跳过。
+/// 返回 (已同步数, 跳过的部署 ID)
+pub fn redeploy_unmodified(conn: &Connection, skill_id: &str) -> Result<(usize, Vec<String>), AppError> {
+    let deployments: Vec<(String, String)> = {
+        let mut stmt = conn.prepare("SELECT id, mode FROM skill_deployments WHERE skill_id = ?1")?;
+        let rows = stmt
+            .query_map(params![skill_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    let mut skipped = Vec::new();
+    for (dep_id, _) in &deployments {
+        let open_conflict: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM change_events
+                           WHERE deployment_id = ?1 AND resolution = 'conflict' AND resolved_at IS NULL)",
+            params![dep_id],
+            |row| row.get(0),
+        )?;
+        if open_conflict || plan_redeploy(conn, dep_id)?.action == "conflict" {
+            skipped.push(dep_id.clone());
+        }
+    }
+    if deployments.iter().any(|(id, mode)| mode != "copy" && skipped.contains(id)) {
+        for (dep_id, mode) in &deployments {
+            if mode != "copy" && !skipped.contains(dep_id) {
+                skipped.push(dep_id.clone());
+            }
+        }
+    }
+
+    let mut synced = 0usize;
+    for (dep_id, _) in deployments.iter().filter(|(id, _)| !skipped.contains(id)) {
+        match redeploy_from_db(conn, dep_id) {
+            Ok(_) => synced += 1,
+            Err(e) => log::warn!("[redeploy] 同步部署失败 {}: {}", dep_id, e),
+        }
+    }
+    if !skipped.is_empty() {
+        info!("[redeploy] {} 个部署有本地修改，未覆盖: {:?}", skipped.len(), skipped);
+    }
+    Ok((synced, skipped))
+}
+
 // ── 文件操作命令 ──
 
 fn tool_skill_subdir(tool: &str) -> Option<&'static str> {
@@ -232,16 +564,17 @@ pub struct DeployConflict {
     pub library_checksum: Option<String>,
 }
 
-#[tauri::command]
-pub async fn deploy_skill_to_project(
+pub async fn deploy_skill_to_project_internal(
+    pool: &DbPool,
     skill_id: String,
     project_id: String,
     tool: String,
     force: Option<bool>,
-    pool: State<'_, DbPool>,
+    mode: Option<String>,
 ) -> Result<DeployResult, AppError> {
     let force = force.unwrap_or(false);
-    info!("[deploy_skill_to_project] skill={}, project={}, tool={}, force={}", skill_id, project_id, tool, force);
+    let mode = normalize_deploy_mode(mode)?;
+    info!("[deploy_skill_to_project] skill={}, project={}, tool={}, force={}, mode={}", skill_id, project_id, tool, force, mode);
 
     let tool_subdir = tool_skill_subdir(&tool)
         .ok_or_else(|| AppError::Validation(format!("不支持的工具: {}", tool)))?;
@@ -265,7 +598,7 @@ pub async fn deploy_skill_to_project(
     };
 
     // ── 计算目标路径：{project}/.cursor/skills/{skill_name} ──
-    let dst = Path::new(&project_path).join(tool_subdir).join(&skill_name);
+    let dst = skill_dir_under(&Path::new(&project_path).join(tool_subdir), &skill_name)?;
     let deploy_path = dst.to_string_lossy().to_string();
 
     // lib_checksum 从 DB 计算
@@ -274,20 +607,22 @@ pub async fn deploy_skill_to_project(
         compute_db_checksum(&conn, &skill_id)
     };
 
-    // 冲突检测：目标已存在且内容与源一致时跳过复制
-    if dst.exists() && !force {
+    // 冲突检测：目标已存在且内容与源一致时跳过复制（链接模式仍需建立链接）
+    if dst.exists() && !force && (mode == "copy" || lib_checksum != compute_dir_checksum(&dst)) {
         let existing_checksum = compute_dir_checksum(&dst);
         if lib_checksum == existing_checksum && lib_checksum.is_some() {
             info!("[deploy_skill_to_project] 目标已存在且内容一致，跳过复制");
             let deployment_id = Uuid::new_v4().to_string();
             let conn = pool.get()?;
+            // 已有记录时保留原部署方式：目标可能是指向库的链接，内容一致不代表它是复制出来的
             conn.execute(
-                "INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum, status, last_synced)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'synced', datetime('now'))
+                "INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum, status, last_synced, mode)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'synced', datetime('now'), 'copy')
                  ON CONFLICT(path) DO UPDATE SET
                     checksum = ?6, status = 'synced', last_synced = datetime('now'), updated_at = datetime('now')",
                 params![deployment_id, skill_id, project_id, tool, deploy_path, existing_checksum.clone()],
             )?;
+            update_project_lock(&conn, &project_path, &skill_id, &tool);
             return Ok(DeployResult {
                 deployment_id,
                 files_copied: 0,
@@ -315,17 +650,10 @@ pub async fn deploy_skill_to_project(
         }
     }
 
-    // ── 执行部署：从 DB skill_files 导出到目标目录 ──
-    if dst.exists() && force {
-        info!("[deploy_skill_to_project] 强制覆盖，清空: {}", dst.display());
-        std::fs::remove_dir_all(&dst)?;
-    }
-
-    info!("[deploy_skill_to_project] 从 DB 导出到: {}", dst.display());
-    let files_copied = {
-        let conn = pool.get()?;
-        db_export_to_dir(&conn, &skill_id, &dst)? as u64
-    };
+    // ── 执行部署：暂存后整体换入目标，旧目录保留到 DB 记录写好 ──
+    info!("[deploy_skill_to_project] 写入部署 ({}): {}", mode, dst.display());
+    let conn = pool.get()?;
+    let (staged, files_copied) = write_deployment(&conn, &skill_id, &skill_name, &dst, &mode)?;
     let checksum = compute_dir_checksum(&dst);
 
     info!("[deploy_skill_to_project] 复制完成: {} 个文件, checksum={:?}", files_copied, checksum);
@@ -333,15 +661,16 @@ pub async fn deploy_skill_to_project(
     let deployment_id = Uuid::new_v4().to_string();
 
     {
-        let conn = pool.get()?;
         conn.execute(
-            "INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum, status, last_synced)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'synced', datetime('now'))
+            "INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum, status, last_synced, mode)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 'synced', datetime('now'), ?7)
              ON CONFLICT(path) DO UPDATE SET
-                checksum = ?6, status = 'synced', last_synced = datetime('now'), updated_at = datetime('now')",
-            params![deployment_id, skill_id, project_id, tool, deploy_path, checksum],
+                checksum = ?6, status = 'synced', last_synced = datetime('now'), mode = ?7, updated_at = datetime('now')",
+            params![deployment_id, skill_id, project_id, tool, deploy_path, checksum, mode],
         )?;
     }
+    staged.commit();
+    update_project_lock(&conn, &project_path, &skill_id, &tool);
 
     Ok(DeployResult {
         deployment_id,
@@ -352,21 +681,44 @@ pub async fn deploy_skill_to_project(
     })
 }
 
+/// 项目部署成功后同步 skills.lock；lock 写失败不影响部署本身
+fn update_project_lock(conn: &Connection, project_path: &str, skill_id: &str, tool: &str) {
+    if let Err(e) = record_project_deployment(conn, Path::new(project_path), skill_id, tool) {
+        log::warn!("[deploy_skill_to_project] 更新 skills.lock 失败: {}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn deploy_skill_to_project(
+    skill_id: String,
+    project_id: String,
+    tool: String,
+    force: Option<bool>,
+    mode: Option<String>,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<DeployResult, AppError> {
+    let result = deploy_skill_to_project_internal(&pool, skill_id, project_id, tool, force, mode).await?;
+    watcher.resync(&pool);
+    Ok(result)
+}
+
 // ── deploy_skill_global (全局部署) ──
 
 fn global_tool_dir(tool: &str) -> Option<&'static str> {
     ALL_TOOLS.iter().find(|t| t.id == tool).map(|t| t.global_dir)
 }
 
-#[tauri::command]
-pub async fn deploy_skill_global(
+pub async fn deploy_skill_global_internal(
+    pool: &DbPool,
     skill_id: String,
     tool: String,
     force: Option<bool>,
-    pool: State<'_, DbPool>,
+    mode: Option<String>,
 ) -> Result<DeployResult, AppError> {
     let force = force.unwrap_or(false);
-    info!("[deploy_skill_global] skill={}, tool={}, force={}", skill_id, tool, force);
+    let mode = normalize_deploy_mode(mode)?;
+    info!("[deploy_skill_global] skill={}, tool={}, force={}, mode={}", skill_id, tool, force, mode);
 
     let global_subdir = global_tool_dir(&tool)
         .ok_or_else(|| AppError::Validation(format!("不支持的工具: {}", tool)))?;
@@ -383,22 +735,22 @@ pub async fn deploy_skill_global(
         ).map_err(|_| AppError::NotFound(format!("Skill 不存在: {}", skill_id)))?
     };
 
-    let dst = home.join(global_subdir).join(&skill_name);
+    let dst = skill_dir_under(&home.join(global_subdir), &skill_name)?;
     let deploy_path = dst.to_string_lossy().to_string();
     let lib_checksum = {
         let conn = pool.get()?;
         compute_db_checksum(&conn, &skill_id)
     };
 
-    if dst.exists() && !force {
+    if dst.exists() && !force && (mode == "copy" || lib_checksum != compute_dir_checksum(&dst)) {
         let existing_checksum = compute_dir_checksum(&dst);
         if lib_checksum == existing_checksum && lib_checksum.is_some() {
             info!("[deploy_skill_global] 目标已存在且内容一致，跳过复制");
             let deployment_id = Uuid::new_v4().to_string();
             let conn = pool.get()?;
             conn.execute(
-                "INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum, status, last_synced)
-                 VALUES (?1, ?2, NULL, ?3, ?4, ?5, 'synced', datetime('now'))
+                "INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum, status, last_synced, mode)
+                 VALUES (?1, ?2, NULL, ?3, ?4, ?5, 'synced', datetime('now'), 'copy')
                  ON CONFLICT(path) DO UPDATE SET
                     checksum = ?5, status = 'synced', last_synced = datetime('now'), updated_at = datetime('now')",
                 params![deployment_id, skill_id, tool, deploy_path, existing_checksum.clone()],
@@ -431,30 +783,23 @@ pub async fn deploy_skill_global(
         }
     }
 
-    if dst.exists() && force {
-        info!("[deploy_skill_global] 强制覆盖");
-        std::fs::remove_dir_all(&dst)?;
-    }
-
-    info!("[deploy_skill_global] 从 DB 导出到: {}", dst.display());
-    let files_copied = {
-        let conn = pool.get()?;
-        db_export_to_dir(&conn, &skill_id, &dst)? as u64
-    };
+    info!("[deploy_skill_global] 写入部署 ({}): {}", mode, dst.display());
+    let conn = pool.get()?;
+    let (staged, files_copied) = write_deployment(&conn, &skill_id, &skill_name, &dst, &mode)?;
     let checksum = compute_dir_checksum(&dst);
     info!("[deploy_skill_global] 完成: {} 个文件, checksum={:?}", files_copied, checksum);
 
     let deployment_id = Uuid::new_v4().to_string();
     {
-        let conn = pool.get()?;
         conn.execute(
-            "INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum, status, last_synced)
-             VALUES (?1, ?2, NULL, ?3, ?4, ?5, 'synced', datetime('now'))
+            "INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum, status, last_synced, mode)
+             VALUES (?1, ?2, NULL, ?3, ?4, ?5, 'synced', datetime('now'), ?6)
              ON CONFLICT(path) DO UPDATE SET
-                checksum = ?5, status = 'synced', last_synced = datetime('now'), updated_at = datetime('now')",
-            params![deployment_id, skill_id, tool, deploy_path, checksum],
+                checksum = ?5, status = 'synced', last_synced = datetime('now'), mode = ?6, updated_at = datetime('now')",
+            params![deployment_id, skill_id, tool, deploy_path, checksum, mode],
         )?;
     }
+    staged.commit();
 
     Ok(DeployResult {
         deployment_id,
@@ -465,6 +810,20 @@ pub async fn deploy_skill_global(
     })
 }
 
+#[tauri::command]
+pub async fn deploy_skill_global(
+    skill_id: String,
+    tool: String,
+    force: Option<bool>,
+    mode: Option<String>,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<DeployResult, AppError> {
+    let result = deploy_skill_global_internal(&pool, skill_id, tool, force, mode).await?;
+    watcher.resync(&pool);
+    Ok(result)
+}
+
 #[derive(serde::Serialize)]
 pub struct SyncResult {
     pub files_copied: u64,
@@ -472,54 +831,26 @@ pub struct SyncResult {
     pub new_checksum: Option<String>,
 }
 
-#[tauri::command]
-pub async fn sync_deployment(
+pub async fn sync_deployment_internal(
+    pool: &DbPool,
     deployment_id: String,
-    pool: State<'_, DbPool>,
 ) -> Result<SyncResult, AppError> {
     info!("[sync_deployment] deployment_id={}", deployment_id);
 
-    let (skill_id, deploy_path, old_checksum) = {
-        let conn = pool.get()?;
-        conn.query_row(
-            "SELECT sd.skill_id, sd.path, sd.checksum
-             FROM skill_deployments sd
-             WHERE sd.id = ?1",
+    let conn = pool.get()?;
+    let old_checksum: Option<String> = conn
+        .query_row(
+            "SELECT checksum FROM skill_deployments WHERE id = ?1",
             params![deployment_id],
-            |row| Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, Option<String>>(2)?,
-            )),
-        ).map_err(|_| AppError::NotFound(format!("部署记录不存在: {}", deployment_id)))?
-    };
-
-    let dst = Path::new(&deploy_path);
-
-    // 同步前清空目标，再从 DB 重新写出
-    if dst.exists() {
-        std::fs::remove_dir_all(dst)?;
-    }
+            |row| row.get(0),
+        )
+        .map_err(|_| AppError::NotFound(format!("部署记录不存在: {}", deployment_id)))?;
 
-    info!("[sync_deployment] 从 DB 同步到: {}", dst.display());
-    let files_copied = {
-        let conn = pool.get()?;
-        db_export_to_dir(&conn, &skill_id, dst)? as u64
-    };
-    let new_checksum = compute_dir_checksum(dst);
+    // 复制部署先清空目标再从 DB 重新写出；链接部署刷新库目录后重新链接
+    let (files_copied, new_checksum) = redeploy_from_db(&conn, &deployment_id)?;
 
     info!("[sync_deployment] 同步完成: {} 个文件, checksum={:?}", files_copied, new_checksum);
 
-    {
-        let conn = pool.get()?;
-        conn.execute(
-            "UPDATE skill_deployments SET checksum = ?1, status = 'synced',
-                    last_synced = datetime('now'), updated_at = datetime('now')
-             WHERE id = ?2",
-            params![new_checksum, deployment_id],
-        )?;
-    }
-
     Ok(SyncResult {
         files_copied,
         old_checksum,
@@ -527,6 +858,14 @@ pub async fn sync_deployment(
     })
 }
 
+#[tauri::command]
+pub async fn sync_deployment(
+    deployment_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<SyncResult, AppError> {
+    sync_deployment_internal(&pool, deployment_id).await
+}
+
 #[derive(serde::Serialize)]
 pub struct ConsistencyReport {
     pub total_deployments: usize,
@@ -547,17 +886,19 @@ pub struct ConsistencyDetail {
     pub deploy_checksum: Option<String>,
 }
 
-#[tauri::command]
-pub async fn check_deployment_consistency(
-    pool: State<'_, DbPool>,
+/// (deployment_id, skill_id 或 skill_name, tool, path, 部署 checksum, mode, 库 checksum)
+type DeploymentCheckRow = (String, String, String, String, Option<String>, String, Option<String>);
+
+pub async fn check_deployment_consistency_internal(
+    pool: &DbPool,
 ) -> Result<ConsistencyReport, AppError> {
     let t0 = std::time::Instant::now();
     info!("[check_deployment_consistency] 开始一致性检查");
 
-    let rows: Vec<(String, String, String, String, Option<String>)> = {
+    let rows: Vec<DeploymentCheckRow> = {
         let conn = pool.get()?;
         let mut stmt = conn.prepare(
-            "SELECT sd.id, s.name, sd.tool, sd.path, sd.checksum
+            "SELECT sd.id, s.name, sd.tool, sd.path, sd.checksum, sd.mode, s.checksum
              FROM skill_deployments sd
              JOIN skills s ON sd.skill_id = s.id"
         )?;
@@ -568,6 +909,8 @@ pub async fn check_deployment_consistency(
                 row.get::<_, String>(2)?,
                 row.get::<_, String>(3)?,
                 row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, Option<String>>(6)?,
             ))
         })?.collect::<Result<Vec<_>, _>>()?;
         result
@@ -583,7 +926,7 @@ pub async fn check_deployment_consistency(
     let mut details = Vec::new();
     let mut updates: Vec<(String, String)> = Vec::new();
 
-    for (idx, (dep_id, skill_name, tool, deploy_path, db_checksum)) in rows.iter().enumerate() {
+    for (idx, (dep_id, skill_name, tool, deploy_path, db_checksum, mode, skill_checksum)) in rows.iter().enumerate() {
         let t_dep = std::time::Instant::now();
         let deploy_dir = PathBuf::from(deploy_path);
         let exists = deploy_dir.exists();
@@ -604,7 +947,10 @@ pub async fn check_deployment_consistency(
         let status = if !exists {
             missing += 1;
             "missing"
-        } else if db_checksum == &deploy_checksum {
+        } else if db_checksum == &deploy_checksum
+            || (mode != "copy" && skill_checksum == &deploy_checksum)
+        {
+            // 链接部署与库共享内容，跟上了库的更新也算同步
             synced += 1;
             "synced"
         } else {
@@ -661,6 +1007,13 @@ pub async fn check_deployment_consistency(
     })
 }
 
+#[tauri::command]
+pub async fn check_deployment_consistency(
+    pool: State<'_, DbPool>,
+) -> Result<ConsistencyReport, AppError> {
+    check_deployment_consistency_internal(&pool).await
+}
+
 // ── 启动时全量对账 ──
 
 #[derive(serde::Serialize)]
@@ -670,19 +1023,22 @@ pub struct ReconcileReport {
     pub diverged_detected: usize,
     pub untracked_found: usize,
     pub change_events_created: usize,
+    /// dry_run 时返回的预演计划，此时不写入状态和变更事件
+    pub plan: Option<DeploymentPlan>,
 }
 
-#[tauri::command]
-pub async fn reconcile_all_deployments(
-    pool: State<'_, DbPool>,
+pub async fn reconcile_all_deployments_internal(
+    pool: &DbPool,
+    dry_run: Option<bool>,
 ) -> Result<ReconcileReport, AppError> {
-    info!("[reconcile] 开始全量对账...");
+    let dry_run = dry_run.unwrap_or(false);
+    info!("[reconcile] 开始全量对账... dry_run={}", dry_run);
 
     // 1. 读取所有部署记录和对应 Skill 信息
-    let deploy_rows: Vec<(String, String, String, String, Option<String>)> = {
+    let deploy_rows: Vec<DeploymentCheckRow> = {
         let conn = pool.get()?;
         let mut stmt = conn.prepare(
-            "SELECT sd.id, sd.skill_id, sd.tool, sd.path, sd.checksum
+            "SELECT sd.id, sd.skill_id, sd.tool, sd.path, sd.checksum, sd.mode, s.checksum
              FROM skill_deployments sd
              JOIN skills s ON sd.skill_id = s.id"
         )?;
@@ -693,6 +1049,8 @@ pub async fn reconcile_all_deployments(
                 row.get::<_, String>(2)?,
                 row.get::<_, String>(3)?,
                 row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, Option<String>>(6)?,
             ))
         })?.collect::<Result<Vec<_>, _>>()?;
         result
@@ -701,40 +1059,43 @@ pub async fn reconcile_all_deployments(
     let deployments_checked = deploy_rows.len();
     let mut missing_detected = 0usize;
     let mut diverged_detected = 0usize;
-    let mut events_to_create: Vec<(String, String, String, Option<String>, Option<String>)> = Vec::new();
-    let mut status_updates: Vec<(String, String)> = Vec::new();
-
-    for (dep_id, skill_id, _tool, deploy_path, db_checksum) in &deploy_rows {
-        let _ = skill_id; // skill_id 在回写逻辑中使用
-        let deploy_dir = Path::new(deploy_path);
-
-        if !deploy_dir.exists() {
-            missing_detected += 1;
-            status_updates.push((dep_id.clone(), "missing".to_string()));
-            events_to_create.push((
-                dep_id.clone(),
-                "deleted".to_string(),
-                skill_id.clone(),
-                db_checksum.clone(),
-                None,
-            ));
-            info!("[reconcile] 部署缺失: {} (路径: {})", dep_id, deploy_path);
-        } else {
-            let current_checksum = compute_dir_checksum(deploy_dir);
-            if db_checksum != &current_checksum {
+    // 每个部署与未跟踪目录的结论；预演时原样返回，执行时交给 apply_reconcile_actions
+    let mut plan = DeploymentPlan::new(PLAN_RECONCILE, None);
+
+    {
+        let conn = pool.get()?;
+        for (dep_id, skill_id, _tool, deploy_path, db_checksum, mode, skill_checksum) in &deploy_rows {
+            let deploy_dir = Path::new(deploy_path);
+            let current_checksum = if deploy_dir.exists() { compute_dir_checksum(deploy_dir) } else { None };
+
+            let status = if !deploy_dir.exists() {
+                missing_detected += 1;
+                info!("[reconcile] 部署缺失: {} (路径: {})", dep_id, deploy_path);
+                "missing"
+            } else if mode != "copy" && db_checksum != &current_checksum && skill_checksum == &current_checksum {
+                // 链接部署随库一起更新，不是偏离，只需跟进记录的 checksum
+                "synced"
+            } else if db_checksum != &current_checksum {
                 diverged_detected += 1;
-                status_updates.push((dep_id.clone(), "diverged".to_string()));
-                events_to_create.push((
-                    dep_id.clone(),
-                    "modified".to_string(),
-                    skill_id.clone(),
-                    db_checksum.clone(),
-                    current_checksum.clone(),
-                ));
                 info!("[reconcile] 部署偏离: {} (db={:?}, disk={:?})", dep_id, db_checksum, current_checksum);
+                "diverged"
             } else {
-                status_updates.push((dep_id.clone(), "synced".to_string()));
-            }
+                "synced"
+            };
+            let files = if dry_run && status == "diverged" {
+                diff_dir_against_db(&conn, skill_id, deploy_dir)?
+            } else {
+                Vec::new()
+            };
+            plan.actions.push(PlanAction {
+                deployment_id: Some(dep_id.clone()),
+                skill_id: Some(skill_id.clone()),
+                path: deploy_path.clone(),
+                action: status.to_string(),
+                disk_checksum: current_checksum,
+                library_checksum: compute_db_checksum(&conn, skill_id),
+                files,
+            });
         }
     }
 
@@ -749,7 +1110,7 @@ pub async fn reconcile_all_deployments(
     };
 
     let mut untracked_found = 0usize;
-    let tracked_paths: std::collections::HashSet<String> = deploy_rows.iter().map(|(_, _, _, p, _)| p.clone()).collect();
+    let tracked_paths: std::collections::HashSet<String> = deploy_rows.iter().map(|(_, _, _, p, ..)| p.clone()).collect();
 
     for (project_id, project_path) in &project_rows {
         for t in ALL_TOOLS {
@@ -761,7 +1122,7 @@ pub async fn reconcile_all_deployments(
             if let Ok(entries) = std::fs::read_dir(&skill_base) {
                 for entry in entries.flatten() {
                     let path = entry.path();
-                    if path.is_dir() {
+                    if path.is_dir() && !is_staging_path(&path) {
                         let path_str = path.to_string_lossy().to_string();
                         if !tracked_paths.contains(&path_str) {
                             untracked_found += 1;
@@ -769,13 +1130,15 @@ pub async fn reconcile_all_deployments(
                                 .map(|n| n.to_string_lossy().to_string())
                                 .unwrap_or_default();
                             info!("[reconcile] 未跟踪 Skill: {} (项目: {}, 工具: {})", skill_name, project_id, tool);
-                            events_to_create.push((
-                                String::new(),
-                                "created".to_string(),
-                                format!("{}:{}:{}", project_id, tool, skill_name),
-                                None,
-                                compute_dir_checksum(&path),
-                            ));
+                            plan.actions.push(PlanAction {
+                                deployment_id: None,
+                                skill_id: None,
+                                disk_checksum: compute_dir_checksum(&path),
+                                path: path_str,
+                                action: "untracked".to_string(),
+                                library_checksum: None,
+                                files: Vec::new(),
+                            });
                         }
                     }
                 }
@@ -783,49 +1146,84 @@ pub async fn reconcile_all_deployments(
         }
     }
 
-    // 3. 批量写入数据库
-    // 只统计有真实 deployment_id 的事件（未跟踪 Skill 的 dep_id 为空，会跳过）
-    let change_events_created = events_to_create.iter().filter(|(dep_id, ..)| !dep_id.is_empty()).count();
-    {
-        let conn = pool.get()?;
-        let tx = conn.unchecked_transaction()?;
-
-        for (dep_id, status) in &status_updates {
-            tx.execute(
-                "UPDATE skill_deployments SET status = ?1, updated_at = datetime('now') WHERE id = ?2",
-                params![status, dep_id],
-            )?;
-        }
-
-        for (dep_id, event_type, _ref_id, old_cs, new_cs) in &events_to_create {
-            // 未跟踪的 Skill（dep_id 为空）没有对应的 skill_deployments 记录，
-            // 无法满足 change_events.deployment_id 的外键约束，跳过
-            if dep_id.is_empty() {
-                continue;
-            }
-            let event_id = Uuid::new_v4().to_string();
-            tx.execute(
-                "INSERT INTO change_events (id, deployment_id, event_type, old_checksum, new_checksum, resolution)
-                 VALUES (?1, ?2, ?3, ?4, ?5, 'pending')",
-                params![event_id, dep_id, event_type, old_cs, new_cs],
-            )?;
-        }
+    // 3. 预演直接返回计划，否则写入数据库
+    let change_events_created = if dry_run {
+        plan.actions
+            .iter()
+            .filter(|a| a.action == "missing" || a.action == "diverged")
+            .count()
+    } else {
+        apply_reconcile_actions(&*pool.get()?, &plan.actions)?
+    };
 
-        tx.commit()?;
+    if dry_run {
+        info!("[reconcile] 预演完成: {} 个路径", plan.actions.len());
+    } else {
+        info!("[reconcile] 对账完成: {} 已检查, {} 缺失, {} 偏离, {} 未跟踪, {} 事件已创建",
+            deployments_checked, missing_detected, diverged_detected, untracked_found, change_events_created);
     }
 
-    info!("[reconcile] 对账完成: {} 已检查, {} 缺失, {} 偏离, {} 未跟踪, {} 事件已创建",
-        deployments_checked, missing_detected, diverged_detected, untracked_found, change_events_created);
-
     Ok(ReconcileReport {
         deployments_checked,
         missing_detected,
         diverged_detected,
         untracked_found,
         change_events_created,
+        plan: dry_run.then_some(plan),
     })
 }
 
+/// 把对账结论写入 DB：更新部署状态，缺失/偏离的部署各记录一个待处理变更事件。
+/// 未跟踪目录没有部署记录，不写入。返回创建的事件数
+pub(crate) fn apply_reconcile_actions(conn: &Connection, actions: &[PlanAction]) -> Result<usize, AppError> {
+    let tx = conn.unchecked_transaction()?;
+    let mut events = 0usize;
+    for action in actions {
+        let Some(dep_id) = &action.deployment_id else {
+            continue;
+        };
+        let recorded: Option<String> = tx.query_row(
+            "SELECT checksum FROM skill_deployments WHERE id = ?1",
+            params![dep_id],
+            |row| row.get(0),
+        )?;
+        tx.execute(
+            "UPDATE skill_deployments SET status = ?1, updated_at = datetime('now') WHERE id = ?2",
+            params![action.action, dep_id],
+        )?;
+        let event_type = match action.action.as_str() {
+            "missing" => "deleted",
+            "diverged" => "modified",
+            _ => {
+                // 链接部署随库变化：记录的 checksum 跟进磁盘
+                if action.disk_checksum != recorded {
+                    tx.execute(
+                        "UPDATE skill_deployments SET checksum = ?1, last_synced = datetime('now') WHERE id = ?2",
+                        params![action.disk_checksum, dep_id],
+                    )?;
+                }
+                continue;
+            }
+        };
+        tx.execute(
+            "INSERT INTO change_events (id, deployment_id, event_type, old_checksum, new_checksum, resolution)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'pending')",
+            params![Uuid::new_v4().to_string(), dep_id, event_type, recorded, action.disk_checksum],
+        )?;
+        events += 1;
+    }
+    tx.commit()?;
+    Ok(events)
+}
+
+#[tauri::command]
+pub async fn reconcile_all_deployments(
+    dry_run: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<ReconcileReport, AppError> {
+    reconcile_all_deployments_internal(&pool, dry_run).await
+}
+
 // ── 部署→库 回写 ──
 
 #[derive(serde::Serialize)]
@@ -906,7 +1304,7 @@ pub async fn update_library_from_deployment(
     {
         let conn = pool.get()?;
         // 先清空旧 DB 文件
-        conn.execute("DELETE FROM skill_files WHERE skill_id = ?1", params![skill_id])?;
+        db_clear_files(&conn, &skill_id)?;
         let files_imported = db_import_from_dir(&conn, &skill_id, deploy_dir)?;
         info!("[update_library_from_deployment] 已导入 {} 个文件到 DB", files_imported);
     }
@@ -954,20 +1352,11 @@ pub async fn update_library_from_deployment(
         };
 
         for (dep_id, dep_path) in &other_deploys {
-            let dst = Path::new(dep_path);
-            if dst.exists() {
-                let _ = std::fs::remove_dir_all(dst);
-            }
             let conn = pool.get()?;
-            let _ = db_export_to_dir(&conn, &skill_id, dst);
-            let dep_checksum = compute_dir_checksum(dst);
-
-            conn.execute(
-                "UPDATE skill_deployments SET checksum = ?1, status = 'synced',
-                        last_synced = datetime('now'), updated_at = datetime('now')
-                 WHERE id = ?2",
-                params![dep_checksum, dep_id],
-            )?;
+            if let Err(e) = redeploy_from_db(&conn, dep_id) {
+                info!("[update_library_from_deployment] 同步部署失败 {}: {}", dep_path, e);
+                continue;
+            }
             other_deployments_synced += 1;
         }
         info!("[update_library_from_deployment] 已同步 {} 个其他部署", other_deployments_synced);
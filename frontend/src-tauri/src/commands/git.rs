@@ -2,12 +2,15 @@ use log::info;
 use rusqlite::params;
 use serde::Serialize;
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use tauri::State;
+use tauri::{AppHandle, State};
 use uuid::Uuid;
 
-use super::skill_files::{compute_db_checksum, db_import_from_dir, has_db_files};
-use super::utils::{compute_dir_checksum, copy_dir_recursive};
+use super::credentials::{self, GitCredentialInput};
+use super::git_engine::{self, GitError, GitOperation};
+use super::git_workspace::{self, GitWorkspace};
+use super::skill_files::{compute_db_checksum, db_clear_files, db_import_from_dir, has_db_files};
+use super::skill_path::skill_dir_under;
+use super::utils::{compute_dir_checksum, copy_dir_recursive, get_skills_lib_path};
 use crate::db::DbPool;
 use crate::error::AppError;
 
@@ -52,51 +55,16 @@ pub struct GitImportResult {
     pub message: String,
 }
 
-// ── Helper: 运行 git 命令 ──
-
-fn run_git(args: &[&str], cwd: &Path) -> Result<String, AppError> {
-    let output = Command::new("git")
-        .args(args)
-        .current_dir(cwd)
-        .output()
-        .map_err(|e| AppError::Internal(format!("git 命令执行失败: {}", e)))?;
-
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
-        Err(AppError::Internal(format!(
-            "git {} 失败: {}",
-            args.join(" "),
-            stderr
-        )))
-    }
-}
-
-fn run_git_allow_fail(args: &[&str], cwd: &Path) -> (bool, String) {
-    match Command::new("git").args(args).current_dir(cwd).output() {
-        Ok(output) => {
-            let out = if output.status.success() {
-                String::from_utf8_lossy(&output.stdout).trim().to_string()
-            } else {
-                String::from_utf8_lossy(&output.stderr).trim().to_string()
-            };
-            (output.status.success(), out)
-        }
-        Err(e) => (false, format!("执行失败: {}", e)),
-    }
-}
-
 // ── Helper: 解析 SKILL.md frontmatter ──
 
-fn parse_skill_frontmatter(content: &str) -> (Option<String>, Option<String>, Option<String>) {
+pub(crate) fn parse_skill_frontmatter(content: &str) -> (Option<String>, Option<String>, Option<String>) {
     let mut name = None;
     let mut description = None;
     let mut version = None;
 
-    if content.starts_with("---") {
-        if let Some(end) = content[3..].find("---") {
-            let frontmatter = &content[3..3 + end];
+    if let Some(rest) = content.strip_prefix("---") {
+        if let Some(end) = rest.find("---") {
+            let frontmatter = &rest[..end];
             for line in frontmatter.lines() {
                 let line = line.trim();
                 if let Some(val) = line.strip_prefix("name:") {
@@ -113,128 +81,80 @@ fn parse_skill_frontmatter(content: &str) -> (Option<String>, Option<String>, Op
     (name, description, version)
 }
 
-// ── Helper: 获取 skills 库路径 ──
-
-fn get_skills_lib_path(pool: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> Result<PathBuf, AppError> {
-    let conn = pool.get()?;
-    let path: Option<String> = conn
-        .query_row(
-            "SELECT value FROM app_settings WHERE key = 'skills_lib_path'",
-            [],
-            |row| row.get(0),
-        )
-        .unwrap_or(None);
-
-    let raw = path
-        .map(|p| {
-            let p = p.trim_matches('"').to_string();
-            if p.starts_with("~/") {
-                if let Some(home) = dirs::home_dir() {
-                    return home.join(&p[2..]).to_string_lossy().to_string();
-                }
-            }
-            p
-        })
-        .unwrap_or_else(|| {
-            dirs::home_dir()
-                .unwrap_or_default()
-                .join(".skills-manager/skills")
-                .to_string_lossy()
-                .to_string()
-        });
-
-    Ok(PathBuf::from(raw))
-}
-
 // ── 1. test_git_connection ──
 
-#[tauri::command]
-pub async fn test_git_connection(
+/// credential 为空时使用该地址已保存的凭据
+pub async fn test_git_connection_internal(
+    pool: &DbPool,
     remote_url: String,
     auth_type: String,
+    credential: Option<GitCredentialInput>,
 ) -> Result<GitTestResult, AppError> {
     info!(
         "[test_git_connection] 测试连接: url={}, auth={}",
-        remote_url, auth_type
+        git_engine::redact_url(&remote_url),
+        auth_type
     );
 
-    let temp_dir = std::env::temp_dir().join("skills-manager-git-test");
-    std::fs::create_dir_all(&temp_dir)
-        .map_err(|e| AppError::Internal(format!("创建临时目录失败: {}", e)))?;
-
-    let (success, msg) = run_git_allow_fail(&["ls-remote", "--exit-code", &remote_url], &temp_dir);
-
-    let _ = std::fs::remove_dir_all(&temp_dir);
-
-    if success {
-        Ok(GitTestResult {
+    let op = GitOperation::new("ls_remote");
+    match credential {
+        Some(input) => op.set_credential(credentials::credential_from(&auth_type, input)?),
+        None if auth_type != "none" => op.set_credential(credentials::load_for_url(&*pool.get()?, &remote_url)?),
+        None => {}
+    }
+    let url = remote_url.clone();
+    match git_engine::blocking(move || Ok(git_engine::check_remote(&url, &op)?)).await {
+        Ok(_) => Ok(GitTestResult {
             success: true,
             message: "连接成功".to_string(),
-        })
-    } else {
-        Ok(GitTestResult {
+        }),
+        Err(e) => Ok(GitTestResult {
             success: false,
-            message: format!("连接失败: {}", msg),
-        })
+            message: format!("连接失败: {}", e),
+        }),
     }
 }
 
+#[tauri::command]
+pub async fn test_git_connection(
+    remote_url: String,
+    auth_type: String,
+    credential: Option<GitCredentialInput>,
+    pool: State<'_, DbPool>,
+) -> Result<GitTestResult, AppError> {
+    test_git_connection_internal(&pool, remote_url, auth_type, credential).await
+}
+
 // ── 2. export_skills_to_git ──
 
-#[tauri::command]
-pub async fn export_skills_to_git(
+/// 导出写入仓库的顶层条目；仓库中的其他文件保持远程的版本
+const EXPORTED_PATHS: &[&str] = &["skills", "README.md"];
+
+pub async fn export_skills_to_git_internal(
+    pool: &DbPool,
     config_id: String,
-    pool: State<'_, DbPool>,
+    op: &GitOperation,
 ) -> Result<GitExportResult, AppError> {
     info!("[export_skills_to_git] 开始导出, config_id={}", config_id);
 
-    let conn = pool.get()?;
+    // 配置的工作副本，已与远程分支对齐；持有期间同一配置的其他 git 操作排队
+    let ws = git_workspace::open(pool, &config_id, op).await?;
+    let (pool, op) = (pool.clone(), op.clone());
+    git_engine::blocking(move || export_to_workspace(&pool, &ws, &op)).await
+}
 
-    // 查询 Git 配置
-    let (remote_url, branch): (String, String) = conn.query_row(
-        "SELECT remote_url, branch FROM git_export_config WHERE id = ?1",
-        params![config_id],
-        |row| Ok((row.get(0)?, row.get(1)?)),
-    )?;
+/// 把库写入工作副本的 skills/ 并提交推送
+fn export_to_workspace(pool: &DbPool, ws: &GitWorkspace, op: &GitOperation) -> Result<GitExportResult, AppError> {
+    let (config_id, remote_url, branch, export_dir, repo) =
+        (&ws.config_id, &ws.remote_url, &ws.branch, &ws.dir, &ws.repo);
 
     info!(
         "[export_skills_to_git] remote={}, branch={}",
-        remote_url, branch
-    );
-
-    // 获取 skills 库路径（保留供将来降级使用）
-    let _skills_lib = get_skills_lib_path(&pool)?;
-
-    // 准备导出目录
-    let export_dir = std::env::temp_dir().join("skills-manager-export");
-    if export_dir.exists() {
-        std::fs::remove_dir_all(&export_dir)
-            .map_err(|e| AppError::Internal(format!("清理导出目录失败: {}", e)))?;
-    }
-
-    // 尝试 clone 现有仓库（如果存在）
-    let (clone_ok, _) = run_git_allow_fail(
-        &[
-            "clone",
-            "--branch",
-            &branch,
-            "--single-branch",
-            "--depth",
-            "1",
-            &remote_url,
-            export_dir.to_str().unwrap_or(""),
-        ],
-        &std::env::temp_dir(),
+        git_engine::redact_url(remote_url),
+        branch
     );
 
-    if !clone_ok {
-        // 仓库不存在或空仓库，初始化新仓库
-        std::fs::create_dir_all(&export_dir)
-            .map_err(|e| AppError::Internal(format!("创建导出目录失败: {}", e)))?;
-        run_git(&["init"], &export_dir)?;
-        run_git(&["remote", "add", "origin", &remote_url], &export_dir)?;
-        run_git(&["checkout", "-b", &branch], &export_dir)?;
-    }
+    let conn = pool.get()?;
 
     // 清理导出目录中的 skills/ 文件夹（保留 .git）
     let export_skills_dir = export_dir.join("skills");
@@ -282,6 +202,9 @@ pub async fn export_skills_to_git(
         info!("[export_skills_to_git] ✅ 所有部署状态正常，无偏离");
     }
 
+    // 导出内容对应的库指纹，导出期间库再有变化时 on-change 会再导出一次
+    let fingerprint = super::auto_export::library_fingerprint(&conn)?;
+
     // 查询所有 Skill，从 DB skill_files 导出到 git export 目录
     let mut stmt = conn.prepare(
         "SELECT id, name, description, version FROM skills ORDER BY name",
@@ -299,7 +222,13 @@ pub async fn export_skills_to_git(
 
     let mut exported = 0;
     for (id, name, _desc, _ver) in &skills {
-        let dest = export_skills_dir.join(name);
+        let dest = match skill_dir_under(&export_skills_dir, name) {
+            Ok(dest) => dest,
+            Err(e) => {
+                info!("[export_skills_to_git] 跳过 {}: {}", name, e);
+                continue;
+            }
+        };
 
         if has_db_files(&conn, id) {
             // 优先从 DB 导出
@@ -330,75 +259,55 @@ pub async fn export_skills_to_git(
         }
     }
 
-    // 生成 README.md
-    let mut readme = String::from("# Skills Manager Backup\n\n");
-    readme.push_str(&format!(
-        "导出时间: {}\n\n",
-        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
-    ));
-    readme.push_str("| 名称 | 版本 | 描述 |\n|------|------|------|\n");
-    for (_id, name, desc, ver) in &skills {
-        readme.push_str(&format!(
-            "| {} | {} | {} |\n",
-            name,
-            ver.as_deref().unwrap_or("-"),
-            desc.as_deref().unwrap_or("-")
-        ));
-    }
-    std::fs::write(export_dir.join("README.md"), &readme)
-        .map_err(|e| AppError::Internal(format!("写入 README.md 失败: {}", e)))?;
+    write_readme(&conn, export_dir)?;
 
     // Git add + commit + push
-    run_git(&["add", "-A"], &export_dir)?;
-
-    let (has_changes, _) =
-        run_git_allow_fail(&["diff", "--cached", "--quiet"], &export_dir);
-    
-    let commit_hash = if !has_changes {
-        // has_changes=false 意味着 diff --cached 有差异（exit code != 0）
-        let msg = format!(
-            "backup: {} skills exported at {}",
-            exported,
-            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
-        );
-        run_git(&["commit", "-m", &msg], &export_dir)?;
-        let hash = run_git(&["rev-parse", "HEAD"], &export_dir)?;
-        Some(hash)
-    } else {
-        None
-    };
-
-    // Push
-    let (push_ok, push_msg) = run_git_allow_fail(
-        &["push", "-u", "origin", &branch],
-        &export_dir,
+    let msg = format!(
+        "backup: {} skills exported at {}",
+        exported,
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
     );
-
-    if !push_ok {
-        info!(
-            "[export_skills_to_git] push 失败，尝试 pull --rebase: {}",
-            push_msg
-        );
-        run_git(&["pull", "--rebase", "origin", &branch], &export_dir)?;
-        run_git(&["push", "-u", "origin", &branch], &export_dir)?;
+    let mut commit_hash = git_engine::commit_all(repo, &msg)?.map(|id| id.to_string());
+
+    // 远程在 fetch 之后有了新提交：在远程最新提交之上重新写入 skills/ 与 README（以本地库为准），
+    // 远程同时加入的其他文件保留
+    match git_engine::push(repo, branch, op) {
+        Ok(()) => {}
+        Err(GitError::Rejected(reason)) => {
+            info!("[export_skills_to_git] push 被拒绝，接在远程最新提交之后重试: {}", reason);
+            ws.fetch(op)?;
+            let remote_head = ws
+                .remote_head()
+                .ok_or_else(|| AppError::Internal("push 被拒绝但远程分支不存在".to_string()))?;
+            let local_head = git_engine::head_commit(repo)
+                .ok_or_else(|| AppError::Internal("导出仓库没有提交".to_string()))?;
+            if let Some(id) = git_engine::recommit_onto(repo, branch, local_head, remote_head, EXPORTED_PATHS)? {
+                commit_hash = Some(id.to_string());
+                git_engine::push(repo, branch, op)?;
+            }
+        }
+        Err(e) => return Err(e.into()),
     }
 
-    // 更新 last_push_at
+    // 更新 last_push_at；远程此时与库一致，作为下次双向同步的 base
+    conn.execute(
+        "UPDATE git_export_config SET last_push_at = datetime('now'), last_sync_commit = ?2,
+                export_fingerprint = ?3, updated_at = datetime('now')
+         WHERE id = ?1",
+        params![config_id, git_engine::head_commit(repo).map(|id| id.to_string()), fingerprint],
+    )?;
     conn.execute(
-        "UPDATE git_export_config SET last_push_at = datetime('now'), updated_at = datetime('now') WHERE id = ?1",
+        "DELETE FROM git_sync_conflicts WHERE config_id = ?1",
         params![config_id],
     )?;
 
     // 写入 sync_history
     conn.execute(
         "INSERT INTO sync_history (id, skill_id, action, status, created_at)
-         VALUES (?1, 'all', 'export', 'success', datetime('now'))",
+         VALUES (?1, NULL, 'export', 'success', datetime('now'))",
         params![Uuid::new_v4().to_string()],
     )?;
 
-    // 清理导出目录
-    let _ = std::fs::remove_dir_all(&export_dir);
-
     info!(
         "[export_skills_to_git] 导出完成: {} 个 Skill",
         exported
@@ -407,10 +316,13 @@ pub async fn export_skills_to_git(
     let msg = if diverged_count > 0 {
         format!(
             "成功导出 {} 个 Skill 到 {}（⚠️ {} 个部署存在偏离/缺失，涉及: {}）",
-            exported, remote_url, diverged_count, diverged_skill_names.join(", ")
+            exported,
+            git_engine::redact_url(remote_url),
+            diverged_count,
+            diverged_skill_names.join(", ")
         )
     } else {
-        format!("成功导出 {} 个 Skill 到 {}", exported, remote_url)
+        format!("成功导出 {} 个 Skill 到 {}", exported, git_engine::redact_url(remote_url))
     };
 
     Ok(GitExportResult {
@@ -423,18 +335,59 @@ pub async fn export_skills_to_git(
     })
 }
 
+#[tauri::command]
+pub async fn export_skills_to_git(
+    config_id: String,
+    op_id: Option<String>,
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<GitExportResult, AppError> {
+    let op = GitOperation::with_app("export", &app, op_id);
+    export_skills_to_git_internal(&pool, config_id, &op).await
+}
+
+/// 仓库根目录的 README.md：库中所有 Skill 的列表。
+/// 导出时间只写在提交信息里，库没变时不产生新提交
+pub(crate) fn write_readme(conn: &rusqlite::Connection, dir: &Path) -> Result<(), AppError> {
+    let mut stmt = conn.prepare("SELECT name, description, version FROM skills ORDER BY name")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut readme = String::from("# Skills Manager Backup\n\n");
+    readme.push_str("| 名称 | 版本 | 描述 |\n|------|------|------|\n");
+    for (name, desc, ver) in &rows {
+        readme.push_str(&format!(
+            "| {} | {} | {} |\n",
+            name,
+            ver.as_deref().unwrap_or("-"),
+            desc.as_deref().unwrap_or("-")
+        ));
+    }
+    std::fs::write(dir.join("README.md"), &readme)
+        .map_err(|e| AppError::Internal(format!("写入 README.md 失败: {}", e)))
+}
+
 // ── 3. clone_git_repo ──
 
-#[tauri::command]
-pub async fn clone_git_repo(
+pub async fn clone_git_repo_internal(
+    pool: &DbPool,
     remote_url: String,
     branch: Option<String>,
-    pool: State<'_, DbPool>,
+    op: &GitOperation,
 ) -> Result<GitCloneResult, AppError> {
     info!(
         "[clone_git_repo] 克隆仓库: url={}, branch={:?}",
-        remote_url, branch
+        git_engine::redact_url(&remote_url),
+        branch
     );
+    op.set_credential(credentials::load_for_url(&*pool.get()?, &remote_url)?);
 
     let clone_dir = std::env::temp_dir().join("skills-manager-import");
     if clone_dir.exists() {
@@ -442,36 +395,11 @@ pub async fn clone_git_repo(
             .map_err(|e| AppError::Internal(format!("清理克隆目录失败: {}", e)))?;
     }
 
+    // 指定分支不存在时退回默认分支
     let branch_str = branch.unwrap_or_else(|| "main".to_string());
-    let (ok, _msg) = run_git_allow_fail(
-        &[
-            "clone",
-            "--branch",
-            &branch_str,
-            "--single-branch",
-            "--depth",
-            "1",
-            &remote_url,
-            clone_dir.to_str().unwrap_or(""),
-        ],
-        &std::env::temp_dir(),
-    );
-
-    if !ok {
-        // 如果指定分支失败，尝试不指定分支
-        let (ok2, msg2) = run_git_allow_fail(
-            &[
-                "clone",
-                "--depth",
-                "1",
-                &remote_url,
-                clone_dir.to_str().unwrap_or(""),
-            ],
-            &std::env::temp_dir(),
-        );
-        if !ok2 {
-            return Err(AppError::Internal(format!("克隆仓库失败: {}", msg2)));
-        }
+    {
+        let (url, dir, op) = (remote_url.clone(), clone_dir.clone(), op.clone());
+        git_engine::blocking(move || Ok(git_engine::clone_with_fallback(&url, &dir, &branch_str, true, &op)?)).await?;
     }
 
     // 扫描 skills/ 目录
@@ -517,7 +445,7 @@ pub async fn clone_git_repo(
             let status = if let Some((_local_id, _local_ver)) = &local {
                 // 比较 checksum
                 let repo_checksum = compute_dir_checksum(&path).unwrap_or_default();
-                let local_skill_path = get_skills_lib_path(&pool)?.join(&name);
+                let local_skill_path = get_skills_lib_path(&*pool.get()?).join(&name);
                 if local_skill_path.exists() {
                     let local_checksum = compute_dir_checksum(&local_skill_path).unwrap_or_default();
                     if repo_checksum == local_checksum {
@@ -553,15 +481,26 @@ pub async fn clone_git_repo(
     })
 }
 
+#[tauri::command]
+pub async fn clone_git_repo(
+    remote_url: String,
+    branch: Option<String>,
+    op_id: Option<String>,
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<GitCloneResult, AppError> {
+    let op = GitOperation::with_app("clone", &app, op_id);
+    clone_git_repo_internal(&pool, remote_url, branch, &op).await
+}
+
 // ── 4. import_from_git_repo ──
 
-#[tauri::command]
-pub async fn import_from_git_repo(
+pub async fn import_from_git_repo_internal(
+    pool: &DbPool,
     clone_path: String,
     skill_names: Vec<String>,
     overwrite_conflicts: bool,
     source_url: Option<String>,
-    pool: State<'_, DbPool>,
 ) -> Result<GitImportResult, AppError> {
     info!(
         "[import_from_git_repo] 导入: path={}, skills={:?}, overwrite={}, source_url={:?}",
@@ -575,7 +514,13 @@ pub async fn import_from_git_repo(
 
     let clone_dir = PathBuf::from(&clone_path);
     let skills_dir = clone_dir.join("skills");
-    let skills_lib = get_skills_lib_path(&pool)?;
+    // 来自 scan_remote_new_skills 的工作副本：读取期间加锁，导入后保留
+    let workspace = git_workspace::config_of_path(&clone_dir);
+    let _guard = match &workspace {
+        Some(config_id) => Some(git_workspace::lock(config_id).await),
+        None => None,
+    };
+    let skills_lib = get_skills_lib_path(&*pool.get()?);
     std::fs::create_dir_all(&skills_lib)
         .map_err(|e| AppError::Internal(format!("创建 Skill 库目录失败: {}", e)))?;
 
@@ -592,8 +537,6 @@ pub async fn import_from_git_repo(
             continue;
         }
 
-        let dest = skills_lib.join(name);
-
         // 解析 SKILL.md
         let skill_md = src.join("SKILL.md");
         let (_, description, version) = if skill_md.exists() {
@@ -631,7 +574,7 @@ pub async fn import_from_git_repo(
             }
 
             // 覆盖更新：清空旧 DB 文件并导入新文件
-            conn.execute("DELETE FROM skill_files WHERE skill_id = ?1", params![skill_id])?;
+            db_clear_files(&conn, &skill_id)?;
             db_import_from_dir(&conn, &skill_id, &src)?;
             let new_checksum = compute_db_checksum(&conn, &skill_id);
             conn.execute(
@@ -679,12 +622,14 @@ pub async fn import_from_git_repo(
     // 写入 sync_history
     conn.execute(
         "INSERT INTO sync_history (id, skill_id, action, status, created_at)
-         VALUES (?1, 'all', 'import', 'success', datetime('now'))",
+         VALUES (?1, NULL, 'import', 'success', datetime('now'))",
         params![Uuid::new_v4().to_string()],
     )?;
 
-    // 清理克隆目录
-    let _ = std::fs::remove_dir_all(&clone_dir);
+    // 清理临时克隆目录
+    if workspace.is_none() {
+        let _ = std::fs::remove_dir_all(&clone_dir);
+    }
 
     info!(
         "[import_from_git_repo] 完成: imported={}, updated={}, skipped={}",
@@ -702,6 +647,17 @@ pub async fn import_from_git_repo(
     })
 }
 
+#[tauri::command]
+pub async fn import_from_git_repo(
+    clone_path: String,
+    skill_names: Vec<String>,
+    overwrite_conflicts: bool,
+    source_url: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<GitImportResult, AppError> {
+    import_from_git_repo_internal(&pool, clone_path, skill_names, overwrite_conflicts, source_url).await
+}
+
 // ── 5. check_git_repo_updates ──
 
 #[derive(Debug, Clone, Serialize)]
@@ -722,10 +678,10 @@ pub struct GitSkillUpdateStatus {
     pub status: String, // "updated", "unchanged", "new_remote", "deleted_remote"
 }
 
-#[tauri::command]
-pub async fn check_git_repo_updates(
+pub async fn check_git_repo_updates_internal(
+    pool: &DbPool,
     config_id: Option<String>,
-    pool: State<'_, DbPool>,
+    op: &GitOperation,
 ) -> Result<Vec<GitRepoUpdateInfo>, AppError> {
     info!("[check_git_repo_updates] config_id={:?}", config_id);
 
@@ -760,38 +716,25 @@ pub async fn check_git_repo_updates(
         return Ok(Vec::new());
     }
 
-    let skills_lib = get_skills_lib_path(&pool)?;
+    let skills_lib = get_skills_lib_path(&*pool.get()?);
     let mut results = Vec::new();
 
     for (cid, remote_url, branch, _provider) in &configs {
-        info!("[check_git_repo_updates] 检查仓库: {} (branch={})", remote_url, branch);
-
-        // 2. Clone to temp dir (shallow)
-        let clone_dir = std::env::temp_dir().join(format!("skills-manager-check-{}", cid));
-        if clone_dir.exists() {
-            let _ = std::fs::remove_dir_all(&clone_dir);
-        }
-
-        let (ok, clone_msg) = run_git_allow_fail(
-            &["clone", "--branch", branch, "--single-branch", "--depth", "1",
-              remote_url, clone_dir.to_str().unwrap_or("")],
-            &std::env::temp_dir(),
-        );
-
-        if !ok {
-            info!("[check_git_repo_updates] clone 失败: {}, 尝试不指定分支", clone_msg);
-            let (ok2, msg2) = run_git_allow_fail(
-                &["clone", "--depth", "1", remote_url, clone_dir.to_str().unwrap_or("")],
-                &std::env::temp_dir(),
-            );
-            if !ok2 {
-                info!("[check_git_repo_updates] clone 最终失败: {}", msg2);
+        info!("[check_git_repo_updates] 检查仓库: {} (branch={})", git_engine::redact_url(remote_url), branch);
+
+        // 2. 工作副本 fetch 并对齐远程分支
+        let ws = match git_workspace::open(pool, cid, op).await {
+            Ok(ws) => ws,
+            Err(AppError::Git(GitError::Cancelled)) => return Err(GitError::Cancelled.into()),
+            Err(e) => {
+                info!("[check_git_repo_updates] 同步工作副本失败: {}", e);
                 continue;
             }
-        }
+        };
+        let clone_dir = &ws.dir;
 
         // 3. Get remote commit hash
-        let remote_commit = run_git(&["rev-parse", "HEAD"], &clone_dir).ok();
+        let remote_commit = ws.remote_head().map(|id| id.to_string());
         info!("[check_git_repo_updates] 远程 commit: {:?}", remote_commit);
 
         // 4. Scan remote skills dir
@@ -895,8 +838,7 @@ pub async fn check_git_repo_updates(
             info!("[check_git_repo_updates] 远程仓库无 skills/ 目录");
         }
 
-        // 5. Cleanup
-        let _ = std::fs::remove_dir_all(&clone_dir);
+        drop(ws);
 
         let update_count = skill_statuses.iter().filter(|s| s.status != "unchanged").count();
         info!(
@@ -918,6 +860,17 @@ pub async fn check_git_repo_updates(
     Ok(results)
 }
 
+#[tauri::command]
+pub async fn check_git_repo_updates(
+    config_id: Option<String>,
+    op_id: Option<String>,
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<GitRepoUpdateInfo>, AppError> {
+    let op = GitOperation::with_app("check", &app, op_id);
+    check_git_repo_updates_internal(&pool, config_id, &op).await
+}
+
 // ── 6. scan_remote_new_skills ──
 
 #[derive(Debug, Clone, Serialize)]
@@ -938,46 +891,23 @@ pub struct ScanRemoteResult {
     pub clone_path: String,
 }
 
-#[tauri::command]
-pub async fn scan_remote_new_skills(
+pub async fn scan_remote_new_skills_internal(
+    pool: &DbPool,
     config_id: String,
-    pool: State<'_, DbPool>,
+    op: &GitOperation,
 ) -> Result<ScanRemoteResult, AppError> {
     info!("[scan_remote_new_skills] config_id={}", config_id);
 
-    let conn = pool.get()?;
-
-    let (remote_url, branch): (String, String) = conn.query_row(
-        "SELECT remote_url, branch FROM git_export_config WHERE id = ?1",
-        params![config_id],
-        |row| Ok((row.get(0)?, row.get(1)?)),
-    )?;
-
+    // 工作副本 fetch 并对齐远程分支；返回的 clone_path 即工作副本，导入时再次加锁读取
+    let ws = git_workspace::open(pool, &config_id, op).await?;
+    let remote_url = ws.remote_url.clone();
+    let clone_dir = ws.dir.clone();
     info!(
         "[scan_remote_new_skills] remote={}, branch={}",
-        remote_url, branch
-    );
-
-    // 浅克隆
-    let clone_dir = std::env::temp_dir().join("skills-manager-scan-remote");
-    if clone_dir.exists() {
-        let _ = std::fs::remove_dir_all(&clone_dir);
-    }
-
-    let (ok, msg) = run_git_allow_fail(
-        &[
-            "clone", "--branch", &branch, "--single-branch",
-            "--depth", "1", &remote_url,
-            clone_dir.to_str().unwrap_or(""),
-        ],
-        &std::env::temp_dir(),
+        remote_url, ws.branch
     );
 
-    if !ok {
-        return Err(AppError::Internal(format!(
-            "克隆远程仓库失败: {}", msg
-        )));
-    }
+    let conn = pool.get()?;
 
     // 扫描远程 skills/ 目录
     let skills_dir = clone_dir.join("skills");
@@ -1063,3 +993,14 @@ pub async fn scan_remote_new_skills(
         clone_path: clone_dir.to_string_lossy().to_string(),
     })
 }
+
+#[tauri::command]
+pub async fn scan_remote_new_skills(
+    config_id: String,
+    op_id: Option<String>,
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<ScanRemoteResult, AppError> {
+    let op = GitOperation::with_app("scan", &app, op_id);
+    scan_remote_new_skills_internal(&pool, config_id, &op).await
+}
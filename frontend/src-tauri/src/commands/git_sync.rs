@@ -0,0 +1,476 @@
+//! 库与 git 仓库的双向同步。
+//!
+//! 以配置上次同步的提交为 base，逐个 Skill 比较 base、库、远程三方：只有一侧改过时同步到另一侧，
+//! 两侧都改过且内容不同的 Skill 报告为 diverged（附合并详情），保持两侧不动，
+//! 并记住它的 base，直到用户在之后的同步里指定保留哪一侧。
+//! 远程删除的 Skill 在库中删除前先备份；拉取后只重写没有本地修改的部署。
+
+use git2::Oid;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+use super::catalog::{merge_update, read_db_files};
+use super::git::{parse_skill_frontmatter, write_readme};
+use super::git_engine::{self, GitError, GitOperation};
+use super::git_workspace::{self, GitWorkspace};
+use super::skill_files::{db_export_to_dir, db_replace_files, has_db_files, refresh_skill_checksum};
+use super::skill_path::{skill_dir_under, SkillPath};
+use super::skills::MergeResult;
+use crate::db::DbPool;
+use crate::error::AppError;
+
+type Files = BTreeMap<String, Vec<u8>>;
+
+/// push 被拒（同步期间远程又有新提交）时整体重试的次数
+const MAX_SYNC_ATTEMPTS: usize = 2;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitSyncResolution {
+    pub skill_name: String,
+    /// local | remote | merged（合并无冲突时使用合并结果）
+    pub keep: String,
+}
+
+#[derive(Serialize)]
+pub struct GitSyncSkill {
+    pub name: String,
+    /// pull | push | merged | delete_local | delete_remote | diverged
+    pub action: String,
+    /// diverged 时的三向合并详情
+    pub merge: Option<MergeResult>,
+    /// delete_local 时删除前导出的备份
+    pub backup_id: Option<String>,
+    /// pull / merged 后有本地修改、未被覆盖的部署
+    pub deployments_skipped: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct GitSyncResult {
+    pub config_id: String,
+    pub base_commit: Option<String>,
+    pub remote_commit: Option<String>,
+    /// 本次推送的提交；没有需要推送的改动时为 None
+    pub commit_hash: Option<String>,
+    pub pulled: usize,
+    pub pushed: usize,
+    pub diverged: Vec<String>,
+    /// 发生变化或需要处理的 Skill（不含两侧一致的）
+    pub skills: Vec<GitSyncSkill>,
+    pub message: String,
+}
+
+// ── 读取三方内容 ──
+
+/// commit 中 skills/<name>/ 下的全部文件，按 Skill 名分组
+fn tree_skills(ws: &GitWorkspace, commit: Oid) -> Result<BTreeMap<String, Files>, AppError> {
+    let rev = commit.to_string();
+    let mut skills: BTreeMap<String, Files> = BTreeMap::new();
+    for path in git_engine::list_files(&ws.repo, &rev, "skills")? {
+        let Some((name, rel)) = path.strip_prefix("skills/").and_then(|p| p.split_once('/')) else {
+            continue;
+        };
+        if SkillPath::parse(rel).is_err() {
+            continue;
+        }
+        let content = git_engine::read_file(&ws.repo, &rev, &path)?;
+        skills
+            .entry(name.to_string())
+            .or_default()
+            .insert(rel.to_string(), content);
+    }
+    Ok(skills)
+}
+
+/// 按 commit 缓存的 base 内容；commit 已不在仓库中（远程被强推）时为 None
+struct BaseTrees<'a> {
+    ws: &'a GitWorkspace,
+    trees: HashMap<Oid, Option<BTreeMap<String, Files>>>,
+}
+
+impl BaseTrees<'_> {
+    fn skill(&mut self, commit: Option<&str>, name: &str) -> Result<Option<Files>, AppError> {
+        let Some(oid) = commit.and_then(|c| Oid::from_str(c).ok()) else {
+            return Ok(None);
+        };
+        if !self.trees.contains_key(&oid) {
+            let tree = if self.ws.repo.find_commit(oid).is_ok() {
+                Some(tree_skills(self.ws, oid)?)
+            } else {
+                warn!("[sync_with_git] base 提交 {} 已不在仓库中，按两向比较", oid);
+                None
+            };
+            self.trees.insert(oid, tree);
+        }
+        Ok(self.trees[&oid].as_ref().and_then(|t| t.get(name).cloned()))
+    }
+}
+
+/// 库中所有 Skill：name → (id, 文件)
+fn library_skills(conn: &Connection) -> Result<BTreeMap<String, (String, Files)>, AppError> {
+    let mut stmt = conn.prepare("SELECT id, name FROM skills")?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    rows.into_iter()
+        .map(|(id, name)| {
+            let files = read_db_files(conn, &id)?;
+            Ok((name, (id, files)))
+        })
+        .collect()
+}
+
+// ── 应用到两侧 ──
+
+/// 用远程内容覆盖库中的 Skill（不存在则新建），并重新同步它的部署；返回有本地修改、未覆盖的部署
+fn pull_into_library(
+    conn: &Connection,
+    local_id: Option<&str>,
+    name: &str,
+    files: &Files,
+) -> Result<Vec<String>, AppError> {
+    let (_, description, version) = files
+        .get("SKILL.md")
+        .map(|c| parse_skill_frontmatter(&String::from_utf8_lossy(c)))
+        .unwrap_or_default();
+    let tx = conn.unchecked_transaction()?;
+    let skill_id = match local_id {
+        Some(id) => id.to_string(),
+        None => {
+            let id = Uuid::new_v4().to_string();
+            tx.execute(
+                "INSERT INTO skills (id, name, description, version) VALUES (?1, ?2, ?3, ?4)",
+                params![id, name, description, version],
+            )?;
+            id
+        }
+    };
+    let from_checksum: Option<String> = tx
+        .query_row("SELECT checksum FROM skills WHERE id = ?1", params![skill_id], |r| r.get(0))
+        .optional()?
+        .flatten();
+    db_replace_files(&tx, &skill_id, files)?;
+    let to_checksum = refresh_skill_checksum(&tx, &skill_id)?;
+    tx.execute(
+        "UPDATE skills SET description = COALESCE(?1, description), version = COALESCE(?2, version),
+                last_modified = datetime('now'), updated_at = datetime('now')
+         WHERE id = ?3",
+        params![description, version, skill_id],
+    )?;
+    tx.execute(
+        "INSERT INTO sync_history (id, skill_id, action, from_checksum, to_checksum, status, created_at)
+         VALUES (?1, ?2, 'git_pull', ?3, ?4, 'success', datetime('now'))",
+        params![Uuid::new_v4().to_string(), skill_id, from_checksum, to_checksum],
+    )?;
+    tx.commit()?;
+
+    let (_, skipped) = super::deployments::redeploy_unmodified(conn, &skill_id)?;
+    Ok(skipped)
+}
+
+/// 远程删除的 Skill 在库中删除前导出一份备份（目录和 skill_backups 记录都保留），返回备份 ID
+fn backup_before_delete(conn: &Connection, skill_id: &str, name: &str) -> Result<Option<String>, AppError> {
+    if !has_db_files(conn, skill_id) {
+        return Ok(None);
+    }
+    let checksum: Option<String> =
+        conn.query_row("SELECT checksum FROM skills WHERE id = ?1", params![skill_id], |r| r.get(0))?;
+    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
+    let backup_path = dirs::home_dir()
+        .unwrap_or_default()
+        .join(".skills-manager")
+        .join("backups")
+        .join(name)
+        .join(format!("git-delete-{}", timestamp));
+    db_export_to_dir(conn, skill_id, &backup_path)?;
+    let backup_id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO skill_backups (id, skill_id, version_label, backup_path, checksum, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, 'before_git_delete')",
+        params![backup_id, skill_id, timestamp, backup_path.to_string_lossy(), checksum],
+    )?;
+    info!("[sync_with_git] 删除前备份 {}: {}", name, backup_path.display());
+    Ok(Some(backup_id))
+}
+
+/// 把 Skill 写进工作区的 skills/<name>/；files 为 None 时删除该目录
+fn write_worktree(ws: &GitWorkspace, name: &str, files: Option<&Files>) -> Result<(), AppError> {
+    let dir = skill_dir_under(&ws.dir.join("skills"), name)?;
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)?;
+    }
+    for (rel, content) in files.into_iter().flatten() {
+        let path = dir.join(SkillPath::parse(rel)?.as_str());
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+    }
+    Ok(())
+}
+
+// ── 同步 ──
+
+struct SyncState {
+    last_sync_commit: Option<String>,
+    /// 未处理的 diverged Skill → 它的 base
+    conflicts: HashMap<String, Option<String>>,
+}
+
+fn load_state(conn: &Connection, config_id: &str) -> Result<SyncState, AppError> {
+    let last_sync_commit = conn.query_row(
+        "SELECT last_sync_commit FROM git_export_config WHERE id = ?1",
+        params![config_id],
+        |row| row.get::<_, Option<String>>(0),
+    )?;
+    let mut stmt = conn.prepare("SELECT skill_name, base_commit FROM git_sync_conflicts WHERE config_id = ?1")?;
+    let conflicts = stmt
+        .query_map(params![config_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(SyncState {
+        last_sync_commit,
+        conflicts,
+    })
+}
+
+/// 一次同步尝试；远程在推送前又有新提交时返回 GitError::Rejected，由调用方重试
+fn sync_once(
+    pool: &DbPool,
+    ws: &GitWorkspace,
+    resolutions: &HashMap<String, String>,
+    op: &GitOperation,
+) -> Result<GitSyncResult, AppError> {
+    let conn = pool.get()?;
+    let state = load_state(&conn, &ws.config_id)?;
+    let remote_head = ws.remote_head();
+    let remote = match remote_head {
+        Some(head) => tree_skills(ws, head)?,
+        None => BTreeMap::new(),
+    };
+    let local = library_skills(&conn)?;
+    let mut bases = BaseTrees {
+        ws,
+        trees: HashMap::new(),
+    };
+
+    let names: BTreeSet<&String> = local.keys().chain(remote.keys()).chain(state.conflicts.keys()).collect();
+    let mut result = GitSyncResult {
+        config_id: ws.config_id.clone(),
+        base_commit: state.last_sync_commit.clone(),
+        remote_commit: remote_head.map(|id| id.to_string()),
+        commit_hash: None,
+        pulled: 0,
+        pushed: 0,
+        diverged: Vec::new(),
+        skills: Vec::new(),
+        message: String::new(),
+    };
+    // 仍然 diverged 的 Skill 及其 base
+    let mut still_diverged: Vec<(String, Option<String>)> = Vec::new();
+
+    for name in names {
+        if skill_dir_under(&ws.dir.join("skills"), name).is_err() {
+            info!("[sync_with_git] 跳过无法作为目录名的 Skill: {}", name);
+            continue;
+        }
+        let base_commit = match state.conflicts.get(name) {
+            Some(base) => base.clone(),
+            None => state.last_sync_commit.clone(),
+        };
+        let base = bases.skill(base_commit.as_deref(), name)?;
+        let (local_id, local_files) = match local.get(name) {
+            Some((id, files)) => (Some(id.as_str()), Some(files)),
+            None => (None, None),
+        };
+        let remote_files = remote.get(name);
+        if local_files == remote_files {
+            continue;
+        }
+
+        // 只有一侧改过时以改过的一侧为准；两侧都改过时看用户的取舍
+        let keep = if local_files == base.as_ref() {
+            "remote"
+        } else if remote_files == base.as_ref() {
+            "local"
+        } else {
+            match resolutions.get(name).map(String::as_str) {
+                Some(keep @ ("local" | "remote" | "merged")) => keep,
+                Some(other) => {
+                    return Err(AppError::Validation(format!("未知的取舍 {}（{}）", other, name)));
+                }
+                None => "",
+            }
+        };
+
+        let empty = Files::new();
+        let mut backup_id = None;
+        let mut deployments_skipped = Vec::new();
+        let action = match keep {
+            "remote" => match remote_files {
+                Some(files) => {
+                    deployments_skipped = pull_into_library(&conn, local_id, name, files)?;
+                    result.pulled += 1;
+                    "pull"
+                }
+                None => {
+                    // 部署记录随之删除，已部署的文件保留在原处。
+                    // 定时同步无人值守，删除前先备份，也不回收 blob
+                    if let Some(id) = local_id {
+                        backup_id = backup_before_delete(&conn, id, name)?;
+                        conn.execute("DELETE FROM skills WHERE id = ?1", params![id])?;
+                    }
+                    result.pulled += 1;
+                    "delete_local"
+                }
+            },
+            "local" => {
+                write_worktree(ws, name, local_files)?;
+                result.pushed += 1;
+                if local_files.is_some() { "push" } else { "delete_remote" }
+            }
+            "merged" => {
+                let (merged, merge) = merge_update(base.as_ref(), local_files.unwrap_or(&empty), remote_files.unwrap_or(&empty));
+                if merge.conflict_count > 0 {
+                    return Err(AppError::Validation(format!(
+                        "{} 有 {} 个文件冲突，不能直接使用合并结果",
+                        name, merge.conflict_count
+                    )));
+                }
+                deployments_skipped = pull_into_library(&conn, local_id, name, &merged)?;
+                write_worktree(ws, name, Some(&merged))?;
+                result.pulled += 1;
+                result.pushed += 1;
+                "merged"
+            }
+            _ => {
+                let (_, merge) = merge_update(base.as_ref(), local_files.unwrap_or(&empty), remote_files.unwrap_or(&empty));
+                result.diverged.push(name.clone());
+                still_diverged.push((name.clone(), base_commit));
+                result.skills.push(GitSyncSkill {
+                    name: name.clone(),
+                    action: "diverged".to_string(),
+                    merge: Some(merge),
+                    backup_id: None,
+                    deployments_skipped: Vec::new(),
+                });
+                continue;
+            }
+        };
+        info!("[sync_with_git] {}: {}", name, action);
+        result.skills.push(GitSyncSkill {
+            name: name.clone(),
+            action: action.to_string(),
+            merge: None,
+            backup_id,
+            deployments_skipped,
+        });
+    }
+
+    // 提交并推送库一侧的改动
+    if result.pushed > 0 {
+        write_readme(&conn, &ws.dir)?;
+        let msg = format!(
+            "sync: {} skills from library at {}",
+            result.pushed,
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+        );
+        if let Some(id) = git_engine::commit_all(&ws.repo, &msg)? {
+            git_engine::push(&ws.repo, &ws.branch, op)?;
+            result.commit_hash = Some(id.to_string());
+        }
+    }
+
+    // 新的 base：推送的提交，或者远程当前的提交
+    let synced = result.commit_hash.clone().or(result.remote_commit.clone());
+    // 没有遗留分歧时远程与库一致，on-change 自动导出无需再跑
+    let fingerprint = if still_diverged.is_empty() {
+        Some(super::auto_export::library_fingerprint(&conn)?)
+    } else {
+        None
+    };
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "UPDATE git_export_config SET last_sync_commit = ?2, last_pull_at = datetime('now'),
+                last_push_at = CASE WHEN ?3 THEN datetime('now') ELSE last_push_at END,
+                export_fingerprint = COALESCE(?4, export_fingerprint),
+                updated_at = datetime('now')
+         WHERE id = ?1",
+        params![ws.config_id, synced, result.commit_hash.is_some(), fingerprint],
+    )?;
+    tx.execute("DELETE FROM git_sync_conflicts WHERE config_id = ?1", params![ws.config_id])?;
+    for (name, base) in &still_diverged {
+        tx.execute(
+            "INSERT INTO git_sync_conflicts (config_id, skill_name, base_commit) VALUES (?1, ?2, ?3)",
+            params![ws.config_id, name, base],
+        )?;
+    }
+    tx.execute(
+        "INSERT INTO sync_history (id, skill_id, action, status, error_message, created_at)
+         VALUES (?1, NULL, 'git_sync', ?2, ?3, datetime('now'))",
+        params![
+            Uuid::new_v4().to_string(),
+            if still_diverged.is_empty() { "success" } else { "conflict" },
+            (!still_diverged.is_empty()).then(|| format!("diverged: {}", result.diverged.join(", ")))
+        ],
+    )?;
+    tx.commit()?;
+
+    result.message = if result.diverged.is_empty() {
+        format!("拉取 {} 个, 推送 {} 个", result.pulled, result.pushed)
+    } else {
+        format!(
+            "拉取 {} 个, 推送 {} 个, {} 个两侧都有修改待处理: {}",
+            result.pulled,
+            result.pushed,
+            result.diverged.len(),
+            result.diverged.join(", ")
+        )
+    };
+    Ok(result)
+}
+
+pub async fn sync_with_git_internal(
+    pool: &DbPool,
+    config_id: String,
+    resolutions: Option<Vec<GitSyncResolution>>,
+    op: &GitOperation,
+) -> Result<GitSyncResult, AppError> {
+    info!("[sync_with_git] 开始同步, config_id={}", config_id);
+    let resolutions: HashMap<String, String> = resolutions
+        .unwrap_or_default()
+        .into_iter()
+        .map(|r| (r.skill_name, r.keep))
+        .collect();
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let ws = git_workspace::open(pool, &config_id, op).await?;
+        let (pool, resolutions, op) = (pool.clone(), resolutions.clone(), op.clone());
+        match git_engine::blocking(move || sync_once(&pool, &ws, &resolutions, &op)).await {
+            Err(AppError::Git(GitError::Rejected(reason))) if attempt < MAX_SYNC_ATTEMPTS => {
+                info!("[sync_with_git] push 被拒绝，重新拉取后再同步: {}", reason);
+            }
+            Ok(result) => {
+                info!("[sync_with_git] 完成: {}", result.message);
+                return Ok(result);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn sync_with_git(
+    config_id: String,
+    resolutions: Option<Vec<GitSyncResolution>>,
+    op_id: Option<String>,
+    app: AppHandle,
+    pool: State<'_, DbPool>,
+) -> Result<GitSyncResult, AppError> {
+    let op = GitOperation::with_app("sync", &app, op_id);
+    sync_with_git_internal(&pool, config_id, resolutions, &op).await
+}
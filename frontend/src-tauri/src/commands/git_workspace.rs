@@ -0,0 +1,221 @@
+//! 每个 git 导出配置在 `~/.skills-manager/git/<config_id>` 保留一份完整的工作副本。
+//!
+//! 导出、检查更新、扫描远程都复用它：首次使用时 clone，之后只 fetch 并把工作区重置到远程分支。
+//! 同一配置的操作通过 `lock` 串行执行，不同配置互不影响；工作副本旁的锁文件让 skm 与桌面端之间也串行。
+
+use git2::build::CheckoutBuilder;
+use git2::Repository;
+use log::info;
+use rusqlite::params;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::OwnedMutexGuard;
+
+use super::credentials;
+use super::git_engine::{self, GitError, GitOperation};
+use super::skill_path::SkillPath;
+use crate::db::DbPool;
+use crate::error::AppError;
+
+/// 每个配置一把锁
+static LOCKS: Mutex<Option<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = Mutex::new(None);
+
+/// 工作副本的根目录 ~/.skills-manager/git
+pub fn workspaces_root() -> Result<PathBuf, AppError> {
+    Ok(dirs::home_dir()
+        .ok_or_else(|| AppError::Internal("无法获取 home 目录".into()))?
+        .join(".skills-manager")
+        .join("git"))
+}
+
+pub fn workspace_dir(config_id: &str) -> Result<PathBuf, AppError> {
+    Ok(workspaces_root()?.join(SkillPath::name(config_id)?.as_str()))
+}
+
+/// 等待并持有配置的锁，guard 释放前其他针对同一配置的 git 操作会排队
+pub async fn lock(config_id: &str) -> OwnedMutexGuard<()> {
+    let mutex = LOCKS
+        .lock()
+        .map(|mut locks| {
+            locks
+                .get_or_insert_with(HashMap::new)
+                .entry(config_id.to_string())
+                .or_default()
+                .clone()
+        })
+        .unwrap_or_default();
+    mutex.lock_owned().await
+}
+
+/// 跨进程的配置锁 ~/.skills-manager/git/<config_id>.lock：skm 与桌面端同时操作同一配置时排队。
+/// 阻塞直到拿到锁，返回的文件关闭时释放；锁文件放在工作副本之外，重新 clone 时不受影响
+fn lock_file(config_id: &str) -> Result<File, AppError> {
+    let root = workspaces_root()?;
+    std::fs::create_dir_all(&root)?;
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(root.join(format!("{}.lock", SkillPath::name(config_id)?.as_str())))?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// path 位于某个工作副本内时返回对应的 config_id
+pub fn config_of_path(path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(workspaces_root().ok()?).ok()?;
+    Some(rel.components().next()?.as_os_str().to_string_lossy().to_string())
+}
+
+/// 已加锁、并与远程分支对齐的工作副本
+pub struct GitWorkspace {
+    pub config_id: String,
+    pub remote_url: String,
+    pub branch: String,
+    pub dir: PathBuf,
+    pub repo: Repository,
+    _file_lock: File,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl GitWorkspace {
+    /// 远程分支的最新提交；远程还没有这个分支时为 None
+    pub fn remote_head(&self) -> Option<git2::Oid> {
+        self.repo.refname_to_id(&self.tracking_ref()).ok()
+    }
+
+    pub fn tracking_ref(&self) -> String {
+        format!("refs/remotes/origin/{}", self.branch)
+    }
+
+    /// 拉取远程分支到 refs/remotes/origin/<branch>；远程没有该分支时不报错
+    pub fn fetch(&self, op: &GitOperation) -> Result<(), AppError> {
+        let refspec = format!("+refs/heads/{}:{}", self.branch, self.tracking_ref());
+        match git_engine::fetch(&self.repo, &[&refspec], op) {
+            Ok(()) | Err(GitError::NotFound(_)) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 丢弃本地改动与未推送的提交，工作区与远程分支一致
+    fn reset_to_remote(&self) -> Result<(), AppError> {
+        let local = format!("refs/heads/{}", self.branch);
+        match self.remote_head() {
+            Some(id) => {
+                self.repo.reference(&local, id, true, "skills-manager: 对齐远程分支")?;
+                self.repo.set_head(&local)?;
+                self.repo
+                    .checkout_head(Some(CheckoutBuilder::new().force().remove_untracked(true)))?;
+            }
+            None => {
+                // 远程为空或分支被删：从空分支开始，下次导出会重新创建
+                if let Ok(mut r) = self.repo.find_reference(&local) {
+                    r.delete()?;
+                }
+                self.repo.set_head(&local)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 取得配置的工作副本：加锁，首次使用（或远程地址变了）时 clone，否则 fetch，最后重置到远程分支。
+/// 配置保存的凭据注入 op，后续 fetch / push 一并使用
+pub async fn open(pool: &DbPool, config_id: &str, op: &GitOperation) -> Result<GitWorkspace, AppError> {
+    let (remote_url, branch): (String, String) = {
+        let conn = pool.get()?;
+        let row = conn
+            .query_row(
+                "SELECT remote_url, branch FROM git_export_config WHERE id = ?1",
+                params![config_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|_| AppError::NotFound(format!("Git 配置不存在: {}", config_id)))?;
+        op.set_credential(credentials::load_for_config(&conn, config_id)?);
+        row
+    };
+    let dir = workspace_dir(config_id)?;
+    let guard = lock(config_id).await;
+
+    let config_id = config_id.to_string();
+    let op = op.clone();
+    // clone / fetch 是阻塞的网络 IO，放到阻塞线程池中执行
+    git_engine::blocking(move || {
+        let file_lock = lock_file(&config_id)?;
+        let existing = Repository::open(&dir).ok().filter(|repo| {
+            repo.find_remote("origin")
+                .ok()
+                .is_some_and(|r| r.url() == Some(remote_url.as_str()))
+        });
+        let (repo, cloned) = match existing {
+            Some(repo) => (repo, false),
+            None => {
+                if dir.exists() {
+                    info!("[git_workspace] {} 的工作副本无效或远程地址已变更，重新 clone", config_id);
+                    std::fs::remove_dir_all(&dir)?;
+                }
+                if let Some(parent) = dir.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let repo = match git_engine::clone(&remote_url, &dir, Some(&branch), false, &op) {
+                    Ok(repo) => repo,
+                    // 只有远程仓库可达、只是还没有这个分支时才从空仓库开始；仓库不存在时报错
+                    Err(GitError::NotFound(msg)) => {
+                        git_engine::check_remote(&remote_url, &op)?;
+                        info!("[git_workspace] 远程没有分支 {}（{}），初始化空仓库", branch, msg);
+                        git_engine::init(&dir, &branch, &remote_url)?
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                (repo, true)
+            }
+        };
+
+        let ws = GitWorkspace {
+            config_id,
+            remote_url,
+            branch,
+            dir,
+            repo,
+            _file_lock: file_lock,
+            _guard: guard,
+        };
+        if !cloned {
+            ws.fetch(&op)?;
+        }
+        ws.reset_to_remote()?;
+        info!(
+            "[git_workspace] {} 就绪: remote_head={:?}",
+            ws.config_id,
+            ws.remote_head().map(|id| id.to_string())
+        );
+        Ok(ws)
+    })
+    .await
+}
+
+/// 删除配置时一并删除工作副本
+pub async fn remove(config_id: &str) -> Result<(), AppError> {
+    let dir = workspace_dir(config_id)?;
+    let _guard = lock(config_id).await;
+    let id = config_id.to_string();
+    git_engine::blocking(move || {
+        let _file_lock = lock_file(&id)?;
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        Ok(())
+    })
+    .await?;
+    // 还有其他任务在等这把锁时保留表项，否则之后的调用会拿到一把新锁，与等待者同时进入
+    if let Ok(mut locks) = LOCKS.lock() {
+        if let Some(locks) = locks.as_mut() {
+            if locks.get(config_id).is_some_and(|m| Arc::strong_count(m) <= 2) {
+                locks.remove(config_id);
+            }
+        }
+    }
+    Ok(())
+}
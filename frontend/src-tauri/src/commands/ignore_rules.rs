@@ -0,0 +1,213 @@
+//! Skill 目录的忽略规则（gitignore 语法）。
+//!
+//! 规则依次叠加，后面的可以用 `!pattern` 取消前面的：
+//! 1. 内置默认规则：隐藏文件、编辑器交换文件、`node_modules`、`__pycache__` 等
+//! 2. 设置项 `ignore_patterns`（JSON 字符串数组，或按行分隔的文本）
+//! 3. Skill 根目录下的 `.skillignore`
+//!
+//! 导入（`db_import_from_dir`）、目录 checksum、DB checksum、部署写出、watcher 和 Git 导出
+//! 都经过这里，保证同一份 Skill 在各处看到的文件集合一致。
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use walkdir::WalkDir;
+
+pub const SKILLIGNORE_FILE: &str = ".skillignore";
+pub const IGNORE_PATTERNS_KEY: &str = "ignore_patterns";
+
+const DEFAULT_PATTERNS: &[&str] = &[
+    // 隐藏文件（含 .DS_Store、.git 以及部署暂存目录），.skillignore 本身随 Skill 保存
+    ".*",
+    "!.skillignore",
+    // 编辑器临时文件
+    "*.swp",
+    "*.swo",
+    "*~",
+    "4913",
+    "Thumbs.db",
+    // 依赖与构建产物
+    "node_modules/",
+    "__pycache__/",
+    "*.pyc",
+];
+
+/// 设置项中的规则，启动时和修改设置时刷新
+static USER_PATTERNS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// 解析设置值：优先按 JSON 数组解析，其次是 JSON 字符串或原始文本（每行一条）
+pub fn parse_patterns(value: &str) -> Vec<String> {
+    if let Ok(list) = serde_json::from_str::<Vec<String>>(value) {
+        return list;
+    }
+    let text = serde_json::from_str::<String>(value).unwrap_or_else(|_| value.to_string());
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+/// 从 app_settings 读取用户规则
+pub fn load_user_patterns(conn: &Connection) {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = ?1",
+            params![IGNORE_PATTERNS_KEY],
+            |row| row.get(0),
+        )
+        .unwrap_or(None);
+    let patterns = value.as_deref().map(parse_patterns).unwrap_or_default();
+    info!("[ignore_rules] 已加载 {} 条自定义忽略规则", patterns.len());
+    *USER_PATTERNS.lock().unwrap_or_else(|e| e.into_inner()) = patterns;
+}
+
+/// 一个 Skill 生效的忽略规则，匹配时传入相对 Skill 根目录的路径
+pub struct IgnoreRules {
+    matcher: Gitignore,
+}
+
+impl IgnoreRules {
+    /// 默认规则 + 设置项 + 给定的 .skillignore 内容
+    pub fn new(skillignore: Option<&str>) -> Self {
+        let user = USER_PATTERNS.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        Self::build(&user, skillignore)
+    }
+
+    fn build(user: &[String], skillignore: Option<&str>) -> Self {
+        let mut builder = GitignoreBuilder::new("");
+        let lines = DEFAULT_PATTERNS
+            .iter()
+            .map(|l| (None, *l))
+            .chain(user.iter().map(|l| (None, l.as_str())))
+            .chain(
+                skillignore
+                    .into_iter()
+                    .flat_map(str::lines)
+                    .map(|l| (Some(SKILLIGNORE_FILE), l)),
+            );
+        for (from, line) in lines {
+            if let Err(e) = builder.add_line(from.map(PathBuf::from), line) {
+                warn!("[ignore_rules] 无效的忽略规则 {:?}: {}", line, e);
+            }
+        }
+        let matcher = builder.build().unwrap_or_else(|e| {
+            warn!("[ignore_rules] 构建忽略规则失败，改为不忽略任何文件: {}", e);
+            Gitignore::empty()
+        });
+        Self { matcher }
+    }
+
+    /// 磁盘上的 Skill 目录：读取目录下的 .skillignore
+    pub fn for_dir(dir: &Path) -> Self {
+        let content = std::fs::read_to_string(dir.join(SKILLIGNORE_FILE)).ok();
+        Self::new(content.as_deref())
+    }
+
+    /// DB 中的 Skill：读取 skill_files 里的 .skillignore
+    pub fn for_db_skill(conn: &Connection, skill_id: &str) -> Self {
+        let content: Option<Vec<u8>> = conn
+            .query_row(
+                "SELECT content FROM skill_files WHERE skill_id = ?1 AND relative_path = ?2",
+                params![skill_id, SKILLIGNORE_FILE],
+                |row| row.get(0),
+            )
+            .ok();
+        Self::new(content.map(|c| String::from_utf8_lossy(&c).into_owned()).as_deref())
+    }
+
+    /// rel 为相对 Skill 根目录的路径；任一上级目录被忽略时也视为忽略
+    pub fn is_ignored(&self, rel: &Path, is_dir: bool) -> bool {
+        if rel.as_os_str().is_empty() || rel.has_root() {
+            return false;
+        }
+        self.matcher
+            .matched_path_or_any_parents(rel, is_dir)
+            .is_ignore()
+    }
+}
+
+/// 列出目录下未被忽略的文件（绝对路径，已排序），被忽略的目录整棵跳过
+pub fn walk_files(dir: &Path) -> Vec<PathBuf> {
+    let rules = IgnoreRules::for_dir(dir);
+    let mut paths: Vec<PathBuf> = WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| {
+            let rel = e.path().strip_prefix(dir).unwrap_or(e.path());
+            !rules.is_ignored(rel, e.file_type().is_dir())
+        })
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    paths.sort();
+    paths
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ignored(rules: &IgnoreRules, rel: &str) -> bool {
+        rules.is_ignored(Path::new(rel), false)
+    }
+
+    #[test]
+    fn parse_patterns_accepts_json_array_and_lines() {
+        assert_eq!(parse_patterns(r#"["*.log", "!keep.log"]"#), vec!["*.log", "!keep.log"]);
+        assert_eq!(parse_patterns(r#""dist/\n# 注释\n\n  *.tmp ""#), vec!["dist/", "*.tmp"]);
+        assert_eq!(parse_patterns("dist/\r\n#x\n*.tmp\n"), vec!["dist/", "*.tmp"]);
+        assert!(parse_patterns("").is_empty());
+    }
+
+    #[test]
+    fn default_and_user_rules_with_negation() {
+        let rules = IgnoreRules::new(None);
+        for rel in [".DS_Store", "docs/.hidden", "a.swp", "notes~", "node_modules/x/index.js", "lib/__pycache__/m.pyc"] {
+            assert!(ignored(&rules, rel), "{}", rel);
+        }
+        for rel in ["SKILL.md", ".skillignore", "docs/usage.md", "app.log"] {
+            assert!(!ignored(&rules, rel), "{}", rel);
+        }
+
+        // 设置项追加规则；.skillignore 可以取消默认规则和设置项
+        let user = parse_patterns(r#"["*.log", "!keep.log"]"#);
+        let rules = IgnoreRules::build(&user, Some("!.env\nbuild/\n!app.log"));
+
+        assert!(ignored(&rules, "debug.log"));
+        assert!(!ignored(&rules, "keep.log"));
+        assert!(!ignored(&rules, "app.log"), ".skillignore 在设置项之后生效");
+        assert!(!ignored(&rules, ".env"));
+        assert!(ignored(&rules, "build/out.js"));
+        assert!(rules.is_ignored(Path::new("build"), true));
+    }
+
+    #[test]
+    fn walk_files_skips_ignored_directories() {
+        let dir = std::env::temp_dir().join(format!("skm-ignore-{}", uuid::Uuid::new_v4()));
+        for rel in [
+            "SKILL.md",
+            "docs/usage.md",
+            ".git/HEAD",
+            "node_modules/pkg/index.js",
+            "build/out.js",
+            "build/keep.md",
+        ] {
+            let path = dir.join(rel);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, rel).unwrap();
+        }
+        std::fs::write(dir.join(SKILLIGNORE_FILE), "build/\n!build/keep.md\n").unwrap();
+
+        // 被忽略的目录整棵跳过，目录内的 ! 规则不会把文件重新加入
+        let rels: Vec<String> = walk_files(&dir)
+            .iter()
+            .map(|p| p.strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert_eq!(rels, vec![".skillignore", "SKILL.md", "docs/usage.md"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,402 @@
+//! 项目根目录下的 `skills.lock`。
+//!
+//! 记录项目用到的每个 Skill：来源（skill_sources 的 type / url / remote_sha / skill_path / registry_id）、
+//! 内容 checksum 以及部署到哪些工具。随仓库提交后，队友可以用 `bootstrap_project_from_lock`
+//! 把缺失的 Skill 安装进自己的库并部署到同样的工具目录。
+
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use tauri::State;
+use uuid::Uuid;
+
+use super::credentials;
+use super::git_engine::{self, GitOperation};
+use super::skill_files::compute_db_checksum;
+use super::skill_path::SkillPath;
+use super::watcher::WatcherService;
+use crate::db::DbPool;
+use crate::error::AppError;
+
+pub const LOCK_FILE_NAME: &str = "skills.lock";
+const LOCK_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillsLock {
+    pub version: u32,
+    pub skills: Vec<LockedSkill>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedSkill {
+    pub name: String,
+    pub checksum: Option<String>,
+    pub source: LockedSource,
+    pub tools: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedSource {
+    #[serde(rename = "type")]
+    pub source_type: String,
+    pub url: Option<String>,
+    pub remote_sha: Option<String>,
+    pub skill_path: Option<String>,
+    /// 从注册源安装时的注册源 id，旧版 lock 中没有
+    pub registry_id: Option<String>,
+}
+
+impl Default for SkillsLock {
+    fn default() -> Self {
+        Self {
+            version: LOCK_VERSION,
+            skills: Vec::new(),
+        }
+    }
+}
+
+pub fn lock_path(project_path: &Path) -> PathBuf {
+    project_path.join(LOCK_FILE_NAME)
+}
+
+/// 读取项目的 lock 文件，不存在时返回 None
+pub fn read_lock(project_path: &Path) -> Result<Option<SkillsLock>, AppError> {
+    let path = lock_path(project_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let lock: SkillsLock = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    if lock.version > LOCK_VERSION {
+        return Err(AppError::Validation(format!(
+            "{} 版本为 {}，当前应用只支持到 {}，请升级",
+            path.display(),
+            lock.version,
+            LOCK_VERSION
+        )));
+    }
+    Ok(Some(lock))
+}
+
+/// 按名称排序后写出，保证 diff 稳定；先写临时文件再改名
+fn write_lock(project_path: &Path, lock: &mut SkillsLock) -> Result<(), AppError> {
+    lock.version = LOCK_VERSION;
+    lock.skills.sort_by(|a, b| a.name.cmp(&b.name));
+    let path = lock_path(project_path);
+    let tmp = project_path.join(format!(".{}.tmp", LOCK_FILE_NAME));
+    std::fs::write(&tmp, serde_json::to_string_pretty(lock)? + "\n")?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// 部署到项目后更新 lock：写入 Skill 当前的来源和 checksum，并把工具并入已有列表
+pub fn record_project_deployment(
+    conn: &Connection,
+    project_path: &Path,
+    skill_id: &str,
+    tool: &str,
+) -> Result<(), AppError> {
+    let (name, source): (String, LockedSource) = conn
+        .query_row(
+            "SELECT s.name, COALESCE(ss.source_type, 'local'), ss.url, ss.remote_sha, ss.skill_path, ss.registry_id
+             FROM skills s
+             LEFT JOIN skill_sources ss ON ss.skill_id = s.id
+             WHERE s.id = ?1",
+            params![skill_id],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    LockedSource {
+                        source_type: row.get(1)?,
+                        url: row.get(2)?,
+                        remote_sha: row.get(3)?,
+                        skill_path: row.get(4)?,
+                        registry_id: row.get(5)?,
+                    },
+                ))
+            },
+        )
+        .map_err(|_| AppError::NotFound(format!("Skill 不存在: {}", skill_id)))?;
+    let checksum = compute_db_checksum(conn, skill_id);
+
+    let mut lock = read_lock(project_path)?.unwrap_or_default();
+    match lock.skills.iter_mut().find(|s| s.name == name) {
+        Some(entry) => {
+            entry.checksum = checksum;
+            entry.source = source;
+            entry.tools.insert(tool.to_string());
+        }
+        None => lock.skills.push(LockedSkill {
+            name,
+            checksum,
+            source,
+            tools: BTreeSet::from([tool.to_string()]),
+        }),
+    }
+    write_lock(project_path, &mut lock)?;
+    info!("[skills.lock] 已更新: {}", lock_path(project_path).display());
+    Ok(())
+}
+
+// ── bootstrap_project_from_lock ──
+
+#[derive(Debug, Serialize)]
+pub struct BootstrapResult {
+    pub project_id: String,
+    pub skills: Vec<BootstrapSkillResult>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BootstrapSkillResult {
+    pub name: String,
+    /// present | installed | checksum_mismatch | unavailable | failed
+    pub status: String,
+    pub message: Option<String>,
+    pub deployed_tools: Vec<String>,
+}
+
+fn find_skill(pool: &DbPool, name: &str) -> Result<Option<(String, Option<String>)>, AppError> {
+    let conn = pool.get()?;
+    Ok(conn
+        .query_row(
+            "SELECT id, checksum FROM skills WHERE name = ?1",
+            params![name],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?)
+}
+
+/// 按 lock 中记录的来源把 Skill 装进本地库，版本固定为 lock 中的 remote_sha
+async fn install_locked_skill(
+    pool: &DbPool,
+    skill: &LockedSkill,
+    token: Option<String>,
+) -> Result<(), AppError> {
+    let src = &skill.source;
+    let url = || {
+        src.url
+            .clone()
+            .ok_or_else(|| AppError::Validation(format!("来源 {} 没有记录 URL，无法安装", src.source_type)))
+    };
+    let sha = || {
+        src.remote_sha
+            .clone()
+            .ok_or_else(|| AppError::Validation("lock 中缺少 remote_sha".into()))
+    };
+
+    match src.source_type.as_str() {
+        "skills-sh" => {
+            let url = url()?;
+            let repo = super::catalog::extract_owner_repo(&url).unwrap_or(url);
+            super::catalog::install_from_catalog_internal(
+                pool,
+                repo,
+                src.skill_path.clone().unwrap_or_default(),
+                skill.name.clone(),
+                sha()?,
+                None,
+                Vec::new(),
+                Some(false),
+                token,
+            )
+            .await?;
+        }
+        "registry" => {
+            let registry_id = src
+                .registry_id
+                .clone()
+                .ok_or_else(|| AppError::Validation("lock 中缺少 registry_id".into()))?;
+            super::registry::install_from_registry_internal(
+                pool,
+                registry_id,
+                src.skill_path.clone().unwrap_or_default(),
+                Some(sha()?),
+                Vec::new(),
+                Some(false),
+                token,
+            )
+            .await?;
+        }
+        "github" | "gitee" | "git" => install_locked_git(pool, skill, url()?).await?,
+        other => {
+            return Err(AppError::Validation(format!("来源类型 {} 不支持自动安装", other)));
+        }
+    }
+    Ok(())
+}
+
+/// 克隆来源仓库并切到 remote_sha（未记录时用默认分支），导入 skill_path（缺省 skills/<name>）
+async fn install_locked_git(pool: &DbPool, skill: &LockedSkill, url: String) -> Result<(), AppError> {
+    let src = &skill.source;
+    let name = SkillPath::name(&skill.name)?;
+    let skill_path = match &src.skill_path {
+        Some(path) => SkillPath::parse(path)?,
+        None => SkillPath::parse(&format!("skills/{}", name.as_str()))?,
+    };
+    let root = std::env::temp_dir().join(format!("skills-manager-lock-{}", Uuid::new_v4()));
+
+    let result = async {
+        let op = GitOperation::new("clone");
+        op.set_credential(credentials::load_for_url(&*pool.get()?, &url)?);
+        let repo_dir = root.join("repo");
+        // 切到指定提交需要完整历史
+        let (clone_url, clone_dir, sha) = (url.clone(), repo_dir.clone(), src.remote_sha.clone());
+        git_engine::blocking(move || {
+            let repo = git_engine::clone(&clone_url, &clone_dir, None, sha.is_none(), &op)?;
+            if let Some(sha) = &sha {
+                git_engine::checkout_detached(&repo, sha)?;
+            }
+            Ok(())
+        })
+        .await?;
+        let dir = skill_path.join_under(&repo_dir)?;
+        if !dir.is_dir() {
+            return Err(AppError::NotFound(format!(
+                "{} 中不存在 {}",
+                git_engine::redact_url(&url),
+                skill_path.as_str()
+            )));
+        }
+
+        // import_from_git_repo 按 skills/<name> 读取
+        let import_dir = root.join("import");
+        std::fs::create_dir_all(import_dir.join("skills"))?;
+        std::fs::rename(&dir, import_dir.join("skills").join(name.as_str()))?;
+        super::git::import_from_git_repo_internal(
+            pool,
+            import_dir.to_string_lossy().to_string(),
+            vec![skill.name.clone()],
+            false,
+            Some(url.clone()),
+        )
+        .await?;
+
+        // 记下锁定的版本和路径，之后更新 lock 时来源不变
+        pool.get()?.execute(
+            "UPDATE skill_sources SET source_type = ?2, remote_sha = ?3, skill_path = ?4, updated_at = datetime('now')
+             WHERE skill_id = (SELECT id FROM skills WHERE name = ?1)",
+            params![skill.name, src.source_type, src.remote_sha, src.skill_path],
+        )?;
+        Ok(())
+    }
+    .await;
+    let _ = std::fs::remove_dir_all(&root);
+    result
+}
+
+pub async fn bootstrap_project_from_lock_internal(
+    pool: &DbPool,
+    project_path: String,
+    token: Option<String>,
+) -> Result<BootstrapResult, AppError> {
+    info!("[bootstrap_project_from_lock] project={}", project_path);
+    let root = Path::new(&project_path);
+    let lock = read_lock(root)?.ok_or_else(|| {
+        AppError::NotFound(format!("未找到 {}", lock_path(root).display()))
+    })?;
+
+    // 项目未登记时先登记
+    let existing_project: Option<String> = {
+        let conn = pool.get()?;
+        conn.query_row(
+            "SELECT id FROM projects WHERE path = ?1",
+            params![project_path],
+            |row| row.get(0),
+        )
+        .optional()?
+    };
+    let project_id = match existing_project {
+        Some(id) => id,
+        None => super::projects::add_project_internal(pool, project_path.clone()).await?.id,
+    };
+
+    let mut results = Vec::new();
+    for locked in &lock.skills {
+        let mut result = BootstrapSkillResult {
+            name: locked.name.clone(),
+            status: "present".to_string(),
+            message: None,
+            deployed_tools: Vec::new(),
+        };
+
+        let mut found = find_skill(pool, &locked.name)?;
+        if found.is_none() {
+            match install_locked_skill(pool, locked, token.clone()).await {
+                Ok(()) => {
+                    result.status = "installed".to_string();
+                    found = find_skill(pool, &locked.name)?;
+                }
+                Err(e @ AppError::Validation(_)) => {
+                    result.status = "unavailable".to_string();
+                    result.message = Some(e.to_string());
+                }
+                Err(e) => {
+                    result.status = "failed".to_string();
+                    result.message = Some(e.to_string());
+                }
+            }
+        }
+
+        let Some((skill_id, checksum)) = found else {
+            warn!("[bootstrap_project_from_lock] 跳过 {}: {:?}", locked.name, result.message);
+            results.push(result);
+            continue;
+        };
+
+        // 内容与 lock 不一致时仍然部署，但提示用户
+        if locked.checksum.is_some() && checksum != locked.checksum {
+            result.status = "checksum_mismatch".to_string();
+            result.message = Some(format!(
+                "本地库 checksum {:?} 与 lock 中的 {:?} 不一致",
+                checksum, locked.checksum
+            ));
+        }
+
+        for tool in &locked.tools {
+            match super::deployments::deploy_skill_to_project_internal(
+                pool,
+                skill_id.clone(),
+                project_id.clone(),
+                tool.clone(),
+                Some(false),
+                None,
+            )
+            .await
+            {
+                Ok(deploy) => match deploy.conflict {
+                    Some(c) if c.status == "exists_different" => {
+                        warn!(
+                            "[bootstrap_project_from_lock] {} 已存在不同内容，跳过: {}",
+                            tool, deploy.deploy_path
+                        );
+                    }
+                    _ => result.deployed_tools.push(tool.clone()),
+                },
+                Err(e) => warn!("[bootstrap_project_from_lock] 部署 {} 到 {} 失败: {}", locked.name, tool, e),
+            }
+        }
+        results.push(result);
+    }
+
+    info!(
+        "[bootstrap_project_from_lock] 完成: {} 个 Skill",
+        results.len()
+    );
+    Ok(BootstrapResult {
+        project_id,
+        skills: results,
+    })
+}
+
+#[tauri::command]
+pub async fn bootstrap_project_from_lock(
+    project_path: String,
+    token: Option<String>,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<BootstrapResult, AppError> {
+    let result = bootstrap_project_from_lock_internal(&pool, project_path, token).await?;
+    watcher.resync(&pool);
+    Ok(result)
+}
@@ -1,10 +1,31 @@
+//! Tauri 命令层。
+//!
+//! 需要数据库的命令拆成两层：`xxx_internal(pool: &DbPool, ...)` 承载全部业务逻辑，
+//! `#[tauri::command] xxx(..., State<DbPool>)` 只做参数转发。
+//! GUI 与 `skm` 命令行共用同一套 `_internal` 函数。
+
 pub mod catalog;
+pub mod catalog_snapshot;
 pub mod projects;
 pub mod skill_files;
 pub mod skills;
 pub mod deployments;
 pub mod settings;
 pub mod scanner;
+pub mod staging;
 pub mod utils;
 pub mod watcher;
 pub mod git;
+pub mod git_engine;
+pub mod git_workspace;
+pub mod git_sync;
+pub mod credentials;
+pub mod auto_export;
+pub mod lockfile;
+pub mod plan;
+pub mod write_journal;
+pub mod ignore_rules;
+pub mod skill_path;
+pub mod registry;
+pub mod search;
+pub mod static_registry;
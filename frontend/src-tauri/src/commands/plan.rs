@@ -0,0 +1,351 @@
+//! 批量操作的预演计划（dry run）。
+//!
+//! `update_skill_from_library`、`reconcile_all_deployments` 在 `dry_run` 模式下不改动磁盘和 DB，
+//! 只返回一份 `DeploymentPlan`：每个路径将被创建、覆盖、跳过还是冲突，以及逐文件的变更统计。
+//! `apply_plan` 执行前重新计算每个路径的 checksum，与计划时不一致就拒绝执行。
+
+use log::info;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tauri::State;
+use uuid::Uuid;
+
+use super::ignore_rules::{walk_files, IgnoreRules};
+use super::skill_files::{compute_db_checksum, db_list_files, db_read_file};
+use super::skills::diff_file_maps;
+use super::utils::compute_dir_checksum;
+use crate::db::DbPool;
+use crate::error::AppError;
+
+pub const PLAN_UPDATE_SKILL: &str = "update_skill_from_library";
+pub const PLAN_RECONCILE: &str = "reconcile_all_deployments";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeploymentPlan {
+    pub plan_id: String,
+    /// update_skill_from_library | reconcile_all_deployments
+    pub kind: String,
+    pub skill_id: Option<String>,
+    pub created_at: String,
+    pub actions: Vec<PlanAction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanAction {
+    pub deployment_id: Option<String>,
+    pub skill_id: Option<String>,
+    pub path: String,
+    /// create | overwrite | unchanged | conflict（更新）；missing | diverged | synced | untracked（对账）
+    pub action: String,
+    /// 计划时磁盘上的 checksum，路径不存在为 None
+    pub disk_checksum: Option<String>,
+    /// 计划时库（DB）中的 checksum
+    pub library_checksum: Option<String>,
+    /// 磁盘现状 → 库内容 的逐文件变更
+    pub files: Vec<PlanFileChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanFileChange {
+    pub path: String,
+    /// added | removed | modified
+    pub status: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+}
+
+impl DeploymentPlan {
+    pub fn new(kind: &str, skill_id: Option<String>) -> Self {
+        Self {
+            plan_id: Uuid::new_v4().to_string(),
+            kind: kind.to_string(),
+            skill_id,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            actions: Vec::new(),
+        }
+    }
+}
+
+fn disk_checksum(path: &Path) -> Option<String> {
+    if path.exists() {
+        compute_dir_checksum(path)
+    } else {
+        None
+    }
+}
+
+fn lossy_texts(files: BTreeMap<String, Vec<u8>>) -> BTreeMap<String, String> {
+    files
+        .into_iter()
+        .map(|(rel, content)| (rel, String::from_utf8_lossy(&content).into_owned()))
+        .collect()
+}
+
+/// 目录下未被忽略的文件：相对路径 → 内容
+pub(crate) fn read_dir_files(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+    let mut files = BTreeMap::new();
+    if !dir.exists() {
+        return files;
+    }
+    for path in walk_files(dir) {
+        if let Ok(rel) = path.strip_prefix(dir) {
+            let content = std::fs::read(&path).unwrap_or_default();
+            files.insert(rel.to_string_lossy().to_string(), content);
+        }
+    }
+    files
+}
+
+/// DB 中 Skill 未被忽略的文件：相对路径 → 内容
+pub(crate) fn read_db_unignored(
+    conn: &Connection,
+    skill_id: &str,
+) -> Result<BTreeMap<String, Vec<u8>>, AppError> {
+    let rules = IgnoreRules::for_db_skill(conn, skill_id);
+    let mut files = BTreeMap::new();
+    for rel_path in db_list_files(conn, skill_id)? {
+        if rules.is_ignored(Path::new(&rel_path), false) {
+            continue;
+        }
+        let content = db_read_file(conn, skill_id, &rel_path)?;
+        files.insert(rel_path, content);
+    }
+    Ok(files)
+}
+
+/// 目录下未被忽略的文件：相对路径 → 文本内容
+fn read_dir_texts(dir: &Path) -> BTreeMap<String, String> {
+    lossy_texts(read_dir_files(dir))
+}
+
+/// DB 中 Skill 未被忽略的文件：相对路径 → 文本内容
+fn read_db_texts(
+    conn: &Connection,
+    skill_id: &str,
+) -> Result<BTreeMap<String, String>, AppError> {
+    Ok(lossy_texts(read_db_unignored(conn, skill_id)?))
+}
+
+/// 目录现状与 DB 中 Skill 内容的逐文件差异
+pub fn diff_dir_against_db(
+    conn: &Connection,
+    skill_id: &str,
+    dir: &Path,
+) -> Result<Vec<PlanFileChange>, AppError> {
+    let current = read_dir_texts(dir);
+    let library = read_db_texts(conn, skill_id)?;
+
+    let (files, _) = diff_file_maps(&current, &library);
+    Ok(files
+        .into_iter()
+        .map(|f| {
+            let (lines_added, lines_removed) = match f.status.as_str() {
+                "added" => (library.get(&f.path).map_or(0, |c| c.lines().count()), 0),
+                "removed" => (0, current.get(&f.path).map_or(0, |c| c.lines().count())),
+                _ => f.hunks.iter().flat_map(|h| &h.lines).fold((0, 0), |(a, r), l| {
+                    match l.tag.as_str() {
+                        "+" => (a + 1, r),
+                        "-" => (a, r + 1),
+                        _ => (a, r),
+                    }
+                }),
+            };
+            PlanFileChange {
+                path: f.path,
+                status: f.status,
+                lines_added,
+                lines_removed,
+            }
+        })
+        .collect())
+}
+
+/// 预演用库内容重写一个部署：目标不存在为 create，与库一致为 unchanged，
+/// 上次同步后被本地改过为 conflict（覆盖会丢失本地修改），否则为 overwrite
+pub fn plan_redeploy(conn: &Connection, deployment_id: &str) -> Result<PlanAction, AppError> {
+    let (skill_id, path, last_checksum): (String, String, Option<String>) = conn
+        .query_row(
+            "SELECT skill_id, path, checksum FROM skill_deployments WHERE id = ?1",
+            params![deployment_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|_| AppError::NotFound(format!("部署记录不存在: {}", deployment_id)))?;
+
+    let dir = Path::new(&path);
+    let disk = disk_checksum(dir);
+    let library = compute_db_checksum(conn, &skill_id);
+
+    let action = if !dir.exists() {
+        "create"
+    } else if disk == library {
+        "unchanged"
+    } else if disk != last_checksum {
+        "conflict"
+    } else {
+        "overwrite"
+    };
+    let files = if action == "unchanged" {
+        Vec::new()
+    } else {
+        diff_dir_against_db(conn, &skill_id, dir)?
+    };
+
+    Ok(PlanAction {
+        deployment_id: Some(deployment_id.to_string()),
+        skill_id: Some(skill_id),
+        path,
+        action: action.to_string(),
+        disk_checksum: disk,
+        library_checksum: library,
+        files,
+    })
+}
+
+/// 计划中的路径或库内容在计划生成后发生变化时返回错误
+fn verify_plan(conn: &Connection, plan: &DeploymentPlan) -> Result<(), AppError> {
+    let mut stale = Vec::new();
+    for action in &plan.actions {
+        let disk_changed = disk_checksum(Path::new(&action.path)) != action.disk_checksum;
+        let library_changed = action
+            .skill_id
+            .as_ref()
+            .is_some_and(|id| compute_db_checksum(conn, id) != action.library_checksum);
+        if disk_changed || library_changed {
+            stale.push(action.path.clone());
+        }
+    }
+    if stale.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::Validation(format!(
+            "计划生成后以下路径已变化，请重新预演: {}",
+            stale.join(", ")
+        )))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ApplyPlanResult {
+    pub plan_id: String,
+    pub applied: Vec<String>,
+    pub skipped: Vec<String>,
+}
+
+pub async fn apply_plan_internal(
+    pool: &DbPool,
+    plan: DeploymentPlan,
+) -> Result<ApplyPlanResult, AppError> {
+    info!(
+        "[apply_plan] plan={}, kind={}, actions={}",
+        plan.plan_id,
+        plan.kind,
+        plan.actions.len()
+    );
+    {
+        let conn = pool.get()?;
+        verify_plan(&conn, &plan)?;
+    }
+
+    let (applied, skipped): (Vec<&PlanAction>, Vec<&PlanAction>) = match plan.kind.as_str() {
+        PLAN_UPDATE_SKILL => {
+            let skill_id = plan
+                .skill_id
+                .clone()
+                .ok_or_else(|| AppError::Validation("计划缺少 skill_id".into()))?;
+            // 冲突的部署保留本地修改，不覆盖
+            let (applied, skipped): (Vec<_>, Vec<_>) = plan
+                .actions
+                .iter()
+                .filter(|a| a.action != "unchanged")
+                .partition(|a| a.action == "create" || a.action == "overwrite");
+            let deployment_ids: Vec<String> =
+                applied.iter().filter_map(|a| a.deployment_id.clone()).collect();
+            super::skills::apply_library_update(pool, &skill_id, &deployment_ids).await?;
+            (applied, skipped)
+        }
+        PLAN_RECONCILE => {
+            // 按预演时的结论写入，不重新对账；verify_plan 已保证这些路径此后没有变化
+            super::deployments::apply_reconcile_actions(&*pool.get()?, &plan.actions)?;
+            plan.actions.iter().partition(|a| a.deployment_id.is_some())
+        }
+        other => return Err(AppError::Validation(format!("未知的计划类型: {}", other))),
+    };
+
+    info!(
+        "[apply_plan] 完成: {} 个路径已应用, {} 个跳过",
+        applied.len(),
+        skipped.len()
+    );
+    Ok(ApplyPlanResult {
+        plan_id: plan.plan_id.clone(),
+        applied: applied.iter().map(|a| a.path.clone()).collect(),
+        skipped: skipped.iter().map(|a| a.path.clone()).collect(),
+    })
+}
+
+#[tauri::command]
+pub async fn apply_plan(
+    plan: DeploymentPlan,
+    pool: State<'_, DbPool>,
+) -> Result<ApplyPlanResult, AppError> {
+    apply_plan_internal(&pool, plan).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::deployments::reconcile_all_deployments_internal;
+    use crate::commands::skill_files::{db_write_file, refresh_skill_checksum};
+    use crate::db::pool::create_pool;
+
+    #[tokio::test]
+    async fn apply_reconcile_plan_writes_only_previewed_actions() {
+        let root = std::env::temp_dir().join(format!("skm-plan-{}", Uuid::new_v4()));
+        let pool = create_pool(&root.join("skills.db")).unwrap();
+        let deployed = root.join("a").join("pdf");
+        {
+            let conn = pool.get().unwrap();
+            conn.execute("INSERT INTO skills (id, name) VALUES ('s1', 'pdf')", []).unwrap();
+            db_write_file(&conn, "s1", "SKILL.md", b"library").unwrap();
+            refresh_skill_checksum(&conn, "s1").unwrap();
+            std::fs::create_dir_all(&deployed).unwrap();
+            std::fs::write(deployed.join("SKILL.md"), "edited").unwrap();
+            conn.execute(
+                "INSERT INTO skill_deployments (id, skill_id, tool, path, checksum)
+                 VALUES ('d1', 's1', 'claude-code', ?1, 'old')",
+                params![deployed.to_string_lossy()],
+            )
+            .unwrap();
+        }
+
+        let plan = reconcile_all_deployments_internal(&pool, Some(true)).await.unwrap().plan.unwrap();
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].action, "diverged");
+
+        // 预演之后新增的部署不在计划里，执行时不应被对账
+        pool.get()
+            .unwrap()
+            .execute(
+                "INSERT INTO skill_deployments (id, skill_id, tool, path) VALUES ('d2', 's1', 'cursor', ?1)",
+                params![root.join("b").join("pdf").to_string_lossy()],
+            )
+            .unwrap();
+
+        let result = apply_plan_internal(&pool, plan).await.unwrap();
+        assert_eq!(result.applied, vec![deployed.to_string_lossy().to_string()]);
+        let conn = pool.get().unwrap();
+        let status = |id: &str| -> String {
+            conn.query_row("SELECT status FROM skill_deployments WHERE id = ?1", params![id], |r| r.get(0))
+                .unwrap()
+        };
+        assert_eq!(status("d1"), "diverged");
+        assert_eq!(status("d2"), "synced");
+        let events: i64 = conn
+            .query_row("SELECT COUNT(*) FROM change_events", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(events, 1);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
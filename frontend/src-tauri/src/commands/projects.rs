@@ -3,12 +3,12 @@ use rusqlite::params;
 use tauri::State;
 use uuid::Uuid;
 
+use super::watcher::WatcherService;
 use crate::db::DbPool;
 use crate::error::AppError;
 use crate::models::{Project, DashboardStats, ProjectDetailDeployment};
 
-#[tauri::command]
-pub async fn get_projects(pool: State<'_, DbPool>) -> Result<Vec<Project>, AppError> {
+pub async fn get_projects_internal(pool: &DbPool) -> Result<Vec<Project>, AppError> {
     info!("[get_projects] 查询所有项目");
     let conn = pool.get()?;
     let mut stmt = conn.prepare(
@@ -45,7 +45,11 @@ pub async fn get_projects(pool: State<'_, DbPool>) -> Result<Vec<Project>, AppEr
 }
 
 #[tauri::command]
-pub async fn add_project(path: String, pool: State<'_, DbPool>) -> Result<Project, AppError> {
+pub async fn get_projects(pool: State<'_, DbPool>) -> Result<Vec<Project>, AppError> {
+    get_projects_internal(&pool).await
+}
+
+pub async fn add_project_internal(pool: &DbPool, path: String) -> Result<Project, AppError> {
     info!("[add_project] 添加项目: {}", path);
     let project_path = std::path::Path::new(&path);
     if !project_path.exists() || !project_path.is_dir() {
@@ -92,6 +96,17 @@ pub async fn add_project(path: String, pool: State<'_, DbPool>) -> Result<Projec
     Ok(project)
 }
 
+#[tauri::command]
+pub async fn add_project(
+    path: String,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<Project, AppError> {
+    let project = add_project_internal(&pool, path).await?;
+    watcher.resync(&pool);
+    Ok(project)
+}
+
 // ── batch_add_projects (批量导入) ──
 
 #[derive(serde::Serialize)]
@@ -111,6 +126,7 @@ pub struct BatchSkippedItem {
 pub async fn batch_add_projects(
     paths: Vec<String>,
     pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
 ) -> Result<BatchAddResult, AppError> {
     info!("[batch_add_projects] 批量添加 {} 个路径", paths.len());
 
@@ -185,12 +201,12 @@ pub async fn batch_add_projects(
         "[batch_add_projects] 完成: {} 个添加, {} 个跳过, 共 {} 个",
         added.len(), skipped.len(), total
     );
+    watcher.resync(&pool);
 
     Ok(BatchAddResult { added, skipped, total })
 }
 
-#[tauri::command]
-pub async fn remove_project(project_id: String, pool: State<'_, DbPool>) -> Result<(), AppError> {
+pub async fn remove_project_internal(pool: &DbPool, project_id: String) -> Result<(), AppError> {
     info!("[remove_project] 删除项目: {}", project_id);
     let conn = pool.get()?;
     let tx = conn.unchecked_transaction()?;
@@ -222,6 +238,17 @@ pub async fn remove_project(project_id: String, pool: State<'_, DbPool>) -> Resu
     Ok(())
 }
 
+#[tauri::command]
+pub async fn remove_project(
+    project_id: String,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<(), AppError> {
+    remove_project_internal(&pool, project_id).await?;
+    watcher.resync(&pool);
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn get_project_deployments(
     project_id: String,
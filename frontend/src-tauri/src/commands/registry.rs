@@ -0,0 +1,1556 @@
+//! 可插拔的 Skill 注册源。
+//!
+//! 每种来源实现 `SkillRegistry`：搜索、元数据、最新版本、文件列表、读取文件。
+//! 注册源配置保存在 `registries` 表（预置 dmgrok catalog 与 skills.sh），
+//! 也可以添加 GitHub 仓库、任意 git 地址或本地目录，例如团队内部的 Skill 仓库。
+//!
+//! Skill 在注册源内的 ID 由各实现定义：catalog 条目 ID、skills.sh 的 `owner/repo/skill`、
+//! 仓库或目录中包含 SKILL.md 的子目录路径（仓库根目录为空串）。
+
+use async_trait::async_trait;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::State;
+use uuid::Uuid;
+
+use super::catalog::{install_from_source, InstallSource};
+use super::catalog_snapshot;
+use super::credentials;
+use super::git::parse_skill_frontmatter;
+use super::git_engine::{self, GitOperation};
+use super::ignore_rules::walk_files;
+use super::skill_path::SkillPath;
+use super::static_registry::{self, StaticManifest};
+use super::utils::compute_dir_checksum;
+use super::watcher::WatcherService;
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::models::*;
+
+pub const DMGROK_REGISTRY_ID: &str = "dmgrok";
+pub const SKILLS_SH_REGISTRY_ID: &str = "skills-sh";
+pub const REGISTRY_KINDS: &[&str] = &["catalog", "skills_sh", "github", "git", "local"];
+
+pub(crate) const USER_AGENT: &str = "shirehub-skills-manager";
+const SKILL_MD: &str = "SKILL.md";
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+/// 一个 Skill 来源
+#[async_trait]
+pub trait SkillRegistry: Send + Sync {
+    fn id(&self) -> &str;
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<RegistrySkill>, AppError>;
+
+    async fn metadata(&self, skill_id: &str) -> Result<RegistrySkill, AppError>;
+
+    /// 当前最新版本（commit SHA 或内容 checksum），作为 list_files / fetch_file 的 revision
+    async fn latest_revision(&self, skill_id: &str) -> Result<String, AppError>;
+
+    /// Skill 目录下所有文件的相对路径（`/` 分隔）
+    async fn list_files(&self, skill_id: &str, revision: &str) -> Result<Vec<String>, AppError>;
+
+    async fn fetch_file(
+        &self,
+        skill_id: &str,
+        revision: &str,
+        rel_path: &str,
+    ) -> Result<Vec<u8>, AppError>;
+}
+
+// ── 公共 helper ──
+
+#[derive(Default, serde::Deserialize)]
+struct RegistryOptions {
+    /// catalog：备用地址，主地址失败时依次尝试
+    #[serde(default)]
+    mirrors: Vec<String>,
+    /// github：只在该子目录下查找 Skill
+    #[serde(default)]
+    path: Option<String>,
+    /// github / git：分支或 tag，缺省为默认分支
+    #[serde(default, alias = "branch")]
+    r#ref: Option<String>,
+}
+
+fn parse_options(options: Option<&str>) -> Result<RegistryOptions, AppError> {
+    match options.map(str::trim).filter(|o| !o.is_empty()) {
+        Some(o) => serde_json::from_str(o)
+            .map_err(|e| AppError::Validation(format!("注册源 options 不是有效 JSON: {}", e))),
+        None => Ok(RegistryOptions::default()),
+    }
+}
+
+fn http_client(timeout_secs: u64) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(timeout_secs))
+        .build()
+        .unwrap_or_default()
+}
+
+pub(crate) async fn http_get(
+    client: &reqwest::Client,
+    url: &str,
+    token: Option<&str>,
+    accept: Option<&str>,
+) -> Result<reqwest::Response, AppError> {
+    let mut req = client
+        .get(url)
+        .header("User-Agent", USER_AGENT)
+        .header("Accept-Encoding", "identity");
+    if let Some(accept) = accept {
+        req = req.header("Accept", accept);
+    }
+    if let Some(t) = token.filter(|t| !t.is_empty()) {
+        req = req.header("Authorization", format!("Bearer {}", t));
+    }
+    let resp = req
+        .send()
+        .await
+        .map_err(|e| AppError::Internal(format!("请求失败 ({}): {}", url, e)))?;
+    if !resp.status().is_success() {
+        return Err(AppError::Internal(format!("HTTP {} ({})", resp.status(), url)));
+    }
+    Ok(resp)
+}
+
+/// 文件路径列表中包含 SKILL.md 的目录（仓库根目录为空串）
+fn skill_dirs_of<'a>(paths: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut dirs: Vec<String> = paths
+        .filter_map(|p| match p.rsplit_once('/') {
+            Some((dir, SKILL_MD)) => Some(dir.to_string()),
+            None if p == SKILL_MD => Some(String::new()),
+            _ => None,
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// `dir/rel` 形式的仓库内路径
+fn join_repo_path(dir: &str, rel: &str) -> String {
+    if dir.is_empty() {
+        rel.to_string()
+    } else {
+        format!("{}/{}", dir, rel)
+    }
+}
+
+fn dir_name<'a>(dir: &'a str, fallback: &'a str) -> &'a str {
+    dir.rsplit('/').next().filter(|n| !n.is_empty()).unwrap_or(fallback)
+}
+
+fn matches_query(query: &str, fields: &[&str]) -> bool {
+    let q = query.to_lowercase();
+    q.is_empty() || fields.iter().any(|f| f.to_lowercase().contains(&q))
+}
+
+/// 由 SKILL.md 内容组装元数据，frontmatter 中没有 name 时用目录名
+fn skill_from_md(
+    registry_id: &str,
+    skill_id: &str,
+    fallback_name: &str,
+    skill_md: Option<&[u8]>,
+) -> RegistrySkill {
+    let (name, description, version) = skill_md
+        .map(|c| parse_skill_frontmatter(&String::from_utf8_lossy(c)))
+        .unwrap_or_default();
+    RegistrySkill {
+        registry_id: registry_id.to_string(),
+        skill_id: skill_id.to_string(),
+        name: name.unwrap_or_else(|| fallback_name.to_string()),
+        description,
+        version,
+        source_url: None,
+        revision: None,
+        installs: None,
+        tags: Vec::new(),
+    }
+}
+
+// ── GitHub 仓库 ──
+
+#[derive(serde::Deserialize)]
+struct GitTreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    entry_type: String,
+}
+
+#[derive(serde::Deserialize)]
+struct GitTree {
+    tree: Vec<GitTreeEntry>,
+    #[serde(default)]
+    truncated: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct CommitInfo {
+    sha: String,
+}
+
+/// 通过 GitHub API 读取一个仓库；Skill ID 为仓库内的目录路径
+pub struct GitHubRegistry {
+    id: String,
+    owner_repo: String,
+    root: String,
+    git_ref: Option<String>,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+impl GitHubRegistry {
+    /// 临时访问任意仓库（catalog / skills.sh 条目指向的源仓库）
+    pub fn for_repo(owner_repo: &str, token: Option<String>) -> Self {
+        Self {
+            id: format!("github:{}", owner_repo),
+            owner_repo: owner_repo.to_string(),
+            root: String::new(),
+            git_ref: None,
+            token,
+            client: http_client(30),
+        }
+    }
+
+    fn from_config(config: &RegistryConfig, token: Option<String>) -> Result<Self, AppError> {
+        let options = parse_options(config.options.as_deref())?;
+        let url = config.url.trim().trim_end_matches('/').trim_end_matches(".git");
+        let owner_repo = super::catalog::extract_owner_repo(url).unwrap_or_else(|| url.to_string());
+        if owner_repo.split('/').count() != 2 {
+            return Err(AppError::Validation(format!(
+                "GitHub 注册源地址应为 owner/repo: {}",
+                config.url
+            )));
+        }
+        let root = match options.path.as_deref() {
+            Some(p) if !p.trim_matches('/').is_empty() => SkillPath::parse(p)?.as_str().to_string(),
+            _ => String::new(),
+        };
+        Ok(Self {
+            id: config.id.clone(),
+            owner_repo,
+            root,
+            git_ref: options.r#ref,
+            token,
+            client: http_client(30),
+        })
+    }
+
+    fn rev(&self) -> &str {
+        self.git_ref.as_deref().unwrap_or("HEAD")
+    }
+
+    fn tree_url(&self, dir: &str, revision: &str) -> String {
+        format!(
+            "https://github.com/{}/tree/{}/{}",
+            self.owner_repo,
+            revision,
+            dir
+        )
+    }
+
+    async fn api<T: serde::de::DeserializeOwned>(&self, path: &str) -> Result<T, AppError> {
+        let url = format!("https://api.github.com/repos/{}/{}", self.owner_repo, path);
+        http_get(
+            &self.client,
+            &url,
+            self.token.as_deref(),
+            Some("application/vnd.github.v3+json"),
+        )
+        .await?
+        .json()
+        .await
+        .map_err(|e| AppError::Internal(format!("解析 GitHub 响应失败 ({}): {}", url, e)))
+    }
+
+    /// 分支（或默认分支）最新的 commit
+    pub async fn head_sha(&self) -> Result<String, AppError> {
+        Ok(self.api::<CommitInfo>(&format!("commits/{}", self.rev())).await?.sha)
+    }
+
+    async fn tree(&self, revision: &str) -> Result<Vec<GitTreeEntry>, AppError> {
+        let tree: GitTree = self.api(&format!("git/trees/{}?recursive=1", revision)).await?;
+        if tree.truncated {
+            warn!("[registry] {} 的文件树被 GitHub 截断，部分 Skill 可能缺失", self.owner_repo);
+        }
+        Ok(tree.tree.into_iter().filter(|e| e.entry_type == "blob").collect())
+    }
+
+    async fn raw(&self, revision: &str, path: &str) -> Result<Vec<u8>, AppError> {
+        let url = format!(
+            "https://raw.githubusercontent.com/{}/{}/{}",
+            self.owner_repo, revision, path
+        );
+        let bytes = http_get(&self.client, &url, self.token.as_deref(), None)
+            .await?
+            .bytes()
+            .await
+            .map_err(|e| AppError::Internal(format!("读取文件 {} 失败: {}", path, e)))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// 仓库（限定在 root 下）中所有 Skill 目录
+    async fn skill_dirs(&self, revision: &str) -> Result<Vec<String>, AppError> {
+        let tree = self.tree(revision).await?;
+        let prefix = format!("{}/", self.root);
+        Ok(skill_dirs_of(tree.iter().map(|e| e.path.as_str()))
+            .into_iter()
+            .filter(|d| self.root.is_empty() || d == &self.root || d.starts_with(&prefix))
+            .collect())
+    }
+
+    /// 按名称查找 Skill 目录：先比对目录名，再比对 SKILL.md 的 frontmatter name。
+    /// 返回 (目录路径, commit SHA)
+    pub async fn find_skill(&self, name: &str) -> Result<(String, String), AppError> {
+        info!("[registry] 在 {} 中查找 Skill: {}", self.owner_repo, name);
+        let commit_sha = self.head_sha().await.unwrap_or_else(|_| "HEAD".to_string());
+        let dirs = self.skill_dirs(&commit_sha).await?;
+        if dirs.is_empty() {
+            return Err(AppError::Internal(format!(
+                "仓库 {} 中未找到任何 SKILL.md 文件",
+                self.owner_repo
+            )));
+        }
+
+        let wanted = name.to_lowercase();
+        if let Some(dir) = dirs.iter().find(|d| dir_name(d, "").to_lowercase() == wanted) {
+            info!("[registry] 按目录名匹配: {} → {}", name, dir);
+            return Ok((dir.clone(), commit_sha));
+        }
+        for dir in &dirs {
+            let Ok(content) = self.raw(&commit_sha, &join_repo_path(dir, SKILL_MD)).await else {
+                continue;
+            };
+            let (fm_name, _, _) = parse_skill_frontmatter(&String::from_utf8_lossy(&content));
+            if fm_name.map(|n| n.to_lowercase()) == Some(wanted.clone()) {
+                info!("[registry] 按 frontmatter name 匹配: {} → {}", name, dir);
+                return Ok((dir.clone(), commit_sha));
+            }
+        }
+
+        Err(AppError::Internal(format!(
+            "在仓库 {} 中未找到名为 '{}' 的 Skill",
+            self.owner_repo, name
+        )))
+    }
+}
+
+/// catalog 仓库惯例：Skill 根目录下的说明文件和 `_` 开头的内部文件不随 Skill 安装
+fn is_repo_noise(rel: &str) -> bool {
+    !rel.contains('/') && (rel == "README.md" || rel == "metadata.json" || rel.starts_with('_'))
+}
+
+#[async_trait]
+impl SkillRegistry for GitHubRegistry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<RegistrySkill>, AppError> {
+        let repo_name = dir_name(&self.owner_repo, "").to_string();
+        Ok(self
+            .skill_dirs(self.rev())
+            .await?
+            .into_iter()
+            .filter(|d| matches_query(query, &[d.as_str()]))
+            .take(limit)
+            .map(|d| {
+                let mut skill = skill_from_md(&self.id, &d, dir_name(&d, &repo_name), None);
+                skill.source_url = Some(self.tree_url(&d, self.rev()));
+                skill
+            })
+            .collect())
+    }
+
+    async fn metadata(&self, skill_id: &str) -> Result<RegistrySkill, AppError> {
+        let revision = self.latest_revision(skill_id).await?;
+        let content = self.raw(&revision, &join_repo_path(skill_id, SKILL_MD)).await?;
+        let repo_name = dir_name(&self.owner_repo, "");
+        let mut skill = skill_from_md(&self.id, skill_id, dir_name(skill_id, repo_name), Some(&content));
+        skill.source_url = Some(self.tree_url(skill_id, &revision));
+        skill.revision = Some(revision);
+        Ok(skill)
+    }
+
+    /// 最后一次改动该目录的 commit，其他目录的提交不会让它显示为有更新
+    async fn latest_revision(&self, skill_id: &str) -> Result<String, AppError> {
+        let path = if skill_id.is_empty() { String::new() } else { format!("&path={}", skill_id) };
+        let commits: Vec<CommitInfo> = self
+            .api(&format!("commits?sha={}&per_page=1{}", self.rev(), path))
+            .await?;
+        match commits.into_iter().next() {
+            Some(c) => Ok(c.sha),
+            None => self.head_sha().await,
+        }
+    }
+
+    async fn list_files(&self, skill_id: &str, revision: &str) -> Result<Vec<String>, AppError> {
+        let prefix = if skill_id.is_empty() { String::new() } else { format!("{}/", skill_id) };
+        let mut files = Vec::new();
+        for entry in self.tree(revision).await? {
+            let Some(rel) = entry.path.strip_prefix(&prefix) else {
+                continue;
+            };
+            if is_repo_noise(rel) {
+                continue;
+            }
+            files.push(SkillPath::parse(rel)?.as_str().to_string());
+        }
+        Ok(files)
+    }
+
+    async fn fetch_file(
+        &self,
+        skill_id: &str,
+        revision: &str,
+        rel_path: &str,
+    ) -> Result<Vec<u8>, AppError> {
+        let rel = SkillPath::parse(rel_path)?;
+        self.raw(revision, &join_repo_path(skill_id, rel.as_str())).await
+    }
+}
+
+// ── dmgrok 格式的 catalog.json ──
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct RawCatalog {
+    pub skills: Vec<RawCatalogSkill>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct RawCatalogSkill {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub provider: String,
+    #[serde(default)]
+    pub category: String,
+    pub license: Option<String>,
+    pub compatibility: Option<String>,
+    pub last_updated_at: Option<String>,
+    #[serde(default)]
+    pub has_scripts: bool,
+    #[serde(default)]
+    pub has_references: bool,
+    #[serde(default)]
+    pub has_assets: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub days_since_update: Option<u32>,
+    pub maintenance_status: Option<String>,
+    #[serde(default)]
+    pub quality_score: u32,
+    pub source: RawCatalogSource,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub(crate) struct RawCatalogSource {
+    #[serde(default)]
+    pub repo: String,
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub skill_md_url: String,
+    #[serde(default)]
+    pub commit_sha: String,
+    /// 静态注册源：相对 index.json 的文件清单地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<String>,
+}
+
+pub(crate) fn raw_to_catalog(r: RawCatalogSkill) -> CatalogSkill {
+    CatalogSkill {
+        id: r.id,
+        name: r.name,
+        description: r.description,
+        provider: r.provider,
+        category: r.category,
+        license: r.license,
+        compatibility: r.compatibility,
+        last_updated_at: r.last_updated_at.unwrap_or_default(),
+        has_scripts: r.has_scripts,
+        has_references: r.has_references,
+        has_assets: r.has_assets,
+        tags: r.tags,
+        days_since_update: r.days_since_update.unwrap_or(0),
+        maintenance_status: r.maintenance_status.unwrap_or_else(|| "unknown".to_string()),
+        quality_score: r.quality_score,
+        source_repo: r.source.repo,
+        source_path: r.source.path,
+        skill_md_url: r.source.skill_md_url,
+        commit_sha: r.source.commit_sha,
+        installs: None,
+        registry_id: None,
+        manifest_url: None,
+    }
+}
+
+struct CatalogCache {
+    skills: Vec<CatalogSkill>,
+    /// 到这个时间后再读取会触发一次后台刷新
+    next_check: Instant,
+}
+
+/// 按注册源 ID 缓存已读取的快照，避免每次搜索都解析一遍
+static CATALOG_CACHE: Mutex<Option<HashMap<String, CatalogCache>>> = Mutex::new(None);
+
+/// 后台刷新失败后，至少隔这么久（秒）再重试
+const CATALOG_RETRY_SECS: u64 = 600;
+
+/// 丢弃注册源的快照与内存缓存（地址变更或删除注册源时）
+pub fn forget_catalog(conn: &Connection, registry_id: &str) -> Result<(), AppError> {
+    if let Ok(mut guard) = CATALOG_CACHE.lock() {
+        if let Some(cache) = guard.as_mut() {
+            cache.remove(registry_id);
+        }
+    }
+    catalog_snapshot::delete_snapshot(conn, registry_id)
+}
+
+/// catalog.json 索引。dmgrok 条目的文件从指向的 GitHub 仓库读取，
+/// 自托管静态注册源的条目（带 manifest）按清单从 index.json 同级目录读取
+#[derive(Clone)]
+pub struct CatalogRegistry {
+    id: String,
+    urls: Vec<String>,
+    token: Option<String>,
+    client: reqwest::Client,
+    pool: DbPool,
+    /// manifest 地址 → 清单，安装时每个文件都要用到
+    manifests: Arc<Mutex<HashMap<String, StaticManifest>>>,
+}
+
+impl CatalogRegistry {
+    fn from_config(pool: &DbPool, config: &RegistryConfig, token: Option<String>) -> Result<Self, AppError> {
+        let options = parse_options(config.options.as_deref())?;
+        let urls = std::iter::once(config.url.clone())
+            .chain(options.mirrors)
+            .filter(|u| !u.trim().is_empty())
+            .collect();
+        Ok(Self {
+            id: config.id.clone(),
+            urls,
+            token,
+            client: http_client(15),
+            pool: pool.clone(),
+            manifests: Arc::new(Mutex::new(HashMap::new())),
+        })
+    }
+
+    fn cache(&self, skills: Vec<CatalogSkill>, fresh_for_secs: u64) {
+        if let Ok(mut guard) = CATALOG_CACHE.lock() {
+            guard.get_or_insert_with(HashMap::new).insert(
+                self.id.clone(),
+                CatalogCache {
+                    skills,
+                    next_check: Instant::now() + Duration::from_secs(fresh_for_secs),
+                },
+            );
+        }
+    }
+
+    /// 内存缓存中的条目；过期时顺带推迟下次检查，保证同一时间只有一个后台刷新
+    fn cached(&self) -> Result<Option<(Vec<CatalogSkill>, bool)>, AppError> {
+        let mut guard = CATALOG_CACHE.lock().map_err(|_| AppError::Internal("锁污染".into()))?;
+        let Some(cache) = guard.as_mut().and_then(|m| m.get_mut(&self.id)) else {
+            return Ok(None);
+        };
+        let stale = Instant::now() >= cache.next_check;
+        if stale {
+            cache.next_check = Instant::now() + Duration::from_secs(CATALOG_RETRY_SECS);
+        }
+        Ok(Some((cache.skills.clone(), stale)))
+    }
+
+    /// 全量条目（按质量分降序）。优先使用内存缓存和本地快照，只有从未拉取过时才等待网络；
+    /// 快照过期时返回旧数据并在后台刷新
+    pub async fn load_all(&self) -> Result<Vec<CatalogSkill>, AppError> {
+        if let Some((skills, stale)) = self.cached()? {
+            if stale {
+                self.refresh_in_background();
+            }
+            return Ok(skills);
+        }
+
+        let snapshot = catalog_snapshot::load_snapshot(&*self.pool.get()?, &self.id)?;
+        if let Some(snapshot) = snapshot {
+            info!(
+                "[registry] {} 使用本地快照，共 {} 条（{} 秒前拉取自 {}）",
+                self.id,
+                snapshot.skills.len(),
+                snapshot.age_secs,
+                snapshot.source_url
+            );
+            let stale = snapshot.age_secs >= catalog_snapshot::SNAPSHOT_TTL_SECS;
+            let fresh_for = if stale {
+                CATALOG_RETRY_SECS
+            } else {
+                catalog_snapshot::SNAPSHOT_TTL_SECS - snapshot.age_secs
+            };
+            self.cache(snapshot.skills.clone(), fresh_for);
+            if stale {
+                self.refresh_in_background();
+            }
+            return Ok(snapshot.skills);
+        }
+
+        self.refresh().await
+    }
+
+    fn refresh_in_background(&self) {
+        let this = self.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = this.refresh().await {
+                warn!("[registry] {} 后台刷新失败，继续使用本地快照: {}", this.id, e);
+            }
+        });
+    }
+
+    /// 依次尝试主地址和镜像拉取 catalog 并写入快照；对上次命中的地址带上 ETag / If-Modified-Since
+    pub async fn refresh(&self) -> Result<Vec<CatalogSkill>, AppError> {
+        let prev = catalog_snapshot::load_snapshot(&*self.pool.get()?, &self.id)?;
+
+        let mut last_err = String::from("未配置地址");
+        for url in &self.urls {
+            let validators = prev
+                .as_ref()
+                .filter(|p| &p.source_url == url)
+                .map(|p| (p.etag.as_deref(), p.last_modified.as_deref()));
+            info!("[registry] 拉取 catalog: {}", url);
+            let (bytes, etag, last_modified) =
+                match catalog_snapshot::fetch_conditional(&self.client, url, validators).await {
+                    Ok(catalog_snapshot::Fetched::Body {
+                        bytes,
+                        etag,
+                        last_modified,
+                    }) => (bytes, etag, last_modified),
+                    Ok(catalog_snapshot::Fetched::NotModified) => {
+                        // 只有带了校验头才可能收到 304，此时 prev 一定存在
+                        let skills = prev.map(|p| p.skills).unwrap_or_default();
+                        info!("[registry] {} 未变化（304），沿用快照 {} 条", self.id, skills.len());
+                        catalog_snapshot::touch_snapshot(&*self.pool.get()?, &self.id)?;
+                        self.cache(skills.clone(), catalog_snapshot::SNAPSHOT_TTL_SECS);
+                        return Ok(skills);
+                    }
+                    Err(e) => {
+                        last_err = e.to_string();
+                        info!("[registry] {}", last_err);
+                        continue;
+                    }
+                };
+            let raw = match serde_json::from_slice::<RawCatalog>(&bytes) {
+                Ok(raw) => raw,
+                Err(e) => {
+                    last_err = format!("解析失败 ({}): {}", url, e);
+                    info!("[registry] {}", last_err);
+                    continue;
+                }
+            };
+
+            let skills = self.to_catalog_skills(url, raw);
+            info!("[registry] {} 拉取成功，共 {} 个 Skill", self.id, skills.len());
+            catalog_snapshot::save_snapshot(
+                &*self.pool.get()?,
+                &catalog_snapshot::CatalogSnapshot {
+                    registry_id: self.id.clone(),
+                    source_url: url.clone(),
+                    etag,
+                    last_modified,
+                    skills: skills.clone(),
+                    age_secs: 0,
+                },
+            )?;
+            self.cache(skills.clone(), catalog_snapshot::SNAPSHOT_TTL_SECS);
+            return Ok(skills);
+        }
+        Err(AppError::Internal(format!("所有镜像均拉取失败，最后错误: {}", last_err)))
+    }
+
+    fn to_catalog_skills(&self, base_url: &str, raw: RawCatalog) -> Vec<CatalogSkill> {
+        let mut skills: Vec<CatalogSkill> = raw
+            .skills
+            .into_iter()
+            .map(|r| {
+                let manifest = r.source.manifest.clone();
+                let mut skill = raw_to_catalog(r);
+                skill.registry_id = Some(self.id.clone());
+                skill.manifest_url = manifest.and_then(|m| match static_registry::resolve_url(base_url, &m) {
+                    Ok(u) => Some(u),
+                    Err(e) => {
+                        warn!("[registry] {} 的清单地址无效: {}", skill.id, e);
+                        None
+                    }
+                });
+                // 静态注册源写的是相对 index.json 的地址，与清单地址一样解析成完整地址
+                if !skill.skill_md_url.is_empty() && !skill.skill_md_url.contains("://") {
+                    skill.skill_md_url = match static_registry::resolve_url(base_url, &skill.skill_md_url) {
+                        Ok(u) => u,
+                        Err(e) => {
+                            warn!("[registry] {} 的 SKILL.md 地址无效: {}", skill.id, e);
+                            String::new()
+                        }
+                    };
+                }
+                skill
+            })
+            .collect();
+        skills.sort_by_key(|s| std::cmp::Reverse(s.quality_score));
+        skills
+    }
+
+    async fn entry(&self, skill_id: &str) -> Result<CatalogSkill, AppError> {
+        self.load_all()
+            .await?
+            .into_iter()
+            .find(|s| s.id == skill_id)
+            .ok_or_else(|| AppError::NotFound(format!("catalog 中不存在: {}", skill_id)))
+    }
+
+    fn source(&self, entry: &CatalogSkill) -> GitHubRegistry {
+        let owner_repo = super::catalog::extract_owner_repo(&entry.source_repo)
+            .unwrap_or_else(|| entry.source_repo.clone());
+        GitHubRegistry::for_repo(&owner_repo, self.token.clone())
+    }
+
+    /// 静态注册源条目的清单；只提供最新版本，revision 与清单不符时报错
+    async fn manifest(&self, manifest_url: &str, revision: &str) -> Result<StaticManifest, AppError> {
+        let cached = self.manifests.lock().ok().and_then(|m| m.get(manifest_url).cloned());
+        let manifest = match cached {
+            Some(m) => m,
+            None => {
+                let m = static_registry::load_manifest(&self.client, manifest_url).await?;
+                if let Ok(mut cache) = self.manifests.lock() {
+                    cache.insert(manifest_url.to_string(), m.clone());
+                }
+                m
+            }
+        };
+        if manifest.revision != revision {
+            return Err(AppError::Validation(format!(
+                "静态注册源中 {} 的版本已变为 {}，请刷新后重试",
+                manifest.name, manifest.revision
+            )));
+        }
+        Ok(manifest)
+    }
+
+    fn to_registry_skill(&self, s: CatalogSkill) -> RegistrySkill {
+        RegistrySkill {
+            registry_id: self.id.clone(),
+            source_url: Some(s.manifest_url.clone().unwrap_or_else(|| {
+                format!(
+                    "https://github.com/{}/tree/{}/{}",
+                    s.source_repo, s.commit_sha, s.source_path
+                )
+            })),
+            skill_id: s.id,
+            name: s.name,
+            description: s.description,
+            version: None,
+            revision: Some(s.commit_sha),
+            installs: s.installs,
+            tags: s.tags,
+        }
+    }
+}
+
+#[async_trait]
+impl SkillRegistry for CatalogRegistry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<RegistrySkill>, AppError> {
+        Ok(self
+            .load_all()
+            .await?
+            .into_iter()
+            .filter(|s| {
+                let mut fields = vec![s.name.as_str(), s.provider.as_str()];
+                fields.extend(s.description.as_deref());
+                fields.extend(s.tags.iter().map(String::as_str));
+                matches_query(query, &fields)
+            })
+            .take(limit)
+            .map(|s| self.to_registry_skill(s))
+            .collect())
+    }
+
+    async fn metadata(&self, skill_id: &str) -> Result<RegistrySkill, AppError> {
+        Ok(self.to_registry_skill(self.entry(skill_id).await?))
+    }
+
+    async fn latest_revision(&self, skill_id: &str) -> Result<String, AppError> {
+        Ok(self.entry(skill_id).await?.commit_sha)
+    }
+
+    async fn list_files(&self, skill_id: &str, revision: &str) -> Result<Vec<String>, AppError> {
+        let entry = self.entry(skill_id).await?;
+        match &entry.manifest_url {
+            Some(url) => Ok(self
+                .manifest(url, revision)
+                .await?
+                .files
+                .into_iter()
+                .map(|f| f.path)
+                .collect()),
+            None => self.source(&entry).list_files(&entry.source_path, revision).await,
+        }
+    }
+
+    async fn fetch_file(
+        &self,
+        skill_id: &str,
+        revision: &str,
+        rel_path: &str,
+    ) -> Result<Vec<u8>, AppError> {
+        let entry = self.entry(skill_id).await?;
+        let Some(url) = &entry.manifest_url else {
+            return self
+                .source(&entry)
+                .fetch_file(&entry.source_path, revision, rel_path)
+                .await;
+        };
+        let manifest = self.manifest(url, revision).await?;
+        let rel = SkillPath::parse(rel_path)?;
+        let file = manifest
+            .files
+            .iter()
+            .find(|f| f.path == rel.as_str())
+            .ok_or_else(|| AppError::NotFound(format!("清单中不存在文件: {}", rel)))?;
+        let content =
+            static_registry::fetch_url(&self.client, &static_registry::file_url(url, rel.as_str())?).await?;
+        static_registry::verify_file(file, &content)?;
+        Ok(content)
+    }
+}
+
+// ── skills.sh ──
+
+/// skills.sh 搜索 API；Skill ID 为 `owner/repo/skill`，文件从源仓库读取
+pub struct SkillsShRegistry {
+    id: String,
+    base_url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+    /// skill_id → 仓库内目录，避免每次调用都重新遍历仓库
+    located: Mutex<HashMap<String, String>>,
+}
+
+impl SkillsShRegistry {
+    fn from_config(config: &RegistryConfig, token: Option<String>) -> Self {
+        Self {
+            id: config.id.clone(),
+            base_url: config.url.trim_end_matches('/').to_string(),
+            token,
+            client: http_client(15),
+            located: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 原始搜索结果；请求失败时返回错误，非 2xx 返回空列表
+    pub async fn search_raw(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SkillsShSearchResult>, AppError> {
+        #[derive(serde::Deserialize)]
+        struct ApiResp {
+            skills: Option<Vec<SkillsShSearchResult>>,
+        }
+
+        let url = format!(
+            "{}/api/search?q={}&limit={}",
+            self.base_url,
+            super::catalog::urlencoding_encode(query),
+            limit
+        );
+        info!("[registry] skills.sh: {}", url);
+        let resp = self
+            .client
+            .get(&url)
+            .header("User-Agent", USER_AGENT)
+            .header("Accept-Encoding", "identity")
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("skills.sh 请求失败: {}", e)))?;
+        if !resp.status().is_success() {
+            info!("[registry] skills.sh HTTP {}", resp.status());
+            return Ok(vec![]);
+        }
+        let data: ApiResp = resp
+            .json()
+            .await
+            .map_err(|_| AppError::Internal("解析 skills.sh 响应失败".into()))?;
+        Ok(data.skills.unwrap_or_default())
+    }
+
+    async fn locate(&self, skill_id: &str) -> Result<(GitHubRegistry, String), AppError> {
+        let (owner_repo, name) = skill_id
+            .rsplit_once('/')
+            .filter(|(repo, _)| repo.contains('/'))
+            .ok_or_else(|| AppError::Validation(format!("skills.sh Skill ID 应为 owner/repo/skill: {}", skill_id)))?;
+        let github = GitHubRegistry::for_repo(owner_repo, self.token.clone());
+        let cached = self.located.lock().ok().and_then(|m| m.get(skill_id).cloned());
+        let dir = match cached {
+            Some(dir) => dir,
+            None => {
+                let (dir, _) = github.find_skill(name).await?;
+                if let Ok(mut m) = self.located.lock() {
+                    m.insert(skill_id.to_string(), dir.clone());
+                }
+                dir
+            }
+        };
+        Ok((github, dir))
+    }
+}
+
+#[async_trait]
+impl SkillRegistry for SkillsShRegistry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<RegistrySkill>, AppError> {
+        Ok(self
+            .search_raw(query, limit)
+            .await?
+            .into_iter()
+            .map(|s| {
+                let skill_id = if s.skill_id.is_empty() { s.name.clone() } else { s.skill_id.clone() };
+                RegistrySkill {
+                    registry_id: self.id.clone(),
+                    skill_id: format!("{}/{}", s.source, skill_id),
+                    source_url: Some(format!("https://github.com/{}", s.source)),
+                    name: s.name,
+                    description: s.description,
+                    version: None,
+                    revision: None,
+                    installs: Some(s.installs),
+                    tags: Vec::new(),
+                }
+            })
+            .collect())
+    }
+
+    async fn metadata(&self, skill_id: &str) -> Result<RegistrySkill, AppError> {
+        let (github, dir) = self.locate(skill_id).await?;
+        let mut skill = github.metadata(&dir).await?;
+        skill.registry_id = self.id.clone();
+        skill.skill_id = skill_id.to_string();
+        Ok(skill)
+    }
+
+    async fn latest_revision(&self, skill_id: &str) -> Result<String, AppError> {
+        let (github, dir) = self.locate(skill_id).await?;
+        github.latest_revision(&dir).await
+    }
+
+    async fn list_files(&self, skill_id: &str, revision: &str) -> Result<Vec<String>, AppError> {
+        let (github, dir) = self.locate(skill_id).await?;
+        github.list_files(&dir, revision).await
+    }
+
+    async fn fetch_file(
+        &self,
+        skill_id: &str,
+        revision: &str,
+        rel_path: &str,
+    ) -> Result<Vec<u8>, AppError> {
+        let (github, dir) = self.locate(skill_id).await?;
+        github.fetch_file(&dir, revision, rel_path).await
+    }
+}
+
+// ── 任意 git 地址 ──
+
+/// 上次 fetch 的时间，同一注册源 5 分钟内不重复 fetch
+static GIT_FETCHED: Mutex<Option<HashMap<String, std::time::Instant>>> = Mutex::new(None);
+const GIT_FETCH_TTL_SECS: u64 = 300;
+
+/// 在 ~/.skills-manager/registries/<id> 维护一份 bare 镜像，按 commit 读取文件
+pub struct GitRegistry {
+    id: String,
+    url: String,
+    git_ref: Option<String>,
+    mirror: PathBuf,
+}
+
+impl GitRegistry {
+    fn from_config(config: &RegistryConfig) -> Result<Self, AppError> {
+        let options = parse_options(config.options.as_deref())?;
+        let mirror = dirs::home_dir()
+            .ok_or_else(|| AppError::Internal("无法获取 home 目录".into()))?
+            .join(".skills-manager")
+            .join("registries")
+            .join(SkillPath::name(&config.id)?.as_str());
+        Ok(Self {
+            id: config.id.clone(),
+            url: config.url.clone(),
+            git_ref: options.r#ref,
+            mirror,
+        })
+    }
+
+    fn rev(&self) -> &str {
+        self.git_ref.as_deref().unwrap_or("HEAD")
+    }
+
+    /// 首次使用时 clone，之后按 TTL fetch；clone / fetch 在阻塞线程池中执行
+    async fn sync(&self) -> Result<(), AppError> {
+        let fresh = GIT_FETCHED
+            .lock()
+            .ok()
+            .and_then(|m| m.as_ref().and_then(|m| m.get(&self.id)).map(|t| t.elapsed().as_secs()))
+            .is_some_and(|age| age < GIT_FETCH_TTL_SECS);
+        if fresh && self.mirror.exists() {
+            return Ok(());
+        }
+
+        let (url, mirror) = (self.url.clone(), self.mirror.clone());
+        git_engine::blocking(move || {
+            let op = GitOperation::new("fetch");
+            if mirror.join("HEAD").exists() {
+                info!("[registry] git fetch: {}", git_engine::redact_url(&url));
+                git_engine::fetch(
+                    &git2::Repository::open_bare(&mirror)?,
+                    &["+refs/heads/*:refs/heads/*", "+refs/tags/*:refs/tags/*"],
+                    &op,
+                )?;
+            } else {
+                info!("[registry] git clone --bare: {}", git_engine::redact_url(&url));
+                if let Some(parent) = mirror.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                git_engine::clone_mirror(&url, &mirror, &op)?;
+            }
+            Ok(())
+        })
+        .await?;
+        if let Ok(mut m) = GIT_FETCHED.lock() {
+            m.get_or_insert_with(HashMap::new)
+                .insert(self.id.clone(), std::time::Instant::now());
+        }
+        Ok(())
+    }
+
+    fn open(&self) -> Result<git2::Repository, AppError> {
+        Ok(git2::Repository::open_bare(&self.mirror)?)
+    }
+
+    fn ls_tree(&self, revision: &str, dir: &str) -> Result<Vec<String>, AppError> {
+        Ok(git_engine::list_files(&self.open()?, revision, dir)?)
+    }
+
+    fn skill_md(&self, revision: &str, dir: &str) -> Option<Vec<u8>> {
+        let repo = self.open().ok()?;
+        git_engine::read_file(&repo, revision, &join_repo_path(dir, SKILL_MD)).ok()
+    }
+
+    fn repo_name(&self) -> &str {
+        dir_name(self.url.trim_end_matches('/').trim_end_matches(".git"), &self.id)
+    }
+}
+
+#[async_trait]
+impl SkillRegistry for GitRegistry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<RegistrySkill>, AppError> {
+        self.sync().await?;
+        let files = self.ls_tree(self.rev(), "")?;
+        let mut results = Vec::new();
+        for dir in skill_dirs_of(files.iter().map(String::as_str)) {
+            let md = self.skill_md(self.rev(), &dir);
+            let mut skill = skill_from_md(&self.id, &dir, dir_name(&dir, self.repo_name()), md.as_deref());
+            skill.source_url = Some(git_engine::redact_url(&self.url));
+            let mut fields = vec![dir.as_str(), skill.name.as_str()];
+            fields.extend(skill.description.as_deref());
+            if matches_query(query, &fields) {
+                results.push(skill);
+            }
+            if results.len() >= limit {
+                break;
+            }
+        }
+        Ok(results)
+    }
+
+    async fn metadata(&self, skill_id: &str) -> Result<RegistrySkill, AppError> {
+        let revision = self.latest_revision(skill_id).await?;
+        let md = self
+            .skill_md(&revision, skill_id)
+            .ok_or_else(|| AppError::NotFound(format!("{} 中不存在 Skill: {}", git_engine::redact_url(&self.url), skill_id)))?;
+        let mut skill = skill_from_md(&self.id, skill_id, dir_name(skill_id, self.repo_name()), Some(&md));
+        skill.source_url = Some(git_engine::redact_url(&self.url));
+        skill.revision = Some(revision);
+        Ok(skill)
+    }
+
+    async fn latest_revision(&self, skill_id: &str) -> Result<String, AppError> {
+        self.sync().await?;
+        git_engine::last_commit_touching(&self.open()?, self.rev(), skill_id)?
+            .ok_or_else(|| AppError::NotFound(format!("{} 中不存在 Skill: {}", git_engine::redact_url(&self.url), skill_id)))
+    }
+
+    async fn list_files(&self, skill_id: &str, revision: &str) -> Result<Vec<String>, AppError> {
+        self.sync().await?;
+        let prefix = if skill_id.is_empty() { String::new() } else { format!("{}/", skill_id) };
+        self.ls_tree(revision, skill_id)?
+            .iter()
+            .filter_map(|p| p.strip_prefix(&prefix))
+            .map(|rel| SkillPath::parse(rel).map(|p| p.as_str().to_string()))
+            .collect()
+    }
+
+    async fn fetch_file(
+        &self,
+        skill_id: &str,
+        revision: &str,
+        rel_path: &str,
+    ) -> Result<Vec<u8>, AppError> {
+        let rel = SkillPath::parse(rel_path)?;
+        let path = join_repo_path(skill_id, rel.as_str());
+        Ok(git_engine::read_file(&self.open()?, revision, &path)?)
+    }
+}
+
+// ── 本地目录 ──
+
+/// 本地或网络共享目录；版本为目录内容的 checksum
+pub struct LocalRegistry {
+    id: String,
+    root: PathBuf,
+}
+
+impl LocalRegistry {
+    fn from_config(config: &RegistryConfig) -> Self {
+        Self {
+            id: config.id.clone(),
+            root: PathBuf::from(&config.url),
+        }
+    }
+
+    fn skill_dir(&self, skill_id: &str) -> Result<PathBuf, AppError> {
+        let dir = if skill_id.is_empty() {
+            self.root.clone()
+        } else {
+            SkillPath::parse(skill_id)?.join_under(&self.root)?
+        };
+        if !dir.join(SKILL_MD).is_file() {
+            return Err(AppError::NotFound(format!("{} 中不存在 Skill: {}", self.root.display(), skill_id)));
+        }
+        Ok(dir)
+    }
+
+    fn read_skill(&self, skill_id: &str) -> Result<RegistrySkill, AppError> {
+        let dir = self.skill_dir(skill_id)?;
+        let md = std::fs::read(dir.join(SKILL_MD)).ok();
+        let fallback = self.root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let mut skill = skill_from_md(&self.id, skill_id, dir_name(skill_id, &fallback), md.as_deref());
+        skill.source_url = Some(dir.to_string_lossy().to_string());
+        skill.revision = compute_dir_checksum(&dir);
+        Ok(skill)
+    }
+}
+
+#[async_trait]
+impl SkillRegistry for LocalRegistry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<RegistrySkill>, AppError> {
+        if !self.root.is_dir() {
+            return Err(AppError::NotFound(format!("目录不存在: {}", self.root.display())));
+        }
+        let rels: Vec<String> = walk_files(&self.root)
+            .iter()
+            .filter_map(|p| p.strip_prefix(&self.root).ok())
+            .filter_map(|p| SkillPath::from_relative(p).ok())
+            .map(|p| p.as_str().to_string())
+            .collect();
+        let mut results = Vec::new();
+        for dir in skill_dirs_of(rels.iter().map(String::as_str)) {
+            let skill = self.read_skill(&dir)?;
+            let mut fields = vec![dir.as_str(), skill.name.as_str()];
+            fields.extend(skill.description.as_deref());
+            if matches_query(query, &fields) {
+                results.push(skill);
+            }
+            if results.len() >= limit {
+                break;
+            }
+        }
+        Ok(results)
+    }
+
+    async fn metadata(&self, skill_id: &str) -> Result<RegistrySkill, AppError> {
+        self.read_skill(skill_id)
+    }
+
+    async fn latest_revision(&self, skill_id: &str) -> Result<String, AppError> {
+        compute_dir_checksum(&self.skill_dir(skill_id)?)
+            .ok_or_else(|| AppError::NotFound(format!("Skill 目录为空: {}", skill_id)))
+    }
+
+    async fn list_files(&self, skill_id: &str, revision: &str) -> Result<Vec<String>, AppError> {
+        let dir = self.skill_dir(skill_id)?;
+        if compute_dir_checksum(&dir).as_deref() != Some(revision) {
+            return Err(AppError::Validation(format!(
+                "本地目录内容已变化，请重新获取版本: {}",
+                dir.display()
+            )));
+        }
+        walk_files(&dir)
+            .iter()
+            .map(|p| SkillPath::from_relative(p.strip_prefix(&dir).unwrap_or(p)).map(|p| p.as_str().to_string()))
+            .collect()
+    }
+
+    async fn fetch_file(
+        &self,
+        skill_id: &str,
+        _revision: &str,
+        rel_path: &str,
+    ) -> Result<Vec<u8>, AppError> {
+        let dir = self.skill_dir(skill_id)?;
+        Ok(std::fs::read(SkillPath::parse(rel_path)?.join_under(&dir)?)?)
+    }
+}
+
+// ── 配置 ──
+
+/// 按配置创建注册源；token 用于访问 GitHub API，未传入时用设置中保存的
+pub fn open_registry(
+    pool: &DbPool,
+    config: &RegistryConfig,
+    token: Option<String>,
+) -> Result<Box<dyn SkillRegistry>, AppError> {
+    let token = credentials::github_token(pool, token);
+    Ok(match config.kind.as_str() {
+        "catalog" => Box::new(CatalogRegistry::from_config(pool, config, token)?),
+        "skills_sh" => Box::new(SkillsShRegistry::from_config(config, token)),
+        "github" => Box::new(GitHubRegistry::from_config(config, token)?),
+        "git" => Box::new(GitRegistry::from_config(config)?),
+        "local" => Box::new(LocalRegistry::from_config(config)),
+        other => {
+            return Err(AppError::Validation(format!("未知的注册源类型: {}", other)));
+        }
+    })
+}
+
+/// 预置注册源的默认配置（表中记录被删除时兜底）
+fn builtin_config(id: &str) -> Option<RegistryConfig> {
+    let (name, kind, url, options) = match id {
+        DMGROK_REGISTRY_ID => (
+            "dmgrok catalog",
+            "catalog",
+            "https://cdn.jsdelivr.net/gh/dmgrok/agent_skills_directory@main/catalog.json",
+            Some(r#"{"mirrors":["https://raw.githubusercontent.com/dmgrok/agent_skills_directory/main/catalog.json"]}"#),
+        ),
+        SKILLS_SH_REGISTRY_ID => ("skills.sh", "skills_sh", "https://skills.sh", None),
+        _ => return None,
+    };
+    Some(RegistryConfig {
+        id: id.to_string(),
+        name: name.to_string(),
+        kind: kind.to_string(),
+        url: url.to_string(),
+        options: options.map(String::from),
+        enabled: true,
+        priority: 0,
+        created_at: String::new(),
+        updated_at: String::new(),
+    })
+}
+
+const REGISTRY_COLUMNS: &str =
+    "id, name, kind, url, options, enabled, priority, created_at, updated_at";
+
+fn row_to_config(row: &rusqlite::Row) -> rusqlite::Result<RegistryConfig> {
+    Ok(RegistryConfig {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        kind: row.get(2)?,
+        url: row.get(3)?,
+        options: row.get(4)?,
+        enabled: row.get::<_, i64>(5)? != 0,
+        priority: row.get(6)?,
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
+
+pub fn get_registry_config(conn: &Connection, registry_id: &str) -> Result<RegistryConfig, AppError> {
+    let config = conn
+        .query_row(
+            &format!("SELECT {} FROM registries WHERE id = ?1", REGISTRY_COLUMNS),
+            params![registry_id],
+            row_to_config,
+        )
+        .optional()?;
+    config
+        .or_else(|| builtin_config(registry_id))
+        .ok_or_else(|| AppError::NotFound(format!("注册源不存在: {}", registry_id)))
+}
+
+/// 已启用的 catalog 类注册源（dmgrok 与自托管静态注册源），市场页与更新检测使用
+pub fn catalog_registries(pool: &DbPool) -> Result<Vec<CatalogRegistry>, AppError> {
+    list_registries_internal(pool)?
+        .iter()
+        .filter(|c| c.enabled && c.kind == "catalog")
+        .map(|c| CatalogRegistry::from_config(pool, c, credentials::github_token(pool, None)))
+        .collect()
+}
+
+/// 按 ID 打开一个 catalog 类注册源
+pub fn catalog_registry(
+    pool: &DbPool,
+    registry_id: &str,
+    token: Option<String>,
+) -> Result<CatalogRegistry, AppError> {
+    let config = get_registry_config(&*pool.get()?, registry_id)?;
+    if config.kind != "catalog" {
+        return Err(AppError::Validation(format!("注册源 {} 不是 catalog 类型", registry_id)));
+    }
+    CatalogRegistry::from_config(pool, &config, credentials::github_token(pool, token))
+}
+
+/// 内置的 skills.sh（搜索与安装量）
+pub fn skills_sh(pool: &DbPool) -> Result<SkillsShRegistry, AppError> {
+    let config = get_registry_config(&*pool.get()?, SKILLS_SH_REGISTRY_ID)?;
+    Ok(SkillsShRegistry::from_config(&config, None))
+}
+
+pub fn list_registries_internal(pool: &DbPool) -> Result<Vec<RegistryConfig>, AppError> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM registries ORDER BY priority, name",
+        REGISTRY_COLUMNS
+    ))?;
+    let rows = stmt
+        .query_map([], row_to_config)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+#[tauri::command]
+pub async fn list_registries(pool: State<'_, DbPool>) -> Result<Vec<RegistryConfig>, AppError> {
+    list_registries_internal(&pool)
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RegistryInput {
+    /// 为空时新建
+    pub id: Option<String>,
+    pub name: String,
+    pub kind: String,
+    pub url: String,
+    pub options: Option<String>,
+    pub enabled: Option<bool>,
+    pub priority: Option<i64>,
+}
+
+/// 新建或更新注册源，保存前校验类型、options 和 ID
+pub fn save_registry_internal(pool: &DbPool, input: RegistryInput) -> Result<RegistryConfig, AppError> {
+    info!("[save_registry] id={:?}, kind={}, url={}", input.id, input.kind, input.url);
+    if !REGISTRY_KINDS.contains(&input.kind.as_str()) {
+        return Err(AppError::Validation(format!(
+            "未知的注册源类型: {}（可选: {}）",
+            input.kind,
+            REGISTRY_KINDS.join(", ")
+        )));
+    }
+    if input.url.trim().is_empty() {
+        return Err(AppError::Validation("注册源地址不能为空".into()));
+    }
+    parse_options(input.options.as_deref())?;
+    let id = match input.id.filter(|id| !id.trim().is_empty()) {
+        Some(id) => SkillPath::name(&id)?.as_str().to_string(),
+        None => Uuid::new_v4().to_string(),
+    };
+    let name = if input.name.trim().is_empty() { id.clone() } else { input.name };
+
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO registries (id, name, kind, url, options, enabled, priority)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(id) DO UPDATE SET
+            name = ?2, kind = ?3, url = ?4, options = ?5, enabled = ?6, priority = ?7,
+            updated_at = datetime('now')",
+        params![
+            id,
+            name,
+            input.kind,
+            input.url.trim(),
+            input.options,
+            input.enabled.unwrap_or(true),
+            input.priority.unwrap_or(100)
+        ],
+    )?;
+    // 地址或镜像可能变了，旧快照不再可信
+    forget_catalog(&conn, &id)?;
+    get_registry_config(&conn, &id)
+}
+
+#[tauri::command]
+pub async fn save_registry(
+    registry: RegistryInput,
+    pool: State<'_, DbPool>,
+) -> Result<RegistryConfig, AppError> {
+    save_registry_internal(&pool, registry)
+}
+
+pub fn delete_registry_internal(pool: &DbPool, registry_id: &str) -> Result<(), AppError> {
+    info!("[delete_registry] id={}", registry_id);
+    let conn = pool.get()?;
+    let n = conn.execute("DELETE FROM registries WHERE id = ?1", params![registry_id])?;
+    if n == 0 {
+        return Err(AppError::NotFound(format!("注册源不存在: {}", registry_id)));
+    }
+    forget_catalog(&conn, registry_id)
+}
+
+#[tauri::command]
+pub async fn delete_registry(registry_id: String, pool: State<'_, DbPool>) -> Result<(), AppError> {
+    delete_registry_internal(&pool, &registry_id)
+}
+
+// ── 搜索与安装 ──
+
+/// 在指定注册源（缺省为全部已启用的）中并发搜索，结果按注册源优先级排列。
+/// 单个注册源失败只记录日志，不影响其他来源
+pub async fn search_registries_internal(
+    pool: &DbPool,
+    query: String,
+    registry_ids: Option<Vec<String>>,
+    limit: Option<usize>,
+    token: Option<String>,
+) -> Result<Vec<RegistrySkill>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    info!("[search_registries] query={}, registries={:?}", query, registry_ids);
+
+    let configs: Vec<RegistryConfig> = match &registry_ids {
+        Some(ids) if !ids.is_empty() => {
+            let conn = pool.get()?;
+            ids.iter()
+                .map(|id| get_registry_config(&conn, id))
+                .collect::<Result<_, _>>()?
+        }
+        _ => list_registries_internal(pool)?
+            .into_iter()
+            .filter(|c| c.enabled)
+            .collect(),
+    };
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for (order, config) in configs.iter().enumerate() {
+        let registry = open_registry(pool, config, token.clone())?;
+        let query = query.clone();
+        tasks.spawn(async move {
+            let result = registry.search(&query, limit).await;
+            (order, registry.id().to_string(), result)
+        });
+    }
+
+    let mut batches = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((order, _, Ok(skills))) => batches.push((order, skills)),
+            Ok((_, id, Err(e))) => warn!("[search_registries] {} 搜索失败: {}", id, e),
+            Err(e) => warn!("[search_registries] 搜索任务异常: {}", e),
+        }
+    }
+    batches.sort_by_key(|(order, _)| *order);
+    let results: Vec<RegistrySkill> = batches.into_iter().flat_map(|(_, s)| s).collect();
+    info!("[search_registries] 返回 {} 条", results.len());
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn search_registries(
+    query: String,
+    registry_ids: Option<Vec<String>>,
+    limit: Option<usize>,
+    token: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<RegistrySkill>, AppError> {
+    search_registries_internal(&pool, query, registry_ids, limit, token).await
+}
+
+/// 从注册源安装 Skill；revision 缺省为最新版本
+#[allow(clippy::too_many_arguments)]
+pub async fn install_from_registry_internal(
+    pool: &DbPool,
+    registry_id: String,
+    skill_id: String,
+    revision: Option<String>,
+    deploy_targets: Vec<DeployTarget>,
+    force_overwrite: Option<bool>,
+    token: Option<String>,
+) -> Result<SkillsShInstallResult, AppError> {
+    info!(
+        "[install_from_registry] registry={}, skill={}, revision={:?}",
+        registry_id, skill_id, revision
+    );
+    let config = get_registry_config(&*pool.get()?, &registry_id)?;
+    let registry = open_registry(pool, &config, token)?;
+    let meta = registry.metadata(&skill_id).await?;
+    let revision = match revision.or(meta.revision.clone()) {
+        Some(rev) => rev,
+        None => registry.latest_revision(&skill_id).await?,
+    };
+
+    install_from_source(
+        pool,
+        registry.as_ref(),
+        &skill_id,
+        &revision,
+        &meta.name,
+        InstallSource {
+            source_type: "registry",
+            url: meta.source_url.clone().unwrap_or_else(|| config.url.clone()),
+            registry_id: Some(config.id.as_str()),
+        },
+        &deploy_targets,
+        force_overwrite.unwrap_or(false),
+    )
+    .await
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn install_from_registry(
+    registry_id: String,
+    skill_id: String,
+    revision: Option<String>,
+    deploy_targets: Vec<DeployTarget>,
+    force_overwrite: Option<bool>,
+    token: Option<String>,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<SkillsShInstallResult, AppError> {
+    let result = install_from_registry_internal(
+        &pool,
+        registry_id,
+        skill_id,
+        revision,
+        deploy_targets,
+        force_overwrite,
+        token,
+    )
+    .await?;
+    watcher.resync(&pool);
+    Ok(result)
+}
@@ -5,7 +5,9 @@ use tauri::State;
 use uuid::Uuid;
 
 use super::skill_files::{compute_db_checksum, db_import_from_dir, has_db_files};
+use super::staging::is_staging_path;
 use super::utils::compute_dir_checksum;
+use super::watcher::WatcherService;
 
 use crate::db::DbPool;
 use crate::error::AppError;
@@ -37,7 +39,7 @@ pub async fn scan_project(project_path: String) -> Result<ScanResult, AppError>
             if let Ok(entries) = std::fs::read_dir(&skill_dir) {
                 for entry in entries.flatten() {
                     let path = entry.path();
-                    if path.is_dir() {
+                    if path.is_dir() && !is_staging_path(&path) {
                         let skill_md = path.join("SKILL.md");
                         if skill_md.exists() {
                             let (name, description, version) = parse_skill_md(&skill_md);
@@ -64,10 +66,9 @@ pub async fn scan_project(project_path: String) -> Result<ScanResult, AppError>
     })
 }
 
-#[tauri::command]
-pub async fn scan_and_import_project(
+pub async fn scan_and_import_project_internal(
+    pool: &DbPool,
     project_path: String,
-    pool: State<'_, DbPool>,
 ) -> Result<ScanResult, AppError> {
     info!("[scan_and_import] 开始扫描并导入: {}", project_path);
     let scan_result = scan_project(project_path.clone()).await?;
@@ -157,6 +158,17 @@ pub async fn scan_and_import_project(
     Ok(scan_result)
 }
 
+#[tauri::command]
+pub async fn scan_and_import_project(
+    project_path: String,
+    pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
+) -> Result<ScanResult, AppError> {
+    let result = scan_and_import_project_internal(&pool, project_path).await?;
+    watcher.resync(&pool);
+    Ok(result)
+}
+
 
 #[derive(serde::Serialize)]
 pub struct GlobalScanResult {
@@ -180,7 +192,7 @@ pub async fn scan_global_skills_internal(pool: &DbPool) -> Result<GlobalScanResu
             if let Ok(entries) = std::fs::read_dir(&global_dir) {
                 for entry in entries.flatten() {
                     let path = entry.path();
-                    if path.is_dir() {
+                    if path.is_dir() && !is_staging_path(&path) {
                         let skill_md = path.join("SKILL.md");
                         if skill_md.exists() {
                             let (name, description, version) = parse_skill_md(&skill_md);
@@ -299,8 +311,11 @@ pub async fn scan_global_skills_internal(pool: &DbPool) -> Result<GlobalScanResu
 #[tauri::command]
 pub async fn scan_global_skills(
     pool: State<'_, DbPool>,
+    watcher: State<'_, WatcherService>,
 ) -> Result<GlobalScanResult, AppError> {
-    scan_global_skills_internal(&pool).await
+    let result = scan_global_skills_internal(&pool).await?;
+    watcher.resync(&pool);
+    Ok(result)
 }
 
 fn parse_skill_md(path: &Path) -> (String, Option<String>, Option<String>) {
@@ -324,9 +339,9 @@ fn parse_skill_md(path: &Path) -> (String, Option<String>, Option<String>) {
     let mut description = None;
     let mut version = None;
 
-    if content.starts_with("---") {
-        if let Some(end) = content[3..].find("---") {
-            let frontmatter = &content[3..3 + end];
+    if let Some(rest) = content.strip_prefix("---") {
+        if let Some(end) = rest.find("---") {
+            let frontmatter = &rest[..end];
             for line in frontmatter.lines() {
                 let line = line.trim();
                 if let Some(val) = line.strip_prefix("name:") {
@@ -0,0 +1,353 @@
+//! 库内 Skill 全文搜索（SQLite FTS5）。
+//!
+//! `skill_search` 中每个 Skill 有一行元数据（path 为空串：名称、描述），每个文本文件一行
+//! （SKILL.md 的 frontmatter 单独成列）。元数据行由 skills 表上的触发器维护，
+//! 文件行由 `skill_files` 的写入 / 删除函数同步。
+//! 使用 trigram 分词：中文和 `gsap.timeline` 这类片段都能按子串命中，但每个词至少 3 个字符，
+//! 更短的词退回 LIKE 扫描。
+
+use log::info;
+use rusqlite::{params, params_from_iter, Connection};
+use serde::Serialize;
+use tauri::State;
+
+use crate::db::DbPool;
+use crate::error::AppError;
+
+/// 超过这个大小的文本文件不进索引
+const MAX_INDEXED_BYTES: usize = 512 * 1024;
+const DEFAULT_LIMIT: usize = 50;
+const SNIPPET_TOKENS: i64 = 16;
+const SNIPPET_CHARS: usize = 40;
+
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_END: &str = "</mark>";
+
+/// 列顺序与 bm25 权重一一对应：skill_id, path, name, description, frontmatter, body
+const COLUMNS: [&str; 6] = ["skill_id", "path", "name", "description", "frontmatter", "body"];
+const WEIGHTS: [f64; 6] = [0.0, 2.0, 10.0, 5.0, 3.0, 1.0];
+
+// ── 索引维护 ──
+
+/// SKILL.md 拆成 (frontmatter, 正文)；其他文件或没有 frontmatter 时全部算正文
+pub fn split_frontmatter<'a>(rel_path: &str, text: &'a str) -> (&'a str, &'a str) {
+    if rel_path != "SKILL.md" {
+        return ("", text);
+    }
+    match text.strip_prefix("---").and_then(|rest| rest.find("---").map(|end| (rest, end))) {
+        Some((rest, end)) => (&rest[..end], &rest[end + 3..]),
+        None => ("", text),
+    }
+}
+
+/// 写入文件后更新索引；二进制和超大文件只删除旧条目
+pub fn index_file(conn: &Connection, skill_id: &str, rel_path: &str, content: &[u8]) -> Result<(), AppError> {
+    unindex_file(conn, skill_id, rel_path)?;
+    if content.len() > MAX_INDEXED_BYTES {
+        return Ok(());
+    }
+    let Ok(text) = std::str::from_utf8(content) else {
+        return Ok(());
+    };
+    let (frontmatter, body) = split_frontmatter(rel_path, text);
+    conn.execute(
+        "INSERT INTO skill_search (skill_id, path, name, description, frontmatter, body)
+         VALUES (?1, ?2, '', '', ?3, ?4)",
+        params![skill_id, rel_path, frontmatter, body],
+    )?;
+    Ok(())
+}
+
+pub fn unindex_file(conn: &Connection, skill_id: &str, rel_path: &str) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM skill_search WHERE skill_id = ?1 AND path = ?2",
+        params![skill_id, rel_path],
+    )?;
+    Ok(())
+}
+
+/// 删除 Skill 所有文件的索引（元数据行保留）
+pub fn unindex_files(conn: &Connection, skill_id: &str) -> Result<(), AppError> {
+    conn.execute(
+        "DELETE FROM skill_search WHERE skill_id = ?1 AND path != ''",
+        params![skill_id],
+    )?;
+    Ok(())
+}
+
+// ── 搜索 ──
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillSearchMatch {
+    /// 命中的文件；名称 / 描述命中时为 None
+    pub path: Option<String>,
+    /// name | description | frontmatter | body | path
+    pub field: String,
+    /// 命中片段，关键词用 `<mark>` `</mark>` 包裹，其余为原文（前端需按文本渲染）
+    pub snippet: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkillSearchHit {
+    pub skill_id: String,
+    pub skill_name: String,
+    pub description: Option<String>,
+    /// 越大越相关
+    pub score: f64,
+    pub matches: Vec<SkillSearchMatch>,
+}
+
+/// 单行命中：(skill_id, name, description, path, score, [(field, snippet)])
+type RowHit = (String, String, Option<String>, String, f64, Vec<(String, String)>);
+
+/// 每个词作为一个短语，词之间为 AND
+fn fts_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|t| format!("\"{}\"", t.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn search_fts(conn: &Connection, terms: &[String], row_limit: usize) -> Result<Vec<RowHit>, AppError> {
+    let snippet = |col: usize| {
+        format!(
+            "snippet(skill_search, {}, '{}', '{}', '…', {})",
+            col, HIGHLIGHT_START, HIGHLIGHT_END, SNIPPET_TOKENS
+        )
+    };
+    let weights = WEIGHTS.iter().map(|w| format!("{:.1}", w)).collect::<Vec<_>>().join(", ");
+    let sql = format!(
+        "SELECT skill_search.skill_id, s.name, s.description, skill_search.path,
+                bm25(skill_search, {}), highlight(skill_search, 1, '{}', '{}'), {}, {}, {}, {}
+         FROM skill_search JOIN skills s ON s.id = skill_search.skill_id
+         WHERE skill_search MATCH ?1
+         ORDER BY bm25(skill_search, {})
+         LIMIT ?2",
+        weights,
+        HIGHLIGHT_START,
+        HIGHLIGHT_END,
+        snippet(2),
+        snippet(3),
+        snippet(4),
+        snippet(5),
+        weights
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt
+        .query_map(params![fts_query(terms), row_limit as i64], |row| {
+            let mut fields = Vec::new();
+            for (i, col) in (5..10).zip(1..6) {
+                let text: String = row.get(i)?;
+                if text.contains(HIGHLIGHT_START) {
+                    fields.push((COLUMNS[col].to_string(), text));
+                }
+            }
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                -row.get::<_, f64>(4)?,
+                fields,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// 在 text 中找第一个命中的词，截取前后若干字符并高亮
+fn like_snippet(text: &str, terms: &[String]) -> Option<String> {
+    let lower = text.to_lowercase();
+    // 大小写转换可能改变字节长度，此时退回区分大小写匹配
+    let haystack = if lower.len() == text.len() { &lower } else { text };
+    let (start, len) = terms
+        .iter()
+        .filter_map(|t| haystack.find(&t.to_lowercase()).map(|i| (i, t.len())))
+        .min()?;
+    let end = start + len;
+    if !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+        return None;
+    }
+    let before: String = {
+        let chars: Vec<char> = text[..start].chars().rev().take(SNIPPET_CHARS).collect();
+        chars.into_iter().rev().collect()
+    };
+    let after: String = text[end..].chars().take(SNIPPET_CHARS).collect();
+    Some(format!(
+        "{}{}{}{}{}{}{}",
+        if before.len() < start { "…" } else { "" },
+        before,
+        HIGHLIGHT_START,
+        &text[start..end],
+        HIGHLIGHT_END,
+        after,
+        if after.len() < text.len() - end { "…" } else { "" }
+    ))
+}
+
+/// trigram 索引不支持少于 3 个字符的词，此时逐行 LIKE 匹配，按命中字段的权重打分
+fn search_like(conn: &Connection, terms: &[String], row_limit: usize) -> Result<Vec<RowHit>, AppError> {
+    let searchable = &COLUMNS[1..];
+    let term_clause = format!(
+        "({})",
+        searchable
+            .iter()
+            .map(|c| format!("f.{} LIKE ? ESCAPE '\\'", c))
+            .collect::<Vec<_>>()
+            .join(" OR ")
+    );
+    let sql = format!(
+        "SELECT f.skill_id, s.name, s.description, f.path, f.name, f.description, f.frontmatter, f.body
+         FROM skill_search f JOIN skills s ON s.id = f.skill_id
+         WHERE {}",
+        vec![term_clause; terms.len()].join(" AND ")
+    );
+    let patterns: Vec<String> = terms
+        .iter()
+        .flat_map(|t| {
+            let escaped = t.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            std::iter::repeat_n(format!("%{}%", escaped), searchable.len())
+        })
+        .collect();
+
+    let mut stmt = conn.prepare(&sql)?;
+    let mut rows = stmt
+        .query_map(params_from_iter(patterns.iter()), |row| {
+            let mut fields = Vec::new();
+            let mut score = 0.0;
+            for (i, col) in (3..8).zip(1..6) {
+                let text: String = row.get(i)?;
+                if let Some(snippet) = like_snippet(&text, terms) {
+                    score += WEIGHTS[col];
+                    fields.push((COLUMNS[col].to_string(), snippet));
+                }
+            }
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, score, fields))
+        })?
+        .collect::<Result<Vec<RowHit>, _>>()?;
+    rows.sort_by(|a, b| b.4.total_cmp(&a.4));
+    rows.truncate(row_limit);
+    Ok(rows)
+}
+
+/// 按相关度返回 Skill，每个 Skill 附带命中的文件与高亮片段
+pub fn search_library(conn: &Connection, query: &str, limit: usize) -> Result<Vec<SkillSearchHit>, AppError> {
+    let terms: Vec<String> = query.split_whitespace().map(String::from).collect();
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
+    let limit = limit.max(1);
+    // 同一个 Skill 可能有多行命中，多取一些行再按 Skill 聚合
+    let row_limit = limit * 20;
+    let rows = if terms.iter().all(|t| t.chars().count() >= 3) {
+        search_fts(conn, &terms, row_limit)?
+    } else {
+        search_like(conn, &terms, row_limit)?
+    };
+
+    let mut hits: Vec<SkillSearchHit> = Vec::new();
+    for (skill_id, skill_name, description, path, score, fields) in rows {
+        let idx = match hits.iter().position(|h| h.skill_id == skill_id) {
+            Some(idx) => idx,
+            None => {
+                hits.push(SkillSearchHit {
+                    skill_id,
+                    skill_name,
+                    description,
+                    score,
+                    matches: Vec::new(),
+                });
+                hits.len() - 1
+            }
+        };
+        let hit = &mut hits[idx];
+        hit.score = hit.score.max(score);
+        let path = (!path.is_empty()).then_some(path);
+        hit.matches.extend(fields.into_iter().map(|(field, snippet)| SkillSearchMatch {
+            path: path.clone(),
+            field,
+            snippet,
+        }));
+    }
+    hits.truncate(limit);
+    Ok(hits)
+}
+
+pub fn search_skills_internal(
+    pool: &DbPool,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SkillSearchHit>, AppError> {
+    info!("[search_skills] query={}, limit={:?}", query, limit);
+    let hits = search_library(&*pool.get()?, &query, limit.unwrap_or(DEFAULT_LIMIT))?;
+    info!("[search_skills] 命中 {} 个 Skill", hits.len());
+    Ok(hits)
+}
+
+#[tauri::command]
+pub async fn search_skills(
+    query: String,
+    limit: Option<usize>,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SkillSearchHit>, AppError> {
+    search_skills_internal(&pool, query, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::skill_files::{db_delete_file, db_write_file_text};
+    use crate::db::schema::init_schema;
+
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        for (id, name, desc) in [("s1", "gsap-animations", "GSAP 动画最佳实践"), ("s2", "pdf", "读写 PDF")] {
+            conn.execute(
+                "INSERT INTO skills (id, name, description) VALUES (?1, ?2, ?3)",
+                params![id, name, desc],
+            )
+            .unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn finds_file_contents_and_follows_writes_and_deletes() {
+        let conn = open();
+        db_write_file_text(&conn, "s1", "SKILL.md", "---\nname: gsap-animations\n---\nUse gsap.timeline() for sequences.").unwrap();
+        db_write_file_text(&conn, "s2", "references/api.md", "no animation here").unwrap();
+
+        let hits = search_library(&conn, "gsap.timeline", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].skill_id, "s1");
+        let m = &hits[0].matches[0];
+        assert_eq!((m.path.as_deref(), m.field.as_str()), (Some("SKILL.md"), "body"));
+        assert!(m.snippet.contains("<mark>gsap.timeline</mark>"));
+
+        db_write_file_text(&conn, "s1", "SKILL.md", "rewritten").unwrap();
+        assert!(search_library(&conn, "gsap.timeline", 10).unwrap().is_empty());
+
+        db_delete_file(&conn, "s2", "references/api.md").unwrap();
+        assert!(search_library(&conn, "animation here", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn ranks_name_matches_first_and_supports_short_terms() {
+        let conn = open();
+        db_write_file_text(&conn, "s2", "SKILL.md", "convert pdf to gsap-friendly svg").unwrap();
+
+        let hits = search_library(&conn, "gsap", 10).unwrap();
+        assert_eq!(hits.iter().map(|h| h.skill_id.as_str()).collect::<Vec<_>>(), ["s1", "s2"]);
+
+        // 两个字的中文词走 LIKE
+        let hits = search_library(&conn, "动画", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].matches[0].field, "description");
+        assert!(hits[0].matches[0].snippet.contains("<mark>动画</mark>"));
+
+        conn.execute("DELETE FROM skills WHERE id = 's2'", []).unwrap();
+        assert_eq!(search_library(&conn, "gsap", 10).unwrap().len(), 1);
+    }
+}
@@ -3,14 +3,19 @@ use rusqlite::params;
 use tauri::{State, WebviewWindow};
 use uuid::Uuid;
 
+use super::credentials::{self, GitCredentialInput};
+use super::git_engine;
+use super::git_workspace;
+use super::ignore_rules;
+use super::skill_files;
 use crate::db::DbPool;
 use crate::error::AppError;
 use crate::models::{AppSetting, GitExportConfig};
 
 // ── App Settings ──
 
-#[tauri::command]
-pub async fn get_all_settings(pool: State<'_, DbPool>) -> Result<Vec<AppSetting>, AppError> {
+/// 所有设置；密钥类设置不返回给前端
+pub fn get_all_settings_internal(pool: &DbPool) -> Result<Vec<AppSetting>, AppError> {
     info!("[get_all_settings] 查询所有设置");
     let conn = pool.get()?;
     let mut stmt = conn.prepare(
@@ -25,12 +30,22 @@ pub async fn get_all_settings(pool: State<'_, DbPool>) -> Result<Vec<AppSetting>
         })
     })?.collect::<Result<Vec<_>, _>>()?;
 
-    Ok(settings)
+    Ok(settings
+        .into_iter()
+        .filter(|s| !credentials::is_secret_setting(&s.key))
+        .collect())
 }
 
 #[tauri::command]
-pub async fn get_setting(key: String, pool: State<'_, DbPool>) -> Result<Option<String>, AppError> {
+pub async fn get_all_settings(pool: State<'_, DbPool>) -> Result<Vec<AppSetting>, AppError> {
+    get_all_settings_internal(&pool)
+}
+
+pub fn get_setting_internal(pool: &DbPool, key: &str) -> Result<Option<String>, AppError> {
     info!("[get_setting] 查询设置: {}", key);
+    if credentials::is_secret_setting(key) {
+        return Ok(None);
+    }
     let conn = pool.get()?;
     let value: Option<String> = conn
         .query_row(
@@ -44,12 +59,16 @@ pub async fn get_setting(key: String, pool: State<'_, DbPool>) -> Result<Option<
 }
 
 #[tauri::command]
-pub async fn set_setting(
-    key: String,
-    value: String,
-    pool: State<'_, DbPool>,
-) -> Result<(), AppError> {
-    info!("[set_setting] 设置: {} = {}", key, value);
+pub async fn get_setting(key: String, pool: State<'_, DbPool>) -> Result<Option<String>, AppError> {
+    get_setting_internal(&pool, &key)
+}
+
+pub fn set_setting_internal(pool: &DbPool, key: &str, value: &str) -> Result<(), AppError> {
+    if credentials::is_secret_setting(key) {
+        info!("[set_setting] 设置: {} = ***", key);
+    } else {
+        info!("[set_setting] 设置: {} = {}", key, value);
+    }
     let conn = pool.get()?;
     conn.execute(
         "INSERT INTO app_settings (key, value, updated_at)
@@ -57,20 +76,36 @@ pub async fn set_setting(
          ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = datetime('now')",
         params![key, value],
     )?;
+    if key == ignore_rules::IGNORE_PATTERNS_KEY {
+        // 规则变化会改变 checksum 覆盖的文件集合，已存的 checksum 随之重算，否则各处都会误判为已修改
+        ignore_rules::load_user_patterns(&conn);
+        let tx = conn.unchecked_transaction()?;
+        skill_files::recompute_checksums(&tx)?;
+        tx.commit()?;
+    }
     Ok(())
 }
 
-// ── Git Export Config ──
-
 #[tauri::command]
-pub async fn get_git_export_configs(
+pub async fn set_setting(
+    key: String,
+    value: String,
     pool: State<'_, DbPool>,
+) -> Result<(), AppError> {
+    set_setting_internal(&pool, &key, &value)
+}
+
+// ── Git Export Config ──
+
+pub async fn get_git_export_configs_internal(
+    pool: &DbPool,
 ) -> Result<Vec<GitExportConfig>, AppError> {
     info!("[get_git_export_configs] 查询 Git 导出配置");
     let conn = pool.get()?;
     let mut stmt = conn.prepare(
         "SELECT id, provider, remote_url, auth_type, branch, auto_export,
-                last_push_at, last_pull_at, created_at, updated_at
+                last_push_at, last_pull_at, created_at, updated_at,
+                EXISTS(SELECT 1 FROM git_credentials g WHERE g.config_id = git_export_config.id)
          FROM git_export_config ORDER BY provider"
     )?;
 
@@ -86,12 +121,20 @@ pub async fn get_git_export_configs(
             last_pull_at: row.get(7)?,
             created_at: row.get(8)?,
             updated_at: row.get(9)?,
+            has_credential: row.get(10)?,
         })
     })?.collect::<Result<Vec<_>, _>>()?;
 
     Ok(configs)
 }
 
+#[tauri::command]
+pub async fn get_git_export_configs(
+    pool: State<'_, DbPool>,
+) -> Result<Vec<GitExportConfig>, AppError> {
+    get_git_export_configs_internal(&pool).await
+}
+
 #[tauri::command]
 pub async fn save_git_export_config(
     provider: String,
@@ -99,9 +142,15 @@ pub async fn save_git_export_config(
     auth_type: String,
     branch: String,
     auto_export: String,
+    credential: Option<GitCredentialInput>,
     pool: State<'_, DbPool>,
 ) -> Result<GitExportConfig, AppError> {
-    info!("[save_git_export_config] 保存 Git 配置: provider={}, url={}", provider, remote_url);
+    info!(
+        "[save_git_export_config] 保存 Git 配置: provider={}, url={}, auth={}",
+        provider,
+        git_engine::redact_url(&remote_url),
+        auth_type
+    );
     let conn = pool.get()?;
     let new_id = Uuid::new_v4().to_string();
 
@@ -117,11 +166,18 @@ pub async fn save_git_export_config(
              updated_at  = datetime('now')",
         params![new_id, provider, remote_url, auth_type, branch, auto_export],
     )?;
+    let config_id: String = conn.query_row(
+        "SELECT id FROM git_export_config WHERE remote_url = ?1",
+        params![remote_url],
+        |row| row.get(0),
+    )?;
+    credentials::save(&conn, &config_id, &auth_type, credential.as_ref())?;
 
     // 查询实际写入的记录（可能是已有记录的 id）
     let config = conn.query_row(
         "SELECT id, provider, remote_url, auth_type, branch, auto_export,
-                last_push_at, last_pull_at, created_at, updated_at
+                last_push_at, last_pull_at, created_at, updated_at,
+                EXISTS(SELECT 1 FROM git_credentials g WHERE g.config_id = git_export_config.id)
          FROM git_export_config WHERE remote_url = ?1",
         params![remote_url],
         |row| Ok(GitExportConfig {
@@ -135,6 +191,7 @@ pub async fn save_git_export_config(
             last_pull_at: row.get(7)?,
             created_at: row.get(8)?,
             updated_at: row.get(9)?,
+            has_credential: row.get(10)?,
         }),
     )?;
 
@@ -146,9 +203,9 @@ pub async fn delete_git_export_config(
     config_id: String,
     pool: State<'_, DbPool>,
 ) -> Result<(), AppError> {
-    let conn = pool.get()?;
-    conn.execute("DELETE FROM git_export_config WHERE id = ?1", params![config_id])?;
-    Ok(())
+    pool.get()?
+        .execute("DELETE FROM git_export_config WHERE id = ?1", params![config_id])?;
+    git_workspace::remove(&config_id).await
 }
 
 // ── App Initialization ──
@@ -231,6 +288,7 @@ pub async fn initialize_app(
             ("startup_page", "projects"),
             ("notifications_enabled", "true"),
             ("file_watch_enabled", "true"),
+            ("watcher_quiet_ms", "500"),
             ("auto_export_frequency", "manual"),
             ("update_check_frequency", "daily"),
             ("auto_update", "false"),
@@ -265,6 +323,9 @@ pub async fn reset_app(pool: State<'_, DbPool>) -> Result<(), AppError> {
          DELETE FROM skill_backups;
          DELETE FROM skill_deployments;
          DELETE FROM skill_sources;
+         DELETE FROM skill_revision_files;
+         DELETE FROM skill_revisions;
+         DELETE FROM skill_blobs;
          DELETE FROM skill_files;
          DELETE FROM skills;
          DELETE FROM projects;
@@ -285,6 +346,7 @@ pub async fn reset_app(pool: State<'_, DbPool>) -> Result<(), AppError> {
     )?;
 
     tx.commit()?;
+    ignore_rules::load_user_patterns(&conn);
     Ok(())
 }
 
@@ -292,3 +354,25 @@ pub async fn reset_app(pool: State<'_, DbPool>) -> Result<(), AppError> {
 pub fn open_devtools(window: WebviewWindow) {
     window.open_devtools();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pool::create_pool;
+
+    #[test]
+    fn secret_settings_are_write_only() {
+        let root = std::env::temp_dir().join(format!("skm-settings-{}", Uuid::new_v4()));
+        let pool = create_pool(&root.join("skills.db")).unwrap();
+        set_setting_internal(&pool, credentials::GITHUB_TOKEN_KEY, "ghp_secret").unwrap();
+        set_setting_internal(&pool, "theme", "\"dark\"").unwrap();
+
+        let all = get_all_settings_internal(&pool).unwrap();
+        assert!(all.iter().any(|s| s.key == "theme"));
+        assert!(all.iter().all(|s| s.key != credentials::GITHUB_TOKEN_KEY && s.value.as_deref() != Some("ghp_secret")));
+        assert_eq!(get_setting_internal(&pool, credentials::GITHUB_TOKEN_KEY).unwrap(), None);
+        // 后端仍然能读到保存的 token
+        assert_eq!(credentials::github_token(&pool, None).as_deref(), Some("ghp_secret"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
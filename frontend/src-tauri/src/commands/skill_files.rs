@@ -1,16 +1,20 @@
-/// skill_files.rs — DB 文件存储核心模块
-///
-/// 提供以 `skill_files` 表为权威源的所有文件读写操作。
-/// 其他模块通过这些函数读写 Skill 文件内容，不再直接操作本地文件系统。
+//! skill_files.rs — DB 文件存储核心模块
+//!
+//! 提供以 `skill_files` 表为权威源的所有文件读写操作。
+//! 其他模块通过这些函数读写 Skill 文件内容，不再直接操作本地文件系统。
 
 use log::info;
 use rusqlite::{params, Connection};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::path::Path;
 use uuid::Uuid;
-use walkdir::WalkDir;
 
+use super::ignore_rules::{walk_files, IgnoreRules, SKILLIGNORE_FILE};
+use super::search;
+use super::skill_path::SkillPath;
 use crate::error::AppError;
+use crate::models::SkillRevision;
 
 // ── 单文件读取 ──────────────────────────────────────────────────────────────
 
@@ -20,10 +24,11 @@ pub fn db_read_file(
     skill_id: &str,
     rel_path: &str,
 ) -> Result<Vec<u8>, AppError> {
+    let rel_path = SkillPath::parse(rel_path)?;
     let content: Vec<u8> = conn
         .query_row(
             "SELECT content FROM skill_files WHERE skill_id = ?1 AND relative_path = ?2",
-            params![skill_id, rel_path],
+            params![skill_id, rel_path.as_str()],
             |row| row.get(0),
         )
         .map_err(|_| {
@@ -48,13 +53,27 @@ pub fn db_read_file_text(
 
 // ── 单文件写入 ──────────────────────────────────────────────────────────────
 
-/// 写入单个文件到 DB（UPSERT）
+/// 写入单个文件到 DB（UPSERT），并记录一个新版本
 pub fn db_write_file(
     conn: &Connection,
     skill_id: &str,
     rel_path: &str,
     content: &[u8],
 ) -> Result<(), AppError> {
+    upsert_file(conn, skill_id, rel_path, content)?;
+    db_record_revision(conn, skill_id)?;
+    Ok(())
+}
+
+/// 所有写入 skill_files 的路径都经过这里校验，库里不会出现越界路径。
+/// 不记录版本：一次改动多个文件时逐个调用，最后调用一次 `db_record_revision`
+pub fn upsert_file(
+    conn: &Connection,
+    skill_id: &str,
+    rel_path: &str,
+    content: &[u8],
+) -> Result<(), AppError> {
+    let rel_path = SkillPath::parse(rel_path)?;
     let id = Uuid::new_v4().to_string();
     let size = content.len() as i64;
     conn.execute(
@@ -62,8 +81,9 @@ pub fn db_write_file(
          VALUES (?1, ?2, ?3, ?4, ?5, datetime('now'))
          ON CONFLICT(skill_id, relative_path) DO UPDATE SET
             content = ?4, size_bytes = ?5, updated_at = datetime('now')",
-        params![id, skill_id, rel_path, content, size],
+        params![id, skill_id, rel_path.as_str(), content, size],
     )?;
+    search::index_file(conn, skill_id, rel_path.as_str(), content)?;
     Ok(())
 }
 
@@ -77,17 +97,31 @@ pub fn db_write_file_text(
     db_write_file(conn, skill_id, rel_path, content.as_bytes())
 }
 
-/// 删除 DB 中的单个文件
+/// 删除 DB 中的单个文件，并记录一个新版本
 pub fn db_delete_file(
     conn: &Connection,
     skill_id: &str,
     rel_path: &str,
 ) -> Result<(), AppError> {
+    remove_file(conn, skill_id, rel_path)?;
+    db_record_revision(conn, skill_id)?;
+    Ok(())
+}
+
+/// 删除单个文件，不记录版本（与 `upsert_file` 配合批量使用）
+pub fn remove_file(conn: &Connection, skill_id: &str, rel_path: &str) -> Result<(), AppError> {
+    let rel_path = SkillPath::parse(rel_path)?;
     conn.execute(
         "DELETE FROM skill_files WHERE skill_id = ?1 AND relative_path = ?2",
-        params![skill_id, rel_path],
+        params![skill_id, rel_path.as_str()],
     )?;
-    Ok(())
+    search::unindex_file(conn, skill_id, rel_path.as_str())
+}
+
+/// 删除 Skill 的全部文件（不记录版本，通常紧接着整体导入）
+pub fn db_clear_files(conn: &Connection, skill_id: &str) -> Result<(), AppError> {
+    conn.execute("DELETE FROM skill_files WHERE skill_id = ?1", params![skill_id])?;
+    search::unindex_files(conn, skill_id)
 }
 
 // ── 文件列表 ────────────────────────────────────────────────────────────────
@@ -109,7 +143,7 @@ pub fn db_list_files(
 // ── 批量导入：目录 → DB ──────────────────────────────────────────────────────
 
 /// 将本地目录中的所有文件导入到 DB skill_files 表（UPSERT）
-/// 返回写入的文件数量
+/// 整个目录只记录一个版本。返回写入的文件数量
 pub fn db_import_from_dir(
     conn: &Connection,
     skill_id: &str,
@@ -124,29 +158,13 @@ pub fn db_import_from_dir(
 
     let mut count = 0usize;
 
-    let mut paths: Vec<std::path::PathBuf> = WalkDir::new(dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .map(|e| e.into_path())
-        .collect();
-    paths.sort();
-
-    for path in paths {
-        let rel = path
-            .strip_prefix(dir)
-            .unwrap_or(&path)
-            .to_string_lossy()
-            .to_string();
-
-        // 跳过隐藏文件和系统文件
-        if rel.starts_with('.') || rel.contains("/.") {
-            continue;
-        }
+    // 隐藏文件、编辑器临时文件等按忽略规则跳过
+    for path in walk_files(dir) {
+        let rel = SkillPath::from_relative(path.strip_prefix(dir).unwrap_or(&path))?;
 
         match std::fs::read(&path) {
             Ok(content) => {
-                db_write_file(conn, skill_id, &rel, &content)?;
+                upsert_file(conn, skill_id, rel.as_str(), &content)?;
                 count += 1;
             }
             Err(e) => {
@@ -159,6 +177,8 @@ pub fn db_import_from_dir(
         }
     }
 
+    db_record_revision(conn, skill_id)?;
+
     info!(
         "[skill_files] db_import_from_dir: skill={}, dir={}, count={}",
         skill_id,
@@ -187,11 +207,15 @@ pub fn db_export_to_dir(
     }
 
     std::fs::create_dir_all(dst)?;
+    let rules = IgnoreRules::for_db_skill(conn, skill_id);
     let mut count = 0usize;
 
     for rel_path in &files {
+        if rules.is_ignored(Path::new(rel_path), false) {
+            continue;
+        }
         let content = db_read_file(conn, skill_id, rel_path)?;
-        let target = dst.join(rel_path);
+        let target = SkillPath::parse(rel_path)?.join_under(dst)?;
         if let Some(parent) = target.parent() {
             std::fs::create_dir_all(parent)?;
         }
@@ -230,16 +254,103 @@ pub fn compute_db_checksum(conn: &Connection, skill_id: &str) -> Option<String>
         .filter_map(|r| r.ok())
         .collect();
 
-    if rows.is_empty() {
-        return None;
+    // 早期导入的被忽略文件仍可能留在 DB 中，不计入 checksum，与 compute_dir_checksum 保持一致
+    hash_unignored(&IgnoreRules::for_db_skill(conn, skill_id), &rows)
+}
+
+/// 忽略规则变化后重新计算已存的 checksum：各版本、Skill，以及部署记录。
+/// 部署记录的 checksum 按原先对应的版本换算；对应不上任何版本的（上次同步后本地改过）保持不变。
+/// 返回改动的 Skill 数
+pub fn recompute_checksums(conn: &Connection) -> Result<usize, AppError> {
+    let skill_ids: Vec<(String, Option<String>)> = {
+        let mut stmt = conn.prepare("SELECT id, checksum FROM skills")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    let mut changed = 0usize;
+    for (skill_id, old_checksum) in &skill_ids {
+        // 旧 checksum → 新 checksum；同一旧值对应多个版本时以较新的版本为准
+        let mut renamed: BTreeMap<String, Option<String>> = BTreeMap::new();
+        let revisions: Vec<(i64, Option<String>)> = {
+            let mut stmt = conn.prepare(
+                "SELECT revision, checksum FROM skill_revisions WHERE skill_id = ?1 ORDER BY revision",
+            )?;
+            let rows = stmt
+                .query_map(params![skill_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        for (revision, old) in revisions {
+            let mut files = Vec::new();
+            for (rel_path, _) in db_revision_manifest(conn, skill_id, revision)? {
+                let content = db_read_revision_file(conn, skill_id, revision, &rel_path)?;
+                files.push((rel_path, content));
+            }
+            let skillignore = files
+                .iter()
+                .find(|(rel, _)| rel == SKILLIGNORE_FILE)
+                .map(|(_, c)| String::from_utf8_lossy(c).into_owned());
+            let new = hash_unignored(&IgnoreRules::new(skillignore.as_deref()), &files);
+            if new != old {
+                conn.execute(
+                    "UPDATE skill_revisions SET checksum = ?1 WHERE skill_id = ?2 AND revision = ?3",
+                    params![new, skill_id, revision],
+                )?;
+            }
+            if let Some(old) = old {
+                renamed.insert(old, new);
+            }
+        }
+
+        let new_checksum = compute_db_checksum(conn, skill_id);
+        if let Some(old) = old_checksum {
+            renamed.insert(old.clone(), new_checksum.clone());
+        }
+        if &new_checksum == old_checksum {
+            continue;
+        }
+        changed += 1;
+        conn.execute(
+            "UPDATE skills SET checksum = ?1 WHERE id = ?2",
+            params![new_checksum, skill_id],
+        )?;
+        let deployments: Vec<(String, String)> = {
+            let mut stmt = conn.prepare(
+                "SELECT id, checksum FROM skill_deployments WHERE skill_id = ?1 AND checksum IS NOT NULL",
+            )?;
+            let rows = stmt
+                .query_map(params![skill_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            rows
+        };
+        for (dep_id, old) in deployments {
+            if let Some(new) = renamed.get(&old) {
+                conn.execute(
+                    "UPDATE skill_deployments SET checksum = ?1 WHERE id = ?2",
+                    params![new, dep_id],
+                )?;
+            }
+        }
     }
+    info!("[skill_files] 忽略规则变化，已重算 {} 个 Skill 的 checksum", changed);
+    Ok(changed)
+}
 
+/// 与 compute_db_checksum 相同的算法：按路径排序的未忽略文件，没有文件时为 None
+fn hash_unignored(rules: &IgnoreRules, files: &[(String, Vec<u8>)]) -> Option<String> {
     let mut hasher = Sha256::new();
-    for (rel_path, content) in &rows {
-        hasher.update(rel_path.as_bytes());
-        hasher.update(content);
+    let mut found = false;
+    for (rel_path, content) in files {
+        if !rules.is_ignored(Path::new(rel_path), false) {
+            hasher.update(rel_path.as_bytes());
+            hasher.update(content);
+            found = true;
+        }
     }
-    Some(hex::encode(hasher.finalize()))
+    found.then(|| hex::encode(hasher.finalize()))
 }
 
 /// 更新 skills 表中的 checksum（从 DB files 重新计算）
@@ -252,6 +363,237 @@ pub fn refresh_skill_checksum(conn: &Connection, skill_id: &str) -> Result<Optio
     Ok(checksum)
 }
 
+// ── 版本历史（内容寻址）──────────────────────────────────────────────────────
+//
+// skill_blobs 按内容 SHA-256 去重存储；skill_revisions + skill_revision_files 记录
+// 每个版本的 {relative_path -> blob}。版本只追加不修改，skill_files 始终是最新版本的工作副本。
+
+/// 把 skill_files 当前内容快照为新版本。与最新版本完全相同时不创建，返回 None。
+pub fn db_record_revision(conn: &Connection, skill_id: &str) -> Result<Option<i64>, AppError> {
+    let rows: Vec<(String, Vec<u8>)> = {
+        let mut stmt = conn.prepare(
+            "SELECT relative_path, content FROM skill_files
+             WHERE skill_id = ?1
+             ORDER BY relative_path",
+        )?;
+        let rows = stmt
+            .query_map(params![skill_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+
+    // checksum 算法与 compute_db_checksum 一致（同样跳过被忽略的文件），便于与 skills.checksum 对照
+    let rules = IgnoreRules::for_db_skill(conn, skill_id);
+    let mut hasher = Sha256::new();
+    let mut manifest: Vec<(String, String)> = Vec::with_capacity(rows.len());
+    for (rel_path, content) in &rows {
+        if !rules.is_ignored(Path::new(rel_path), false) {
+            hasher.update(rel_path.as_bytes());
+            hasher.update(content);
+        }
+        manifest.push((rel_path.clone(), hex::encode(Sha256::digest(content))));
+    }
+
+    let latest = db_latest_revision(conn, skill_id)?;
+    match latest {
+        Some(rev) if db_revision_manifest(conn, skill_id, rev)? == manifest => return Ok(None),
+        None if rows.is_empty() => return Ok(None),
+        _ => {}
+    }
+
+    let revision = latest.unwrap_or(0) + 1;
+    let checksum = if rows.is_empty() {
+        None
+    } else {
+        Some(hex::encode(hasher.finalize()))
+    };
+
+    conn.execute(
+        "INSERT INTO skill_revisions (skill_id, revision, checksum, file_count)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![skill_id, revision, checksum, rows.len() as i64],
+    )?;
+    for ((rel_path, content), (_, sha)) in rows.iter().zip(&manifest) {
+        conn.execute(
+            "INSERT OR IGNORE INTO skill_blobs (sha256, content, size_bytes) VALUES (?1, ?2, ?3)",
+            params![sha, content, content.len() as i64],
+        )?;
+        conn.execute(
+            "INSERT INTO skill_revision_files (skill_id, revision, relative_path, blob_sha256)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![skill_id, revision, rel_path, sha],
+        )?;
+    }
+
+    info!(
+        "[skill_files] 记录版本: skill={}, revision={}, files={}",
+        skill_id,
+        revision,
+        rows.len()
+    );
+    Ok(Some(revision))
+}
+
+/// Skill 的最新版本号，没有任何版本时返回 None
+pub fn db_latest_revision(conn: &Connection, skill_id: &str) -> Result<Option<i64>, AppError> {
+    let rev: Option<i64> = conn.query_row(
+        "SELECT MAX(revision) FROM skill_revisions WHERE skill_id = ?1",
+        params![skill_id],
+        |row| row.get(0),
+    )?;
+    Ok(rev)
+}
+
+/// 列出 Skill 的所有版本（新 → 旧）
+pub fn db_list_revisions(conn: &Connection, skill_id: &str) -> Result<Vec<SkillRevision>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT skill_id, revision, checksum, file_count, created_at
+         FROM skill_revisions WHERE skill_id = ?1
+         ORDER BY revision DESC",
+    )?;
+    let revisions = stmt
+        .query_map(params![skill_id], |row| {
+            Ok(SkillRevision {
+                skill_id: row.get(0)?,
+                revision: row.get(1)?,
+                checksum: row.get(2)?,
+                file_count: row.get(3)?,
+                created_at: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(revisions)
+}
+
+/// 某个版本的文件清单：[(relative_path, blob_sha256)]，按路径排序
+pub fn db_revision_manifest(
+    conn: &Connection,
+    skill_id: &str,
+    revision: i64,
+) -> Result<Vec<(String, String)>, AppError> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM skill_revisions WHERE skill_id = ?1 AND revision = ?2",
+        params![skill_id, revision],
+        |row| row.get(0),
+    )?;
+    if exists == 0 {
+        return Err(AppError::NotFound(format!(
+            "版本不存在: skill_id={}, revision={}",
+            skill_id, revision
+        )));
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT relative_path, blob_sha256 FROM skill_revision_files
+         WHERE skill_id = ?1 AND revision = ?2
+         ORDER BY relative_path",
+    )?;
+    let manifest = stmt
+        .query_map(params![skill_id, revision], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(manifest)
+}
+
+/// 读取某个版本中单个文件的内容
+pub fn db_read_revision_file(
+    conn: &Connection,
+    skill_id: &str,
+    revision: i64,
+    rel_path: &str,
+) -> Result<Vec<u8>, AppError> {
+    conn.query_row(
+        "SELECT b.content FROM skill_revision_files f
+         JOIN skill_blobs b ON b.sha256 = f.blob_sha256
+         WHERE f.skill_id = ?1 AND f.revision = ?2 AND f.relative_path = ?3",
+        params![skill_id, revision, rel_path],
+        |row| row.get(0),
+    )
+    .map_err(|_| {
+        AppError::NotFound(format!(
+            "文件不存在: skill_id={}, revision={}, path={}",
+            skill_id, revision, rel_path
+        ))
+    })
+}
+
+/// 按 checksum 查找版本（同一内容出现多次时取最新的一个）
+pub fn db_find_revision_by_checksum(
+    conn: &Connection,
+    skill_id: &str,
+    checksum: &str,
+) -> Result<Option<i64>, AppError> {
+    let rev: Option<i64> = conn.query_row(
+        "SELECT MAX(revision) FROM skill_revisions WHERE skill_id = ?1 AND checksum = ?2",
+        params![skill_id, checksum],
+        |row| row.get(0),
+    )?;
+    Ok(rev)
+}
+
+/// 将某个历史版本写出到目录，返回写出的文件数量
+pub fn db_export_revision_to_dir(
+    conn: &Connection,
+    skill_id: &str,
+    revision: i64,
+    dst: &Path,
+) -> Result<usize, AppError> {
+    let manifest = db_revision_manifest(conn, skill_id, revision)?;
+    std::fs::create_dir_all(dst)?;
+    for (rel_path, _) in &manifest {
+        let content = db_read_revision_file(conn, skill_id, revision, rel_path)?;
+        let target = SkillPath::parse(rel_path)?.join_under(dst)?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&target, &content)?;
+    }
+    Ok(manifest.len())
+}
+
+/// 用历史版本覆盖 skill_files。恢复本身会记录为一个新版本，历史不会被改写。
+/// 返回恢复的文件数量
+pub fn db_restore_revision(
+    conn: &Connection,
+    skill_id: &str,
+    revision: i64,
+) -> Result<usize, AppError> {
+    let manifest = db_revision_manifest(conn, skill_id, revision)?;
+
+    db_clear_files(conn, skill_id)?;
+    for (rel_path, _) in &manifest {
+        let content = db_read_revision_file(conn, skill_id, revision, rel_path)?;
+        upsert_file(conn, skill_id, rel_path, &content)?;
+    }
+    db_record_revision(conn, skill_id)?;
+
+    Ok(manifest.len())
+}
+
+/// 用一组文件整体替换 skill_files（不在其中的文件被删除），只记录一个新版本。
+/// 返回写入的文件数量
+pub fn db_replace_files(
+    conn: &Connection,
+    skill_id: &str,
+    files: &BTreeMap<String, Vec<u8>>,
+) -> Result<usize, AppError> {
+    db_clear_files(conn, skill_id)?;
+    for (rel_path, content) in files {
+        upsert_file(conn, skill_id, rel_path, content)?;
+    }
+    db_record_revision(conn, skill_id)?;
+    Ok(files.len())
+}
+
+/// 删除不再被任何版本引用的 blob，返回删除数量
+pub fn db_gc_blobs(conn: &Connection) -> Result<usize, AppError> {
+    let n = conn.execute(
+        "DELETE FROM skill_blobs
+         WHERE sha256 NOT IN (SELECT DISTINCT blob_sha256 FROM skill_revision_files)",
+        [],
+    )?;
+    Ok(n)
+}
+
 // ── 文件是否存在检测 ─────────────────────────────────────────────────────────
 
 /// 检测 Skill 在 DB 中是否有文件（用于判断是否需要迁移）
@@ -264,3 +606,57 @@ pub fn has_db_files(conn: &Connection, skill_id: &str) -> bool {
     .unwrap_or(0)
         > 0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::schema::init_schema;
+
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        conn.execute("INSERT INTO skills (id, name) VALUES ('s1', 'pdf')", []).unwrap();
+        conn
+    }
+
+    fn revisions(conn: &Connection) -> Vec<i64> {
+        db_list_revisions(conn, "s1").unwrap().iter().map(|r| r.revision).collect()
+    }
+
+    #[test]
+    fn batch_writes_record_one_revision() {
+        let conn = open();
+        for i in 0..5 {
+            upsert_file(&conn, "s1", &format!("ref/{}.md", i), b"x").unwrap();
+        }
+        remove_file(&conn, "s1", "ref/0.md").unwrap();
+        assert!(revisions(&conn).is_empty());
+
+        assert_eq!(db_record_revision(&conn, "s1").unwrap(), Some(1));
+        assert_eq!(db_revision_manifest(&conn, "s1", 1).unwrap().len(), 4);
+        // 内容未变时不产生新版本
+        assert_eq!(db_record_revision(&conn, "s1").unwrap(), None);
+        assert_eq!(revisions(&conn), vec![1]);
+    }
+
+    #[test]
+    fn restore_appends_revision_and_gc_keeps_referenced_blobs() {
+        let conn = open();
+        db_write_file(&conn, "s1", "SKILL.md", b"v1").unwrap();
+        db_write_file(&conn, "s1", "SKILL.md", b"v2").unwrap();
+        db_write_file(&conn, "s1", "extra.md", b"extra").unwrap();
+        assert_eq!(revisions(&conn), vec![3, 2, 1]);
+
+        assert_eq!(db_restore_revision(&conn, "s1", 1).unwrap(), 1);
+        assert_eq!(revisions(&conn), vec![4, 3, 2, 1]);
+        assert_eq!(db_read_file(&conn, "s1", "SKILL.md").unwrap(), b"v1");
+        assert!(db_read_file(&conn, "s1", "extra.md").is_err());
+        assert_eq!(db_read_revision_file(&conn, "s1", 3, "SKILL.md").unwrap(), b"v2");
+
+        // 所有 blob 都被某个版本引用，GC 不删；删掉版本后才回收
+        assert_eq!(db_gc_blobs(&conn).unwrap(), 0);
+        conn.execute("DELETE FROM skill_revision_files WHERE revision > 1", []).unwrap();
+        assert_eq!(db_gc_blobs(&conn).unwrap(), 2);
+        assert_eq!(db_read_revision_file(&conn, "s1", 1, "SKILL.md").unwrap(), b"v1");
+    }
+}
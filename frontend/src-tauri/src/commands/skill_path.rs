@@ -0,0 +1,192 @@
+//! Skill 内文件的相对路径校验。
+//!
+//! 前端参数、合并结果、catalog / Git 仓库里的文件名、skill_files 中的记录都可能来自不可信来源。
+//! 拼到磁盘目录之前一律先解析成 `SkillPath`：拒绝 `..`、绝对路径、Windows 盘符和 UNC 路径，
+//! 分隔符统一为 `/`；`join_under` 再检查目标目录下已存在的符号链接没有指向目录外。
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+
+/// 校验过的相对路径，总是 `a/b/c` 形式，不含 `.`、`..` 和空段
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SkillPath(String);
+
+fn invalid(raw: &str, reason: &str) -> AppError {
+    AppError::Validation(format!("非法的文件路径 {:?}: {}", raw, reason))
+}
+
+/// `C:`、`c:foo` 这类盘符前缀
+fn has_drive_prefix(segment: &str) -> bool {
+    let bytes = segment.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+impl SkillPath {
+    pub fn parse(raw: &str) -> Result<Self, AppError> {
+        if raw.contains('\0') {
+            return Err(invalid(raw, "包含空字符"));
+        }
+        if raw.starts_with('/') || raw.starts_with('\\') || Path::new(raw).has_root() {
+            return Err(invalid(raw, "不能是绝对路径"));
+        }
+
+        let mut segments = Vec::new();
+        for segment in raw.split(['/', '\\']) {
+            match segment {
+                "" | "." => continue,
+                ".." => return Err(invalid(raw, "不能包含 ..")),
+                s if segments.is_empty() && has_drive_prefix(s) => {
+                    return Err(invalid(raw, "不能包含盘符"));
+                }
+                s => segments.push(s),
+            }
+        }
+        if segments.is_empty() {
+            return Err(invalid(raw, "路径为空"));
+        }
+        Ok(Self(segments.join("/")))
+    }
+
+    /// 遍历目录得到的相对路径（strip_prefix 的结果）
+    pub fn from_relative(rel: &Path) -> Result<Self, AppError> {
+        Self::parse(&rel.to_string_lossy())
+    }
+
+    /// 单层目录名（Skill 名），不能含分隔符
+    pub fn name(raw: &str) -> Result<Self, AppError> {
+        let path = Self::parse(raw)?;
+        if path.0.contains('/') {
+            return Err(invalid(raw, "名称不能包含路径分隔符"));
+        }
+        Ok(path)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// 拼到 base 下。base 之下已存在的每一级如果是符号链接，解析后必须仍在 base 内；
+    /// 无法解析的悬空链接同样拒绝（写入会落到链接指向的位置）。
+    pub fn join_under(&self, base: &Path) -> Result<PathBuf, AppError> {
+        let mut target = base.to_path_buf();
+        target.extend(self.0.split('/'));
+
+        let Ok(root) = base.canonicalize() else {
+            // base 还不存在，其下也不可能有符号链接
+            return Ok(target);
+        };
+        let mut current = base.to_path_buf();
+        for segment in self.0.split('/') {
+            current.push(segment);
+            let Ok(meta) = current.symlink_metadata() else {
+                break;
+            };
+            if meta.file_type().is_symlink() {
+                let resolved = current
+                    .canonicalize()
+                    .map_err(|_| invalid(&self.0, "指向无法解析的符号链接"))?;
+                if !resolved.starts_with(&root) {
+                    return Err(invalid(&self.0, "符号链接指向目标目录之外"));
+                }
+            }
+        }
+        Ok(target)
+    }
+}
+
+impl fmt::Display for SkillPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// base 下名为 name 的 Skill 目录（部署目标、库目录、导出目录）。
+/// 只校验名称：链接部署本身就是指向库目录的符号链接，不能按 `join_under` 拒绝
+pub fn skill_dir_under(base: &Path, name: &str) -> Result<PathBuf, AppError> {
+    Ok(base.join(SkillPath::name(name)?.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SkillPath;
+    use std::path::Path;
+
+    #[test]
+    fn normalizes_relative_paths() {
+        for (raw, expected) in [
+            ("SKILL.md", "SKILL.md"),
+            ("docs/usage.md", "docs/usage.md"),
+            ("./docs//usage.md", "docs/usage.md"),
+            ("docs\\usage.md", "docs/usage.md"),
+            ("notes:v2.md", "notes:v2.md"),
+        ] {
+            assert_eq!(SkillPath::parse(raw).unwrap().as_str(), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn rejects_parent_segments() {
+        for raw in ["..", "../x", "docs/../../x", "docs/..", "..\\x", "docs\\..\\..\\x"] {
+            assert!(SkillPath::parse(raw).is_err(), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        for raw in ["/etc/passwd", "\\Windows\\x", "\\\\server\\share\\x", "//server/share", "", ".", "./"] {
+            assert!(SkillPath::parse(raw).is_err(), "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn rejects_windows_drives() {
+        for raw in ["C:\\Windows\\x", "c:/x", "C:x", "D:", "\\\\?\\C:\\x"] {
+            assert!(SkillPath::parse(raw).is_err(), "{}", raw);
+        }
+    }
+
+    #[test]
+    fn name_is_single_segment() {
+        assert_eq!(SkillPath::name("my-skill").unwrap().as_str(), "my-skill");
+        for raw in ["a/b", "..", "../a", "/a", "C:"] {
+            assert!(SkillPath::name(raw).is_err(), "{}", raw);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn join_under_rejects_symlink_escapes() {
+        use std::os::unix::fs::symlink;
+
+        let root = std::env::temp_dir().join(format!("skm-path-{}", uuid::Uuid::new_v4()));
+        let base = root.join("skill");
+        let outside = root.join("outside");
+        std::fs::create_dir_all(base.join("docs")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        symlink(&outside, base.join("escape")).unwrap();
+        symlink(base.join("docs"), base.join("inner")).unwrap();
+        symlink(outside.join("missing.md"), base.join("dangling.md")).unwrap();
+        symlink(outside.join("secret.md"), base.join("file.md")).unwrap();
+        std::fs::write(outside.join("secret.md"), b"x").unwrap();
+
+        let join = |raw: &str| SkillPath::parse(raw).unwrap().join_under(&base);
+        assert_eq!(join("docs/a.md").unwrap(), base.join("docs").join("a.md"));
+        assert_eq!(join("new/dir/a.md").unwrap(), base.join("new").join("dir").join("a.md"));
+        assert!(join("inner/a.md").is_ok());
+        assert!(join("escape/a.md").is_err());
+        assert!(join("escape").is_err());
+        assert!(join("dangling.md").is_err());
+        assert!(join("file.md").is_err());
+
+        // base 本身是符号链接（链接部署）时以解析后的位置为准
+        let linked = root.join("linked");
+        symlink(&base, &linked).unwrap();
+        assert!(SkillPath::parse("inner/a.md").unwrap().join_under(&linked).is_ok());
+        assert!(SkillPath::parse("escape/a.md").unwrap().join_under(&linked).is_err());
+        assert!(SkillPath::parse("a.md").unwrap().join_under(Path::new("/nonexistent/skm")).is_ok());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
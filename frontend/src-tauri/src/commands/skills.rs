@@ -1,19 +1,25 @@
 use log::info;
 use rusqlite::params;
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 use tauri::State;
 use uuid::Uuid;
 
 use super::skill_files::{
-    compute_db_checksum, db_export_to_dir, db_import_from_dir,
-    db_list_files, db_read_file_text, db_write_file_text, has_db_files, refresh_skill_checksum,
+    compute_db_checksum, db_clear_files, db_export_to_dir, db_find_revision_by_checksum, db_gc_blobs,
+    db_import_from_dir, db_latest_revision, db_list_files, db_list_revisions, db_read_file_text,
+    db_read_revision_file, db_replace_files, db_restore_revision, db_revision_manifest,
+    db_write_file_text, has_db_files, refresh_skill_checksum,
 };
+use super::skill_path::SkillPath;
+use super::catalog::{merge_is_conflict, merge_update, read_db_files};
+use super::ignore_rules::IgnoreRules;
+use super::plan::{plan_redeploy, read_db_unignored, read_dir_files, DeploymentPlan, PLAN_UPDATE_SKILL};
 use crate::db::DbPool;
 use crate::error::AppError;
-use crate::models::{Skill, SkillSource, SkillBackup};
+use crate::models::{Skill, SkillSource, SkillBackup, SkillRevision};
 
-#[tauri::command]
-pub async fn get_skills(pool: State<'_, DbPool>) -> Result<Vec<Skill>, AppError> {
+pub async fn get_skills_internal(pool: &DbPool) -> Result<Vec<Skill>, AppError> {
     info!("[get_skills] 查询所有 Skill");
     let conn = pool.get()?;
     let mut stmt = conn.prepare(
@@ -47,7 +53,11 @@ pub async fn get_skills(pool: State<'_, DbPool>) -> Result<Vec<Skill>, AppError>
 }
 
 #[tauri::command]
-pub async fn get_skill_by_id(skill_id: String, pool: State<'_, DbPool>) -> Result<Skill, AppError> {
+pub async fn get_skills(pool: State<'_, DbPool>) -> Result<Vec<Skill>, AppError> {
+    get_skills_internal(&pool).await
+}
+
+pub async fn get_skill_by_id_internal(pool: &DbPool, skill_id: String) -> Result<Skill, AppError> {
     info!("[get_skill_by_id] 查询 Skill: {}", skill_id);
     let conn = pool.get()?;
     let skill = conn.query_row(
@@ -78,6 +88,11 @@ pub async fn get_skill_by_id(skill_id: String, pool: State<'_, DbPool>) -> Resul
     Ok(skill)
 }
 
+#[tauri::command]
+pub async fn get_skill_by_id(skill_id: String, pool: State<'_, DbPool>) -> Result<Skill, AppError> {
+    get_skill_by_id_internal(&pool, skill_id).await
+}
+
 #[tauri::command]
 pub async fn create_skill(
     name: String,
@@ -143,17 +158,23 @@ pub async fn create_skill(
     Ok(skill)
 }
 
-#[tauri::command]
-pub async fn delete_skill(skill_id: String, pool: State<'_, DbPool>) -> Result<(), AppError> {
+pub async fn delete_skill_internal(pool: &DbPool, skill_id: String) -> Result<(), AppError> {
     info!("[delete_skill] 删除 Skill: {}", skill_id);
     let conn = pool.get()?;
     let affected = conn.execute("DELETE FROM skills WHERE id = ?1", params![skill_id])?;
     if affected == 0 {
         return Err(AppError::NotFound(format!("Skill 不存在: {}", skill_id)));
     }
+    conn.execute("DELETE FROM skill_backups WHERE skill_id = ?1", params![skill_id])?;
+    db_gc_blobs(&conn)?;
     Ok(())
 }
 
+#[tauri::command]
+pub async fn delete_skill(skill_id: String, pool: State<'_, DbPool>) -> Result<(), AppError> {
+    delete_skill_internal(&pool, skill_id).await
+}
+
 // ── Watcher 变更决策命令 ──
 
 /// 清除 watcher 待处理标记（接受入库：全量同步或仅入库共用此命令）。
@@ -206,7 +227,7 @@ pub async fn discard_watcher_change(
             let backup_dir = std::path::Path::new(bp);
             if backup_dir.exists() {
                 let conn = pool.get()?;
-                conn.execute("DELETE FROM skill_files WHERE skill_id = ?1", params![skill_id])?;
+                db_clear_files(&conn, &skill_id)?;
                 let n = super::skill_files::db_import_from_dir(&conn, &skill_id, backup_dir)?;
                 let cs = super::skill_files::compute_db_checksum(&conn, &skill_id);
                 conn.execute(
@@ -234,17 +255,8 @@ pub async fn discard_watcher_change(
         };
         if let Some(ref dp) = deploy_path {
             let conn = pool.get()?;
-            let dst = std::path::Path::new(dp);
-            if dst.exists() { let _ = std::fs::remove_dir_all(dst); }
-            match super::skill_files::db_export_to_dir(&conn, &skill_id, dst) {
+            match super::deployments::redeploy_from_db(&conn, dep_id) {
                 Ok(_) => {
-                    let new_cs = super::utils::compute_dir_checksum(dst);
-                    let _ = conn.execute(
-                        "UPDATE skill_deployments SET checksum = ?1, status = 'synced',
-                         last_synced = datetime('now'), updated_at = datetime('now')
-                         WHERE id = ?2",
-                        params![new_cs, dep_id],
-                    );
                     info!("[discard_watcher_change] 已推旧版回触发部署: {}", dp);
                 }
                 Err(e) => log::warn!("[discard_watcher_change] 推回部署失败: {}", e),
@@ -305,8 +317,8 @@ pub async fn batch_delete_skill(
     let mut files_removed = 0usize;
     for (dep_id, dep_path) in &deployments {
         let path = Path::new(dep_path);
-        if path.exists() {
-            match std::fs::remove_dir_all(path) {
+        if path.symlink_metadata().is_ok() {
+            match super::deployments::remove_deploy_target(path) {
                 Ok(_) => {
                     files_removed += 1;
                     info!("[batch_delete_skill]   删除部署文件: {} (id={})", dep_path, dep_id);
@@ -322,8 +334,10 @@ pub async fn batch_delete_skill(
 
     let deployments_deleted = deployments.len();
 
-    // 4. 删除数据库记录（CASCADE 自动删除 skill_files, deployments, sources, backups）
+    // 4. 删除数据库记录（CASCADE 自动删除 skill_files, deployments, sources；备份记录不级联，单独删除）
     conn.execute("DELETE FROM skills WHERE id = ?1", params![skill_id])?;
+    conn.execute("DELETE FROM skill_backups WHERE skill_id = ?1", params![skill_id])?;
+    db_gc_blobs(&conn)?;
     info!(
         "[batch_delete_skill] 完成: skill='{}', deployments_deleted={}, files_removed={}",
         skill_name, deployments_deleted, files_removed
@@ -366,10 +380,9 @@ pub async fn get_skill_source(
     Ok(source)
 }
 
-#[tauri::command]
-pub async fn get_skill_backups(
+pub async fn get_skill_backups_internal(
+    pool: &DbPool,
     skill_id: String,
-    pool: State<'_, DbPool>,
 ) -> Result<Vec<SkillBackup>, AppError> {
     info!("[get_skill_backups] 查询 Skill 备份: {}", skill_id);
     let conn = pool.get()?;
@@ -395,6 +408,178 @@ pub async fn get_skill_backups(
     Ok(backups)
 }
 
+#[tauri::command]
+pub async fn get_skill_backups(
+    skill_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SkillBackup>, AppError> {
+    get_skill_backups_internal(&pool, skill_id).await
+}
+
+// ── 版本历史 ──
+
+pub async fn get_skill_revisions_internal(
+    pool: &DbPool,
+    skill_id: String,
+) -> Result<Vec<SkillRevision>, AppError> {
+    info!("[get_skill_revisions] skill={}", skill_id);
+    let conn = pool.get()?;
+    db_list_revisions(&conn, &skill_id)
+}
+
+#[tauri::command]
+pub async fn get_skill_revisions(
+    skill_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<SkillRevision>, AppError> {
+    get_skill_revisions_internal(&pool, skill_id).await
+}
+
+#[derive(serde::Serialize)]
+pub struct RevisionFileChange {
+    pub relative_path: String,
+    /// added | removed | modified
+    pub status: String,
+    pub old_content: Option<String>,
+    pub new_content: Option<String>,
+}
+
+/// 比较同一 Skill 的两个版本，只返回有变化的文件；二进制文件不返回内容
+pub async fn diff_skill_revisions_internal(
+    pool: &DbPool,
+    skill_id: String,
+    from_revision: i64,
+    to_revision: i64,
+) -> Result<Vec<RevisionFileChange>, AppError> {
+    info!(
+        "[diff_skill_revisions] skill={}, {} -> {}",
+        skill_id, from_revision, to_revision
+    );
+    let conn = pool.get()?;
+    let old: HashMap<String, String> = db_revision_manifest(&conn, &skill_id, from_revision)?
+        .into_iter()
+        .collect();
+    let new: HashMap<String, String> = db_revision_manifest(&conn, &skill_id, to_revision)?
+        .into_iter()
+        .collect();
+
+    let mut paths: Vec<&String> = old.keys().chain(new.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let read_text = |revision: i64, path: &str| -> Result<Option<String>, AppError> {
+        let bytes = db_read_revision_file(&conn, &skill_id, revision, path)?;
+        Ok(String::from_utf8(bytes).ok())
+    };
+
+    let mut changes = Vec::new();
+    for path in paths {
+        let status = match (old.get(path), new.get(path)) {
+            (Some(a), Some(b)) if a == b => continue,
+            (Some(_), Some(_)) => "modified",
+            (None, Some(_)) => "added",
+            (Some(_), None) => "removed",
+            (None, None) => continue,
+        };
+        changes.push(RevisionFileChange {
+            relative_path: path.clone(),
+            status: status.to_string(),
+            old_content: if old.contains_key(path) { read_text(from_revision, path)? } else { None },
+            new_content: if new.contains_key(path) { read_text(to_revision, path)? } else { None },
+        });
+    }
+
+    Ok(changes)
+}
+
+#[tauri::command]
+pub async fn diff_skill_revisions(
+    skill_id: String,
+    from_revision: i64,
+    to_revision: i64,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<RevisionFileChange>, AppError> {
+    diff_skill_revisions_internal(&pool, skill_id, from_revision, to_revision).await
+}
+
+#[derive(serde::Serialize)]
+pub struct RestoreRevisionResult {
+    pub skill_id: String,
+    pub restored_revision: i64,
+    pub new_revision: Option<i64>,
+    pub files_restored: usize,
+    pub deployments_synced: usize,
+}
+
+/// 从版本历史恢复 Skill。恢复会产生一个新版本，当前内容仍保留在历史中，无需额外备份。
+pub async fn restore_skill_revision_internal(
+    pool: &DbPool,
+    skill_id: String,
+    revision: i64,
+    sync_deployments: bool,
+) -> Result<RestoreRevisionResult, AppError> {
+    info!(
+        "[restore_skill_revision] skill={}, revision={}, sync={}",
+        skill_id, revision, sync_deployments
+    );
+
+    let (files_restored, new_revision) = {
+        let conn = pool.get()?;
+        let tx = conn.unchecked_transaction()?;
+        let n = db_restore_revision(&tx, &skill_id, revision)?;
+        let new_checksum = compute_db_checksum(&tx, &skill_id);
+        tx.execute(
+            "UPDATE skills SET checksum = ?1, last_modified = datetime('now'), updated_at = datetime('now')
+             WHERE id = ?2",
+            params![new_checksum, skill_id],
+        )?;
+        let new_revision = db_latest_revision(&tx, &skill_id)?;
+        tx.commit()?;
+        (n, new_revision)
+    };
+
+    let mut deployments_synced = 0usize;
+    if sync_deployments {
+        let dep_ids: Vec<String> = {
+            let conn = pool.get()?;
+            let mut stmt = conn.prepare("SELECT id FROM skill_deployments WHERE skill_id = ?1")?;
+            let ids = stmt
+                .query_map(params![skill_id], |row| row.get(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            ids
+        };
+        for dep_id in dep_ids {
+            match super::deployments::sync_deployment_internal(pool, dep_id.clone()).await {
+                Ok(_) => deployments_synced += 1,
+                Err(e) => log::warn!("[restore_skill_revision] 同步部署失败 {}: {}", dep_id, e),
+            }
+        }
+    }
+
+    info!(
+        "[restore_skill_revision] 完成: files={}, new_revision={:?}, synced={}",
+        files_restored, new_revision, deployments_synced
+    );
+
+    Ok(RestoreRevisionResult {
+        skill_id,
+        restored_revision: revision,
+        new_revision,
+        files_restored,
+        deployments_synced,
+    })
+}
+
+#[tauri::command]
+pub async fn restore_skill_revision(
+    skill_id: String,
+    revision: i64,
+    sync_deployments: bool,
+    pool: State<'_, DbPool>,
+) -> Result<RestoreRevisionResult, AppError> {
+    restore_skill_revision_internal(&pool, skill_id, revision, sync_deployments).await
+}
+
 use rusqlite::OptionalExtension;
 
 /// 从 DB 读取 Skill 文件内容（文本）
@@ -426,16 +611,23 @@ pub async fn write_skill_file(
 }
 
 /// 列出 DB 中 Skill 的所有文件相对路径
-#[tauri::command]
-pub async fn list_skill_files(
+pub async fn list_skill_files_internal(
+    pool: &DbPool,
     skill_id: String,
-    pool: State<'_, DbPool>,
 ) -> Result<Vec<String>, AppError> {
     info!("[list_skill_files] skill={}", skill_id);
     let conn = pool.get()?;
     db_list_files(&conn, &skill_id)
 }
 
+#[tauri::command]
+pub async fn list_skill_files(
+    skill_id: String,
+    pool: State<'_, DbPool>,
+) -> Result<Vec<String>, AppError> {
+    list_skill_files_internal(&pool, skill_id).await
+}
+
 
 // ── 更新检测 ──
 
@@ -502,18 +694,96 @@ pub struct UpdateResult {
     pub backup_id: Option<String>,
     pub deployments_synced: usize,
     pub new_checksum: Option<String>,
+    /// dry_run 时返回的预演计划，此时不做任何改动
+    pub plan: Option<DeploymentPlan>,
 }
 
-#[tauri::command]
-pub async fn update_skill_from_library(
+/// 按项目 / 工具筛选要同步的部署 ID，筛选条件为空表示不限
+fn select_sync_deployments(
+    conn: &rusqlite::Connection,
+    skill_id: &str,
+    project_ids: &Option<Vec<String>>,
+    tool_names: &Option<Vec<String>>,
+) -> Result<Vec<String>, AppError> {
+    let mut stmt = conn.prepare(
+        "SELECT id, path, project_id, tool FROM skill_deployments WHERE skill_id = ?1"
+    )?;
+    let all_rows = stmt.query_map(params![skill_id], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, Option<String>>(3)?,
+        ))
+    })?.collect::<Result<Vec<_>, _>>()?;
+
+    Ok(all_rows.into_iter()
+        .filter(|(_id, _path, pid, tool)| {
+            let project_ok = match project_ids {
+                Some(ids) if !ids.is_empty() => {
+                    pid.as_ref().map(|p| ids.contains(p)).unwrap_or(false)
+                }
+                _ => true,
+            };
+            let tool_ok = match tool_names {
+                Some(names) if !names.is_empty() => {
+                    tool.as_ref().map(|t| names.contains(t)).unwrap_or(false)
+                }
+                _ => true,
+            };
+            project_ok && tool_ok
+        })
+        .map(|(id, _, _, _)| id)
+        .collect())
+}
+
+pub async fn update_skill_from_library_internal(
+    pool: &DbPool,
     skill_id: String,
     sync_deployments: bool,
     project_ids: Option<Vec<String>>,
     tool_names: Option<Vec<String>>,
-    pool: State<'_, DbPool>,
+    dry_run: Option<bool>,
 ) -> Result<UpdateResult, AppError> {
-    info!("[update_skill_from_library] skill={}, sync={}, projects={:?}, tools={:?}",
-        skill_id, sync_deployments, project_ids, tool_names);
+    let dry_run = dry_run.unwrap_or(false);
+    info!("[update_skill_from_library] skill={}, sync={}, projects={:?}, tools={:?}, dry_run={}",
+        skill_id, sync_deployments, project_ids, tool_names, dry_run);
+
+    let deployment_ids = if sync_deployments {
+        let conn = pool.get()?;
+        select_sync_deployments(&conn, &skill_id, &project_ids, &tool_names)?
+    } else {
+        Vec::new()
+    };
+
+    if dry_run {
+        let conn = pool.get()?;
+        conn.query_row("SELECT 1 FROM skills WHERE id = ?1", params![skill_id], |_| Ok(()))
+            .map_err(|_| AppError::NotFound(format!("Skill 不存在: {}", skill_id)))?;
+        let mut plan = DeploymentPlan::new(PLAN_UPDATE_SKILL, Some(skill_id.clone()));
+        for dep_id in &deployment_ids {
+            plan.actions.push(plan_redeploy(&conn, dep_id)?);
+        }
+        info!("[update_skill_from_library] 预演完成: {} 个部署", plan.actions.len());
+        return Ok(UpdateResult {
+            new_checksum: compute_db_checksum(&conn, &skill_id),
+            skill_id,
+            backup_id: None,
+            deployments_synced: 0,
+            plan: Some(plan),
+        });
+    }
+
+    apply_library_update(pool, &skill_id, &deployment_ids).await
+}
+
+/// 备份、刷新库 checksum，并用库内容重写指定的部署
+pub(crate) async fn apply_library_update(
+    pool: &DbPool,
+    skill_id: &str,
+    deployment_ids: &[String],
+) -> Result<UpdateResult, AppError> {
+    let skill_id = skill_id.to_string();
 
     // 1. 获取 Skill 信息
     let (skill_name, old_checksum) = {
@@ -584,60 +854,17 @@ pub async fn update_skill_from_library(
         cs
     };
 
-    // 4. 可选：同步到部署（从 DB 导出到各部署目录）
+    // 4. 同步到部署（从 DB 导出到各部署目录）
     let mut deployments_synced = 0usize;
-    if sync_deployments {
-        let deploy_rows: Vec<(String, String)> = {
-            let conn = pool.get()?;
-            let mut stmt = conn.prepare(
-                "SELECT id, path, project_id, tool FROM skill_deployments WHERE skill_id = ?1"
-            )?;
-            let all_rows = stmt.query_map(params![skill_id], |row| {
-                Ok((
-                    row.get::<_, String>(0)?,
-                    row.get::<_, String>(1)?,
-                    row.get::<_, Option<String>>(2)?,
-                    row.get::<_, Option<String>>(3)?,
-                ))
-            })?.collect::<Result<Vec<_>, _>>()?;
-
-            all_rows.into_iter()
-                .filter(|(_id, _path, pid, tool)| {
-                    let project_ok = match &project_ids {
-                        Some(ids) if !ids.is_empty() => {
-                            pid.as_ref().map(|p| ids.contains(p)).unwrap_or(false)
-                        }
-                        _ => true,
-                    };
-                    let tool_ok = match &tool_names {
-                        Some(names) if !names.is_empty() => {
-                            tool.as_ref().map(|t| names.contains(t)).unwrap_or(false)
-                        }
-                        _ => true,
-                    };
-                    project_ok && tool_ok
-                })
-                .map(|(id, path, _, _)| (id, path))
-                .collect()
-        };
-
-        for (dep_id, deploy_path) in &deploy_rows {
-            let dst = Path::new(deploy_path);
-            if dst.exists() {
-                let _ = std::fs::remove_dir_all(dst);
-            }
-            let conn = pool.get()?;
-            let _ = db_export_to_dir(&conn, &skill_id, dst);
-            let dep_checksum = super::utils::compute_dir_checksum(dst);
-
-            conn.execute(
-                "UPDATE skill_deployments SET checksum = ?1, status = 'synced',
-                        last_synced = datetime('now'), updated_at = datetime('now')
-                 WHERE id = ?2",
-                params![dep_checksum, dep_id],
-            )?;
-            deployments_synced += 1;
+    for dep_id in deployment_ids {
+        let conn = pool.get()?;
+        if let Err(e) = super::deployments::redeploy_from_db(&conn, dep_id) {
+            info!("[update_skill_from_library] 同步部署失败 {}: {}", dep_id, e);
+            continue;
         }
+        deployments_synced += 1;
+    }
+    if !deployment_ids.is_empty() {
         info!("[update_skill_from_library] 已同步 {} 个部署", deployments_synced);
     }
 
@@ -646,9 +873,22 @@ pub async fn update_skill_from_library(
         backup_id,
         deployments_synced,
         new_checksum,
+        plan: None,
     })
 }
 
+#[tauri::command]
+pub async fn update_skill_from_library(
+    skill_id: String,
+    sync_deployments: bool,
+    project_ids: Option<Vec<String>>,
+    tool_names: Option<Vec<String>>,
+    dry_run: Option<bool>,
+    pool: State<'_, DbPool>,
+) -> Result<UpdateResult, AppError> {
+    update_skill_from_library_internal(&pool, skill_id, sync_deployments, project_ids, tool_names, dry_run).await
+}
+
 #[derive(serde::Serialize)]
 pub struct RestoreResult {
     pub skill_id: String,
@@ -711,7 +951,7 @@ pub async fn restore_from_backup(
             let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
             let current_backup_path = backup_base.join(&timestamp);
 
-            if let Ok(_) = db_export_to_dir(&conn, &skill_id, &current_backup_path) {
+            if db_export_to_dir(&conn, &skill_id, &current_backup_path).is_ok() {
                 let bid = Uuid::new_v4().to_string();
                 let bp_str = current_backup_path.to_string_lossy().to_string();
                 let _ = conn.execute(
@@ -727,7 +967,7 @@ pub async fn restore_from_backup(
     // 4. 将备份目录导入到 DB skill_files（覆盖）
     {
         let conn = pool.get()?;
-        conn.execute("DELETE FROM skill_files WHERE skill_id = ?1", params![skill_id])?;
+        db_clear_files(&conn, &skill_id)?;
         let files_imported = db_import_from_dir(&conn, &skill_id, backup_dir)?;
         info!("[restore_from_backup] 恢复完成: {} 个文件导入到 DB", files_imported);
     }
@@ -769,20 +1009,11 @@ pub async fn restore_from_backup(
         };
 
         for (dep_id, deploy_path) in &deploy_rows {
-            let dst = Path::new(deploy_path);
-            if dst.exists() {
-                let _ = std::fs::remove_dir_all(dst);
-            }
             let conn = pool.get()?;
-            let _ = db_export_to_dir(&conn, &skill_id, dst);
-            let dep_checksum = super::utils::compute_dir_checksum(dst);
-
-            conn.execute(
-                "UPDATE skill_deployments SET checksum = ?1, status = 'synced',
-                        last_synced = datetime('now'), updated_at = datetime('now')
-                 WHERE id = ?2",
-                params![dep_checksum, dep_id],
-            )?;
+            if let Err(e) = super::deployments::redeploy_from_db(&conn, dep_id) {
+                info!("[restore_from_backup] 同步部署失败 {}: {}", deploy_path, e);
+                continue;
+            }
             deployments_synced += 1;
         }
         info!("[restore_from_backup] 已同步 {} 个部署", deployments_synced);
@@ -808,14 +1039,14 @@ pub async fn restore_from_backup(
 
 // ── compute_skill_diff ──
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct FileDiff {
     pub path: String,
     pub status: String, // "added", "removed", "modified", "unchanged"
     pub hunks: Vec<DiffHunk>,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct DiffHunk {
     pub old_start: usize,
     pub old_count: usize,
@@ -824,7 +1055,7 @@ pub struct DiffHunk {
     pub lines: Vec<DiffLine>,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct DiffLine {
     pub tag: String, // "+", "-", " "
     pub content: String,
@@ -838,7 +1069,7 @@ pub struct SkillDiffResult {
     pub summary: DiffSummary,
 }
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct DiffSummary {
     pub added: usize,
     pub removed: usize,
@@ -916,9 +1147,8 @@ fn compute_file_diff(old_content: &str, new_content: &str) -> Vec<DiffHunk> {
         let mut old_count = 0;
         let mut new_count = 0;
         let mut lines = Vec::new();
-        for j in hunk_start..hunk_end {
-            let (tag, _, _, ref content) = all_changes[j];
-            let tag_str = match tag {
+        for (tag, _, _, content) in &all_changes[hunk_start..hunk_end] {
+            let tag_str = match *tag {
                 ChangeTag::Insert => { new_count += 1; "+".to_string() }
                 ChangeTag::Delete => { old_count += 1; "-".to_string() }
                 ChangeTag::Equal => { old_count += 1; new_count += 1; " ".to_string() }
@@ -932,24 +1162,12 @@ fn compute_file_diff(old_content: &str, new_content: &str) -> Vec<DiffHunk> {
     hunks
 }
 
-#[tauri::command]
-pub async fn compute_skill_diff(
-    left_path: String,
-    right_path: String,
-) -> Result<SkillDiffResult, AppError> {
-    info!("[compute_skill_diff] left={}, right={}", left_path, right_path);
-
-    let left_dir = Path::new(&left_path);
-    let right_dir = Path::new(&right_path);
-
-    if !left_dir.exists() && !right_dir.exists() {
-        return Err(AppError::Validation("两个路径都不存在".to_string()));
-    }
-
-    let left_files: std::collections::HashSet<String> = collect_relative_files(left_dir).into_iter().collect();
-    let right_files: std::collections::HashSet<String> = collect_relative_files(right_dir).into_iter().collect();
-
-    let all_files: std::collections::BTreeSet<&String> = left_files.iter().chain(right_files.iter()).collect();
+/// 比较两组 (相对路径 → 文本内容)，返回变更文件列表（不含未变文件）及统计
+pub fn diff_file_maps(
+    left: &std::collections::BTreeMap<String, String>,
+    right: &std::collections::BTreeMap<String, String>,
+) -> (Vec<FileDiff>, DiffSummary) {
+    let all_files: std::collections::BTreeSet<&String> = left.keys().chain(right.keys()).collect();
 
     let mut files = Vec::new();
     let mut added = 0usize;
@@ -958,60 +1176,77 @@ pub async fn compute_skill_diff(
     let mut unchanged = 0usize;
 
     for rel_path in all_files {
-        let in_left = left_files.contains(rel_path);
-        let in_right = right_files.contains(rel_path);
-
-        if in_left && !in_right {
-            // removed
-            removed += 1;
-            files.push(FileDiff {
-                path: rel_path.clone(),
-                status: "removed".to_string(),
-                hunks: Vec::new(),
-            });
-        } else if !in_left && in_right {
-            // added
-            added += 1;
-            files.push(FileDiff {
-                path: rel_path.clone(),
-                status: "added".to_string(),
-                hunks: Vec::new(),
-            });
-        } else {
-            // both exist - compare
-            let left_content = std::fs::read_to_string(left_dir.join(rel_path)).unwrap_or_default();
-            let right_content = std::fs::read_to_string(right_dir.join(rel_path)).unwrap_or_default();
-
-            if left_content == right_content {
-                unchanged += 1;
-                // skip unchanged files
-            } else {
-                let hunks = compute_file_diff(&left_content, &right_content);
-                modified += 1;
+        match (left.get(rel_path), right.get(rel_path)) {
+            (Some(_), None) => {
+                removed += 1;
                 files.push(FileDiff {
                     path: rel_path.clone(),
-                    status: "modified".to_string(),
-                    hunks,
+                    status: "removed".to_string(),
+                    hunks: Vec::new(),
                 });
             }
+            (None, Some(_)) => {
+                added += 1;
+                files.push(FileDiff {
+                    path: rel_path.clone(),
+                    status: "added".to_string(),
+                    hunks: Vec::new(),
+                });
+            }
+            (Some(left_content), Some(right_content)) => {
+                if left_content == right_content {
+                    unchanged += 1;
+                } else {
+                    modified += 1;
+                    files.push(FileDiff {
+                        path: rel_path.clone(),
+                        status: "modified".to_string(),
+                        hunks: compute_file_diff(left_content, right_content),
+                    });
+                }
+            }
+            (None, None) => {}
         }
     }
 
+    (files, DiffSummary { added, removed, modified, unchanged })
+}
+
+#[tauri::command]
+pub async fn compute_skill_diff(
+    left_path: String,
+    right_path: String,
+) -> Result<SkillDiffResult, AppError> {
+    info!("[compute_skill_diff] left={}, right={}", left_path, right_path);
+
+    let left_dir = Path::new(&left_path);
+    let right_dir = Path::new(&right_path);
+
+    if !left_dir.exists() && !right_dir.exists() {
+        return Err(AppError::Validation("两个路径都不存在".to_string()));
+    }
+
+    let read_dir_files = |dir: &Path| -> std::collections::BTreeMap<String, String> {
+        collect_relative_files(dir)
+            .into_iter()
+            .map(|rel| {
+                let content = std::fs::read_to_string(dir.join(&rel)).unwrap_or_default();
+                (rel, content)
+            })
+            .collect()
+    };
+    let (files, summary) = diff_file_maps(&read_dir_files(left_dir), &read_dir_files(right_dir));
+
     info!(
         "[compute_skill_diff] 完成: added={}, removed={}, modified={}, unchanged={}",
-        added, removed, modified, unchanged
+        summary.added, summary.removed, summary.modified, summary.unchanged
     );
 
     Ok(SkillDiffResult {
         left_path,
         right_path,
         files,
-        summary: DiffSummary {
-            added,
-            removed,
-            modified,
-            unchanged,
-        },
+        summary,
     })
 }
 
@@ -1038,68 +1273,173 @@ pub struct MergeResult {
 #[derive(serde::Deserialize)]
 pub struct MergeResolution {
     pub path: String,
-    pub content: String,
+    /// None 表示删除该文件
+    pub content: Option<String>,
 }
 
-fn three_way_merge_text(base: &str, left: &str, right: &str) -> (String, bool) {
-    use similar::{ChangeTag, TextDiff};
+impl MergeResolution {
+    /// 内容中仍有未处理的冲突标记
+    pub(crate) fn has_conflict_markers(&self) -> bool {
+        self.content
+            .iter()
+            .flat_map(|c| c.lines())
+            .any(|l| l.starts_with("<<<<<<< ") || l.starts_with(">>>>>>> "))
+    }
+}
+
+/// 一侧相对 base 的一个改动块：base[base_start..base_end] 被替换为 side[side_start..side_end]
+#[derive(Debug, Clone, Copy)]
+struct MergeHunk {
+    base_start: usize,
+    base_end: usize,
+    side_start: usize,
+    side_end: usize,
+}
+
+/// 把 base → side 的 diff 中连续的非 Equal 操作合并为改动块
+fn diff_hunks(base: &[&str], side: &[&str]) -> Vec<MergeHunk> {
+    use similar::{capture_diff_slices, Algorithm, DiffTag};
 
-    // If left == right, no conflict
+    let mut hunks: Vec<MergeHunk> = Vec::new();
+    let mut current: Option<MergeHunk> = None;
+    for op in capture_diff_slices(Algorithm::Myers, base, side) {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            hunks.extend(current.take());
+            continue;
+        }
+        current = Some(match current {
+            Some(h) => MergeHunk { base_end: old.end, side_end: new.end, ..h },
+            None => MergeHunk {
+                base_start: old.start,
+                base_end: old.end,
+                side_start: new.start,
+                side_end: new.end,
+            },
+        });
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// 两个改动块在 base 上是否冲突：区间相交，或在同一位置插入
+fn hunks_overlap(a_start: usize, a_end: usize, b_start: usize, b_end: usize) -> bool {
+    (a_start < b_end && b_start < a_end)
+        || (a_start == b_start && (a_start == a_end || b_start == b_end))
+}
+
+/// 把聚类得到的 base 区间 [start, end) 映射到一侧的行区间。
+/// 恰好插在 start 处的块属于本组；插在 end 处的块属于下一组（纯插入组 start == end 除外）。
+fn side_range(hunks: &[MergeHunk], start: usize, end: usize) -> (usize, usize) {
+    let delta = |h: &MergeHunk| {
+        (h.side_end - h.side_start) as isize - (h.base_end - h.base_start) as isize
+    };
+    let before_start: isize = hunks
+        .iter()
+        .filter(|h| h.base_end < start || (h.base_end == start && h.base_start < start))
+        .map(delta)
+        .sum();
+    let through_end: isize = hunks
+        .iter()
+        .filter(|h| h.base_end < end || (h.base_end == end && (h.base_start < end || start == end)))
+        .map(delta)
+        .sum();
+    (
+        (start as isize + before_start) as usize,
+        (end as isize + through_end) as usize,
+    )
+}
+
+fn push_lines(out: &mut String, lines: &[&str]) {
+    for line in lines {
+        out.push_str(line);
+    }
+}
+
+fn push_conflict_side(out: &mut String, lines: &[&str]) {
+    push_lines(out, lines);
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// diff3 风格的行级三向合并。
+/// 两侧互不重叠的改动都会被应用；只有真正冲突的改动块才输出冲突标记。
+/// 返回 (合并结果, 是否有冲突)
+fn three_way_merge_text(base: &str, left: &str, right: &str) -> (String, bool) {
     if left == right {
         return (left.to_string(), false);
     }
-    // If left == base, right changed → take right
     if left == base {
         return (right.to_string(), false);
     }
-    // If right == base, left changed → take left
     if right == base {
         return (left.to_string(), false);
     }
 
-    // Both changed from base — try line-level merge
-    let base_lines: Vec<&str> = base.lines().collect();
-    let left_lines: Vec<&str> = left.lines().collect();
-    let right_lines: Vec<&str> = right.lines().collect();
+    let base_lines: Vec<&str> = base.split_inclusive('\n').collect();
+    let left_lines: Vec<&str> = left.split_inclusive('\n').collect();
+    let right_lines: Vec<&str> = right.split_inclusive('\n').collect();
 
-    let diff_left = TextDiff::from_slices(&base_lines, &left_lines);
-    let diff_right = TextDiff::from_slices(&base_lines, &right_lines);
+    let left_hunks = diff_hunks(&base_lines, &left_lines);
+    let right_hunks = diff_hunks(&base_lines, &right_lines);
 
-    let left_changes: std::collections::HashMap<usize, (ChangeTag, String)> = diff_left
-        .iter_all_changes()
-        .enumerate()
-        .filter(|(_, c)| c.tag() != ChangeTag::Equal)
-        .map(|(i, c)| (i, (c.tag(), c.value().to_string())))
+    // 按 base 起点排序后贪心聚类，彼此重叠的改动块归为同一组
+    let mut tagged: Vec<(bool, MergeHunk)> = left_hunks
+        .iter()
+        .map(|h| (true, *h))
+        .chain(right_hunks.iter().map(|h| (false, *h)))
         .collect();
+    tagged.sort_by_key(|(is_left, h)| (h.base_start, h.base_end, !*is_left));
+
+    let mut merged = String::new();
+    let mut has_conflict = false;
+    let mut base_pos = 0usize;
+    let mut i = 0usize;
+
+    while i < tagged.len() {
+        let mut start = tagged[i].1.base_start;
+        let mut end = tagged[i].1.base_end;
+        let mut has_left = tagged[i].0;
+        let mut has_right = !tagged[i].0;
+        let mut j = i + 1;
+        while j < tagged.len() && hunks_overlap(start, end, tagged[j].1.base_start, tagged[j].1.base_end) {
+            start = start.min(tagged[j].1.base_start);
+            end = end.max(tagged[j].1.base_end);
+            has_left |= tagged[j].0;
+            has_right |= !tagged[j].0;
+            j += 1;
+        }
 
-    let right_changes: std::collections::HashMap<usize, (ChangeTag, String)> = diff_right
-        .iter_all_changes()
-        .enumerate()
-        .filter(|(_, c)| c.tag() != ChangeTag::Equal)
-        .map(|(i, c)| (i, (c.tag(), c.value().to_string())))
-        .collect();
+        push_lines(&mut merged, &base_lines[base_pos..start]);
 
-    // Check if changes overlap (simple heuristic: any shared indices = conflict)
-    let has_overlap = left_changes.keys().any(|k| right_changes.contains_key(k));
-
-    if has_overlap {
-        // Real conflict — return conflict markers
-        let mut merged = String::new();
-        merged.push_str("<<<<<<< LOCAL\n");
-        merged.push_str(left);
-        if !left.ends_with('\n') { merged.push('\n'); }
-        merged.push_str("=======\n");
-        merged.push_str(right);
-        if !right.ends_with('\n') { merged.push('\n'); }
-        merged.push_str(">>>>>>> DEPLOYMENT\n");
-        return (merged, true);
+        let (ls, le) = side_range(&left_hunks, start, end);
+        let (rs, re) = side_range(&right_hunks, start, end);
+        let left_chunk = &left_lines[ls..le];
+        let right_chunk = &right_lines[rs..re];
+
+        if !has_right {
+            push_lines(&mut merged, left_chunk);
+        } else if !has_left || left_chunk == right_chunk {
+            push_lines(&mut merged, right_chunk);
+        } else {
+            has_conflict = true;
+            if !merged.is_empty() && !merged.ends_with('\n') {
+                merged.push('\n');
+            }
+            merged.push_str("<<<<<<< LOCAL\n");
+            push_conflict_side(&mut merged, left_chunk);
+            merged.push_str("=======\n");
+            push_conflict_side(&mut merged, right_chunk);
+            merged.push_str(">>>>>>> DEPLOYMENT\n");
+        }
+
+        base_pos = end;
+        i = j;
     }
+    push_lines(&mut merged, &base_lines[base_pos..]);
 
-    // Non-overlapping changes: apply both sets
-    // Simple approach: take left (which includes left's changes from base)
-    // and also apply right's unique changes — this is complex, so for non-overlapping
-    // we just take left since it's the "local" version with priority
-    (left.to_string(), false)
+    (merged, has_conflict)
 }
 
 #[tauri::command]
@@ -1124,35 +1464,41 @@ pub async fn merge_skill_versions(
         return Err(AppError::Validation(format!("右侧路径不存在: {}", right_path)));
     }
 
-    let left_files: std::collections::HashSet<String> = collect_relative_files(left_dir).into_iter().collect();
-    let right_files: std::collections::HashSet<String> = collect_relative_files(right_dir).into_iter().collect();
-    let base_files: std::collections::HashSet<String> = base_dir
-        .map(|d| collect_relative_files(d).into_iter().collect())
-        .unwrap_or_default();
+    let read_dir = |dir: &Path| -> BTreeMap<String, String> {
+        collect_relative_files(dir)
+            .into_iter()
+            .map(|rel| {
+                let content = std::fs::read_to_string(dir.join(&rel)).unwrap_or_default();
+                (rel, content)
+            })
+            .collect()
+    };
+    let base = base_dir.map(read_dir);
+    Ok(merge_file_maps(base.as_ref(), &read_dir(left_dir), &read_dir(right_dir)))
+}
 
-    let all_files: std::collections::BTreeSet<&String> = left_files.iter()
-        .chain(right_files.iter())
-        .chain(base_files.iter())
-        .collect();
+/// 按文件集合做三向合并：left 为库一侧，right 为部署一侧，base 为共同祖先（未知时为 None）
+pub fn merge_file_maps(
+    base: Option<&BTreeMap<String, String>>,
+    left: &BTreeMap<String, String>,
+    right: &BTreeMap<String, String>,
+) -> MergeResult {
+    let empty = BTreeMap::new();
+    let base = base.unwrap_or(&empty);
+    let all_files: std::collections::BTreeSet<&String> =
+        left.keys().chain(right.keys()).chain(base.keys()).collect();
 
     let mut files = Vec::new();
     let mut auto_merged_count = 0usize;
     let mut conflict_count = 0usize;
 
     for rel_path in &all_files {
-        let in_left = left_files.contains(*rel_path);
-        let in_right = right_files.contains(*rel_path);
-        let in_base = base_files.contains(*rel_path);
-
-        let left_content = if in_left {
-            Some(std::fs::read_to_string(left_dir.join(rel_path)).unwrap_or_default())
-        } else { None };
-        let right_content = if in_right {
-            Some(std::fs::read_to_string(right_dir.join(rel_path)).unwrap_or_default())
-        } else { None };
-        let base_content = if in_base {
-            base_dir.and_then(|d| std::fs::read_to_string(d.join(rel_path)).ok())
-        } else { None };
+        let left_content = left.get(*rel_path).cloned();
+        let right_content = right.get(*rel_path).cloned();
+        let base_content = base.get(*rel_path).cloned();
+        let in_left = left_content.is_some();
+        let in_right = right_content.is_some();
+        let in_base = base_content.is_some();
 
         info!(
             "[merge_skill_versions] 文件: {} | in_base={}, in_left={}, in_right={}",
@@ -1269,12 +1615,12 @@ pub async fn merge_skill_versions(
         total_files, auto_merged_count, conflict_count
     );
 
-    Ok(MergeResult {
+    MergeResult {
         files,
         auto_merged_count,
         conflict_count,
         total_files,
-    })
+    }
 }
 
 #[tauri::command]
@@ -1288,18 +1634,29 @@ pub async fn apply_merge_result(
     );
 
     let target_dir = Path::new(&target_path);
+    // 先校验全部路径，避免写到一半才发现非法路径
+    let targets = resolutions
+        .iter()
+        .map(|r| SkillPath::parse(&r.path)?.join_under(target_dir))
+        .collect::<Result<Vec<_>, _>>()?;
     if !target_dir.exists() {
         std::fs::create_dir_all(target_dir)
             .map_err(|e| AppError::Internal(format!("创建目标目录失败: {}", e)))?;
     }
 
-    for resolution in &resolutions {
-        let file_path = target_dir.join(&resolution.path);
+    for (resolution, file_path) in resolutions.iter().zip(targets) {
+        let Some(content) = &resolution.content else {
+            info!("[apply_merge_result] 删除: {}", file_path.display());
+            if file_path.is_file() {
+                std::fs::remove_file(&file_path)?;
+            }
+            continue;
+        };
         info!("[apply_merge_result] 写入: {}", file_path.display());
         if let Some(parent) = file_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        std::fs::write(&file_path, &resolution.content)?;
+        std::fs::write(&file_path, content)?;
     }
 
     info!("[apply_merge_result] 完成: 写入 {} 个文件到 {}", resolutions.len(), target_path);
@@ -1307,6 +1664,319 @@ pub async fn apply_merge_result(
 }
 
 
+// ── 按 ID 合并 ──
+//
+// 库一侧直接读 skill_files，部署一侧读部署目录，base 取历史版本或备份；
+// 调用方只需传 ID，不必自己准备临时目录。
+
+/// 合并的 base：指定的历史版本或备份；都未指定时取部署上次同步时对应的版本，找不到则为 None
+fn load_merge_base(
+    conn: &rusqlite::Connection,
+    skill_id: &str,
+    deployment_checksum: Option<&str>,
+    base_revision: Option<i64>,
+    base_backup_id: Option<&str>,
+) -> Result<Option<BTreeMap<String, Vec<u8>>>, AppError> {
+    if let Some(backup_id) = base_backup_id {
+        let backup_path: String = conn
+            .query_row(
+                "SELECT backup_path FROM skill_backups WHERE id = ?1 AND skill_id = ?2",
+                params![backup_id, skill_id],
+                |row| row.get(0),
+            )
+            .map_err(|_| AppError::NotFound(format!("备份不存在: {}", backup_id)))?;
+        let dir = Path::new(&backup_path);
+        if !dir.is_dir() {
+            return Err(AppError::NotFound(format!("备份目录不存在: {}", backup_path)));
+        }
+        return Ok(Some(read_dir_files(dir)));
+    }
+
+    let revision = match (base_revision, deployment_checksum) {
+        (Some(rev), _) => Some(rev),
+        (None, Some(checksum)) => db_find_revision_by_checksum(conn, skill_id, checksum)?,
+        (None, None) => None,
+    };
+    let Some(revision) = revision else {
+        return Ok(None);
+    };
+    let mut files = BTreeMap::new();
+    for (rel_path, _) in db_revision_manifest(conn, skill_id, revision)? {
+        let content = db_read_revision_file(conn, skill_id, revision, &rel_path)?;
+        files.insert(rel_path, content);
+    }
+    Ok(Some(files))
+}
+
+/// 部署路径与上次同步时记录的 checksum，同时校验部署属于该 Skill
+fn skill_deployment_path(
+    conn: &rusqlite::Connection,
+    skill_id: &str,
+    deployment_id: &str,
+) -> Result<(String, Option<String>), AppError> {
+    conn.query_row(
+        "SELECT path, checksum FROM skill_deployments WHERE id = ?1 AND skill_id = ?2",
+        params![deployment_id, skill_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .map_err(|_| AppError::NotFound(format!("部署不存在或不属于该 Skill: {}", deployment_id)))
+}
+
+/// 库（left）与部署（right）的三向合并预览，不做任何改动
+pub fn merge_skill_by_id_internal(
+    pool: &DbPool,
+    skill_id: String,
+    deployment_id: String,
+    base_revision: Option<i64>,
+    base_backup_id: Option<String>,
+) -> Result<MergeResult, AppError> {
+    info!(
+        "[merge_skill_by_id] skill={}, deployment={}, base_revision={:?}, base_backup={:?}",
+        skill_id, deployment_id, base_revision, base_backup_id
+    );
+    let conn = pool.get()?;
+    let (_, merge) = merge_with_deployment(
+        &conn,
+        &skill_id,
+        &deployment_id,
+        base_revision,
+        base_backup_id.as_deref(),
+    )?;
+    Ok(merge)
+}
+
+/// 库与部署按字节三向合并，只比较未被忽略的文件。
+/// 返回无冲突文件的合并内容（冲突文件暂取库中内容）和合并详情
+fn merge_with_deployment(
+    conn: &rusqlite::Connection,
+    skill_id: &str,
+    deployment_id: &str,
+    base_revision: Option<i64>,
+    base_backup_id: Option<&str>,
+) -> Result<(BTreeMap<String, Vec<u8>>, MergeResult), AppError> {
+    let (deploy_path, dep_checksum) = skill_deployment_path(conn, skill_id, deployment_id)?;
+    let deploy_dir = Path::new(&deploy_path);
+    if !deploy_dir.is_dir() {
+        return Err(AppError::Validation(format!("部署目录不存在: {}", deploy_path)));
+    }
+
+    let rules = IgnoreRules::for_db_skill(conn, skill_id);
+    let mut base = load_merge_base(conn, skill_id, dep_checksum.as_deref(), base_revision, base_backup_id)?;
+    if let Some(base) = &mut base {
+        base.retain(|rel, _| !rules.is_ignored(Path::new(rel), false));
+    }
+    let left = read_db_unignored(conn, skill_id)?;
+    let right = read_dir_files(deploy_dir);
+    Ok(merge_update(base.as_ref(), &left, &right))
+}
+
+#[tauri::command]
+pub async fn merge_skill_by_id(
+    skill_id: String,
+    deployment_id: String,
+    base_revision: Option<i64>,
+    base_backup_id: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<MergeResult, AppError> {
+    merge_skill_by_id_internal(&pool, skill_id, deployment_id, base_revision, base_backup_id)
+}
+
+#[derive(serde::Serialize)]
+pub struct MergeCommitResult {
+    pub skill_id: String,
+    pub backup_id: Option<String>,
+    pub revision: Option<i64>,
+    pub new_checksum: Option<String>,
+    pub files_written: usize,
+    pub conflicts_resolved: usize,
+    pub deployments_synced: usize,
+}
+
+/// 把合并结果写回库。传入 deployment_id 时先按字节与该部署合并（与 merge_skill_by_id 相同，
+/// base_revision / base_backup_id 应与预览时一致），每个冲突文件都须在 resolutions 中给出结果；
+/// 再用 resolutions 覆盖对应文件（content 为 None 时删除）；未列出的文件保持合并结果，
+/// 二进制文件和被忽略的库文件不会被改写或删除。
+/// 写入前自动备份；传入 deployment_id 时一并关闭该部署未处理的 watcher 冲突；
+/// sync_deployment_ids 中的部署随后用新的库内容重写。
+pub fn commit_skill_merge_internal(
+    pool: &DbPool,
+    skill_id: String,
+    resolutions: Vec<MergeResolution>,
+    deployment_id: Option<String>,
+    base_revision: Option<i64>,
+    base_backup_id: Option<String>,
+    sync_deployment_ids: Option<Vec<String>>,
+) -> Result<MergeCommitResult, AppError> {
+    let sync_deployment_ids = sync_deployment_ids.unwrap_or_default();
+    info!(
+        "[commit_skill_merge] skill={}, files={}, deployment={:?}, base_revision={:?}, base_backup={:?}, sync={:?}",
+        skill_id, resolutions.len(), deployment_id, base_revision, base_backup_id, sync_deployment_ids
+    );
+
+    // 与部署合并时，无冲突的合并结果本身就是要提交的内容
+    if resolutions.is_empty() && deployment_id.is_none() {
+        return Err(AppError::Validation("合并结果为空".into()));
+    }
+    for r in &resolutions {
+        SkillPath::parse(&r.path)?;
+    }
+    if let Some(r) = resolutions.iter().find(|r| r.has_conflict_markers()) {
+        return Err(AppError::Validation(format!("文件仍包含冲突标记: {}", r.path)));
+    }
+
+    let conn = pool.get()?;
+    let (skill_name, old_checksum): (String, Option<String>) = conn
+        .query_row(
+            "SELECT name, checksum FROM skills WHERE id = ?1",
+            params![skill_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|_| AppError::NotFound(format!("Skill 不存在: {}", skill_id)))?;
+    if let Some(dep_id) = &deployment_id {
+        skill_deployment_path(&conn, &skill_id, dep_id)?;
+    }
+    for dep_id in &sync_deployment_ids {
+        skill_deployment_path(&conn, &skill_id, dep_id)?;
+    }
+
+    // 与部署合并；冲突文件缺少结果时拒绝提交，否则部署一侧的修改会随冲突关闭而丢失
+    let merged = match &deployment_id {
+        Some(dep_id) => {
+            let (merged, merge) =
+                merge_with_deployment(&conn, &skill_id, dep_id, base_revision, base_backup_id.as_deref())?;
+            let unresolved: Vec<&str> = merge
+                .files
+                .iter()
+                .filter(|f| merge_is_conflict(f) && !resolutions.iter().any(|r| r.path == f.path))
+                .map(|f| f.path.as_str())
+                .collect();
+            if !unresolved.is_empty() {
+                return Err(AppError::Validation(format!(
+                    "{} 个冲突文件未给出合并结果: {}",
+                    unresolved.len(),
+                    unresolved.join(", ")
+                )));
+            }
+            Some(merged)
+        }
+        None => None,
+    };
+
+    // 1. 备份当前库内容
+    let backup_id = if has_db_files(&conn, &skill_id) {
+        let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S").to_string();
+        let backup_path = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".skills-manager")
+            .join("backups")
+            .join(&skill_name)
+            .join(format!("merge-{}", timestamp));
+        db_export_to_dir(&conn, &skill_id, &backup_path)?;
+        let bid = Uuid::new_v4().to_string();
+        conn.execute(
+            "INSERT INTO skill_backups (id, skill_id, version_label, backup_path, checksum, reason)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'before_merge')",
+            params![bid, skill_id, timestamp, backup_path.to_string_lossy(), old_checksum],
+        )?;
+        info!("[commit_skill_merge] 备份完成: {}", backup_path.display());
+        Some(bid)
+    } else {
+        None
+    };
+
+    // 2. 写入合并结果（一个新版本）并关闭冲突
+    let mut files = read_db_files(&conn, &skill_id)?;
+    if let Some(merged) = merged {
+        // 参与合并的文件以合并结果为准，被忽略的库文件原样保留
+        let rules = IgnoreRules::for_db_skill(&conn, &skill_id);
+        files.retain(|rel, _| rules.is_ignored(Path::new(rel), false));
+        files.extend(merged);
+    }
+    for r in resolutions {
+        let rel = SkillPath::parse(&r.path)?.to_string();
+        match r.content {
+            Some(content) => files.insert(rel, content.into_bytes()),
+            None => files.remove(&rel),
+        };
+    }
+    let tx = conn.unchecked_transaction()?;
+    let files_written = db_replace_files(&tx, &skill_id, &files)?;
+    let new_checksum = refresh_skill_checksum(&tx, &skill_id)?;
+    tx.execute(
+        "UPDATE skills SET last_modified = datetime('now') WHERE id = ?1",
+        params![skill_id],
+    )?;
+    let revision = db_latest_revision(&tx, &skill_id)?;
+    let resolved_events: Vec<String> = match &deployment_id {
+        Some(dep_id) => {
+            let ids = tx
+                .prepare(
+                    "SELECT id FROM change_events
+                     WHERE deployment_id = ?1 AND resolution = 'conflict' AND resolved_at IS NULL",
+                )?
+                .query_map(params![dep_id], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            tx.execute(
+                "UPDATE change_events SET resolution = 'merged', resolved_at = datetime('now')
+                 WHERE deployment_id = ?1 AND resolution = 'conflict' AND resolved_at IS NULL",
+                params![dep_id],
+            )?;
+            ids
+        }
+        None => Vec::new(),
+    };
+    tx.commit()?;
+    for event_id in &resolved_events {
+        super::watcher::remove_conflict_snapshots(event_id);
+    }
+    let conflicts_resolved = resolved_events.len();
+
+    // 3. 用新的库内容重写选中的部署
+    let mut deployments_synced = 0usize;
+    for dep_id in &sync_deployment_ids {
+        match super::deployments::redeploy_from_db(&conn, dep_id) {
+            Ok(_) => deployments_synced += 1,
+            Err(e) => log::warn!("[commit_skill_merge] 同步部署失败 {}: {}", dep_id, e),
+        }
+    }
+
+    info!(
+        "[commit_skill_merge] 完成: files={}, revision={:?}, conflicts={}, synced={}",
+        files_written, revision, conflicts_resolved, deployments_synced
+    );
+    Ok(MergeCommitResult {
+        skill_id,
+        backup_id,
+        revision,
+        new_checksum,
+        files_written,
+        conflicts_resolved,
+        deployments_synced,
+    })
+}
+
+#[tauri::command]
+pub async fn commit_skill_merge(
+    skill_id: String,
+    resolutions: Vec<MergeResolution>,
+    deployment_id: Option<String>,
+    base_revision: Option<i64>,
+    base_backup_id: Option<String>,
+    sync_deployment_ids: Option<Vec<String>>,
+    pool: State<'_, DbPool>,
+) -> Result<MergeCommitResult, AppError> {
+    commit_skill_merge_internal(
+        &pool,
+        skill_id,
+        resolutions,
+        deployment_id,
+        base_revision,
+        base_backup_id,
+        sync_deployment_ids,
+    )
+}
+
+
 #[tauri::command]
 pub async fn open_in_editor(
     path: String,
@@ -1371,3 +2041,157 @@ pub async fn open_in_editor(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::three_way_merge_text;
+
+    struct Case {
+        name: &'static str,
+        base: &'static str,
+        left: &'static str,
+        right: &'static str,
+        merged: &'static str,
+        conflict: bool,
+    }
+
+    #[test]
+    fn three_way_merge_cases() {
+        let cases = [
+            Case {
+                name: "all identical",
+                base: "a\nb\n",
+                left: "a\nb\n",
+                right: "a\nb\n",
+                merged: "a\nb\n",
+                conflict: false,
+            },
+            Case {
+                name: "only left changed",
+                base: "a\nb\nc\n",
+                left: "a\nB\nc\n",
+                right: "a\nb\nc\n",
+                merged: "a\nB\nc\n",
+                conflict: false,
+            },
+            Case {
+                name: "only right changed",
+                base: "a\nb\nc\n",
+                left: "a\nb\nc\n",
+                right: "a\nb\nC\n",
+                merged: "a\nb\nC\n",
+                conflict: false,
+            },
+            Case {
+                name: "both made the same change",
+                base: "a\nb\nc\nd\n",
+                left: "a\nX\nc\nd\n",
+                right: "a\nX\nc\nD\n",
+                merged: "a\nX\nc\nD\n",
+                conflict: false,
+            },
+            Case {
+                name: "edits in distant regions",
+                base: "1\n2\n3\n4\n5\n6\n",
+                left: "one\n2\n3\n4\n5\n6\n",
+                right: "1\n2\n3\n4\n5\nsix\n",
+                merged: "one\n2\n3\n4\n5\nsix\n",
+                conflict: false,
+            },
+            Case {
+                name: "edits on adjacent lines",
+                base: "1\n2\n3\n4\n",
+                left: "1\ntwo\n3\n4\n",
+                right: "1\n2\nthree\n4\n",
+                merged: "1\ntwo\nthree\n4\n",
+                conflict: false,
+            },
+            Case {
+                name: "insert at top and append at end",
+                base: "a\nb\n",
+                left: "header\na\nb\n",
+                right: "a\nb\nfooter\n",
+                merged: "header\na\nb\nfooter\n",
+                conflict: false,
+            },
+            Case {
+                name: "left deletes, right edits elsewhere",
+                base: "a\nb\nc\nd\n",
+                left: "a\nc\nd\n",
+                right: "a\nb\nc\nD\n",
+                merged: "a\nc\nD\n",
+                conflict: false,
+            },
+            Case {
+                name: "insert right after a line the other side edited",
+                base: "a\nb\nc\n",
+                left: "a\nB\nc\n",
+                right: "a\nb\nnew\nc\n",
+                merged: "a\nB\nnew\nc\n",
+                conflict: false,
+            },
+            Case {
+                name: "same line edited differently",
+                base: "a\nb\nc\n",
+                left: "a\nL\nc\n",
+                right: "a\nR\nc\n",
+                merged: "a\n<<<<<<< LOCAL\nL\n=======\nR\n>>>>>>> DEPLOYMENT\nc\n",
+                conflict: true,
+            },
+            Case {
+                name: "conflict markers only around the conflicting hunk",
+                base: "1\n2\n3\n4\n5\n6\n",
+                left: "one\n2\n3\nL\n5\n6\n",
+                right: "1\n2\n3\nR\n5\nsix\n",
+                merged: "one\n2\n3\n<<<<<<< LOCAL\nL\n=======\nR\n>>>>>>> DEPLOYMENT\n5\nsix\n",
+                conflict: true,
+            },
+            Case {
+                name: "both insert different lines at the same place",
+                base: "a\nb\n",
+                left: "a\nleft\nb\n",
+                right: "a\nright\nb\n",
+                merged: "a\n<<<<<<< LOCAL\nleft\n=======\nright\n>>>>>>> DEPLOYMENT\nb\n",
+                conflict: true,
+            },
+            Case {
+                name: "left deletes a line right edited",
+                base: "a\nb\nc\n",
+                left: "a\nc\n",
+                right: "a\nB\nc\n",
+                merged: "a\n<<<<<<< LOCAL\n=======\nB\n>>>>>>> DEPLOYMENT\nc\n",
+                conflict: true,
+            },
+            Case {
+                name: "multi-line overlap",
+                base: "a\nb\nc\nd\n",
+                left: "a\nB\nC\nd\n",
+                right: "a\nb\nX\nd\n",
+                merged: "a\n<<<<<<< LOCAL\nB\nC\n=======\nb\nX\n>>>>>>> DEPLOYMENT\nd\n",
+                conflict: true,
+            },
+            Case {
+                name: "missing trailing newline is preserved",
+                base: "a\nb",
+                left: "A\nb",
+                right: "a\nB",
+                merged: "A\nB",
+                conflict: false,
+            },
+            Case {
+                name: "empty base, both add different content",
+                base: "",
+                left: "x\n",
+                right: "y\n",
+                merged: "<<<<<<< LOCAL\nx\n=======\ny\n>>>>>>> DEPLOYMENT\n",
+                conflict: true,
+            },
+        ];
+
+        for case in &cases {
+            let (merged, conflict) = three_way_merge_text(case.base, case.left, case.right);
+            assert_eq!(merged, case.merged, "case: {}", case.name);
+            assert_eq!(conflict, case.conflict, "case: {}", case.name);
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! 部署目录的原子替换。
+//!
+//! 新内容先写到目标旁边的暂存目录 `.{name}.skm-staging-{uuid}`，写完后：
+//! 旧目录改名为 `.{name}.skm-retired-{uuid}`，暂存目录改名为目标。
+//! 旧目录一直保留到调用方把 DB 记录写好再 `commit()`；中途出错（或 `StagedDir` 被提前 drop）
+//! 会把旧目录换回去。进程在两次 rename 之间崩溃时，下次启动由 `cleanup_stale_staging` 收尾。
+
+use log::{info, warn};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::deployments::remove_deploy_target;
+use super::utils::get_skills_lib_path;
+use super::write_journal;
+use crate::db::DbPool;
+use crate::error::AppError;
+use crate::tools::ALL_TOOLS;
+
+const STAGING_MARKER: &str = ".skm-staging-";
+const RETIRED_MARKER: &str = ".skm-retired-";
+
+/// 是否为暂存/待删除的旧目录（扫描、监听、对账时应忽略）
+pub fn is_staging_path(path: &Path) -> bool {
+    path.components().any(|c| {
+        let name = c.as_os_str().to_string_lossy();
+        name.starts_with('.') && (name.contains(STAGING_MARKER) || name.contains(RETIRED_MARKER))
+    })
+}
+
+fn sibling(dst: &Path, marker: &str) -> Result<PathBuf, AppError> {
+    let name = dst
+        .file_name()
+        .ok_or_else(|| AppError::Validation(format!("无效的部署路径: {}", dst.display())))?
+        .to_string_lossy();
+    let parent = dst.parent().unwrap_or_else(|| Path::new("."));
+    Ok(parent.join(format!(".{}{}{}", name, marker, Uuid::new_v4())))
+}
+
+/// 一次暂存中的目录替换
+pub struct StagedDir {
+    dst: PathBuf,
+    staging: PathBuf,
+    retired: Option<PathBuf>,
+    swapped: bool,
+    committed: bool,
+}
+
+impl StagedDir {
+    /// 在目标旁边分配暂存路径（不创建目录，写入方自行创建）
+    pub fn prepare(dst: &Path) -> Result<Self, AppError> {
+        if let Some(parent) = dst.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(Self {
+            dst: dst.to_path_buf(),
+            staging: sibling(dst, STAGING_MARKER)?,
+            retired: None,
+            swapped: false,
+            committed: false,
+        })
+    }
+
+    /// 新内容应写入的路径
+    pub fn path(&self) -> &Path {
+        &self.staging
+    }
+
+    /// 用暂存内容替换目标，旧目录改名保留
+    pub fn swap(&mut self) -> Result<(), AppError> {
+        let old_files = write_journal::snapshot_files(&self.dst);
+        if self.dst.symlink_metadata().is_ok() {
+            let retired = sibling(&self.dst, RETIRED_MARKER)?;
+            std::fs::rename(&self.dst, &retired)?;
+            self.retired = Some(retired);
+        }
+        if let Err(e) = std::fs::rename(&self.staging, &self.dst) {
+            if let Some(retired) = self.retired.take() {
+                let _ = std::fs::rename(&retired, &self.dst);
+            }
+            return Err(e.into());
+        }
+        self.swapped = true;
+        write_journal::record_replaced_tree(&self.dst, &old_files);
+        Ok(())
+    }
+
+    /// DB 已提交，删除旧目录
+    pub fn commit(mut self) {
+        self.committed = true;
+        if let Some(retired) = self.retired.take() {
+            if let Err(e) = remove_deploy_target(&retired) {
+                warn!("[staging] 删除旧目录失败: {} — {}", retired.display(), e);
+            }
+        }
+    }
+}
+
+impl Drop for StagedDir {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        if self.swapped {
+            let _ = remove_deploy_target(&self.dst);
+            if let Some(retired) = self.retired.take() {
+                let _ = std::fs::rename(&retired, &self.dst);
+            }
+            write_journal::record_tree(&self.dst);
+            info!("[staging] 已回滚部署目录: {}", self.dst.display());
+        } else {
+            let _ = remove_deploy_target(&self.staging);
+        }
+    }
+}
+
+/// 启动时清理上次崩溃留下的暂存目录。
+/// 目标已缺失而旧目录还在（崩溃发生在两次 rename 之间）时把旧目录恢复回去，其余一律删除。
+pub fn cleanup_stale_staging(pool: &DbPool) -> Result<usize, AppError> {
+    let conn = pool.get()?;
+    let mut dirs: HashSet<PathBuf> = HashSet::new();
+
+    {
+        let mut stmt = conn.prepare("SELECT path FROM skill_deployments")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        for path in rows.flatten() {
+            if let Some(parent) = Path::new(&path).parent() {
+                dirs.insert(parent.to_path_buf());
+            }
+        }
+    }
+    {
+        let mut stmt = conn.prepare("SELECT path FROM projects")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        for path in rows.flatten() {
+            for t in ALL_TOOLS {
+                dirs.insert(Path::new(&path).join(t.project_dir));
+            }
+        }
+    }
+    if let Some(home) = dirs::home_dir() {
+        for t in ALL_TOOLS {
+            dirs.insert(home.join(t.global_dir));
+        }
+    }
+    dirs.insert(get_skills_lib_path(&conn));
+
+    let mut cleaned = 0usize;
+    for dir in &dirs {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(stripped) = name.strip_prefix('.') else {
+                continue;
+            };
+            let path = entry.path();
+            if stripped.contains(STAGING_MARKER) {
+                remove_deploy_target(&path)?;
+                info!("[staging] 已清理暂存目录: {}", path.display());
+                cleaned += 1;
+            } else if let Some((target, _)) = stripped.split_once(RETIRED_MARKER) {
+                let dst = dir.join(target);
+                if dst.symlink_metadata().is_err() {
+                    std::fs::rename(&path, &dst)?;
+                    info!("[staging] 已恢复未完成替换的部署: {}", dst.display());
+                } else {
+                    remove_deploy_target(&path)?;
+                    info!("[staging] 已清理旧目录: {}", path.display());
+                }
+                cleaned += 1;
+            }
+        }
+    }
+
+    Ok(cleaned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("skm-staging-{}", Uuid::new_v4()));
+        let dst = root.join("pdf");
+        std::fs::create_dir_all(&dst).unwrap();
+        std::fs::write(dst.join("SKILL.md"), "old").unwrap();
+        (root, dst)
+    }
+
+    fn stage(dst: &Path, content: &str) -> StagedDir {
+        let staged = StagedDir::prepare(dst).unwrap();
+        std::fs::create_dir_all(staged.path()).unwrap();
+        std::fs::write(staged.path().join("SKILL.md"), content).unwrap();
+        staged
+    }
+
+    fn siblings(root: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(root)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn swap_then_commit_replaces_and_removes_old_dir() {
+        let (root, dst) = fixture();
+        let mut staged = stage(&dst, "new");
+        assert!(is_staging_path(staged.path()));
+        staged.swap().unwrap();
+        assert_eq!(std::fs::read_to_string(dst.join("SKILL.md")).unwrap(), "new");
+        assert_eq!(siblings(&root).len(), 2);
+
+        staged.commit();
+        assert_eq!(siblings(&root), vec!["pdf"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn drop_without_commit_rolls_back() {
+        let (root, dst) = fixture();
+        {
+            let mut staged = stage(&dst, "new");
+            staged.swap().unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(dst.join("SKILL.md")).unwrap(), "old");
+        assert_eq!(siblings(&root), vec!["pdf"]);
+
+        // 未 swap 就放弃：只删除暂存目录
+        drop(stage(&dst, "new"));
+        assert_eq!(std::fs::read_to_string(dst.join("SKILL.md")).unwrap(), "old");
+        assert_eq!(siblings(&root), vec!["pdf"]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
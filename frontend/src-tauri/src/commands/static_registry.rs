@@ -0,0 +1,296 @@
+//! 自托管的静态注册源。
+//!
+//! 团队可以把审核过的 Skill 发布成一组静态文件，放在任意 HTTP 服务器上，或直接用 `file://` 读取：
+//!
+//! ```text
+//! index.json                      与 dmgrok catalog.json 同构，source.manifest 指向文件清单
+//! skills/<name>/manifest.json     文件清单：相对路径、大小、SHA-256
+//! skills/<name>/files/<相对路径>   Skill 文件
+//! ```
+//!
+//! 条目的 `source.commit_sha` 是 Skill 内容的 checksum（与 `compute_dir_checksum` 同一算法），
+//! 用作更新检测和安装时的版本号。把 index.json 的地址配置成一个 `catalog` 类型的注册源即可。
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::ignore_rules::walk_files;
+use super::registry::{http_get, RawCatalog, RawCatalogSkill, RawCatalogSource};
+use super::skill_files::db_export_to_dir;
+use super::skill_path::{skill_dir_under, SkillPath};
+use super::staging::StagedDir;
+use super::utils::compute_dir_checksum;
+use crate::db::DbPool;
+use crate::error::AppError;
+
+pub const INDEX_FILE: &str = "index.json";
+pub const MANIFEST_FILE: &str = "manifest.json";
+const SKILLS_DIR: &str = "skills";
+const FILES_DIR: &str = "files";
+
+// ── 清单格式 ──
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticManifest {
+    pub name: String,
+    /// 与 index.json 中的 commit_sha 相同
+    pub revision: String,
+    pub files: Vec<StaticManifestFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StaticManifestFile {
+    /// 相对 Skill 根目录，`/` 分隔
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+// ── 读取 ──
+
+/// 注册源地址：http(s)、file://，或本地绝对路径
+fn parse_location(location: &str) -> Result<reqwest::Url, AppError> {
+    if !location.contains("://") {
+        return reqwest::Url::from_file_path(location)
+            .map_err(|_| AppError::Validation(format!("无效的注册源地址: {}", location)));
+    }
+    reqwest::Url::parse(location)
+        .map_err(|e| AppError::Validation(format!("无效的注册源地址 {}: {}", location, e)))
+}
+
+/// 相对 base 解析 rel；结果必须与 base 协议、主机相同且位于 base 所在目录下，
+/// 远程 index.json 不能借 `file:/etc/x`、`%2e%2e` 之类的写法指向目录外或本机任意文件
+pub fn resolve_url(base: &str, rel: &str) -> Result<String, AppError> {
+    let rel = SkillPath::parse(rel)?;
+    // 首段带 `:` 时 join 会把它当成带协议的绝对地址
+    if rel.as_str().split('/').next().is_some_and(|first| first.contains(':')) {
+        return Err(AppError::Validation(format!("相对地址不能带协议: {}", rel)));
+    }
+    let base_url = parse_location(base)?;
+    let url = base_url
+        .join(rel.as_str())
+        .map_err(|e| AppError::Validation(format!("无法解析地址 {} + {}: {}", base, rel, e)))?;
+    let base_dir = base_url.path().rfind('/').map_or("/", |i| &base_url.path()[..=i]);
+    let same_origin = url.scheme() == base_url.scheme()
+        && url.host_str() == base_url.host_str()
+        && url.port_or_known_default() == base_url.port_or_known_default();
+    if !same_origin || !url.path().starts_with(base_dir) {
+        return Err(AppError::Validation(format!("地址 {} 超出了 {} 所在目录", rel, base)));
+    }
+    Ok(url.into())
+}
+
+/// 读取 http(s) 或 file:// 地址的内容
+pub async fn fetch_url(client: &reqwest::Client, location: &str) -> Result<Vec<u8>, AppError> {
+    let url = parse_location(location)?;
+    if url.scheme() == "file" {
+        let path = url
+            .to_file_path()
+            .map_err(|_| AppError::Validation(format!("无效的文件地址: {}", location)))?;
+        return std::fs::read(&path)
+            .map_err(|e| AppError::NotFound(format!("读取 {} 失败: {}", path.display(), e)));
+    }
+    let bytes = http_get(client, url.as_str(), None, None)
+        .await?
+        .bytes()
+        .await
+        .map_err(|e| AppError::Internal(format!("读取 {} 失败: {}", location, e)))?;
+    Ok(bytes.to_vec())
+}
+
+pub async fn load_manifest(
+    client: &reqwest::Client,
+    manifest_url: &str,
+) -> Result<StaticManifest, AppError> {
+    let manifest: StaticManifest = serde_json::from_slice(&fetch_url(client, manifest_url).await?)
+        .map_err(|e| AppError::Internal(format!("解析文件清单失败 ({}): {}", manifest_url, e)))?;
+    for file in &manifest.files {
+        SkillPath::parse(&file.path)?;
+    }
+    Ok(manifest)
+}
+
+/// 清单中某个文件的下载地址
+pub fn file_url(manifest_url: &str, rel_path: &str) -> Result<String, AppError> {
+    resolve_url(manifest_url, &format!("{}/{}", FILES_DIR, SkillPath::parse(rel_path)?))
+}
+
+/// 校验下载内容与清单中的 SHA-256 一致
+pub fn verify_file(file: &StaticManifestFile, content: &[u8]) -> Result<(), AppError> {
+    let actual = hex::encode(Sha256::digest(content));
+    if !actual.eq_ignore_ascii_case(&file.sha256) {
+        return Err(AppError::Validation(format!(
+            "文件 {} 的 SHA-256 与清单不符（清单 {}，实际 {}）",
+            file.path, file.sha256, actual
+        )));
+    }
+    Ok(())
+}
+
+// ── 生成 ──
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StaticRegistryResult {
+    pub output_dir: String,
+    pub index_path: String,
+    pub skills_exported: usize,
+    pub files_exported: usize,
+}
+
+/// 输出目录已存在时必须是之前生成的注册源，避免误删其他目录
+fn check_output_dir(output: &Path) -> Result<(), AppError> {
+    let Ok(mut entries) = std::fs::read_dir(output) else {
+        return Ok(());
+    };
+    if entries.next().is_some() && !output.join(INDEX_FILE).is_file() {
+        return Err(AppError::Validation(format!(
+            "输出目录不为空且不是静态注册源（缺少 {}）: {}",
+            INDEX_FILE,
+            output.display()
+        )));
+    }
+    Ok(())
+}
+
+struct LibrarySkill {
+    id: String,
+    name: String,
+    description: Option<String>,
+    updated_at: String,
+}
+
+/// 把库中的 Skill（skill_ids 为空时全部）导出为静态注册源，整体替换 output_dir
+pub fn generate_static_registry_internal(
+    pool: &DbPool,
+    output_dir: String,
+    skill_ids: Option<Vec<String>>,
+    provider: Option<String>,
+) -> Result<StaticRegistryResult, AppError> {
+    info!("[generate_static_registry] output={}, skills={:?}", output_dir, skill_ids);
+    let output = PathBuf::from(&output_dir);
+    check_output_dir(&output)?;
+
+    let conn = pool.get()?;
+    let skills: Vec<LibrarySkill> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, description, COALESCE(last_modified, updated_at)
+             FROM skills ORDER BY name",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(LibrarySkill {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    description: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        rows
+    };
+    let selected: Vec<&LibrarySkill> = match &skill_ids {
+        Some(ids) if !ids.is_empty() => {
+            for id in ids {
+                if !skills.iter().any(|s| &s.id == id) {
+                    return Err(AppError::NotFound(format!("Skill 不存在: {}", id)));
+                }
+            }
+            skills.iter().filter(|s| ids.contains(&s.id)).collect()
+        }
+        _ => skills.iter().collect(),
+    };
+
+    let provider = provider.unwrap_or_else(|| "local".to_string());
+    let mut staged = StagedDir::prepare(&output)?;
+    let mut index = RawCatalog { skills: Vec::new() };
+    let mut files_exported = 0usize;
+
+    for skill in selected {
+        let skill_root = skill_dir_under(&staged.path().join(SKILLS_DIR), &skill.name)?;
+        let files_dir = skill_root.join(FILES_DIR);
+        if let Err(e) = db_export_to_dir(&conn, &skill.id, &files_dir) {
+            warn!("[generate_static_registry] 跳过 {}: {}", skill.name, e);
+            continue;
+        }
+
+        let mut files = Vec::new();
+        for path in walk_files(&files_dir) {
+            let rel = SkillPath::from_relative(path.strip_prefix(&files_dir).unwrap_or(&path))?;
+            let content = std::fs::read(&path)?;
+            files.push(StaticManifestFile {
+                path: rel.to_string(),
+                size: content.len() as u64,
+                sha256: hex::encode(Sha256::digest(&content)),
+            });
+        }
+        let Some(revision) = compute_dir_checksum(&files_dir) else {
+            warn!("[generate_static_registry] 跳过 {}: 没有可发布的文件", skill.name);
+            continue;
+        };
+        let has_dir = |dir: &str| files.iter().any(|f| f.path.starts_with(&format!("{}/", dir)));
+
+        let skill_path = format!("{}/{}", SKILLS_DIR, skill.name);
+        index.skills.push(RawCatalogSkill {
+            id: skill.name.clone(),
+            name: skill.name.clone(),
+            description: skill.description.clone(),
+            provider: provider.clone(),
+            category: String::new(),
+            license: None,
+            compatibility: None,
+            last_updated_at: Some(skill.updated_at.clone()),
+            has_scripts: has_dir("scripts"),
+            has_references: has_dir("references"),
+            has_assets: has_dir("assets"),
+            tags: Vec::new(),
+            days_since_update: None,
+            maintenance_status: None,
+            quality_score: 0,
+            source: RawCatalogSource {
+                repo: String::new(),
+                path: skill_path.clone(),
+                skill_md_url: format!("{}/{}/SKILL.md", skill_path, FILES_DIR),
+                commit_sha: revision.clone(),
+                manifest: Some(format!("{}/{}", skill_path, MANIFEST_FILE)),
+            },
+        });
+
+        files_exported += files.len();
+        let manifest = StaticManifest {
+            name: skill.name.clone(),
+            revision,
+            files,
+        };
+        std::fs::write(skill_root.join(MANIFEST_FILE), serde_json::to_vec_pretty(&manifest)?)?;
+    }
+
+    std::fs::create_dir_all(staged.path())?;
+    std::fs::write(staged.path().join(INDEX_FILE), serde_json::to_vec_pretty(&index)?)?;
+    staged.swap()?;
+    staged.commit();
+
+    info!(
+        "[generate_static_registry] 完成: {} 个 Skill, {} 个文件",
+        index.skills.len(),
+        files_exported
+    );
+    Ok(StaticRegistryResult {
+        index_path: output.join(INDEX_FILE).to_string_lossy().to_string(),
+        output_dir,
+        skills_exported: index.skills.len(),
+        files_exported,
+    })
+}
+
+#[tauri::command]
+pub async fn generate_static_registry(
+    output_dir: String,
+    skill_ids: Option<Vec<String>>,
+    provider: Option<String>,
+    pool: State<'_, DbPool>,
+) -> Result<StaticRegistryResult, AppError> {
+    generate_static_registry_internal(&pool, output_dir, skill_ids, provider)
+}
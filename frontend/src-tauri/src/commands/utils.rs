@@ -1,24 +1,42 @@
+use rusqlite::Connection;
 use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+
+use super::ignore_rules::walk_files;
+
+/// Skill 库根目录：app_settings.skills_lib_path，未设置时为 ~/.skills-manager/skills
+pub fn get_skills_lib_path(conn: &Connection) -> PathBuf {
+    let path: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_settings WHERE key = 'skills_lib_path'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap_or(None);
+
+    match path {
+        Some(p) => {
+            let p = p.trim_matches('"');
+            match (p.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(rel), Some(home)) => home.join(rel),
+                _ => PathBuf::from(p),
+            }
+        }
+        None => dirs::home_dir()
+            .unwrap_or_default()
+            .join(".skills-manager/skills"),
+    }
+}
 
 pub fn compute_dir_checksum(dir: &Path) -> Option<String> {
     let mut hasher = Sha256::new();
     let mut found_files = false;
 
-    let mut paths: Vec<PathBuf> = WalkDir::new(dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-        .map(|e| e.into_path())
-        .collect();
-
-    paths.sort();
-
-    for path in paths {
+    for path in walk_files(dir) {
         if let Ok(content) = std::fs::read(&path) {
+            // 与 skill_files 中的路径一致，统一用 `/` 分隔
             let relative = path.strip_prefix(dir).unwrap_or(&path);
-            hasher.update(relative.to_string_lossy().as_bytes());
+            hasher.update(relative.to_string_lossy().replace('\\', "/").as_bytes());
             hasher.update(&content);
             found_files = true;
         }
@@ -31,24 +49,19 @@ pub fn compute_dir_checksum(dir: &Path) -> Option<String> {
     }
 }
 
+/// 复制目录，跳过忽略规则命中的文件
 pub fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<u64> {
     let mut count = 0u64;
     std::fs::create_dir_all(dst)?;
 
-    for entry in WalkDir::new(src).into_iter().filter_map(|e| e.ok()) {
-        let src_path = entry.path();
-        let relative = src_path.strip_prefix(src).unwrap_or(src_path);
+    for src_path in walk_files(src) {
+        let relative = src_path.strip_prefix(src).unwrap_or(&src_path);
         let dst_path = dst.join(relative);
-
-        if entry.file_type().is_dir() {
-            std::fs::create_dir_all(&dst_path)?;
-        } else if entry.file_type().is_file() {
-            if let Some(parent) = dst_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-            std::fs::copy(src_path, &dst_path)?;
-            count += 1;
+        if let Some(parent) = dst_path.parent() {
+            std::fs::create_dir_all(parent)?;
         }
+        std::fs::copy(&src_path, &dst_path)?;
+        count += 1;
     }
 
     Ok(count)
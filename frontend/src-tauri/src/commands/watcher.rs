@@ -1,69 +1,86 @@
-use log::info;
+use log::{info, warn};
+use notify::event::RemoveKind;
 use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc;
-use std::time::Duration;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 use tauri::AppHandle;
 use tauri::Emitter;
+use tauri::State;
 
-use super::skill_files::{db_delete_file, db_export_to_dir, db_write_file, has_db_files, refresh_skill_checksum};
-use super::utils::compute_dir_checksum;
+use super::deployments::refresh_linked_deployments;
+use super::ignore_rules::IgnoreRules;
+use super::staging::is_staging_path;
+use super::skill_files::{
+    compute_db_checksum, db_export_revision_to_dir, db_export_to_dir, db_find_revision_by_checksum,
+    db_list_files, db_read_file, db_record_revision, has_db_files, refresh_skill_checksum, remove_file,
+    upsert_file,
+};
+use super::utils::{compute_dir_checksum, copy_dir_recursive};
+use super::write_journal;
 use crate::db::DbPool;
+use crate::error::AppError;
 use crate::tools::ALL_TOOLS;
 
-/// 收集所有需要监听的目录（仅部署目录，DB 为单一数据源）
+/// 根据 DB 收集所有应该监听的目录（仅部署目录，DB 为单一数据源）：
+/// 每个项目下各工具的 Skill 目录（可能尚未创建），以及全局部署的目录
 fn collect_watch_paths(pool: &DbPool) -> Vec<PathBuf> {
     let mut paths = Vec::new();
+    let Ok(conn) = pool.get() else {
+        return paths;
+    };
 
-    // 所有项目的工具 Skill 目录
-    if let Ok(conn) = pool.get() {
-        if let Ok(mut stmt) = conn.prepare("SELECT path FROM projects") {
-            if let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) {
-                for row in rows.flatten() {
-                    let project_path = Path::new(&row);
-                    for tool_dir in ALL_TOOLS.iter().map(|t| t.project_dir) {
-                        let skill_dir = project_path.join(tool_dir);
-                        if skill_dir.exists() && skill_dir.is_dir() {
-                            paths.push(skill_dir);
-                        }
-                    }
+    if let Ok(mut stmt) = conn.prepare("SELECT path FROM projects") {
+        if let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) {
+            for row in rows.flatten() {
+                let project_path = Path::new(&row);
+                for tool_dir in ALL_TOOLS.iter().map(|t| t.project_dir) {
+                    paths.push(project_path.join(tool_dir));
                 }
             }
         }
     }
 
+    if let Ok(mut stmt) = conn.prepare("SELECT path FROM skill_deployments WHERE project_id IS NULL") {
+        if let Ok(rows) = stmt.query_map([], |row| row.get::<_, String>(0)) {
+            paths.extend(rows.flatten().map(PathBuf::from));
+        }
+    }
+
     paths
 }
 
-/// 从部署路径和文件路径查找 skill_id 及 relative_path
-fn resolve_skill_for_file(
-    pool: &DbPool,
-    file_path: &str,
-) -> Option<(String, String, String)> {
-    // 返回 (deployment_id, skill_id, relative_path_within_skill)
-    if let Ok(conn) = pool.get() {
-        if let Ok((dep_id, skill_id, deploy_path)) = conn.query_row(
-            "SELECT id, skill_id, path FROM skill_deployments WHERE ?1 LIKE path || '%'",
-            params![file_path],
-            |row| Ok((
-                row.get::<_, String>(0)?,
-                row.get::<_, String>(1)?,
-                row.get::<_, String>(2)?,
-            )),
-        ) {
-            // 计算相对路径：file_path 减去 deploy_path 前缀
-            let rel = file_path
-                .strip_prefix(&deploy_path)
-                .map(|s| s.trim_start_matches('/').trim_start_matches('\\').to_string())
-                .unwrap_or_default();
-            if !rel.is_empty() {
-                return Some((dep_id, skill_id, rel));
+/// 部署目录索引：每个批次加载一次，按路径前缀把变更文件归属到部署
+struct DeploymentIndex {
+    /// (部署目录, deployment_id, skill_id)，更深的目录排在前面
+    entries: Vec<(PathBuf, String, String)>,
+}
+
+impl DeploymentIndex {
+    fn load(conn: &Connection) -> Self {
+        let mut entries: Vec<(PathBuf, String, String)> = Vec::new();
+        if let Ok(mut stmt) = conn.prepare("SELECT path, id, skill_id FROM skill_deployments") {
+            if let Ok(rows) = stmt.query_map([], |row| {
+                Ok((PathBuf::from(row.get::<_, String>(0)?), row.get(1)?, row.get(2)?))
+            }) {
+                entries.extend(rows.flatten());
             }
         }
+        entries.sort_by_key(|(path, _, _)| std::cmp::Reverse(path.components().count()));
+        Self { entries }
+    }
+
+    /// 返回 (部署目录, deployment_id, skill_id, relative_path_within_skill)
+    fn resolve(&self, file_path: &Path) -> Option<(&Path, String, String, String)> {
+        self.entries.iter().find_map(|(dir, dep_id, skill_id)| {
+            let rel = file_path.strip_prefix(dir).ok()?.to_string_lossy().to_string();
+            (!rel.is_empty()).then(|| (dir.as_path(), dep_id.clone(), skill_id.clone(), rel))
+        })
     }
-    None
 }
 
 /// 写入 DB 前自动备份当前 skill 内容，返回备份 ID（可用于"放弃并还原"）
@@ -110,209 +127,732 @@ fn auto_backup_before_watcher(conn: &Connection, skill_id: &str) -> Option<Strin
     }
 }
 
-/// 处理文件变更事件，回写到 skill_files DB 并写入 change_events
-fn handle_fs_event(event: &Event, pool: &DbPool, app_handle: &AppHandle) {
-    let event_type = match event.kind {
-        EventKind::Create(_) => "file_created",
-        EventKind::Modify(_) => "file_modified",
-        EventKind::Remove(_) => "file_deleted",
-        _ => return,
-    };
+// ── 事件合并 ──
 
-    for path in &event.paths {
-        // 只处理文件，跳过目录
-        if path.is_dir() {
-            continue;
+/// 静默窗口默认值（毫秒），可通过设置项 `watcher_quiet_ms` 调整
+const DEFAULT_QUIET_MS: u64 = 500;
+/// 事件持续不断时，一个批次最多等待这么多个静默窗口
+const MAX_BATCH_WINDOWS: u32 = 10;
+
+/// 读取静默窗口：窗口内没有新事件才处理累积的变更
+pub fn quiet_window(pool: &DbPool) -> Duration {
+    let ms = pool
+        .get()
+        .ok()
+        .and_then(|conn| {
+            conn.query_row(
+                "SELECT value FROM app_settings WHERE key = 'watcher_quiet_ms'",
+                [],
+                |row| row.get::<_, Option<String>>(0),
+            )
+            .ok()
+            .flatten()
+        })
+        .and_then(|v| v.trim_matches('"').parse::<u64>().ok())
+        .unwrap_or(DEFAULT_QUIET_MS);
+    Duration::from_millis(ms)
+}
+
+/// 窗口内一个路径累积的变更类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PendingChange {
+    Created,
+    Modified,
+    Removed,
+    /// 事件明确标为目录的删除
+    RemovedDir,
+}
+
+impl PendingChange {
+    /// 同一路径先后两个事件合并：删除总是覆盖之前的事件，删除后又新建视为修改
+    fn then(self, next: PendingChange) -> PendingChange {
+        match (self, next) {
+            (_, PendingChange::Removed | PendingChange::RemovedDir) => next,
+            (PendingChange::Removed | PendingChange::RemovedDir, _) => PendingChange::Modified,
+            _ => self,
+        }
+    }
+}
+
+/// 一个静默窗口内累积的文件变更，同一路径只保留一条
+#[derive(Default)]
+pub struct EventBatch {
+    paths: BTreeMap<PathBuf, PendingChange>,
+    started: Option<Instant>,
+}
+
+impl EventBatch {
+    pub fn push(&mut self, event: &Event) {
+        let change = match event.kind {
+            EventKind::Create(_) => PendingChange::Created,
+            EventKind::Modify(_) => PendingChange::Modified,
+            EventKind::Remove(RemoveKind::Folder) => PendingChange::RemovedDir,
+            EventKind::Remove(_) => PendingChange::Removed,
+            _ => return,
+        };
+        for path in &event.paths {
+            // 只处理文件，跳过仍存在的目录；已删除的路径是不是目录要到处理时按 DB 判断。
+            // 部署替换过程中的暂存目录也不是用户改动
+            if path.is_dir() || is_staging_path(path) {
+                continue;
+            }
+            self.paths
+                .entry(path.clone())
+                .and_modify(|pending| *pending = pending.then(change))
+                .or_insert(change);
+            self.started.get_or_insert_with(Instant::now);
         }
+    }
 
-        let path_str = path.to_string_lossy().to_string();
-
-        info!(
-            "[watcher] 文件变更: type={}, path={}",
-            event_type, path_str
-        );
-
-        if let Ok(conn) = pool.get() {
-            // 尝试找到对应的 deployment + skill_id + relative_path
-            if let Some((dep_id, skill_id, rel_path)) =
-                resolve_skill_for_file(pool, &path_str)
-            {
-                // ── 写入前自动备份（仅首次变更，已有 watcher_modified_at 时跳过重复备份）──
-                let existing_backup_id: Option<String> = conn
-                    .query_row(
-                        "SELECT watcher_backup_id FROM skills WHERE id = ?1",
-                        params![skill_id],
-                        |row| row.get(0),
-                    )
-                    .ok()
-                    .flatten();
-                let backup_id = if existing_backup_id.is_none() {
-                    auto_backup_before_watcher(&conn, &skill_id)
-                } else {
-                    existing_backup_id
-                };
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
 
-                // ── 回写到 DB skill_files ──
-                let mut write_succeeded = false;
-                match event_type {
-                    "file_deleted" => {
-                        let _ = db_delete_file(&conn, &skill_id, &rel_path);
-                        let _ = refresh_skill_checksum(&conn, &skill_id);
-                        write_succeeded = true;
-                        info!(
-                            "[watcher] 已从 DB 删除文件: skill={}, path={}",
-                            skill_id, rel_path
-                        );
-                    }
-                    "file_created" | "file_modified" => {
-                        if let Ok(content) = std::fs::read(path) {
-                            if let Err(e) = db_write_file(&conn, &skill_id, &rel_path, &content) {
-                                info!("[watcher] 回写 DB 失败: {} — {}", rel_path, e);
-                            } else {
-                                let _ = refresh_skill_checksum(&conn, &skill_id);
-                                write_succeeded = true;
-                                info!(
-                                    "[watcher] 已回写到 DB: skill={}, path={}",
-                                    skill_id, rel_path
-                                );
-                            }
-                        }
-                    }
-                    _ => {}
+    fn age(&self) -> Duration {
+        self.started.map(|t| t.elapsed()).unwrap_or_default()
+    }
+}
+
+/// 已删除的目录没法再从磁盘判断类型：DB 中该路径下有文件时展开为其中每个文件的删除；
+/// 事件标明是目录但 DB 中没有对应文件的（未部署的目录）直接丢弃
+fn expand_removed_dirs(
+    conn: &Connection,
+    index: &DeploymentIndex,
+    paths: BTreeMap<PathBuf, PendingChange>,
+) -> BTreeMap<PathBuf, PendingChange> {
+    let mut expanded = BTreeMap::new();
+    for (path, change) in paths {
+        let removed = matches!(change, PendingChange::Removed | PendingChange::RemovedDir);
+        if !removed || path.exists() {
+            expanded.insert(path, change);
+            continue;
+        }
+        let files = index.resolve(&path).map(|(_, _, skill_id, rel_path)| {
+            let prefix = format!("{}/", rel_path.replace('\\', "/"));
+            db_list_files(conn, &skill_id)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|rel| rel.strip_prefix(&prefix).map(String::from))
+                .collect::<Vec<_>>()
+        });
+        match files {
+            Some(files) if !files.is_empty() => {
+                for rel in files {
+                    expanded.entry(path.join(rel)).or_insert(PendingChange::Removed);
                 }
+            }
+            _ if change == PendingChange::RemovedDir => {}
+            _ => {
+                expanded.insert(path, change);
+            }
+        }
+    }
+    expanded
+}
+
+/// 批次内一个文件的最终变更，类型按处理时的磁盘状态确定
+struct FileChange {
+    path: PathBuf,
+    rel_path: String,
+    event_type: &'static str,
+}
+
+fn handle_batch(batch: EventBatch, pool: &DbPool, app_handle: &AppHandle) {
+    if let Some(payload) = process_batch(batch, pool) {
+        let _ = app_handle.emit("skill-change", payload);
+    }
+}
 
-                // ── 设置 watcher 三字段 ──
-                if write_succeeded {
-                    let _ = conn.execute(
-                        "UPDATE skills SET watcher_modified_at = datetime('now'),
-                         watcher_backup_id = ?1, watcher_trigger_dep_id = ?2
-                         WHERE id = ?3",
-                        params![backup_id, dep_id, skill_id],
-                    );
-                    info!("[watcher] 已设置 watcher 字段: skill={}", skill_id);
+/// 处理一个批次：按 Skill 分组回写 skill_files 并写入 change_events（每个 Skill 一个事务），
+/// 返回本批次唯一的 skill-change 载荷；没有需要处理的变更时返回 None
+pub fn process_batch(batch: EventBatch, pool: &DbPool) -> Option<serde_json::Value> {
+    let conn = pool.get().ok()?;
+    let index = DeploymentIndex::load(&conn);
+
+    // skill_id → deployment_id → 文件变更
+    let mut by_skill: BTreeMap<String, BTreeMap<String, Vec<FileChange>>> = BTreeMap::new();
+    let mut unmatched: Vec<(PathBuf, &'static str)> = Vec::new();
+    let mut rules: BTreeMap<String, IgnoreRules> = BTreeMap::new();
+    let global_rules = IgnoreRules::new(None);
+    for (path, change) in expand_removed_dirs(&conn, &index, batch.paths) {
+        // 本进程刚写入且磁盘仍是写入时的内容
+        if write_journal::is_own_write(&path) {
+            continue;
+        }
+        let disk_content = std::fs::read(&path).ok();
+        let event_type = match (&disk_content, change) {
+            (None, _) => "file_deleted",
+            (Some(_), PendingChange::Created) => "file_created",
+            (Some(_), _) => "file_modified",
+        };
+        match index.resolve(&path) {
+            Some((dir, dep_id, skill_id, rel_path)) => {
+                let ignored = rules
+                    .entry(dep_id.clone())
+                    .or_insert_with(|| IgnoreRules::for_dir(dir))
+                    .is_ignored(Path::new(&rel_path), false);
+                if ignored {
+                    continue;
                 }
+                // 内容与 DB 一致的变更（链接部署刷新、自身导出等）不是用户修改，直接忽略
+                if disk_content == db_read_file(&conn, &skill_id, &rel_path).ok() {
+                    continue;
+                }
+                by_skill
+                    .entry(skill_id)
+                    .or_default()
+                    .entry(dep_id)
+                    .or_default()
+                    .push(FileChange {
+                        path,
+                        rel_path,
+                        event_type,
+                    });
+            }
+            None => {
+                // 不知道 Skill 根目录，只按文件名判断（交换文件、.DS_Store 等）
+                let ignored = path
+                    .file_name()
+                    .is_some_and(|name| global_rules.is_ignored(Path::new(name), false));
+                if !ignored {
+                    unmatched.push((path, event_type));
+                }
+            }
+        }
+    }
 
-                // ── 同步更新 skill_deployments.checksum 和 status ──
-                // 回写成功后，计算部署目录的最新 checksum 并更新记录，
-                // 避免下次 reconcile 误报 diverged
-                if write_succeeded {
-                    if let Ok(deploy_path) = conn.query_row(
-                        "SELECT path FROM skill_deployments WHERE id = ?1",
-                        params![dep_id],
-                        |row| row.get::<_, String>(0),
-                    ) {
-                        let new_dir_checksum = compute_dir_checksum(Path::new(&deploy_path));
-                        let _ = conn.execute(
-                            "UPDATE skill_deployments
-                             SET checksum = ?1, status = 'synced',
-                                 last_synced = datetime('now'), updated_at = datetime('now')
-                             WHERE id = ?2",
-                            params![new_dir_checksum, dep_id],
-                        );
-                        info!(
-                            "[watcher] 已更新部署记录: dep={}, checksum={:?}",
-                            dep_id, new_dir_checksum
-                        );
-                    }
+    let skill_ids: Vec<String> = by_skill.keys().cloned().collect();
+    let deployment_ids: Vec<String> = by_skill.values().flat_map(|d| d.keys().cloned()).collect();
+    let mut files = Vec::new();
+    let mut conflict_event_ids = Vec::new();
+    for (skill_id, deployments) in by_skill {
+        // 库在部署上次同步之后也被改过：两边都有修改，不能直接覆盖库
+        let library_checksum = compute_db_checksum(&conn, &skill_id);
+        let (conflicted, clean): (BTreeMap<_, _>, BTreeMap<_, _>) = deployments
+            .into_iter()
+            .partition(|(dep_id, _)| library_diverged(&conn, dep_id, library_checksum.as_deref()));
+
+        // deployment_id → (change_event id, resolution)
+        let mut event_ids: BTreeMap<String, (String, &str)> = BTreeMap::new();
+        for (dep_id, changes) in &conflicted {
+            match record_conflict(&conn, &skill_id, dep_id, changes) {
+                Ok(event_id) => {
+                    conflict_event_ids.push(event_id.clone());
+                    event_ids.insert(dep_id.clone(), (event_id, "conflict"));
                 }
+                Err(e) => warn!("[watcher] 记录冲突失败: deployment={} — {}", dep_id, e),
+            }
+        }
+        if !clean.is_empty() {
+            match apply_skill_changes(&conn, &skill_id, &clean) {
+                Ok(ids) => event_ids.extend(ids.into_iter().map(|(dep, id)| (dep, (id, "lib_updated")))),
+                Err(e) => warn!("[watcher] 回写 DB 失败: skill={} — {}", skill_id, e),
+            }
+        }
 
-                // ── 记录 change_event ──
-                let old_checksum: Option<String> = conn
-                    .query_row(
-                        "SELECT checksum FROM skill_deployments WHERE id = ?1",
-                        params![dep_id],
-                        |row| row.get(0),
-                    )
-                    .ok()
-                    .flatten();
-
-                let event_id = Uuid::new_v4().to_string();
-                let _ = conn.execute(
-                    "INSERT INTO change_events (id, deployment_id, event_type, old_checksum, new_checksum, resolution)
-                     VALUES (?1, ?2, ?3, ?4, NULL, 'lib_updated')",
-                    params![event_id, dep_id, event_type, old_checksum],
-                );
-
-                let _ = app_handle.emit("skill-change", serde_json::json!({
+        for (dep_id, changes) in clean.iter().chain(conflicted.iter()) {
+            let Some((event_id, resolution)) = event_ids.get(dep_id) else {
+                continue;
+            };
+            for change in changes {
+                files.push(serde_json::json!({
                     "event_id": event_id,
-                    "event_type": event_type,
-                    "path": path_str,
+                    "event_type": change.event_type,
+                    "resolution": resolution,
+                    "path": change.path.to_string_lossy(),
                     "deployment_id": dep_id,
                     "skill_id": skill_id,
-                    "rel_path": rel_path,
-                }));
-            } else {
-                // 无法匹配到已知部署，仍记录事件（状态 pending）
-                let event_id = Uuid::new_v4().to_string();
-                let dep_id = path_str.clone();
-                let _ = conn.execute(
-                    "INSERT INTO change_events (id, deployment_id, event_type, old_checksum, new_checksum, resolution)
-                     VALUES (?1, ?2, ?3, NULL, NULL, 'pending')",
-                    params![event_id, dep_id, event_type],
-                );
-                let _ = app_handle.emit("skill-change", serde_json::json!({
-                    "event_id": event_id,
-                    "event_type": event_type,
-                    "path": path_str,
-                    "deployment_id": dep_id,
+                    "rel_path": change.rel_path,
                 }));
             }
         }
     }
-}
 
-/// 启动文件系统监听，返回 watcher 实例（需保持存活）
-pub fn start_file_watcher(pool: DbPool, app_handle: AppHandle) -> Option<RecommendedWatcher> {
-    let watch_paths = collect_watch_paths(&pool);
+    // 无法匹配到已知部署，仍通知前端
+    files.extend(unmatched_entries(&unmatched));
 
-    if watch_paths.is_empty() {
-        info!("[watcher] 没有需要监听的目录");
+    if files.is_empty() {
         return None;
     }
-
     info!(
-        "[watcher] 开始监听 {} 个目录: {:?}",
-        watch_paths.len(),
-        watch_paths
+        "[watcher] 批次处理完成: {} 个文件, {} 个 Skill, {} 个冲突",
+        files.len(),
+        skill_ids.len(),
+        conflict_event_ids.len()
     );
+    Some(serde_json::json!({
+        "batch_id": Uuid::new_v4().to_string(),
+        "skill_ids": skill_ids,
+        "deployment_ids": deployment_ids,
+        "conflict_event_ids": conflict_event_ids,
+        "files": files,
+    }))
+}
+
+/// 部署记录的 checksum（上次同步时的内容）与库当前 checksum 不一致，说明库在那之后也被修改过
+fn library_diverged(conn: &Connection, dep_id: &str, library_checksum: Option<&str>) -> bool {
+    let recorded: Option<String> = conn
+        .query_row(
+            "SELECT checksum FROM skill_deployments WHERE id = ?1",
+            params![dep_id],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
+    matches!((recorded.as_deref(), library_checksum), (Some(r), Some(l)) if r != l)
+}
 
-    let (tx, rx) = mpsc::channel();
+/// 同一批次内类型一致时沿用该类型，混合变更记为修改
+fn batch_event_type(changes: &[FileChange]) -> &'static str {
+    match changes.first() {
+        Some(first) if changes.iter().all(|c| c.event_type == first.event_type) => first.event_type,
+        _ => "file_modified",
+    }
+}
 
-    let mut watcher = match RecommendedWatcher::new(
-        move |result: Result<Event, notify::Error>| {
-            if let Ok(event) = result {
-                let _ = tx.send(event);
-            }
-        },
-        Config::default().with_poll_interval(Duration::from_secs(2)),
-    ) {
-        Ok(w) => w,
-        Err(e) => {
-            log::error!("[watcher] 创建文件监听器失败: {}", e);
-            return None;
+fn conflicts_root() -> Result<PathBuf, AppError> {
+    let home = dirs::home_dir().ok_or_else(|| AppError::Internal("无法获取用户主目录".into()))?;
+    Ok(home.join(".skills-manager").join("conflicts"))
+}
+
+/// 冲突处理完后删除 record_conflict 为该事件写下的快照
+pub(crate) fn remove_conflict_snapshots(event_id: &str) {
+    let Ok(dir) = conflicts_root().map(|root| root.join(event_id)) else {
+        return;
+    };
+    if dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&dir) {
+            warn!("[watcher] 删除冲突快照失败: {} — {}", dir.display(), e);
+        }
+    }
+}
+
+fn snapshot_dir(src: &Path, dst: &Path) -> Result<(), AppError> {
+    if dst.exists() {
+        std::fs::remove_dir_all(dst)?;
+    }
+    copy_dir_recursive(src, dst)?;
+    Ok(())
+}
+
+/// 记录库与部署的并发修改：把三方合并的输入写到 ~/.skills-manager/conflicts/{event_id}/
+/// （base = 部署上次同步时的库版本，library = 库当前内容，deployment = 部署当前内容），
+/// 插入 resolution = 'conflict' 的 change_event。库和部署内容都不改动。
+/// 同一部署已有未处理的冲突时只刷新部署一侧的快照，返回已有的事件 ID。
+fn record_conflict(
+    conn: &Connection,
+    skill_id: &str,
+    dep_id: &str,
+    changes: &[FileChange],
+) -> Result<String, AppError> {
+    let (deploy_path, recorded_checksum): (String, Option<String>) = conn.query_row(
+        "SELECT path, checksum FROM skill_deployments WHERE id = ?1",
+        params![dep_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let deploy_dir = Path::new(&deploy_path);
+    let disk_checksum = compute_dir_checksum(deploy_dir);
+
+    let existing: Option<(String, Option<String>)> = conn
+        .query_row(
+            "SELECT id, merge_right_path FROM change_events
+             WHERE deployment_id = ?1 AND resolution = 'conflict' AND resolved_at IS NULL
+             ORDER BY created_at DESC LIMIT 1",
+            params![dep_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    if let Some((event_id, Some(right))) = existing {
+        snapshot_dir(deploy_dir, Path::new(&right))?;
+        conn.execute(
+            "UPDATE change_events SET new_checksum = ?1 WHERE id = ?2",
+            params![disk_checksum, event_id],
+        )?;
+        info!("[watcher] 已刷新冲突快照: deployment={}, event={}", dep_id, event_id);
+        return Ok(event_id);
+    }
+
+    let event_id = Uuid::new_v4().to_string();
+    let root = conflicts_root()?.join(&event_id);
+    let base_revision = match recorded_checksum.as_deref() {
+        Some(checksum) => db_find_revision_by_checksum(conn, skill_id, checksum)?,
+        None => None,
+    };
+    let base_path = match base_revision {
+        Some(revision) => {
+            let dir = root.join("base");
+            db_export_revision_to_dir(conn, skill_id, revision, &dir)?;
+            Some(dir)
         }
+        None => None,
     };
+    let left_path = root.join("library");
+    db_export_to_dir(conn, skill_id, &left_path)?;
+    let right_path = root.join("deployment");
+    snapshot_dir(deploy_dir, &right_path)?;
+
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "INSERT INTO change_events (id, deployment_id, event_type, old_checksum, new_checksum, resolution,
+                                    merge_base_path, merge_left_path, merge_right_path)
+         VALUES (?1, ?2, ?3, ?4, ?5, 'conflict', ?6, ?7, ?8)",
+        params![
+            event_id,
+            dep_id,
+            batch_event_type(changes),
+            recorded_checksum,
+            disk_checksum,
+            base_path.map(|p| p.to_string_lossy().to_string()),
+            left_path.to_string_lossy(),
+            right_path.to_string_lossy(),
+        ],
+    )?;
+    tx.execute(
+        "UPDATE skill_deployments SET status = 'diverged', updated_at = datetime('now') WHERE id = ?1",
+        params![dep_id],
+    )?;
+    tx.commit()?;
+
+    info!(
+        "[watcher] 库与部署同时被修改，已记录冲突: skill={}, deployment={}, base_revision={:?}",
+        skill_id, dep_id, base_revision
+    );
+    Ok(event_id)
+}
+
+/// 在一个事务中把某个 Skill 的全部变更回写 DB，返回 deployment_id → change_event id
+fn apply_skill_changes(
+    conn: &Connection,
+    skill_id: &str,
+    deployments: &BTreeMap<String, Vec<FileChange>>,
+) -> Result<BTreeMap<String, String>, AppError> {
+    let tx = conn.unchecked_transaction()?;
+
+    // ── 写入前自动备份（仅首次变更，已有 watcher_backup_id 时跳过重复备份）──
+    let existing_backup_id: Option<String> = tx
+        .query_row(
+            "SELECT watcher_backup_id FROM skills WHERE id = ?1",
+            params![skill_id],
+            |row| row.get(0),
+        )
+        .ok()
+        .flatten();
+    let backup_id = existing_backup_id.or_else(|| auto_backup_before_watcher(&tx, skill_id));
+
+    // ── 回写到 DB skill_files，整批只记录一个版本 ──
+    for change in deployments.values().flatten() {
+        match change.event_type {
+            "file_deleted" => remove_file(&tx, skill_id, &change.rel_path)?,
+            _ => upsert_file(&tx, skill_id, &change.rel_path, &std::fs::read(&change.path)?)?,
+        }
+    }
+    db_record_revision(&tx, skill_id)?;
+    refresh_skill_checksum(&tx, skill_id)?;
 
-    for path in &watch_paths {
-        if let Err(e) = watcher.watch(path, RecursiveMode::Recursive) {
-            log::warn!("[watcher] 监听目录失败: {} - {}", path.display(), e);
+    // ── 设置 watcher 三字段 ──
+    let trigger_dep_id = deployments.keys().next();
+    tx.execute(
+        "UPDATE skills SET watcher_modified_at = datetime('now'),
+         watcher_backup_id = ?1, watcher_trigger_dep_id = ?2
+         WHERE id = ?3",
+        params![backup_id, trigger_dep_id, skill_id],
+    )?;
+
+    // 链接部署：把改动物化回库目录，其他链接部署随之更新
+    let mut linked_skill_name: Option<String> = None;
+    for dep_id in deployments.keys() {
+        let (skill_name, mode): (String, String) = tx.query_row(
+            "SELECT s.name, sd.mode FROM skill_deployments sd
+             JOIN skills s ON s.id = sd.skill_id WHERE sd.id = ?1",
+            params![dep_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if mode != "copy" {
+            linked_skill_name = Some(skill_name);
+        }
+    }
+    if let Some(skill_name) = linked_skill_name {
+        if let Err(e) = refresh_linked_deployments(&tx, skill_id, &skill_name) {
+            warn!("[watcher] 刷新链接部署失败: skill={} — {}", skill_id, e);
         }
     }
 
-    // 后台线程处理事件
-    std::thread::spawn(move || {
-        info!("[watcher] 后台事件处理线程已启动");
-        loop {
-            match rx.recv_timeout(Duration::from_secs(5)) {
-                Ok(event) => handle_fs_event(&event, &pool, &app_handle),
-                Err(mpsc::RecvTimeoutError::Timeout) => continue,
-                Err(mpsc::RecvTimeoutError::Disconnected) => {
-                    info!("[watcher] 通道已断开，停止监听");
-                    break;
+    // ── 同步部署记录的 checksum / status，并为每个部署记录一条 change_event ──
+    // 回写成功后部署目录即为最新内容，避免下次 reconcile 误报 diverged
+    let mut event_ids = BTreeMap::new();
+    for (dep_id, changes) in deployments {
+        let (deploy_path, old_checksum): (String, Option<String>) = tx.query_row(
+            "SELECT path, checksum FROM skill_deployments WHERE id = ?1",
+            params![dep_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let new_checksum = compute_dir_checksum(Path::new(&deploy_path));
+        tx.execute(
+            "UPDATE skill_deployments
+             SET checksum = ?1, status = 'synced',
+                 last_synced = datetime('now'), updated_at = datetime('now')
+             WHERE id = ?2",
+            params![new_checksum, dep_id],
+        )?;
+
+        let event_type = batch_event_type(changes);
+        let event_id = Uuid::new_v4().to_string();
+        tx.execute(
+            "INSERT INTO change_events (id, deployment_id, event_type, old_checksum, new_checksum, resolution)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'lib_updated')",
+            params![event_id, dep_id, event_type, old_checksum, new_checksum],
+        )?;
+        event_ids.insert(dep_id.clone(), event_id);
+    }
+
+    tx.commit()?;
+    info!(
+        "[watcher] 已回写到 DB: skill={}, files={}",
+        skill_id,
+        deployments.values().map(Vec::len).sum::<usize>()
+    );
+    Ok(event_ids)
+}
+
+/// 待处理的库/部署并发修改，merge_* 三个路径可直接作为 merge_skill_versions 的参数
+#[derive(Debug, Serialize)]
+pub struct WatcherConflict {
+    pub event_id: String,
+    pub deployment_id: String,
+    pub skill_id: String,
+    pub skill_name: String,
+    pub deployment_path: String,
+    pub merge_base_path: Option<String>,
+    pub merge_left_path: Option<String>,
+    pub merge_right_path: Option<String>,
+    pub created_at: String,
+}
+
+pub fn list_watcher_conflicts_internal(pool: &DbPool) -> Result<Vec<WatcherConflict>, AppError> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT ce.id, ce.deployment_id, sd.skill_id, s.name, sd.path,
+                ce.merge_base_path, ce.merge_left_path, ce.merge_right_path, ce.created_at
+         FROM change_events ce
+         JOIN skill_deployments sd ON sd.id = ce.deployment_id
+         JOIN skills s ON s.id = sd.skill_id
+         WHERE ce.resolution = 'conflict' AND ce.resolved_at IS NULL
+         ORDER BY ce.created_at DESC",
+    )?;
+    let conflicts = stmt
+        .query_map([], |row| {
+            Ok(WatcherConflict {
+                event_id: row.get(0)?,
+                deployment_id: row.get(1)?,
+                skill_id: row.get(2)?,
+                skill_name: row.get(3)?,
+                deployment_path: row.get(4)?,
+                merge_base_path: row.get(5)?,
+                merge_left_path: row.get(6)?,
+                merge_right_path: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(conflicts)
+}
+
+#[tauri::command]
+pub async fn list_watcher_conflicts(
+    pool: State<'_, DbPool>,
+) -> Result<Vec<WatcherConflict>, AppError> {
+    list_watcher_conflicts_internal(&pool)
+}
+
+/// 未归属到任何部署的变更只推送给前端：没有部署记录可关联，不写入 change_events
+fn unmatched_entries(unmatched: &[(PathBuf, &'static str)]) -> Vec<serde_json::Value> {
+    unmatched
+        .iter()
+        .map(|(path, event_type)| {
+            serde_json::json!({
+                "event_id": null,
+                "event_type": event_type,
+                "resolution": "unmatched",
+                "path": path.to_string_lossy(),
+                "deployment_id": null,
+            })
+        })
+        .collect()
+}
+
+// ── 监听服务 ──
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchedPath {
+    pub path: String,
+    /// false 表示目录尚不存在，创建后自动开始监听
+    pub active: bool,
+}
+
+struct WatcherInner {
+    watcher: Option<RecommendedWatcher>,
+    /// 期望监听的目录 → 是否已生效
+    paths: BTreeMap<PathBuf, bool>,
+}
+
+/// 文件监听服务，由 Tauri 托管。项目、部署相关命令执行后调用 `resync`，
+/// 尚不存在的目录会在事件线程空闲时重试。
+#[derive(Clone)]
+pub struct WatcherService {
+    inner: Arc<Mutex<WatcherInner>>,
+}
+
+impl WatcherService {
+    /// 创建监听器和后台事件线程，并按 DB 登记初始目录
+    pub fn start(pool: DbPool, app_handle: AppHandle) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        let watcher = match RecommendedWatcher::new(
+            move |result: Result<Event, notify::Error>| {
+                if let Ok(event) = result {
+                    let _ = tx.send(event);
                 }
+            },
+            Config::default().with_poll_interval(Duration::from_secs(2)),
+        ) {
+            Ok(w) => Some(w),
+            Err(e) => {
+                log::error!("[watcher] 创建文件监听器失败: {}", e);
+                None
             }
+        };
+
+        let service = Self {
+            inner: Arc::new(Mutex::new(WatcherInner {
+                watcher,
+                paths: BTreeMap::new(),
+            })),
+        };
+        service.resync(&pool);
+
+        // 后台线程处理事件
+        let background = service.clone();
+        std::thread::spawn(move || {
+            info!("[watcher] 后台事件处理线程已启动");
+            let mut batch = EventBatch::default();
+            let mut quiet = quiet_window(&pool);
+            loop {
+                let timeout = if batch.is_empty() {
+                    Duration::from_secs(5)
+                } else {
+                    (quiet * MAX_BATCH_WINDOWS).saturating_sub(batch.age()).min(quiet)
+                };
+                match rx.recv_timeout(timeout) {
+                    Ok(event) => {
+                        if batch.is_empty() {
+                            quiet = quiet_window(&pool);
+                        }
+                        batch.push(&event);
+                        if batch.age() >= quiet * MAX_BATCH_WINDOWS {
+                            handle_batch(std::mem::take(&mut batch), &pool, &app_handle);
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) if batch.is_empty() => {
+                        background.activate_pending()
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => {
+                        handle_batch(std::mem::take(&mut batch), &pool, &app_handle)
+                    }
+                    Err(mpsc::RecvTimeoutError::Disconnected) => {
+                        info!("[watcher] 通道已断开，停止监听");
+                        break;
+                    }
+                }
+            }
+        });
+
+        service
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, WatcherInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 开始监听目录，返回是否已生效（目录不存在时先登记，等创建后再监听）
+    pub fn add_path(&self, path: &Path) -> bool {
+        let mut inner = self.lock();
+        if inner.paths.get(path) == Some(&true) {
+            return true;
+        }
+        let active = path.is_dir()
+            && match inner.watcher.as_mut() {
+                Some(w) => match w.watch(path, RecursiveMode::Recursive) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        log::warn!("[watcher] 监听目录失败: {} - {}", path.display(), e);
+                        false
+                    }
+                },
+                None => false,
+            };
+        if active {
+            info!("[watcher] 开始监听: {}", path.display());
+        }
+        inner.paths.insert(path.to_path_buf(), active);
+        active
+    }
+
+    /// 停止监听目录
+    pub fn remove_path(&self, path: &Path) {
+        let mut inner = self.lock();
+        if inner.paths.remove(path) == Some(true) {
+            if let Some(w) = inner.watcher.as_mut() {
+                let _ = w.unwatch(path);
+            }
+            info!("[watcher] 停止监听: {}", path.display());
+        }
+    }
+
+    pub fn watched_paths(&self) -> Vec<WatchedPath> {
+        self.lock()
+            .paths
+            .iter()
+            .map(|(path, active)| WatchedPath {
+                path: path.to_string_lossy().to_string(),
+                active: *active,
+            })
+            .collect()
+    }
+
+    /// 按 DB 当前的项目和全局部署增删监听目录
+    pub fn resync(&self, pool: &DbPool) {
+        let wanted: BTreeSet<PathBuf> = collect_watch_paths(pool).into_iter().collect();
+        let stale: Vec<PathBuf> = self
+            .lock()
+            .paths
+            .keys()
+            .filter(|p| !wanted.contains(*p))
+            .cloned()
+            .collect();
+        for path in &stale {
+            self.remove_path(path);
+        }
+        for path in &wanted {
+            self.add_path(path);
         }
-    });
+    }
+
+    /// 重试尚未生效的目录（例如之后才创建的工具目录）
+    fn activate_pending(&self) {
+        let pending: Vec<PathBuf> = self
+            .lock()
+            .paths
+            .iter()
+            .filter(|(path, active)| !**active && path.is_dir())
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &pending {
+            self.add_path(path);
+        }
+    }
+}
 
-    Some(watcher)
+#[tauri::command]
+pub fn list_watched_paths(watcher: State<'_, WatcherService>) -> Vec<WatchedPath> {
+    watcher.watched_paths()
 }
@@ -0,0 +1,104 @@
+//! 本进程对部署目录的写入登记。
+//!
+//! 部署、同步、恢复等操作写完文件后登记「路径 → 预期内容的 checksum」（删除登记为预期不存在），
+//! watcher 收到事件时先查这里：磁盘现状与登记一致，说明是自己写的，不当作用户修改。
+//! 登记在 `JOURNAL_TTL` 后过期，之后同一路径上的变化照常处理。
+
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use walkdir::WalkDir;
+
+/// 登记保留时长，需覆盖 notify 的事件延迟
+const JOURNAL_TTL: Duration = Duration::from_secs(30);
+
+struct Expected {
+    /// None 表示预期该路径不存在
+    checksum: Option<String>,
+    recorded_at: Instant,
+}
+
+static JOURNAL: Mutex<BTreeMap<PathBuf, Expected>> = Mutex::new(BTreeMap::new());
+
+fn file_checksum(path: &Path) -> Option<String> {
+    let content = std::fs::read(path).ok()?;
+    Some(hex::encode(Sha256::digest(&content)))
+}
+
+fn files_under(dir: &Path) -> Vec<PathBuf> {
+    if !dir.exists() {
+        return Vec::new();
+    }
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect()
+}
+
+fn record(entries: impl IntoIterator<Item = (PathBuf, Option<String>)>) {
+    let now = Instant::now();
+    let mut journal = JOURNAL.lock().unwrap_or_else(|e| e.into_inner());
+    journal.retain(|_, e| now.duration_since(e.recorded_at) < JOURNAL_TTL);
+    for (path, checksum) in entries {
+        journal.insert(
+            path,
+            Expected {
+                checksum,
+                recorded_at: now,
+            },
+        );
+    }
+}
+
+/// 登记目录下所有文件的当前内容（在写完之后调用）
+pub fn record_tree(dir: &Path) {
+    record(files_under(dir).into_iter().map(|p| {
+        let checksum = file_checksum(&p);
+        (p, checksum)
+    }));
+}
+
+/// 登记目录下所有文件即将被删除（在删除之前调用）
+pub fn record_tree_removal(dir: &Path) {
+    record(files_under(dir).into_iter().map(|p| (p, None)));
+}
+
+/// 整体替换目录后登记：新文件按内容登记，旧树中不再存在的文件登记为已删除
+pub fn record_replaced_tree(dir: &Path, old_files: &[PathBuf]) {
+    let new_files = files_under(dir);
+    let removed = old_files
+        .iter()
+        .filter(|p| !new_files.contains(p))
+        .map(|p| (p.clone(), None))
+        .collect::<Vec<_>>();
+    record(
+        new_files
+            .into_iter()
+            .map(|p| {
+                let checksum = file_checksum(&p);
+                (p, checksum)
+            })
+            .chain(removed),
+    );
+}
+
+/// 旧树的文件列表，供 `record_replaced_tree` 使用
+pub fn snapshot_files(dir: &Path) -> Vec<PathBuf> {
+    files_under(dir)
+}
+
+/// 该路径的当前状态是否正是本进程登记过的写入结果
+pub fn is_own_write(path: &Path) -> bool {
+    let journal = JOURNAL.lock().unwrap_or_else(|e| e.into_inner());
+    match journal.get(path) {
+        Some(expected) if expected.recorded_at.elapsed() < JOURNAL_TTL => {
+            let current = if path.is_file() { file_checksum(path) } else { None };
+            current == expected.checksum
+        }
+        _ => false,
+    }
+}
@@ -1,13 +1,143 @@
-use rusqlite::{Connection, Result};
 use log::info;
+use rusqlite::{params, Connection, Transaction};
+use sha2::{Digest, Sha256};
 
-/// 初始化所有表结构。
-/// 全部使用 CREATE TABLE IF NOT EXISTS / ALTER TABLE ... ADD COLUMN IF NOT EXISTS，
-/// 每次启动幂等执行，无需版本追踪，无迁移状态可破坏。
-pub fn init_schema(conn: &Connection) -> Result<()> {
+use crate::error::AppError;
+
+/// 一个 schema 迁移步骤。`up` 在独立事务中执行，成功后才把 `PRAGMA user_version` 推进到 `version`。
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub up: fn(&Transaction) -> rusqlite::Result<()>,
+}
+
+/// 按版本号升序排列，只能追加，不能修改已发布的迁移。
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline schema",
+        up: migrate_v1_baseline,
+    },
+    Migration {
+        version: 2,
+        description: "content-addressed skill revisions",
+        up: migrate_v2_skill_revisions,
+    },
+    Migration {
+        version: 3,
+        description: "deployment mode",
+        up: migrate_v3_deployment_mode,
+    },
+    Migration {
+        version: 4,
+        description: "watcher conflict merge inputs",
+        up: migrate_v4_change_event_merge_inputs,
+    },
+    Migration {
+        version: 5,
+        description: "skill registries",
+        up: migrate_v5_registries,
+    },
+    Migration {
+        version: 6,
+        description: "offline catalog snapshots",
+        up: migrate_v6_catalog_snapshots,
+    },
+    Migration {
+        version: 7,
+        description: "full-text skill search",
+        up: migrate_v7_skill_search,
+    },
+    Migration {
+        version: 8,
+        description: "repository-wide sync history",
+        up: migrate_v8_repo_sync_history,
+    },
+    Migration {
+        version: 9,
+        description: "bidirectional git sync state",
+        up: migrate_v9_git_sync_state,
+    },
+    Migration {
+        version: 10,
+        description: "auto-export library fingerprint",
+        up: migrate_v10_export_fingerprint,
+    },
+    Migration {
+        version: 11,
+        description: "git remote credentials",
+        up: migrate_v11_git_credentials,
+    },
+    Migration {
+        version: 12,
+        description: "keep backups of deleted skills",
+        up: migrate_v12_orphan_backups,
+    },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+/// 初始化数据库：依据 `PRAGMA user_version` 依次执行尚未应用的迁移。
+/// 任一迁移失败即回滚该迁移并返回 `AppError::Migration`，阻止应用继续启动。
+pub fn init_schema(conn: &Connection) -> Result<(), AppError> {
     info!("[schema] 初始化数据库表结构...");
+    run_migrations(conn, MIGRATIONS)?;
+    info!("[schema] 数据库表结构初始化完成, version={}", latest_version());
+    Ok(())
+}
+
+fn run_migrations(conn: &Connection, migrations: &[Migration]) -> Result<(), AppError> {
+    let current = user_version(conn)?;
+    let latest = migrations.last().map(|m| m.version).unwrap_or(0);
+
+    if current > latest {
+        return Err(AppError::Migration {
+            version: current,
+            message: format!("数据库版本 {} 高于当前应用支持的最新版本 {}，请升级应用", current, latest),
+        });
+    }
+
+    for migration in migrations.iter().filter(|m| m.version > current) {
+        info!(
+            "[schema] 执行迁移 v{}: {}",
+            migration.version, migration.description
+        );
+        apply_migration(conn, migration).map_err(|e| AppError::Migration {
+            version: migration.version,
+            message: format!("{} ({})", e, migration.description),
+        })?;
+    }
+
+    Ok(())
+}
 
-    conn.execute_batch("
+fn apply_migration(conn: &Connection, migration: &Migration) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    (migration.up)(&tx)?;
+    tx.pragma_update(None, "user_version", migration.version)?;
+    tx.commit()
+}
+
+pub fn user_version(conn: &Connection) -> Result<u32, AppError> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> rusqlite::Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(names.iter().any(|n| n == column))
+}
+
+// ── 迁移 ──
+
+/// v1：引入版本号之前的全部表结构。
+/// 旧库（user_version = 0）可能已有这些表，因此仍使用 IF NOT EXISTS，并按需补齐后加的列。
+fn migrate_v1_baseline(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch("
         -- ── 项目表 ──
         CREATE TABLE IF NOT EXISTS projects (
             id           TEXT PRIMARY KEY,
@@ -175,11 +305,454 @@ pub fn init_schema(conn: &Connection) -> Result<()> {
             ('auto_export_frequency','manual');
     ")?;
 
-    // 对已有数据库幂等补列（失败则忽略，列已存在时 SQLite 会报错）
-    let _ = conn.execute("ALTER TABLE skills ADD COLUMN watcher_modified_at DATETIME", []);
-    let _ = conn.execute("ALTER TABLE skills ADD COLUMN watcher_backup_id TEXT", []);
-    let _ = conn.execute("ALTER TABLE skills ADD COLUMN watcher_trigger_dep_id TEXT", []);
+    for column in ["watcher_modified_at DATETIME", "watcher_backup_id TEXT", "watcher_trigger_dep_id TEXT"] {
+        let name = column.split_whitespace().next().unwrap_or_default();
+        if !has_column(tx, "skills", name)? {
+            tx.execute(&format!("ALTER TABLE skills ADD COLUMN {}", column), [])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// v2：内容寻址的版本历史。已有 Skill 的当前文件回填为 revision 1。
+fn migrate_v2_skill_revisions(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch("
+        CREATE TABLE skill_blobs (
+            sha256     TEXT PRIMARY KEY,
+            content    BLOB NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT (datetime('now'))
+        );
+
+        CREATE TABLE skill_revisions (
+            skill_id   TEXT NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+            revision   INTEGER NOT NULL,
+            checksum   TEXT,
+            file_count INTEGER NOT NULL,
+            created_at DATETIME NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (skill_id, revision)
+        );
+
+        CREATE TABLE skill_revision_files (
+            skill_id      TEXT NOT NULL,
+            revision      INTEGER NOT NULL,
+            relative_path TEXT NOT NULL,
+            blob_sha256   TEXT NOT NULL REFERENCES skill_blobs(sha256),
+            PRIMARY KEY (skill_id, revision, relative_path),
+            FOREIGN KEY (skill_id, revision) REFERENCES skill_revisions(skill_id, revision) ON DELETE CASCADE
+        );
+        CREATE INDEX idx_skill_revision_files_blob ON skill_revision_files(blob_sha256);
+    ")?;
+
+    let rows: Vec<(String, String, Vec<u8>)> = {
+        let mut stmt = tx.prepare(
+            "SELECT skill_id, relative_path, content FROM skill_files ORDER BY skill_id, relative_path",
+        )?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+
+    let mut i = 0;
+    while i < rows.len() {
+        let skill_id = &rows[i].0;
+        let end = i + rows[i..].iter().take_while(|r| &r.0 == skill_id).count();
+
+        let mut hasher = Sha256::new();
+        for (_, rel_path, content) in &rows[i..end] {
+            hasher.update(rel_path.as_bytes());
+            hasher.update(content);
+        }
+        tx.execute(
+            "INSERT INTO skill_revisions (skill_id, revision, checksum, file_count) VALUES (?1, 1, ?2, ?3)",
+            params![skill_id, hex::encode(hasher.finalize()), (end - i) as i64],
+        )?;
+        for (_, rel_path, content) in &rows[i..end] {
+            let sha = hex::encode(Sha256::digest(content));
+            tx.execute(
+                "INSERT OR IGNORE INTO skill_blobs (sha256, content, size_bytes) VALUES (?1, ?2, ?3)",
+                params![sha, content, content.len() as i64],
+            )?;
+            tx.execute(
+                "INSERT INTO skill_revision_files (skill_id, revision, relative_path, blob_sha256)
+                 VALUES (?1, 1, ?2, ?3)",
+                params![skill_id, rel_path, sha],
+            )?;
+        }
+        i = end;
+    }
 
-    info!("[schema] 数据库表结构初始化完成");
     Ok(())
 }
+
+/// v3：部署模式 copy | symlink | hardlink，已有部署均为 copy
+fn migrate_v3_deployment_mode(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE skill_deployments ADD COLUMN mode TEXT NOT NULL DEFAULT 'copy';",
+    )
+}
+
+/// v4：resolution = 'conflict' 的事件记录三方合并的输入目录，
+/// 对应 merge_skill_versions 的 base_path / left_path（库）/ right_path（部署）
+fn migrate_v4_change_event_merge_inputs(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        "ALTER TABLE change_events ADD COLUMN merge_base_path  TEXT;
+         ALTER TABLE change_events ADD COLUMN merge_left_path  TEXT;
+         ALTER TABLE change_events ADD COLUMN merge_right_path TEXT;",
+    )
+}
+
+/// v5：可配置的 Skill 注册源，预置 dmgrok catalog 与 skills.sh；
+/// skill_sources.registry_id 记录从哪个注册源安装
+fn migrate_v5_registries(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE registries (
+            id         TEXT PRIMARY KEY,
+            name       TEXT NOT NULL,
+            kind       TEXT NOT NULL,
+            url        TEXT NOT NULL,
+            options    TEXT,
+            enabled    INTEGER NOT NULL DEFAULT 1,
+            priority   INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME NOT NULL DEFAULT (datetime('now')),
+            updated_at DATETIME NOT NULL DEFAULT (datetime('now'))
+        );
+
+        INSERT INTO registries (id, name, kind, url, options, priority) VALUES
+            ('dmgrok', 'dmgrok catalog', 'catalog',
+             'https://cdn.jsdelivr.net/gh/dmgrok/agent_skills_directory@main/catalog.json',
+             '{"mirrors":["https://raw.githubusercontent.com/dmgrok/agent_skills_directory/main/catalog.json","https://ghproxy.com/https://raw.githubusercontent.com/dmgrok/agent_skills_directory/main/catalog.json"]}',
+             0),
+            ('skills-sh', 'skills.sh', 'skills_sh', 'https://skills.sh', NULL, 10);
+
+        ALTER TABLE skill_sources ADD COLUMN registry_id TEXT;
+        "#,
+    )
+}
+
+fn migrate_v6_catalog_snapshots(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE catalog_snapshots (
+            registry_id   TEXT PRIMARY KEY,
+            source_url    TEXT NOT NULL,
+            etag          TEXT,
+            last_modified TEXT,
+            skills_json   TEXT NOT NULL,
+            skill_count   INTEGER NOT NULL DEFAULT 0,
+            fetched_at    DATETIME NOT NULL DEFAULT (datetime('now')),
+            updated_at    DATETIME NOT NULL DEFAULT (datetime('now'))
+        );
+        "#,
+    )
+}
+
+/// v7：FTS5 全文索引。元数据行由触发器维护，文件行由 skill_files 写入函数维护，这里回填已有文件
+fn migrate_v7_skill_search(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE VIRTUAL TABLE skill_search USING fts5(
+            skill_id UNINDEXED,
+            path,
+            name,
+            description,
+            frontmatter,
+            body,
+            tokenize = 'trigram'
+        );
+
+        CREATE TRIGGER skill_search_skill_insert AFTER INSERT ON skills BEGIN
+            INSERT INTO skill_search (skill_id, path, name, description, frontmatter, body)
+            VALUES (new.id, '', new.name, COALESCE(new.description, ''), '', '');
+        END;
+
+        CREATE TRIGGER skill_search_skill_update AFTER UPDATE OF name, description ON skills BEGIN
+            UPDATE skill_search SET name = new.name, description = COALESCE(new.description, '')
+            WHERE skill_id = old.id AND path = '';
+        END;
+
+        CREATE TRIGGER skill_search_skill_delete AFTER DELETE ON skills BEGIN
+            DELETE FROM skill_search WHERE skill_id = old.id;
+        END;
+
+        INSERT INTO skill_search (skill_id, path, name, description, frontmatter, body)
+        SELECT id, '', name, COALESCE(description, ''), '', '' FROM skills;
+        "#,
+    )?;
+
+    let rows: Vec<(String, String, Vec<u8>)> = {
+        let mut stmt = tx.prepare("SELECT skill_id, relative_path, content FROM skill_files")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+    for (skill_id, rel_path, content) in rows {
+        // 与 search::index_file 相同的规则：只索引 512 KiB 以内的 UTF-8 文本
+        let Some(text) = std::str::from_utf8(&content).ok().filter(|t| t.len() <= 512 * 1024) else {
+            continue;
+        };
+        let (frontmatter, body) = match text.strip_prefix("---").filter(|_| rel_path == "SKILL.md") {
+            Some(rest) => match rest.find("---") {
+                Some(end) => (&rest[..end], &rest[end + 3..]),
+                None => ("", text),
+            },
+            None => ("", text),
+        };
+        tx.execute(
+            "INSERT INTO skill_search (skill_id, path, name, description, frontmatter, body)
+             VALUES (?1, ?2, '', '', ?3, ?4)",
+            params![skill_id, rel_path, frontmatter, body],
+        )?;
+    }
+    Ok(())
+}
+
+/// v8：git 导出 / 导入是整个库的操作，skill_id 为 NULL；此前写入的 'all' 违反外键，一并改为 NULL
+fn migrate_v8_repo_sync_history(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE sync_history_new (
+            id            TEXT PRIMARY KEY,
+            skill_id      TEXT,
+            deployment_id TEXT,
+            action        TEXT NOT NULL,
+            from_checksum TEXT,
+            to_checksum   TEXT,
+            status        TEXT NOT NULL DEFAULT 'success',
+            error_message TEXT,
+            created_at    DATETIME NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (skill_id)      REFERENCES skills(id)           ON DELETE CASCADE,
+            FOREIGN KEY (deployment_id) REFERENCES skill_deployments(id) ON DELETE SET NULL
+        );
+        INSERT INTO sync_history_new
+            SELECT id, NULLIF(skill_id, 'all'), deployment_id, action, from_checksum, to_checksum,
+                   status, error_message, created_at
+            FROM sync_history;
+        DROP TABLE sync_history;
+        ALTER TABLE sync_history_new RENAME TO sync_history;
+        CREATE INDEX idx_sync_history_skill      ON sync_history(skill_id);
+        CREATE INDEX idx_sync_history_deployment ON sync_history(deployment_id);
+        CREATE INDEX idx_sync_history_created    ON sync_history(created_at DESC);
+        "#,
+    )
+}
+
+/// v9：双向同步的 base。每个配置记录上次同步的提交；两侧都改过、尚未处理的 Skill 保留各自的 base
+fn migrate_v9_git_sync_state(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        ALTER TABLE git_export_config ADD COLUMN last_sync_commit TEXT;
+        CREATE TABLE git_sync_conflicts (
+            config_id   TEXT NOT NULL REFERENCES git_export_config(id) ON DELETE CASCADE,
+            skill_name  TEXT NOT NULL,
+            base_commit TEXT,
+            created_at  DATETIME NOT NULL DEFAULT (datetime('now')),
+            PRIMARY KEY (config_id, skill_name)
+        );
+        "#,
+    )
+}
+
+/// v10：上次导出时库的指纹，自动导出（on-change）据此判断库是否有变化
+fn migrate_v10_export_fingerprint(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch("ALTER TABLE git_export_config ADD COLUMN export_fingerprint TEXT;")
+}
+
+/// v11：git 导出配置的凭据，按 auth_type 使用 token 或 SSH 私钥；随配置一起删除
+fn migrate_v11_git_credentials(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE git_credentials (
+            config_id      TEXT PRIMARY KEY REFERENCES git_export_config(id) ON DELETE CASCADE,
+            username       TEXT,
+            token          TEXT,
+            ssh_key_path   TEXT,
+            ssh_passphrase TEXT,
+            updated_at     DATETIME NOT NULL DEFAULT (datetime('now'))
+        );
+        "#,
+    )
+}
+
+/// v12：备份不再随 Skill 级联删除。同步时远程删除的 Skill 先备份再删除，备份记录需要留下；
+/// 用户手动删除 Skill 时由 delete_skill 一并删除备份记录
+fn migrate_v12_orphan_backups(tx: &Transaction) -> rusqlite::Result<()> {
+    tx.execute_batch(
+        r#"
+        CREATE TABLE skill_backups_new (
+            id            TEXT PRIMARY KEY,
+            skill_id      TEXT NOT NULL,
+            version_label TEXT,
+            backup_path   TEXT NOT NULL,
+            checksum      TEXT NOT NULL,
+            reason        TEXT NOT NULL,
+            metadata      TEXT,
+            created_at    DATETIME NOT NULL DEFAULT (datetime('now'))
+        );
+        INSERT INTO skill_backups_new
+            SELECT id, skill_id, version_label, backup_path, checksum, reason, metadata, created_at
+            FROM skill_backups;
+        DROP TABLE skill_backups;
+        ALTER TABLE skill_backups_new RENAME TO skill_backups;
+        CREATE INDEX idx_skill_backups_skill   ON skill_backups(skill_id);
+        CREATE INDEX idx_skill_backups_created ON skill_backups(created_at DESC);
+        "#,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const V0_SNAPSHOT: &str = include_str!("snapshots/v0_baseline.sql");
+
+    fn open() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON;").unwrap();
+        conn
+    }
+
+    /// 表名 -> 列名列表，用来比较两个库的结构是否一致
+    fn table_columns(conn: &Connection) -> BTreeMap<String, Vec<String>> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+            .unwrap();
+        let tables: Vec<String> = stmt
+            .query_map([], |r| r.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        tables
+            .into_iter()
+            .map(|t| {
+                let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", t)).unwrap();
+                let mut cols: Vec<String> = stmt
+                    .query_map([], |r| r.get(1))
+                    .unwrap()
+                    .collect::<rusqlite::Result<_>>()
+                    .unwrap();
+                cols.sort();
+                (t, cols)
+            })
+            .collect()
+    }
+
+    fn fresh_columns() -> BTreeMap<String, Vec<String>> {
+        let conn = open();
+        init_schema(&conn).unwrap();
+        table_columns(&conn)
+    }
+
+    #[test]
+    fn fresh_database_migrates_to_latest() {
+        let conn = open();
+        assert_eq!(user_version(&conn).unwrap(), 0);
+
+        init_schema(&conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+
+        // 重复执行是空操作
+        init_schema(&conn).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn upgrades_v0_snapshot_to_latest() {
+        let conn = open();
+        conn.execute_batch(V0_SNAPSHOT).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), 0);
+
+        init_schema(&conn).unwrap();
+
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+        assert_eq!(table_columns(&conn), fresh_columns());
+
+        let name: String = conn
+            .query_row("SELECT name FROM skills WHERE id = 's1'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(name, "pdf");
+        let content: Vec<u8> = conn
+            .query_row(
+                "SELECT content FROM skill_files WHERE skill_id = 's1' AND relative_path = 'SKILL.md'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(content, b"---\nname: pdf\n---\n");
+        let deployments: i64 = conn
+            .query_row("SELECT COUNT(*) FROM skill_deployments", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(deployments, 1);
+        // 用户改过的设置不能被默认值覆盖
+        let theme: String = conn
+            .query_row("SELECT value FROM app_settings WHERE key = 'theme'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(theme, "dark");
+    }
+
+    #[test]
+    fn upgrades_snapshot_missing_watcher_columns() {
+        let conn = open();
+        conn.execute_batch(V0_SNAPSHOT).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE skills DROP COLUMN watcher_modified_at;
+             ALTER TABLE skills DROP COLUMN watcher_backup_id;
+             ALTER TABLE skills DROP COLUMN watcher_trigger_dep_id;",
+        )
+        .unwrap();
+
+        init_schema(&conn).unwrap();
+
+        assert_eq!(table_columns(&conn), fresh_columns());
+    }
+
+    fn migrate_ok(tx: &Transaction) -> rusqlite::Result<()> {
+        tx.execute_batch("CREATE TABLE t_ok (id INTEGER PRIMARY KEY);")
+    }
+
+    fn migrate_fails_halfway(tx: &Transaction) -> rusqlite::Result<()> {
+        tx.execute_batch("CREATE TABLE t_partial (id INTEGER PRIMARY KEY);")?;
+        tx.execute_batch("ALTER TABLE no_such_table ADD COLUMN x TEXT;")
+    }
+
+    #[test]
+    fn failed_migration_rolls_back_and_stops() {
+        let conn = open();
+        let migrations = [
+            Migration { version: 1, description: "ok", up: migrate_ok },
+            Migration { version: 2, description: "broken", up: migrate_fails_halfway },
+            Migration { version: 3, description: "never runs", up: migrate_ok },
+        ];
+
+        let err = run_migrations(&conn, &migrations).unwrap_err();
+
+        match err {
+            AppError::Migration { version, .. } => assert_eq!(version, 2),
+            other => panic!("unexpected error: {}", other),
+        }
+        assert_eq!(user_version(&conn).unwrap(), 1);
+        let partial: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 't_partial'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        assert_eq!(partial, 0);
+    }
+
+    #[test]
+    fn rejects_database_from_newer_app() {
+        let conn = open();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+
+        let err = init_schema(&conn).unwrap_err();
+
+        assert!(matches!(err, AppError::Migration { .. }));
+    }
+}
@@ -0,0 +1,177 @@
+-- 引入 PRAGMA user_version 之前（user_version = 0）的数据库快照，
+-- 用于测试迁移能把老库无损升级到最新版本。不要修改。
+
+-- ── 项目表 ──
+CREATE TABLE IF NOT EXISTS projects (
+    id           TEXT PRIMARY KEY,
+    name         TEXT NOT NULL,
+    path         TEXT NOT NULL UNIQUE,
+    status       TEXT NOT NULL DEFAULT 'unsynced',
+    last_scanned DATETIME,
+    created_at   DATETIME NOT NULL DEFAULT (datetime('now')),
+    updated_at   DATETIME NOT NULL DEFAULT (datetime('now'))
+);
+CREATE INDEX IF NOT EXISTS idx_projects_status ON projects(status);
+CREATE INDEX IF NOT EXISTS idx_projects_name   ON projects(name);
+
+-- ── Skill 表 ──
+CREATE TABLE IF NOT EXISTS skills (
+    id                     TEXT PRIMARY KEY,
+    name                   TEXT NOT NULL UNIQUE,
+    description            TEXT,
+    version                TEXT,
+    checksum               TEXT,
+    last_modified          DATETIME,
+    created_at             DATETIME NOT NULL DEFAULT (datetime('now')),
+    updated_at             DATETIME NOT NULL DEFAULT (datetime('now')),
+    watcher_modified_at    DATETIME,
+    watcher_backup_id      TEXT,
+    watcher_trigger_dep_id TEXT
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_skills_name ON skills(name);
+
+-- ── Skill 来源表 ──
+CREATE TABLE IF NOT EXISTS skill_sources (
+    id                TEXT PRIMARY KEY,
+    skill_id          TEXT NOT NULL UNIQUE,
+    source_type       TEXT NOT NULL,
+    url               TEXT,
+    remote_sha        TEXT,
+    skill_path        TEXT,
+    installed_version TEXT,
+    original_checksum TEXT,
+    created_at        DATETIME NOT NULL DEFAULT (datetime('now')),
+    updated_at        DATETIME NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (skill_id) REFERENCES skills(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_skill_sources_type ON skill_sources(source_type);
+
+-- ── Skill 部署表（无 CHECK 约束，支持任意工具 ID）──
+CREATE TABLE IF NOT EXISTS skill_deployments (
+    id          TEXT PRIMARY KEY,
+    skill_id    TEXT NOT NULL,
+    project_id  TEXT,
+    tool        TEXT NOT NULL,
+    path        TEXT NOT NULL UNIQUE,
+    checksum    TEXT,
+    status      TEXT NOT NULL DEFAULT 'synced',
+    last_synced DATETIME,
+    created_at  DATETIME NOT NULL DEFAULT (datetime('now')),
+    updated_at  DATETIME NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (skill_id)   REFERENCES skills(id)   ON DELETE CASCADE,
+    FOREIGN KEY (project_id) REFERENCES projects(id) ON DELETE SET NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_skill_deployments_unique
+    ON skill_deployments(skill_id, COALESCE(project_id, '__global__'), tool);
+CREATE INDEX IF NOT EXISTS idx_skill_deployments_skill   ON skill_deployments(skill_id);
+CREATE INDEX IF NOT EXISTS idx_skill_deployments_project ON skill_deployments(project_id);
+CREATE INDEX IF NOT EXISTS idx_skill_deployments_tool    ON skill_deployments(tool);
+CREATE INDEX IF NOT EXISTS idx_skill_deployments_status  ON skill_deployments(status);
+
+-- ── Skill 文件表（DB 内容存储）──
+CREATE TABLE IF NOT EXISTS skill_files (
+    id            TEXT PRIMARY KEY,
+    skill_id      TEXT NOT NULL REFERENCES skills(id) ON DELETE CASCADE,
+    relative_path TEXT NOT NULL,
+    content       BLOB NOT NULL,
+    size_bytes    INTEGER,
+    updated_at    DATETIME NOT NULL DEFAULT (datetime('now')),
+    UNIQUE(skill_id, relative_path)
+);
+CREATE INDEX IF NOT EXISTS idx_skill_files_skill_id ON skill_files(skill_id);
+
+-- ── Skill 备份表 ──
+CREATE TABLE IF NOT EXISTS skill_backups (
+    id            TEXT PRIMARY KEY,
+    skill_id      TEXT NOT NULL,
+    version_label TEXT,
+    backup_path   TEXT NOT NULL,
+    checksum      TEXT NOT NULL,
+    reason        TEXT NOT NULL,
+    metadata      TEXT,
+    created_at    DATETIME NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (skill_id) REFERENCES skills(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_skill_backups_skill   ON skill_backups(skill_id);
+CREATE INDEX IF NOT EXISTS idx_skill_backups_created ON skill_backups(created_at DESC);
+
+-- ── 同步历史表 ──
+CREATE TABLE IF NOT EXISTS sync_history (
+    id            TEXT PRIMARY KEY,
+    skill_id      TEXT NOT NULL,
+    deployment_id TEXT,
+    action        TEXT NOT NULL,
+    from_checksum TEXT,
+    to_checksum   TEXT,
+    status        TEXT NOT NULL DEFAULT 'success',
+    error_message TEXT,
+    created_at    DATETIME NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (skill_id)      REFERENCES skills(id)           ON DELETE CASCADE,
+    FOREIGN KEY (deployment_id) REFERENCES skill_deployments(id) ON DELETE SET NULL
+);
+CREATE INDEX IF NOT EXISTS idx_sync_history_skill      ON sync_history(skill_id);
+CREATE INDEX IF NOT EXISTS idx_sync_history_deployment ON sync_history(deployment_id);
+CREATE INDEX IF NOT EXISTS idx_sync_history_created    ON sync_history(created_at DESC);
+
+-- ── 变更事件表 ──
+CREATE TABLE IF NOT EXISTS change_events (
+    id            TEXT PRIMARY KEY,
+    deployment_id TEXT NOT NULL,
+    event_type    TEXT NOT NULL,
+    old_checksum  TEXT,
+    new_checksum  TEXT,
+    resolution    TEXT DEFAULT 'pending',
+    resolved_at   DATETIME,
+    created_at    DATETIME NOT NULL DEFAULT (datetime('now')),
+    FOREIGN KEY (deployment_id) REFERENCES skill_deployments(id) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_change_events_deployment ON change_events(deployment_id);
+CREATE INDEX IF NOT EXISTS idx_change_events_resolution ON change_events(resolution);
+CREATE INDEX IF NOT EXISTS idx_change_events_created    ON change_events(created_at DESC);
+
+-- ── Git 导出配置表 ──
+CREATE TABLE IF NOT EXISTS git_export_config (
+    id           TEXT PRIMARY KEY,
+    provider     TEXT NOT NULL,
+    remote_url   TEXT NOT NULL UNIQUE,
+    auth_type    TEXT NOT NULL,
+    branch       TEXT NOT NULL DEFAULT 'main',
+    auto_export  TEXT NOT NULL DEFAULT 'manual',
+    last_push_at DATETIME,
+    last_pull_at DATETIME,
+    created_at   DATETIME NOT NULL DEFAULT (datetime('now')),
+    updated_at   DATETIME NOT NULL DEFAULT (datetime('now'))
+);
+
+-- ── Catalog 安装量缓存表（TTL 7天）──
+CREATE TABLE IF NOT EXISTS catalog_installs_cache (
+    skill_name TEXT PRIMARY KEY,
+    installs   INTEGER NOT NULL,
+    fetched_at INTEGER NOT NULL
+);
+
+-- ── 应用设置表 ──
+CREATE TABLE IF NOT EXISTS app_settings (
+    key        TEXT PRIMARY KEY,
+    value      TEXT,
+    updated_at DATETIME NOT NULL DEFAULT (datetime('now'))
+);
+
+-- 写入默认设置（已存在则忽略）
+INSERT OR IGNORE INTO app_settings (key, value) VALUES
+    ('onboarding_completed', 'false'),
+    ('theme',                'system'),
+    ('language',             'zh-CN'),
+    ('startup_page',         'projects'),
+    ('notifications_enabled','true'),
+    ('file_watch_enabled',   'true'),
+    ('auto_export_frequency','manual');
+
+-- ── 示例数据 ──
+INSERT INTO projects (id, name, path) VALUES ('p1', 'demo', '/tmp/demo');
+INSERT INTO skills (id, name, description, version, checksum) VALUES ('s1', 'pdf', 'PDF 工具', '1.0.0', 'abc');
+INSERT INTO skill_sources (id, skill_id, source_type, url) VALUES ('src1', 's1', 'git', 'https://example.com/pdf.git');
+INSERT INTO skill_files (id, skill_id, relative_path, content, size_bytes) VALUES ('f1', 's1', 'SKILL.md', X'2D2D2D0A6E616D653A207064660A2D2D2D0A', 17);
+INSERT INTO skill_deployments (id, skill_id, project_id, tool, path, checksum) VALUES ('d1', 's1', 'p1', 'claude-code', '/tmp/demo/.claude/skills/pdf', 'abc');
+INSERT INTO change_events (id, deployment_id, event_type) VALUES ('e1', 'd1', 'modified');
+INSERT OR REPLACE INTO app_settings (key, value) VALUES ('theme', 'dark');
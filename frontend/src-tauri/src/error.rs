@@ -23,11 +23,23 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    #[error("Migration v{version} failed: {message}")]
+    Migration { version: u32, message: String },
+
+    #[error("Git error: {0}")]
+    Git(#[from] crate::commands::git_engine::GitError),
+
     #[allow(dead_code)]
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+impl From<git2::Error> for AppError {
+    fn from(e: git2::Error) -> Self {
+        AppError::Git(e.into())
+    }
+}
+
 impl serde::Serialize for AppError {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -1,11 +1,11 @@
-mod commands;
-mod db;
-mod error;
-mod models;
+pub mod commands;
+pub mod db;
+pub mod error;
+pub mod models;
 pub mod tools;
 
 use db::pool;
-use log::info;
+use log::{error, info};
 use tauri::Manager;
 
 
@@ -19,12 +19,29 @@ pub fn run() {
     info!("[启动] 初始化数据库连接池...");
     let db_path = pool::get_db_path();
     info!("[启动] 数据库路径: {}", db_path.display());
-    let db_pool = pool::create_pool(&db_path).expect("Failed to create database pool");
+    let db_pool = match pool::create_pool(&db_path) {
+        Ok(p) => p,
+        Err(e) => {
+            error!("[启动] 数据库初始化失败: {}", e);
+            panic!("Failed to create database pool: {}", e);
+        }
+    };
     info!("[启动] 数据库连接池创建成功");
 
+    // 上次运行中断的部署替换：清理暂存目录，必要时恢复旧目录
+    match commands::staging::cleanup_stale_staging(&db_pool) {
+        Ok(0) => {}
+        Ok(n) => info!("[启动] 已清理 {} 个残留的部署暂存目录", n),
+        Err(e) => log::warn!("[启动] 清理部署暂存目录失败: {}", e),
+    }
+    if let Ok(conn) = db_pool.get() {
+        commands::ignore_rules::load_user_patterns(&conn);
+    }
+
     info!("[启动] 构建 Tauri 应用...");
     let watcher_pool = db_pool.clone();
     let scan_pool = db_pool.clone();
+    let export_pool = db_pool.clone();
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
@@ -42,28 +59,30 @@ pub fn run() {
                 }
             }
 
-            // 启动文件系统监听
+            // 启动文件系统监听服务，项目/部署命令通过托管状态更新监听目录
             info!("[启动] 启动文件系统监听...");
-            let _watcher = commands::watcher::start_file_watcher(watcher_pool, app.handle().clone());
-            if _watcher.is_some() {
-                app.manage(_watcher);
-                info!("[启动] 文件系统监听已启动");
-            } else {
-                info!("[启动] 暂无需要监听的目录");
-            }
+            let watcher = commands::watcher::WatcherService::start(watcher_pool, app.handle().clone());
+            app.manage(watcher.clone());
 
             // 后台自动扫描全局文件系统技能，将 CLI 安装的 Skill 同步入 SQLite
             info!("[启动] 启动后台文件系统扫描...");
             tauri::async_runtime::spawn(async move {
                 match commands::scanner::scan_global_skills_internal(&scan_pool).await {
-                    Ok(r) => info!(
-                        "[启动] 自动扫描完成: {} 个 Skill 新入库, {} 个部署记录",
-                        r.skills_imported, r.deployments_created
-                    ),
+                    Ok(r) => {
+                        info!(
+                            "[启动] 自动扫描完成: {} 个 Skill 新入库, {} 个部署记录",
+                            r.skills_imported, r.deployments_created
+                        );
+                        watcher.resync(&scan_pool);
+                    }
                     Err(e) => log::warn!("[启动] 自动扫描失败: {}", e),
                 }
             });
 
+            // 按 auto_export 设置定时导出到各 git 配置
+            info!("[启动] 启动自动导出调度...");
+            commands::auto_export::start(export_pool, app.handle().clone());
+
             info!("[启动] Tauri 应用就绪");
             Ok(())
         })
@@ -84,9 +103,13 @@ pub fn run() {
             commands::skills::batch_delete_skill,
             commands::skills::get_skill_source,
             commands::skills::get_skill_backups,
+            commands::skills::get_skill_revisions,
+            commands::skills::diff_skill_revisions,
+            commands::skills::restore_skill_revision,
             commands::skills::read_skill_file,
             commands::skills::write_skill_file,
             commands::skills::list_skill_files,
+            commands::search::search_skills,
             commands::skills::check_skill_updates,
             commands::skills::dismiss_watcher_change,
             commands::skills::discard_watcher_change,
@@ -95,6 +118,8 @@ pub fn run() {
             commands::skills::compute_skill_diff,
             commands::skills::merge_skill_versions,
             commands::skills::apply_merge_result,
+            commands::skills::merge_skill_by_id,
+            commands::skills::commit_skill_merge,
             commands::skills::open_in_editor,
             // Deployments
             commands::deployments::get_deployments,
@@ -109,6 +134,10 @@ pub fn run() {
             commands::deployments::check_deployment_consistency,
             commands::deployments::get_skills_by_tool,
             commands::deployments::reconcile_all_deployments,
+            commands::watcher::list_watched_paths,
+            commands::watcher::list_watcher_conflicts,
+            commands::plan::apply_plan,
+            commands::lockfile::bootstrap_project_from_lock,
             commands::deployments::update_library_from_deployment,
             // Settings
             commands::settings::get_all_settings,
@@ -132,6 +161,8 @@ pub fn run() {
             commands::git::import_from_git_repo,
             commands::git::check_git_repo_updates,
             commands::git::scan_remote_new_skills,
+            commands::git_engine::cancel_git_operation,
+            commands::git_sync::sync_with_git,
             // Catalog (dmgrok)
             commands::catalog::fetch_catalog,
             commands::catalog::search_catalog,
@@ -139,9 +170,19 @@ pub fn run() {
             commands::catalog::enrich_batch_by_category,
             commands::catalog::install_from_catalog,
             commands::catalog::check_catalog_updates,
+            commands::catalog::apply_catalog_update,
+            commands::catalog_snapshot::get_catalog_status,
+            commands::catalog_snapshot::refresh_catalog,
             // skills.sh 直连搜索与安装
             commands::catalog::search_skills_sh,
             commands::catalog::install_from_skills_sh,
+            // Skill 注册源
+            commands::registry::list_registries,
+            commands::registry::save_registry,
+            commands::registry::delete_registry,
+            commands::registry::search_registries,
+            commands::registry::install_from_registry,
+            commands::static_registry::generate_static_registry,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -82,6 +82,12 @@ pub struct CatalogSkill {
     pub commit_sha: String,
     /// 由 skills.sh API 补充（可能为 None）
     pub installs: Option<u64>,
+    /// 条目所属的注册源（registries.id）
+    #[serde(default)]
+    pub registry_id: Option<String>,
+    /// 静态注册源条目的文件清单地址（已解析为绝对地址），GitHub 来源为 None
+    #[serde(default)]
+    pub manifest_url: Option<String>,
 }
 
 // ── skills.sh Search Results ──
@@ -127,6 +133,39 @@ pub struct InstallConflict {
     pub local_checksum: Option<String>,
 }
 
+// ── Skill Registries ──
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryConfig {
+    pub id: String,
+    pub name: String,
+    /// 'catalog' | 'skills_sh' | 'github' | 'git' | 'local'
+    pub kind: String,
+    /// catalog.json 地址、skills.sh 站点、owner/repo、git 地址或本地目录
+    pub url: String,
+    /// 各类型的附加配置（JSON），如 catalog 的 mirrors、github 的 path / ref
+    pub options: Option<String>,
+    pub enabled: bool,
+    /// 越小越靠前
+    pub priority: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// 注册源中的一个 Skill，skill_id 由注册源自行定义，安装时原样传回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistrySkill {
+    pub registry_id: String,
+    pub skill_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub source_url: Option<String>,
+    pub revision: Option<String>,
+    pub installs: Option<u64>,
+    pub tags: Vec<String>,
+}
+
 // ── Remote Update Check ──
 
 #[derive(Debug, Clone, Serialize)]
@@ -158,6 +197,8 @@ pub struct SkillDeployment {
     pub last_synced: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// copy | symlink | hardlink
+    pub mode: String,
 }
 
 // ── Skill Backups ──
@@ -174,6 +215,17 @@ pub struct SkillBackup {
     pub created_at: String,
 }
 
+// ── Skill Revision ──
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillRevision {
+    pub skill_id: String,
+    pub revision: i64,
+    pub checksum: Option<String>,
+    pub file_count: i64,
+    pub created_at: String,
+}
+
 // ── Git Export Config ──
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,6 +240,8 @@ pub struct GitExportConfig {
     pub last_pull_at: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// 是否保存了凭据；凭据本身不返回给前端
+    pub has_credential: bool,
 }
 
 // ── App Settings ──
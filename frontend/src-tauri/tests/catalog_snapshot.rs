@@ -0,0 +1,197 @@
+//! catalog 注册源的离线快照：断网时使用快照、304 只刷新拉取时间、主地址失败时改用镜像。
+//!
+//! 条件请求由测试内的一个最小 HTTP 服务应答，不依赖网络。
+
+mod common;
+
+use common::TestEnv;
+use rusqlite::params;
+use skills_manager_lib::commands::catalog_snapshot::{load_snapshot, save_snapshot, CatalogSnapshot};
+use skills_manager_lib::commands::registry::catalog_registry;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+const INDEX: &str = r#"{"skills":[
+    {"id":"pdf","name":"pdf","description":"Read PDF files","provider":"team","quality_score":80,
+     "source":{"path":"skills/pdf","skill_md_url":"skills/pdf/files/SKILL.md","commit_sha":"c1",
+               "manifest":"skills/pdf/manifest.json"}},
+    {"id":"docx","name":"docx","provider":"team","quality_score":90,
+     "source":{"path":"skills/docx","commit_sha":"c2"}}
+]}"#;
+
+struct Fixture {
+    env: TestEnv,
+}
+
+impl Deref for Fixture {
+    type Target = TestEnv;
+
+    fn deref(&self) -> &TestEnv {
+        &self.env
+    }
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Self { env: TestEnv::new("snapshot") }
+    }
+
+    /// 添加 catalog 注册源，id 每次不同，避免共用进程内缓存
+    fn add_registry(&self, url: &str, mirrors: &[&str]) -> String {
+        let id = format!("catalog-{}", Uuid::new_v4());
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "INSERT INTO registries (id, name, kind, url, options) VALUES (?1, ?1, 'catalog', ?2, ?3)",
+                params![id, url, serde_json::json!({ "mirrors": mirrors }).to_string()],
+            )
+            .unwrap();
+        id
+    }
+
+    fn write_index(&self) -> String {
+        let path = self.root.join("index.json");
+        std::fs::write(&path, INDEX).unwrap();
+        file_url(&path)
+    }
+
+    fn snapshot(&self, registry_id: &str) -> CatalogSnapshot {
+        load_snapshot(&self.pool.get().unwrap(), registry_id).unwrap().expect("应已保存快照")
+    }
+
+    /// 把快照的拉取时间往前推，模拟快照已过期
+    fn age_snapshot(&self, registry_id: &str) {
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "UPDATE catalog_snapshots SET fetched_at = datetime('now', '-2 days') WHERE registry_id = ?1",
+                params![registry_id],
+            )
+            .unwrap();
+    }
+}
+
+fn file_url(path: &Path) -> String {
+    format!("file://{}", path.display())
+}
+
+fn ids(skills: &[skills_manager_lib::models::CatalogSkill]) -> Vec<&str> {
+    skills.iter().map(|s| s.id.as_str()).collect()
+}
+
+/// 第一次请求返回 index 和 ETag，之后带着 If-None-Match 的请求返回 304。记录每次请求的头部
+async fn serve_with_etag() -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/index.json", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let seen = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = Vec::new();
+            let mut chunk = [0u8; 1024];
+            while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                match stream.read(&mut chunk).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                }
+            }
+            let request = String::from_utf8_lossy(&buf).to_lowercase();
+            let response = if request.contains("if-none-match: \"v1\"") {
+                "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n".to_string()
+            } else {
+                format!(
+                    "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    INDEX.len(),
+                    INDEX
+                )
+            };
+            seen.lock().unwrap().push(request);
+            let _ = stream.write_all(response.as_bytes()).await;
+            let _ = stream.shutdown().await;
+        }
+    });
+    (url, requests)
+}
+
+#[tokio::test]
+async fn offline_reads_are_served_from_the_snapshot() {
+    let fx = Fixture::new();
+    let url = fx.write_index();
+    let id = fx.add_registry(&url, &[]);
+    let registry = catalog_registry(&fx.pool, &id, None).unwrap();
+    assert_eq!(ids(&registry.refresh().await.unwrap()), vec!["docx", "pdf"]);
+
+    // 索引不可达：刷新失败，但浏览仍返回快照中的条目
+    std::fs::remove_file(fx.root.join("index.json")).unwrap();
+    assert!(registry.refresh().await.is_err());
+    assert_eq!(fx.snapshot(&id).skills.len(), 2);
+
+    // 新进程（内存缓存为空）直接读快照
+    let restored = format!("catalog-{}", Uuid::new_v4());
+    let mut snapshot = fx.snapshot(&id);
+    snapshot.registry_id = restored.clone();
+    fx.pool
+        .get()
+        .unwrap()
+        .execute(
+            "INSERT INTO registries (id, name, kind, url) VALUES (?1, ?1, 'catalog', ?2)",
+            params![restored, url],
+        )
+        .unwrap();
+    save_snapshot(&fx.pool.get().unwrap(), &snapshot).unwrap();
+    let offline = catalog_registry(&fx.pool, &restored, None).unwrap().load_all().await.unwrap();
+    assert_eq!(ids(&offline), vec!["docx", "pdf"]);
+    assert!(offline[1].skill_md_url.starts_with("file://"));
+}
+
+#[tokio::test]
+async fn not_modified_only_touches_the_snapshot() {
+    let fx = Fixture::new();
+    let (url, requests) = serve_with_etag().await;
+    let id = fx.add_registry(&url, &[]);
+    let registry = catalog_registry(&fx.pool, &id, None).unwrap();
+
+    assert_eq!(registry.refresh().await.unwrap().len(), 2);
+    let first = fx.snapshot(&id);
+    assert_eq!(first.etag.as_deref(), Some("\"v1\""));
+    assert_eq!(first.source_url, url);
+
+    fx.age_snapshot(&id);
+    assert!(fx.snapshot(&id).age_secs > 86400);
+    let skills = registry.refresh().await.unwrap();
+    assert_eq!(ids(&skills), vec!["docx", "pdf"]);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(!requests[0].contains("if-none-match"));
+    assert!(requests[1].contains("if-none-match: \"v1\""));
+    let touched = fx.snapshot(&id);
+    assert!(touched.age_secs < 60, "304 后应刷新拉取时间");
+    assert_eq!(touched.skills.len(), 2);
+    assert_eq!(touched.etag.as_deref(), Some("\"v1\""));
+}
+
+#[tokio::test]
+async fn mirror_is_used_when_the_primary_fails() {
+    let fx = Fixture::new();
+    let mirror = fx.write_index();
+    let missing = file_url(&fx.root.join("missing").join("index.json"));
+    let id = fx.add_registry(&missing, &[&mirror]);
+
+    let skills = catalog_registry(&fx.pool, &id, None).unwrap().refresh().await.unwrap();
+    assert_eq!(ids(&skills), vec!["docx", "pdf"]);
+    // 静态条目的地址相对命中的镜像解析
+    let pdf = skills.iter().find(|s| s.id == "pdf").unwrap();
+    assert_eq!(pdf.skill_md_url, file_url(&fx.root.join("skills/pdf/files/SKILL.md")));
+    assert_eq!(
+        pdf.manifest_url.as_deref(),
+        Some(file_url(&fx.root.join("skills/pdf/manifest.json")).as_str())
+    );
+    assert_eq!(fx.snapshot(&id).source_url, mirror);
+}
@@ -0,0 +1,110 @@
+//! 集成测试共用的脚手架：临时目录里的库和数据库，以及指向临时目录的 HOME。
+//!
+//! 每个测试文件只在自己的 `Fixture` 里保留场景相关的准备步骤。
+
+#![allow(dead_code)]
+
+use rusqlite::params;
+use skills_manager_lib::commands::deployments::deploy_skill_to_project_internal;
+use skills_manager_lib::commands::skill_files::{db_read_file, db_write_file, refresh_skill_checksum};
+use skills_manager_lib::db::{pool::create_pool, DbPool};
+use std::path::{Path, PathBuf};
+use std::sync::Once;
+use uuid::Uuid;
+
+static HOME: Once = Once::new();
+
+/// 临时目录 `skm-<prefix>-<uuid>` 下的数据库和库目录，drop 时整个删除
+pub struct TestEnv {
+    pub root: PathBuf,
+    pub pool: DbPool,
+}
+
+impl TestEnv {
+    /// 冲突快照、备份、git 工作副本都写在 `~/.skills-manager`，测试进程把 HOME 指向临时目录
+    pub fn new(prefix: &str) -> Self {
+        HOME.call_once(|| {
+            std::env::set_var("HOME", std::env::temp_dir().join(format!("skm-test-home-{}", Uuid::new_v4())))
+        });
+        let root = std::env::temp_dir().join(format!("skm-{}-{}", prefix, Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let pool = create_pool(&root.join("db").join("skills.db")).unwrap();
+        pool.get()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO app_settings (key, value) VALUES ('skills_lib_path', ?1)",
+                params![root.join("lib").to_string_lossy()],
+            )
+            .unwrap();
+        Self { root, pool }
+    }
+
+    /// 在库中新建 Skill 并写入文件，返回 Skill ID
+    pub fn add_skill(&self, name: &str, files: &[(&str, &[u8])]) -> String {
+        let conn = self.pool.get().unwrap();
+        let id = Uuid::new_v4().to_string();
+        conn.execute("INSERT INTO skills (id, name) VALUES (?1, ?2)", params![id, name])
+            .unwrap();
+        for (rel, content) in files {
+            db_write_file(&conn, &id, rel, content).unwrap();
+        }
+        refresh_skill_checksum(&conn, &id).unwrap();
+        id
+    }
+
+    /// 登记位于 root/<name> 的项目，返回 (项目 ID, 项目目录)
+    pub fn add_project(&self, name: &str) -> (String, PathBuf) {
+        let dir = self.root.join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let id = Uuid::new_v4().to_string();
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "INSERT INTO projects (id, name, path) VALUES (?1, ?2, ?3)",
+                params![id, name, dir.to_string_lossy()],
+            )
+            .unwrap();
+        (id, dir)
+    }
+
+    /// 部署到 claude-code，返回 (部署 ID, 部署目录)
+    pub async fn deploy(&self, skill_id: &str, project_id: &str, copy: Option<bool>) -> (String, PathBuf) {
+        let deploy = deploy_skill_to_project_internal(
+            &self.pool,
+            skill_id.to_string(),
+            project_id.to_string(),
+            "claude-code".into(),
+            copy,
+            None,
+        )
+        .await
+        .unwrap();
+        (deploy.deployment_id, PathBuf::from(deploy.deploy_path))
+    }
+
+    pub fn skill_id(&self, name: &str) -> String {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row("SELECT id FROM skills WHERE name = ?1", params![name], |r| r.get(0))
+            .unwrap()
+    }
+
+    /// 库中某个文件的内容，文件不存在时为 None
+    pub fn library_file(&self, skill_id: &str, rel: &str) -> Option<Vec<u8>> {
+        db_read_file(&self.pool.get().unwrap(), skill_id, rel).ok()
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
+
+pub fn write(dir: &Path, rel: &str, content: impl AsRef<[u8]>) {
+    let path = dir.join(rel);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
@@ -0,0 +1,94 @@
+//! 修改设置项 `ignore_patterns` 后，已存的 Skill / 版本 / 部署 checksum 按新规则重算。
+//!
+//! 自定义规则是进程级状态，这个文件只放一个测试，避免与其他测试互相影响。
+
+mod common;
+
+use common::TestEnv;
+use rusqlite::params;
+use skills_manager_lib::commands::plan::plan_redeploy;
+use skills_manager_lib::commands::settings::set_setting_internal;
+use skills_manager_lib::commands::skill_files::db_find_revision_by_checksum;
+use skills_manager_lib::commands::utils::compute_dir_checksum;
+use std::ops::Deref;
+use std::path::PathBuf;
+
+struct Fixture {
+    env: TestEnv,
+    skill_id: String,
+}
+
+impl Deref for Fixture {
+    type Target = TestEnv;
+
+    fn deref(&self) -> &TestEnv {
+        &self.env
+    }
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let env = TestEnv::new("ignore");
+        let skill_id = env.add_skill("logs", &[("SKILL.md", b"logs\n"), ("app.log", b"trace\n")]);
+        Self { env, skill_id }
+    }
+
+    async fn deploy(&self, project: &str) -> (String, PathBuf) {
+        let (project_id, _) = self.add_project(project);
+        self.env.deploy(&self.skill_id, &project_id, None).await
+    }
+
+    fn skill_checksum(&self) -> Option<String> {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row("SELECT checksum FROM skills WHERE id = ?1", params![self.skill_id], |r| r.get(0))
+            .unwrap()
+    }
+
+    fn deployment_checksum(&self, deployment_id: &str) -> Option<String> {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT checksum FROM skill_deployments WHERE id = ?1",
+                params![deployment_id],
+                |r| r.get(0),
+            )
+            .unwrap()
+    }
+
+    fn redeploy_action(&self, deployment_id: &str) -> String {
+        plan_redeploy(&self.pool.get().unwrap(), deployment_id).unwrap().action
+    }
+}
+
+#[tokio::test]
+async fn changing_ignore_patterns_recomputes_stored_checksums() {
+    let fx = Fixture::new();
+    let (clean, clean_dir) = fx.deploy("clean").await;
+    let (edited, edited_dir) = fx.deploy("edited").await;
+    std::fs::write(edited_dir.join("SKILL.md"), "local edit\n").unwrap();
+    let before = fx.skill_checksum();
+    assert_eq!(fx.deployment_checksum(&clean), before);
+
+    set_setting_internal(&fx.pool, "ignore_patterns", r#"["*.log"]"#).unwrap();
+    let after = fx.skill_checksum();
+    assert_ne!(after, before);
+    assert_eq!(after, compute_dir_checksum(&clean_dir), "app.log 不再计入 checksum");
+
+    // 未改过的部署仍然一致，改过的部署仍然算本地修改
+    assert_eq!(fx.deployment_checksum(&clean), after);
+    assert_eq!(fx.redeploy_action(&clean), "unchanged");
+    assert_eq!(fx.redeploy_action(&edited), "conflict");
+    // 版本的 checksum 也换成新值，合并时仍能按部署的 checksum 找到 base
+    let conn = fx.pool.get().unwrap();
+    assert!(db_find_revision_by_checksum(&conn, &fx.skill_id, after.as_deref().unwrap())
+        .unwrap()
+        .is_some());
+    drop(conn);
+
+    set_setting_internal(&fx.pool, "ignore_patterns", "[]").unwrap();
+    assert_eq!(fx.skill_checksum(), before);
+    assert_eq!(fx.deployment_checksum(&clean), before);
+}
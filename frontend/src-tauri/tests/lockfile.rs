@@ -0,0 +1,122 @@
+//! `bootstrap_project_from_lock` 按 skills.lock 记录的版本安装缺失的 Skill。
+//!
+//! git 来源使用临时目录里的 bare 仓库，注册源来源使用本地目录注册源，不依赖网络。
+
+mod common;
+
+use common::{write, TestEnv};
+use git2::Repository;
+use rusqlite::params;
+use skills_manager_lib::commands::git_engine::{self, GitOperation};
+use skills_manager_lib::commands::lockfile::{bootstrap_project_from_lock_internal, LOCK_FILE_NAME};
+use skills_manager_lib::commands::utils::compute_dir_checksum;
+use std::ops::Deref;
+use std::path::PathBuf;
+
+struct Fixture {
+    env: TestEnv,
+    project: PathBuf,
+}
+
+impl Deref for Fixture {
+    type Target = TestEnv;
+
+    fn deref(&self) -> &TestEnv {
+        &self.env
+    }
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let env = TestEnv::new("lock");
+        let project = env.root.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        Self { env, project }
+    }
+
+    fn write_lock(&self, source: serde_json::Value) {
+        let lock = serde_json::json!({
+            "version": 1,
+            "skills": [{ "name": "pdf", "checksum": null, "source": source, "tools": ["claude-code"] }],
+        });
+        std::fs::write(self.project.join(LOCK_FILE_NAME), lock.to_string()).unwrap();
+    }
+
+    async fn bootstrap(&self) -> String {
+        let result = bootstrap_project_from_lock_internal(&self.pool, self.project.to_string_lossy().to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(result.skills.len(), 1);
+        let skill = &result.skills[0];
+        assert_eq!(skill.status, "installed", "{:?}", skill.message);
+        assert_eq!(skill.deployed_tools, vec!["claude-code"]);
+        String::from_utf8(std::fs::read(self.project.join(".claude/skills/pdf/SKILL.md")).unwrap()).unwrap()
+    }
+
+    fn library_pdf(&self) -> Vec<u8> {
+        self.library_file(&self.skill_id("pdf"), "SKILL.md").unwrap()
+    }
+}
+
+#[tokio::test]
+async fn git_source_installs_the_locked_commit_and_subdirectory() {
+    let fx = Fixture::new();
+    let remote = fx.root.join("remote.git");
+    Repository::init_bare(&remote).unwrap().set_head("refs/heads/main").unwrap();
+    let url = remote.to_string_lossy().to_string();
+
+    // 锁定第一个提交；之后远程 HEAD 上的内容已经变了
+    let op = GitOperation::new("test");
+    let work = git_engine::init(&fx.root.join("work"), "main", &url).unwrap();
+    let dir = work.workdir().unwrap().to_path_buf();
+    write(&dir, "tools/pdf/SKILL.md", "pdf v1\n");
+    write(&dir, "skills/pdf/SKILL.md", "wrong dir\n");
+    let locked = git_engine::commit_all(&work, "v1").unwrap().unwrap();
+    write(&dir, "tools/pdf/SKILL.md", "pdf v2\n");
+    git_engine::commit_all(&work, "v2").unwrap().unwrap();
+    git_engine::push(&work, "main", &op).unwrap();
+
+    fx.write_lock(serde_json::json!({
+        "type": "git",
+        "url": url,
+        "remote_sha": locked.to_string(),
+        "skill_path": "tools/pdf",
+    }));
+    assert_eq!(fx.bootstrap().await, "pdf v1\n");
+    assert_eq!(fx.library_pdf(), b"pdf v1\n");
+
+    // 重新写 lock 时来源保持不变
+    let lock: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fx.project.join(LOCK_FILE_NAME)).unwrap()).unwrap();
+    let source = &lock["skills"][0]["source"];
+    assert_eq!(source["remote_sha"], locked.to_string());
+    assert_eq!(source["skill_path"], "tools/pdf");
+}
+
+#[tokio::test]
+async fn registry_source_installs_from_the_recorded_registry() {
+    let fx = Fixture::new();
+    let shared = fx.root.join("shared");
+    write(&shared, "pdf/SKILL.md", "---\nname: pdf\n---\nshared\n");
+    fx.pool
+        .get()
+        .unwrap()
+        .execute(
+            "INSERT INTO registries (id, name, kind, url) VALUES ('team', 'team', 'local', ?1)",
+            params![shared.to_string_lossy()],
+        )
+        .unwrap();
+
+    fx.write_lock(serde_json::json!({
+        "type": "registry",
+        "url": null,
+        "remote_sha": compute_dir_checksum(&shared.join("pdf")).unwrap(),
+        "skill_path": "pdf",
+        "registry_id": "team",
+    }));
+    assert_eq!(fx.bootstrap().await, "---\nname: pdf\n---\nshared\n");
+
+    let lock: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(fx.project.join(LOCK_FILE_NAME)).unwrap()).unwrap();
+    assert_eq!(lock["skills"][0]["source"]["registry_id"], "team");
+}
@@ -0,0 +1,195 @@
+//! 本地目录与任意 git 地址注册源：搜索、文件列表、按版本安装。
+//!
+//! git 注册源使用临时目录里的 bare 仓库，镜像写在 `~/.skills-manager/registries`，
+//! 测试进程把 HOME 指向临时目录。
+
+mod common;
+
+use common::{write, TestEnv};
+use git2::Repository;
+use rusqlite::params;
+use skills_manager_lib::commands::catalog::apply_catalog_update_internal;
+use skills_manager_lib::commands::git_engine::{self, GitOperation};
+use skills_manager_lib::commands::registry::{
+    get_registry_config, install_from_registry_internal, open_registry, SkillRegistry,
+};
+use skills_manager_lib::commands::utils::compute_dir_checksum;
+use std::ops::Deref;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const PDF_MD: &str = "---\nname: pdf\ndescription: Read PDF files\n---\npdf\n";
+const DOCX_MD: &str = "---\nname: docx\ndescription: Word documents\n---\ndocx\n";
+
+struct Fixture {
+    env: TestEnv,
+}
+
+impl Deref for Fixture {
+    type Target = TestEnv;
+
+    fn deref(&self) -> &TestEnv {
+        &self.env
+    }
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Self { env: TestEnv::new("registry") }
+    }
+
+    /// 添加注册源，id 每次不同，避免与其他测试共用 git 镜像和 fetch 缓存
+    fn add_registry(&self, kind: &str, url: &str) -> (String, Box<dyn SkillRegistry>) {
+        let id = format!("{}-{}", kind, Uuid::new_v4());
+        let conn = self.pool.get().unwrap();
+        conn.execute(
+            "INSERT INTO registries (id, name, kind, url) VALUES (?1, ?1, ?2, ?3)",
+            params![id, kind, url],
+        )
+        .unwrap();
+        let config = get_registry_config(&conn, &id).unwrap();
+        let registry = open_registry(&self.pool, &config, None).unwrap();
+        (id, registry)
+    }
+
+    async fn install(&self, registry_id: &str, skill_id: &str) -> usize {
+        let result = install_from_registry_internal(
+            &self.pool,
+            registry_id.to_string(),
+            skill_id.to_string(),
+            None,
+            Vec::new(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(result.conflict.is_none());
+        result.files_downloaded
+    }
+
+    /// 部署到一个新项目，返回 (部署 ID, 部署目录)
+    async fn deploy(&self, name: &str, project: &str) -> (String, PathBuf) {
+        let (project_id, _) = self.add_project(project);
+        self.env.deploy(&self.skill_id(name), &project_id, None).await
+    }
+
+    fn library(&self, name: &str, rel: &str) -> Vec<u8> {
+        self.library_file(&self.skill_id(name), rel).unwrap()
+    }
+}
+
+fn names(skills: &[skills_manager_lib::models::RegistrySkill]) -> Vec<&str> {
+    skills.iter().map(|s| s.skill_id.as_str()).collect()
+}
+
+#[tokio::test]
+async fn local_registry_searches_lists_and_installs() {
+    let fx = Fixture::new();
+    let shared = fx.root.join("shared");
+    write(&shared, "pdf/SKILL.md", PDF_MD);
+    write(&shared, "pdf/scripts/extract.py", "print()\n");
+    write(&shared, "office/docx/SKILL.md", DOCX_MD);
+    write(&shared, "notes/README.md", "not a skill\n");
+    let (id, registry) = fx.add_registry("local", &shared.to_string_lossy());
+
+    assert_eq!(names(&registry.search("", 10).await.unwrap()), vec!["office/docx", "pdf"]);
+    let found = registry.search("word", 10).await.unwrap();
+    assert_eq!(names(&found), vec!["office/docx"]);
+    assert_eq!(found[0].name, "docx");
+
+    let revision = registry.latest_revision("pdf").await.unwrap();
+    assert_eq!(Some(&revision), compute_dir_checksum(&shared.join("pdf")).as_ref());
+    let mut files = registry.list_files("pdf", &revision).await.unwrap();
+    files.sort();
+    assert_eq!(files, vec!["SKILL.md", "scripts/extract.py"]);
+    assert!(registry.metadata("notes").await.is_err());
+
+    // 目录内容变化后旧版本不再可用
+    write(&shared, "pdf/scripts/extract.py", "print(1)\n");
+    assert!(registry.list_files("pdf", &revision).await.is_err());
+
+    assert_eq!(fx.install(&id, "pdf").await, 2);
+    assert_eq!(fx.library("pdf", "scripts/extract.py"), b"print(1)\n");
+    let (source_type, registry_id): (String, Option<String>) = fx
+        .pool
+        .get()
+        .unwrap()
+        .query_row(
+            "SELECT ss.source_type, ss.registry_id FROM skill_sources ss JOIN skills s ON s.id = ss.skill_id
+             WHERE s.name = 'pdf'",
+            [],
+            |r| Ok((r.get(0)?, r.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!(source_type, "registry");
+    assert_eq!(registry_id.as_deref(), Some(id.as_str()));
+}
+
+#[tokio::test]
+async fn git_registry_searches_lists_and_installs_from_mirror() {
+    let fx = Fixture::new();
+    let remote = fx.root.join("remote.git");
+    Repository::init_bare(&remote).unwrap().set_head("refs/heads/main").unwrap();
+    let url = remote.to_string_lossy().to_string();
+
+    let work = git_engine::init(&fx.root.join("work"), "main", &url).unwrap();
+    let dir = work.workdir().unwrap().to_path_buf();
+    write(&dir, "skills/pdf/SKILL.md", PDF_MD);
+    write(&dir, "skills/pdf/reference.md", "v1\n");
+    write(&dir, "skills/docx/SKILL.md", DOCX_MD);
+    write(&dir, "README.md", "# skills\n");
+    git_engine::commit_all(&work, "add skills").unwrap().unwrap();
+    write(&dir, "skills/pdf/reference.md", "v2\n");
+    let pdf_commit = git_engine::commit_all(&work, "update pdf").unwrap().unwrap();
+    write(&dir, "skills/docx/SKILL.md", DOCX_MD.replace("docx\n", "docx v2\n"));
+    git_engine::commit_all(&work, "update docx").unwrap().unwrap();
+    git_engine::push(&work, "main", &GitOperation::new("test")).unwrap();
+
+    let (id, registry) = fx.add_registry("git", &url);
+    assert_eq!(names(&registry.search("", 10).await.unwrap()), vec!["skills/docx", "skills/pdf"]);
+    assert_eq!(names(&registry.search("pdf", 10).await.unwrap()), vec!["skills/pdf"]);
+    assert!(dirs::home_dir().unwrap().join(".skills-manager/registries").join(&id).join("HEAD").exists());
+
+    // 版本是最后一次改动该目录的提交
+    let revision = registry.latest_revision("skills/pdf").await.unwrap();
+    assert_eq!(revision, pdf_commit.to_string());
+    let mut files = registry.list_files("skills/pdf", &revision).await.unwrap();
+    files.sort();
+    assert_eq!(files, vec!["SKILL.md", "reference.md"]);
+    assert_eq!(registry.fetch_file("skills/pdf", &revision, "reference.md").await.unwrap(), b"v2\n");
+
+    assert_eq!(fx.install(&id, "skills/pdf").await, 2);
+    assert_eq!(fx.library("pdf", "reference.md"), b"v2\n");
+    assert_eq!(fx.library("pdf", "SKILL.md"), PDF_MD.as_bytes());
+}
+
+#[tokio::test]
+async fn catalog_update_skips_locally_modified_deployments() {
+    let fx = Fixture::new();
+    let shared = fx.root.join("shared");
+    write(&shared, "pdf/SKILL.md", PDF_MD);
+    let (id, _) = fx.add_registry("local", &shared.to_string_lossy());
+    fx.install(&id, "pdf").await;
+    let (edited, edited_dir) = fx.deploy("pdf", "edited").await;
+    let (_, clean_dir) = fx.deploy("pdf", "clean").await;
+    write(&edited_dir, "SKILL.md", "local edit\n");
+
+    let v2 = PDF_MD.replace("pdf\n", "pdf v2\n");
+    write(&shared, "pdf/SKILL.md", &v2);
+    let skill_id: String = fx
+        .pool
+        .get()
+        .unwrap()
+        .query_row("SELECT id FROM skills WHERE name = 'pdf'", [], |r| r.get(0))
+        .unwrap();
+    let result = apply_catalog_update_internal(&fx.pool, skill_id, None, None, None).await.unwrap();
+    assert_eq!(result.status, "updated");
+    assert_eq!(fx.library("pdf", "SKILL.md"), v2.as_bytes());
+
+    // 本地改过的部署保留修改，未改过的部署换成新版本
+    assert_eq!(result.deployments_synced, 1);
+    assert_eq!(result.deployments_skipped, vec![edited]);
+    assert_eq!(std::fs::read(edited_dir.join("SKILL.md")).unwrap(), b"local edit\n");
+    assert_eq!(std::fs::read(clean_dir.join("SKILL.md")).unwrap(), v2.as_bytes());
+}
@@ -0,0 +1,148 @@
+//! 静态注册源：从库生成，再通过 `file://` 的 index.json 作为 catalog 注册源读取并安装。
+
+mod common;
+
+use common::TestEnv;
+use rusqlite::params;
+use sha2::{Digest, Sha256};
+use skills_manager_lib::commands::registry::{catalog_registry, install_from_registry_internal};
+use skills_manager_lib::commands::static_registry::{
+    generate_static_registry_internal, resolve_url, verify_file, StaticManifestFile, INDEX_FILE,
+};
+use skills_manager_lib::error::AppError;
+use std::ops::Deref;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+const SKILL_MD: &[u8] = b"---\nname: pdf\ndescription: Read PDF files\n---\npdf\n";
+const SCRIPT: &[u8] = b"print('pdf')\n";
+
+struct Fixture {
+    env: TestEnv,
+}
+
+impl Deref for Fixture {
+    type Target = TestEnv;
+
+    fn deref(&self) -> &TestEnv {
+        &self.env
+    }
+}
+
+impl Fixture {
+    fn new() -> Self {
+        Self { env: TestEnv::new("static") }
+    }
+
+    /// 库中有一个带脚本的 pdf，发布到 root/out
+    fn published() -> (Self, PathBuf) {
+        let fx = Self::new();
+        let id = fx.add_skill("pdf", &[("SKILL.md", SKILL_MD), ("scripts/extract.py", SCRIPT)]);
+        fx.pool
+            .get()
+            .unwrap()
+            .execute("UPDATE skills SET description = 'Read PDF files' WHERE id = ?1", params![id])
+            .unwrap();
+        let out = fx.root.join("out");
+        let result = generate_static_registry_internal(&fx.pool, out.to_string_lossy().to_string(), None, None).unwrap();
+        assert_eq!((result.skills_exported, result.files_exported), (1, 2));
+        (fx, out)
+    }
+
+    /// 以 file:// 地址添加 catalog 注册源
+    fn add_static_registry(&self, out: &std::path::Path) -> String {
+        let id = format!("static-{}", Uuid::new_v4());
+        self.pool
+            .get()
+            .unwrap()
+            .execute(
+                "INSERT INTO registries (id, name, kind, url) VALUES (?1, ?1, 'catalog', ?2)",
+                params![id, format!("file://{}", out.join(INDEX_FILE).display())],
+            )
+            .unwrap();
+        id
+    }
+
+    async fn install(&self, registry_id: &str) -> Result<usize, AppError> {
+        install_from_registry_internal(&self.pool, registry_id.to_string(), "pdf".into(), None, Vec::new(), None, None)
+            .await
+            .map(|r| r.files_downloaded)
+    }
+
+    fn library(&self, rel: &str) -> Vec<u8> {
+        self.library_file(&self.skill_id("pdf"), rel).unwrap()
+    }
+}
+
+fn file_path(url: &str) -> PathBuf {
+    reqwest::Url::parse(url).unwrap().to_file_path().unwrap()
+}
+
+#[tokio::test]
+async fn generated_registry_installs_over_file_url() {
+    let (_publisher, out) = Fixture::published();
+    let fx = Fixture::new();
+    let registry_id = fx.add_static_registry(&out);
+
+    let skills = catalog_registry(&fx.pool, &registry_id, None).unwrap().refresh().await.unwrap();
+    assert_eq!(skills.len(), 1);
+    let pdf = &skills[0];
+    assert_eq!(pdf.registry_id.as_deref(), Some(registry_id.as_str()));
+    // 相对地址按 index.json 解析
+    assert_eq!(std::fs::read(file_path(&pdf.skill_md_url)).unwrap(), SKILL_MD);
+    assert!(file_path(pdf.manifest_url.as_deref().unwrap()).is_file());
+
+    assert_eq!(fx.install(&registry_id).await.unwrap(), 2);
+    assert_eq!(fx.library("SKILL.md"), SKILL_MD);
+    assert_eq!(fx.library("scripts/extract.py"), SCRIPT);
+}
+
+#[tokio::test]
+async fn tampered_file_is_rejected_on_install() {
+    let (_publisher, out) = Fixture::published();
+    std::fs::write(out.join("skills/pdf/files/scripts/extract.py"), b"print('evil')\n").unwrap();
+
+    let fx = Fixture::new();
+    let registry_id = fx.add_static_registry(&out);
+    let err = fx.install(&registry_id).await.unwrap_err();
+    assert!(err.to_string().contains("SHA-256"), "{}", err);
+    let installed: i64 = fx
+        .pool
+        .get()
+        .unwrap()
+        .query_row("SELECT COUNT(*) FROM skills", [], |r| r.get(0))
+        .unwrap();
+    assert_eq!(installed, 0);
+}
+
+#[test]
+fn verify_file_compares_sha256() {
+    let file = StaticManifestFile {
+        path: "SKILL.md".into(),
+        size: SKILL_MD.len() as u64,
+        sha256: hex::encode(Sha256::digest(SKILL_MD)).to_uppercase(),
+    };
+    assert!(verify_file(&file, SKILL_MD).is_ok());
+    let err = verify_file(&file, b"changed").unwrap_err();
+    assert!(matches!(err, AppError::Validation(_)));
+    assert!(err.to_string().contains("SKILL.md"));
+}
+
+#[test]
+fn resolved_urls_stay_under_the_index_directory() {
+    let base = "https://example.com/registry/index.json";
+    assert_eq!(
+        resolve_url(base, "skills/pdf/manifest.json").unwrap(),
+        "https://example.com/registry/skills/pdf/manifest.json"
+    );
+    assert_eq!(
+        resolve_url("file:///srv/registry/index.json", "skills/a.md").unwrap(),
+        "file:///srv/registry/skills/a.md"
+    );
+
+    for rel in ["file:/etc/passwd", "https://evil.example/x", "c:/x", "a:b/x", "../x", "/etc/passwd", "%2e%2e/x", "skills/%2e%2e/%2e%2e/x"] {
+        let err = resolve_url(base, rel).expect_err(rel);
+        assert!(matches!(err, AppError::Validation(_)), "{}: {}", rel, err);
+    }
+    assert!(resolve_url("/srv/registry/index.json", "file:/etc/passwd").is_err());
+}
@@ -0,0 +1,292 @@
+//! 库和部署在上次同步后都被修改：watcher 记录冲突而不是覆盖库，之后按 ID 合并并提交。
+//!
+//! 冲突快照和合并前的备份写在 `~/.skills-manager`，测试进程把 HOME 指向临时目录。
+
+mod common;
+
+use common::TestEnv;
+use notify::event::ModifyKind;
+use notify::{Event, EventKind};
+use rusqlite::params;
+use skills_manager_lib::commands::skill_files::{db_write_file, refresh_skill_checksum};
+use skills_manager_lib::commands::skills::{
+    commit_skill_merge_internal, merge_skill_by_id_internal, MergeResolution,
+};
+use skills_manager_lib::commands::watcher::{process_batch, EventBatch};
+use skills_manager_lib::error::AppError;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+const SKILL_MD: &str = "SKILL.md";
+const LOGO_V1: &[u8] = &[0x89, 0xff, 0x00, 1];
+const LOGO_V2: &[u8] = &[0x89, 0xff, 0x00, 2];
+
+struct Fixture {
+    env: TestEnv,
+    skill_id: String,
+    deployment_id: String,
+    deploy_dir: PathBuf,
+}
+
+impl Deref for Fixture {
+    type Target = TestEnv;
+
+    fn deref(&self) -> &TestEnv {
+        &self.env
+    }
+}
+
+impl Fixture {
+    /// 部署一个只有 SKILL.md 的 Skill
+    async fn deployed() -> Self {
+        Self::deployed_with(&[("SKILL.md", b"base\n")]).await
+    }
+
+    async fn deployed_with(files: &[(&str, &[u8])]) -> Self {
+        let env = TestEnv::new("conflict");
+        let (project_id, _) = env.add_project("project");
+        let skill_id = env.add_skill("conflict-demo", files);
+        let (deployment_id, deploy_dir) = env.deploy(&skill_id, &project_id, Some(true)).await;
+        Self {
+            env,
+            skill_id,
+            deployment_id,
+            deploy_dir,
+        }
+    }
+
+    fn edit_library(&self, content: &[u8]) {
+        let conn = self.pool.get().unwrap();
+        db_write_file(&conn, &self.skill_id, "SKILL.md", content).unwrap();
+        refresh_skill_checksum(&conn, &self.skill_id).unwrap();
+    }
+
+    /// 改写部署中的 SKILL.md 并把这次修改交给 watcher
+    fn edit_deployment(&self, content: &[u8]) -> serde_json::Value {
+        let path = self.deploy_dir.join("SKILL.md");
+        std::fs::write(&path, content).unwrap();
+        let mut batch = EventBatch::default();
+        batch.push(&Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path));
+        process_batch(batch, &self.pool).expect("部署修改应产生 skill-change")
+    }
+
+    fn library_md(&self) -> Vec<u8> {
+        self.library(SKILL_MD).unwrap()
+    }
+
+    fn library(&self, rel: &str) -> Option<Vec<u8>> {
+        self.library_file(&self.skill_id, rel)
+    }
+
+    fn resolve(&self, resolutions: Vec<MergeResolution>, deployment: bool) -> usize {
+        commit_skill_merge_internal(
+            &self.pool,
+            self.skill_id.clone(),
+            resolutions,
+            deployment.then(|| self.deployment_id.clone()),
+            None,
+            None,
+            None,
+        )
+        .unwrap()
+        .files_written
+    }
+
+    /// (resolution, merge_base_path, merge_left_path, merge_right_path)
+    fn event(&self, event_id: &str) -> (String, Option<String>, String, String) {
+        self.pool
+            .get()
+            .unwrap()
+            .query_row(
+                "SELECT resolution, merge_base_path, merge_left_path, merge_right_path
+                 FROM change_events WHERE id = ?1",
+                params![event_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap()
+    }
+}
+
+fn read(dir: &str) -> Vec<u8> {
+    std::fs::read(Path::new(dir).join("SKILL.md")).unwrap()
+}
+
+#[tokio::test]
+async fn concurrent_edits_are_recorded_as_conflict_and_cleaned_up_on_merge() {
+    let fx = Fixture::deployed().await;
+    fx.edit_library(b"library\n");
+
+    let payload = fx.edit_deployment(b"deployment\n");
+    let event_id = payload["conflict_event_ids"][0].as_str().unwrap().to_string();
+    assert_eq!(payload["files"][0]["resolution"], "conflict");
+    assert_eq!(fx.library_md(), b"library\n", "冲突时不回写库");
+
+    let (resolution, base, left, right) = fx.event(&event_id);
+    assert_eq!(resolution, "conflict");
+    assert_eq!(read(&base.unwrap()), b"base\n");
+    assert_eq!(read(&left), b"library\n");
+    assert_eq!(read(&right), b"deployment\n");
+    let status: String = fx
+        .pool
+        .get()
+        .unwrap()
+        .query_row(
+            "SELECT status FROM skill_deployments WHERE id = ?1",
+            params![fx.deployment_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(status, "diverged");
+
+    // 未处理前再次修改部署：沿用同一个冲突，只刷新部署一侧的快照
+    let again = fx.edit_deployment(b"deployment v2\n");
+    assert_eq!(again["conflict_event_ids"][0], event_id.as_str());
+    assert_eq!(read(&fx.event(&event_id).3), b"deployment v2\n");
+    let events: i64 = fx
+        .pool
+        .get()
+        .unwrap()
+        .query_row("SELECT COUNT(*) FROM change_events", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(events, 1);
+    assert_eq!(fx.library_md(), b"library\n");
+
+    // 提交合并后冲突关闭，快照目录删除
+    let snapshot_root = Path::new(&left).parent().unwrap().to_path_buf();
+    assert!(snapshot_root.is_dir());
+    let result = commit_skill_merge_internal(
+        &fx.pool,
+        fx.skill_id.clone(),
+        vec![merged(SKILL_MD, Some("merged\n"))],
+        Some(fx.deployment_id.clone()),
+        None,
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(result.conflicts_resolved, 1);
+    assert_eq!(fx.event(&event_id).0, "merged");
+    assert_eq!(fx.library_md(), b"merged\n");
+    assert!(!snapshot_root.exists());
+}
+
+#[tokio::test]
+async fn commit_with_an_unresolved_conflict_is_rejected() {
+    let fx = Fixture::deployed_with(&[(SKILL_MD, b"base\n"), ("notes.md", b"notes\n")]).await;
+    fx.edit_library(b"library\n");
+    let payload = fx.edit_deployment(b"deployment\n");
+    let event_id = payload["conflict_event_ids"][0].as_str().unwrap().to_string();
+
+    // 只给出了未冲突文件的结果，SKILL.md 的冲突没有处理
+    let err = commit_skill_merge_internal(
+        &fx.pool,
+        fx.skill_id.clone(),
+        vec![merged("notes.md", Some("notes v2\n"))],
+        Some(fx.deployment_id.clone()),
+        None,
+        None,
+        None,
+    )
+    .err()
+    .expect("冲突未处理时应拒绝提交");
+    assert!(matches!(err, AppError::Validation(_)));
+    assert!(err.to_string().contains(SKILL_MD), "{}", err);
+    assert_eq!(fx.event(&event_id).0, "conflict", "冲突应保持未处理");
+    assert_eq!(fx.library_md(), b"library\n");
+    assert_eq!(fx.library("notes.md").unwrap(), b"notes\n");
+
+    // 提交沿用预览时选的 base：以库的当前版本为 base 时部署一侧的修改直接胜出，没有冲突
+    let latest: i64 = fx
+        .pool
+        .get()
+        .unwrap()
+        .query_row(
+            "SELECT MAX(revision) FROM skill_revisions WHERE skill_id = ?1",
+            params![fx.skill_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    let preview =
+        merge_skill_by_id_internal(&fx.pool, fx.skill_id.clone(), fx.deployment_id.clone(), Some(latest), None)
+            .unwrap();
+    assert_eq!(preview.conflict_count, 0);
+    let result = commit_skill_merge_internal(
+        &fx.pool,
+        fx.skill_id.clone(),
+        Vec::new(),
+        Some(fx.deployment_id.clone()),
+        Some(latest),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(result.conflicts_resolved, 1);
+    assert_eq!(fx.library_md(), b"deployment\n");
+}
+
+fn merged(path: &str, content: Option<&str>) -> MergeResolution {
+    MergeResolution { path: path.into(), content: content.map(String::from) }
+}
+
+/// 库改第一行；部署改最后一行并替换二进制文件。库中还存着被默认规则忽略、不会部署的 .env
+async fn diverged_with_binary() -> Fixture {
+    let fx = Fixture::deployed_with(&[
+        (SKILL_MD, b"a\nb\nc\n"),
+        ("logo.png", LOGO_V1),
+        ("notes.md", b"notes\n"),
+        (".env", b"SECRET=1\n"),
+    ])
+    .await;
+    assert!(!fx.deploy_dir.join(".env").exists());
+    fx.edit_library(b"A\nb\nc\n");
+    std::fs::write(fx.deploy_dir.join(SKILL_MD), "a\nb\nC\n").unwrap();
+    std::fs::write(fx.deploy_dir.join("logo.png"), LOGO_V2).unwrap();
+    fx
+}
+
+#[tokio::test]
+async fn merge_by_id_compares_bytes_and_skips_ignored_files() {
+    let fx = diverged_with_binary().await;
+    let merge = merge_skill_by_id_internal(&fx.pool, fx.skill_id.clone(), fx.deployment_id.clone(), None, None)
+        .unwrap();
+    assert_eq!(merge.conflict_count, 0);
+    let file = |path: &str| merge.files.iter().find(|f| f.path == path);
+    assert_eq!(file(SKILL_MD).unwrap().status, "auto_merged");
+    assert_eq!(file(SKILL_MD).unwrap().merged_content.as_deref(), Some("A\nb\nC\n"));
+    assert_eq!(file("logo.png").unwrap().status, "auto_merged");
+    assert_eq!(file("notes.md").unwrap().status, "unchanged");
+    assert!(file(".env").is_none(), "未部署的忽略文件不应被当成部署一侧删除");
+}
+
+#[tokio::test]
+async fn commit_merge_keeps_files_not_addressed() {
+    let fx = diverged_with_binary().await;
+
+    // 只给出文本文件的结果：二进制文件取合并结果，忽略文件原样保留
+    let written = fx.resolve(
+        vec![merged(SKILL_MD, Some("A\nb\nC\n")), merged("notes.md", None)],
+        true,
+    );
+    assert_eq!(written, 3);
+    assert_eq!(fx.library(SKILL_MD).unwrap(), b"A\nb\nC\n");
+    assert_eq!(fx.library("logo.png").unwrap(), LOGO_V2);
+    assert_eq!(fx.library(".env").unwrap(), b"SECRET=1\n");
+    assert!(fx.library("notes.md").is_none());
+
+    // 不关联部署时只改写列出的文件
+    fx.resolve(vec![merged(SKILL_MD, Some("final\n"))], false);
+    assert_eq!(fx.library_md(), b"final\n");
+    assert_eq!(fx.library("logo.png").unwrap(), LOGO_V2);
+    assert_eq!(fx.library(".env").unwrap(), b"SECRET=1\n");
+
+    let marked = commit_skill_merge_internal(
+        &fx.pool,
+        fx.skill_id.clone(),
+        vec![merged(SKILL_MD, Some("<<<<<<< LOCAL\n"))],
+        None,
+        None,
+        None,
+        None,
+    );
+    assert!(marked.is_err());
+}
@@ -0,0 +1,203 @@
+//! 部署写入经写入登记后，watcher 不会把它当成用户修改。
+//!
+//! 在临时目录里建库、登记项目并用真实的 notify 监听工具目录，
+//! 收集部署过程产生的事件，合并成一个批次交给 `process_batch` 处理。
+
+mod common;
+
+use common::TestEnv;
+use notify::event::{CreateKind, ModifyKind, RemoveKind};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use rusqlite::params;
+use skills_manager_lib::commands::skill_files::{db_write_file, refresh_skill_checksum};
+use skills_manager_lib::commands::watcher::{process_batch, EventBatch};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+struct Fixture {
+    env: TestEnv,
+    project_id: String,
+    skill_id: String,
+    skills_dir: PathBuf,
+}
+
+impl Deref for Fixture {
+    type Target = TestEnv;
+
+    fn deref(&self) -> &TestEnv {
+        &self.env
+    }
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let env = TestEnv::new("journal");
+        let (project_id, project) = env.add_project("project");
+        let skills_dir = project.join(".claude").join("skills");
+        std::fs::create_dir_all(&skills_dir).unwrap();
+        let skill_id = env.add_skill(
+            "journal-demo",
+            &[("SKILL.md", b"# journal-demo\n"), ("docs/usage.md", b"usage\n")],
+        );
+        Self {
+            env,
+            project_id,
+            skill_id,
+            skills_dir,
+        }
+    }
+
+    async fn deploy(&self) -> PathBuf {
+        self.env.deploy(&self.skill_id, &self.project_id, Some(true)).await.1
+    }
+
+    fn watcher_flagged(&self) -> bool {
+        let conn = self.pool.get().unwrap();
+        let modified_at: Option<String> = conn
+            .query_row(
+                "SELECT watcher_modified_at FROM skills WHERE id = ?1",
+                params![self.skill_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let events: i64 = conn
+            .query_row("SELECT COUNT(*) FROM change_events", [], |row| row.get(0))
+            .unwrap();
+        modified_at.is_some() || events > 0
+    }
+}
+
+/// 监听 dir，执行 f 之后收集一段时间内的事件
+async fn collect_events<F, Fut, T>(dir: &Path, f: F) -> (T, Vec<Event>)
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = T>,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+        if let Ok(event) = res {
+            let _ = tx.send(event);
+        }
+    })
+    .unwrap();
+    watcher.watch(dir, RecursiveMode::Recursive).unwrap();
+
+    let out = f().await;
+    let mut events = Vec::new();
+    while let Ok(event) = rx.recv_timeout(Duration::from_millis(1000)) {
+        events.push(event);
+    }
+    (out, events)
+}
+
+/// 部署目录下每个文件的创建/修改事件（有的平台会对改名目录的子路径逐个上报）
+fn synthetic_events(dir: &Path) -> Vec<Event> {
+    walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .flat_map(|e| {
+            [
+                Event::new(EventKind::Create(CreateKind::File)).add_path(e.path().to_path_buf()),
+                Event::new(EventKind::Modify(ModifyKind::Any)).add_path(e.path().to_path_buf()),
+            ]
+        })
+        .collect()
+}
+
+fn batch_of(events: &[Event]) -> EventBatch {
+    let mut batch = EventBatch::default();
+    for event in events {
+        batch.push(event);
+    }
+    batch
+}
+
+#[tokio::test]
+async fn deploy_does_not_flag_skill_as_changed() {
+    let fx = Fixture::new();
+    let (dst, mut events) = collect_events(&fx.skills_dir, || fx.deploy()).await;
+    assert!(dst.join("SKILL.md").is_file());
+
+    // 事件处理前库里已经有了新的修改：只比较 DB 内容的话，部署写入会被误认为用户改动并覆盖回库
+    {
+        let conn = fx.pool.get().unwrap();
+        db_write_file(&conn, &fx.skill_id, "SKILL.md", b"# journal-demo v2\n").unwrap();
+        refresh_skill_checksum(&conn, &fx.skill_id).unwrap();
+    }
+
+    events.extend(synthetic_events(&dst));
+    assert!(process_batch(batch_of(&events), &fx.pool).is_none());
+    assert!(!fx.watcher_flagged());
+
+    let conn = fx.pool.get().unwrap();
+    let content: Vec<u8> = conn
+        .query_row(
+            "SELECT content FROM skill_files WHERE skill_id = ?1 AND relative_path = 'SKILL.md'",
+            params![fx.skill_id],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(content, b"# journal-demo v2\n");
+}
+
+#[tokio::test]
+async fn user_edit_after_deploy_is_still_detected() {
+    let fx = Fixture::new();
+    let dst = fx.deploy().await;
+
+    let edited = dst.join("SKILL.md");
+    let (_, events) = collect_events(&fx.skills_dir, || async {
+        std::fs::write(&edited, b"# edited by user\n").unwrap();
+    })
+    .await;
+
+    let events = if events.is_empty() {
+        synthetic_events(&dst)
+    } else {
+        events
+    };
+    let payload = process_batch(batch_of(&events), &fx.pool).expect("用户修改应产生 skill-change");
+    assert_eq!(payload["files"][0]["rel_path"], "SKILL.md");
+    assert!(fx.watcher_flagged());
+}
+
+#[test]
+fn unmatched_paths_are_reported_without_change_events() {
+    let fx = Fixture::new();
+    let stray = fx.skills_dir.join("stray").join("SKILL.md");
+    std::fs::create_dir_all(stray.parent().unwrap()).unwrap();
+    std::fs::write(&stray, b"# stray\n").unwrap();
+    std::fs::write(fx.skills_dir.join(".DS_Store"), b"").unwrap();
+
+    let events = synthetic_events(&fx.skills_dir);
+    let payload = process_batch(batch_of(&events), &fx.pool).expect("未匹配的变更也应通知前端");
+    let files = payload["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(files[0]["path"], stray.to_string_lossy().as_ref());
+    assert_eq!(files[0]["resolution"], "unmatched");
+    assert!(files[0]["event_id"].is_null());
+    assert!(!fx.watcher_flagged());
+}
+
+#[tokio::test]
+async fn removed_directories_are_expanded_to_their_files() {
+    let fx = Fixture::new();
+    let dst = fx.deploy().await;
+    std::fs::remove_dir_all(dst.join("docs")).unwrap();
+
+    // 有的平台只上报被删目录本身；部署外新建后又删除的目录不算改动
+    let stray = fx.skills_dir.join("tmp-dir");
+    let events = [
+        Event::new(EventKind::Remove(RemoveKind::Any)).add_path(dst.join("docs")),
+        Event::new(EventKind::Create(CreateKind::Folder)).add_path(stray.clone()),
+        Event::new(EventKind::Remove(RemoveKind::Folder)).add_path(stray),
+    ];
+    let payload = process_batch(batch_of(&events), &fx.pool).expect("删除目录应产生 skill-change");
+    let files = payload["files"].as_array().unwrap();
+    assert_eq!(files.len(), 1, "{:?}", files);
+    assert_eq!(files[0]["rel_path"], "docs/usage.md");
+    assert_eq!(files[0]["event_type"], "file_deleted");
+}
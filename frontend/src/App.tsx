@@ -16,6 +16,7 @@ import { useProjectStore } from '@/stores/useProjectStore'
 import { useSkillStore } from '@/stores/useSkillStore'
 import { useSettingsStore } from '@/stores/useSettingsStore'
 import { settingsApi, scannerApi, deploymentsApi } from '@/lib/tauri-api'
+import type { SkillChangeBatch, AutoExportEventData } from '@/lib/tauri-api'
 import { toast } from 'sonner'
 
 function App() {
@@ -106,15 +107,24 @@ function App() {
   // 监听后端 skill-change 事件，自动刷新数据
   useEffect(() => {
     let unlisten: (() => void) | undefined
-    listen<{ event_id: string; event_type: string; path: string; deployment_id: string; skill_id?: string }>(
+    listen<SkillChangeBatch>(
       'skill-change',
       (event) => {
         console.log('[App] 收到 skill-change 事件:', event.payload)
         fetchDeployments()
         fetchSkills()
         checkSkillUpdates()
+        const { files, conflict_event_ids } = event.payload
+        const first = files[0]?.path.split('/').slice(-3).join('/') ?? ''
+        if (conflict_event_ids.length > 0) {
+          toast.warning(`库与部署同时被修改，需要合并`, {
+            description: `${conflict_event_ids.length} 个部署未回写到库，请在 Skill 详情中处理冲突`,
+            duration: 6000,
+          })
+          return
+        }
         toast.info(`检测到部署目录文件变更`, {
-          description: event.payload.path.split('/').slice(-3).join('/'),
+          description: files.length > 1 ? `${first} 等 ${files.length} 个文件` : first,
           duration: 4000,
         })
       }
@@ -122,6 +132,27 @@ function App() {
     return () => { unlisten?.() }
   }, [fetchDeployments, fetchSkills, checkSkillUpdates])
 
+  // 后台自动导出：只提示冲突和失败，成功静默记录
+  useEffect(() => {
+    let unlisten: (() => void) | undefined
+    listen<AutoExportEventData>('git-auto-export', (event) => {
+      console.log('[App] 收到 git-auto-export 事件:', event.payload)
+      const { status, message, remote_url, retry_in_secs } = event.payload
+      if (status === 'conflict') {
+        toast.warning(`自动导出有冲突待处理: ${remote_url}`, {
+          description: `${message}（请在 Git 同步中选择保留哪一侧）`,
+          duration: 8000,
+        })
+      } else if (status === 'error') {
+        toast.error(`自动导出失败: ${remote_url}`, {
+          description: retry_in_secs ? `${message}（${Math.round(retry_in_secs / 60)} 分钟后重试）` : message,
+          duration: 6000,
+        })
+      }
+    }).then((fn) => { unlisten = fn })
+    return () => { unlisten?.() }
+  }, [])
+
   if (!appReady) {
     return (
       <div className="min-h-screen flex items-center justify-center bg-cream-50">
@@ -60,8 +60,25 @@ export interface SkillBackupRow {
   created_at: string
 }
 
+export interface SkillSearchMatchData {
+  path: string | null
+  field: 'name' | 'description' | 'frontmatter' | 'body' | 'path'
+  /** 关键词以 <mark></mark> 包裹，其余为原文 */
+  snippet: string
+}
+
+export interface SkillSearchHitData {
+  skill_id: string
+  skill_name: string
+  description: string | null
+  score: number
+  matches: SkillSearchMatchData[]
+}
+
 export const skillsApi = {
   getAll: () => invoke<SkillRow[]>('get_skills'),
+  search: (query: string, limit?: number) =>
+    invoke<SkillSearchHitData[]>('search_skills', { query, limit: limit ?? null }),
   getBackups: (skillId: string) => invoke<SkillBackupRow[]>('get_skill_backups', { skillId }),
   readFile: (skillId: string, relPath: string) =>
     invoke<string>('read_skill_file', { skillId, relPath }),
@@ -77,6 +94,63 @@ export const skillsApi = {
     invoke<void>('dismiss_watcher_change', { skillId }),
   discardWatcherChange: (skillId: string) =>
     invoke<void>('discard_watcher_change', { skillId }),
+  /** 库与部署的三向合并预览；base 默认取部署上次同步时的版本 */
+  mergeById: (skillId: string, deploymentId: string, base?: { revision?: number; backupId?: string }) =>
+    invoke<MergeResultData>('merge_skill_by_id', {
+      skillId,
+      deploymentId,
+      baseRevision: base?.revision ?? null,
+      baseBackupId: base?.backupId ?? null,
+    }),
+  /** 合并结果写回库（自动备份），可选同步部署；base 须与 mergeById 预览时一致，每个冲突文件都要给出结果 */
+  commitMerge: (
+    skillId: string,
+    resolutions: MergeResolutionData[],
+    deploymentId?: string,
+    syncDeploymentIds?: string[],
+    base?: { revision?: number; backupId?: string },
+  ) =>
+    invoke<MergeCommitResultData>('commit_skill_merge', {
+      skillId,
+      resolutions,
+      deploymentId: deploymentId ?? null,
+      baseRevision: base?.revision ?? null,
+      baseBackupId: base?.backupId ?? null,
+      syncDeploymentIds: syncDeploymentIds ?? null,
+    }),
+}
+
+export interface MergeFileResultData {
+  path: string
+  status: 'auto_merged' | 'conflict' | 'added_left' | 'added_right' | 'deleted_left' | 'deleted_right' | 'unchanged'
+  merged_content: string | null
+  left_content: string | null
+  right_content: string | null
+  base_content: string | null
+}
+
+export interface MergeResultData {
+  files: MergeFileResultData[]
+  auto_merged_count: number
+  conflict_count: number
+  total_files: number
+}
+
+/** 要写入的合并结果；未列出的文件保持合并结果或库中内容 */
+export interface MergeResolutionData {
+  path: string
+  /** null 表示删除该文件 */
+  content: string | null
+}
+
+export interface MergeCommitResultData {
+  skill_id: string
+  backup_id: string | null
+  revision: number | null
+  new_checksum: string | null
+  files_written: number
+  conflicts_resolved: number
+  deployments_synced: number
 }
 
 export interface BatchDeleteResultData {
@@ -106,8 +180,11 @@ export interface DeploymentRow {
   last_synced: string | null
   created_at: string
   updated_at: string
+  mode: DeployMode
 }
 
+export type DeployMode = 'copy' | 'symlink' | 'hardlink'
+
 export interface DeployConflictData {
   status: string  // "exists_same" | "exists_different"
   existing_checksum: string | null
@@ -152,6 +229,77 @@ export interface ReconcileReportData {
   diverged_detected: number
   untracked_found: number
   change_events_created: number
+  plan: DeploymentPlanData | null
+}
+
+export interface PlanFileChangeData {
+  path: string
+  status: string  // "added" | "removed" | "modified"
+  lines_added: number
+  lines_removed: number
+}
+
+export interface PlanActionData {
+  deployment_id: string | null
+  skill_id: string | null
+  path: string
+  action: string  // create | overwrite | unchanged | conflict | missing | diverged | synced | untracked
+  disk_checksum: string | null
+  library_checksum: string | null
+  files: PlanFileChangeData[]
+}
+
+export interface DeploymentPlanData {
+  plan_id: string
+  kind: string  // "update_skill_from_library" | "reconcile_all_deployments"
+  skill_id: string | null
+  created_at: string
+  actions: PlanActionData[]
+}
+
+export interface WatchedPathData {
+  path: string
+  active: boolean  // false: 目录尚不存在，创建后自动监听
+}
+
+/** watcher 一个静默窗口内合并后的变更，每批只推送一次 skill-change */
+export interface SkillChangeFile {
+  /** 未匹配到部署时为 null（不记录 change_events） */
+  event_id: string | null
+  event_type: 'file_created' | 'file_modified' | 'file_deleted'
+  /** lib_updated: 已回写到库；conflict: 库在上次同步后也被修改，等待合并；unmatched: 未匹配到部署 */
+  resolution: 'lib_updated' | 'conflict' | 'unmatched'
+  path: string
+  deployment_id: string | null
+  skill_id?: string
+  rel_path?: string
+}
+
+export interface SkillChangeBatch {
+  batch_id: string
+  skill_ids: string[]
+  deployment_ids: string[]
+  conflict_event_ids: string[]
+  files: SkillChangeFile[]
+}
+
+/** 库与部署并发修改，merge_* 依次对应 merge_skill_versions 的 base_path / left_path / right_path */
+export interface WatcherConflictData {
+  event_id: string
+  deployment_id: string
+  skill_id: string
+  skill_name: string
+  deployment_path: string
+  merge_base_path: string | null
+  merge_left_path: string | null
+  merge_right_path: string | null
+  created_at: string
+}
+
+export interface ApplyPlanResultData {
+  plan_id: string
+  applied: string[]
+  skipped: string[]
 }
 
 export const deploymentsApi = {
@@ -160,16 +308,22 @@ export const deploymentsApi = {
     invoke<DeploymentRow[]>('get_skill_deployments', { skillId }),
   delete: (deploymentId: string) =>
     invoke<void>('delete_deployment', { deploymentId }),
-  deployToProject: (skillId: string, projectId: string, tool: string, force?: boolean) =>
-    invoke<DeployResultData>('deploy_skill_to_project', { skillId, projectId, tool, force: force ?? false }),
-  deployGlobal: (skillId: string, tool: string, force?: boolean) =>
-    invoke<DeployResultData>('deploy_skill_global', { skillId, tool, force: force ?? false }),
+  deployToProject: (skillId: string, projectId: string, tool: string, force?: boolean, mode?: DeployMode) =>
+    invoke<DeployResultData>('deploy_skill_to_project', { skillId, projectId, tool, force: force ?? false, mode }),
+  deployGlobal: (skillId: string, tool: string, force?: boolean, mode?: DeployMode) =>
+    invoke<DeployResultData>('deploy_skill_global', { skillId, tool, force: force ?? false, mode }),
   syncDeployment: (deploymentId: string) =>
     invoke<SyncResultData>('sync_deployment', { deploymentId }),
   checkConsistency: () =>
     invoke<ConsistencyReportData>('check_deployment_consistency'),
-  reconcile: () =>
-    invoke<ReconcileReportData>('reconcile_all_deployments'),
+  reconcile: (dryRun?: boolean) =>
+    invoke<ReconcileReportData>('reconcile_all_deployments', { dryRun: dryRun ?? false }),
+  applyPlan: (plan: DeploymentPlanData) =>
+    invoke<ApplyPlanResultData>('apply_plan', { plan }),
+  listWatchedPaths: () =>
+    invoke<WatchedPathData[]>('list_watched_paths'),
+  listWatcherConflicts: () =>
+    invoke<WatcherConflictData[]>('list_watcher_conflicts'),
 }
 
 // ── Settings ──
@@ -191,6 +345,16 @@ export interface GitExportConfigRow {
   last_pull_at: string | null
   created_at: string
   updated_at: string
+  /** 凭据只保存在本地，这里只告知是否已设置 */
+  has_credential: boolean
+}
+
+/** Git 远程凭据；字段不传表示保留已保存的值，空串表示清除 */
+export interface GitCredentialInput {
+  username?: string
+  token?: string
+  ssh_key_path?: string
+  ssh_passphrase?: string
 }
 
 export interface AppInitStatus {
@@ -215,7 +379,8 @@ export const settingsApi = {
     authType: string
     branch: string
     autoExport: string
-  }) => invoke<GitExportConfigRow>('save_git_export_config', params),
+    credential?: GitCredentialInput
+  }) => invoke<GitExportConfigRow>('save_git_export_config', { ...params, credential: params.credential ?? null }),
   deleteGitConfig: (configId: string) =>
     invoke<void>('delete_git_export_config', { configId }),
   getInitStatus: () => invoke<AppInitStatus>('get_app_init_status'),
@@ -306,17 +471,68 @@ export interface GitImportResult {
   message: string
 }
 
+/** 两侧都改过的 Skill 保留哪一侧；merged 仅在合并无冲突时可用 */
+export interface GitSyncResolution {
+  skill_name: string
+  keep: 'local' | 'remote' | 'merged'
+}
+
+export interface GitSyncSkillData {
+  name: string
+  action: 'pull' | 'push' | 'merged' | 'delete_local' | 'delete_remote' | 'diverged'
+  merge: MergeResultData | null
+  backup_id: string | null  // delete_local 时删除前的备份
+  deployments_skipped: string[]  // 有本地修改、未被覆盖的部署
+}
+
+export interface GitSyncResultData {
+  config_id: string
+  base_commit: string | null
+  remote_commit: string | null
+  commit_hash: string | null
+  pulled: number
+  pushed: number
+  diverged: string[]
+  skills: GitSyncSkillData[]
+  message: string
+}
+
+/** `git-progress` 事件；opId 与调用时传入的一致，可用于取消 */
+export interface GitProgressData {
+  op_id: string
+  operation: string
+  stage: 'receiving' | 'resolving' | 'checkout' | 'pushing'
+  current: number
+  total: number
+  received_bytes: number
+}
+
+/** 后台自动导出（双向同步）的一次结果（git-auto-export 事件） */
+export interface AutoExportEventData {
+  config_id: string
+  remote_url: string
+  /** conflict：有两侧都改过的 Skill，需要在同步页处理 */
+  status: 'success' | 'conflict' | 'error'
+  message: string
+  commit_hash: string | null
+  retry_in_secs: number | null
+}
+
 export const gitApi = {
-  testConnection: (remoteUrl: string, authType: string) =>
-    invoke<GitTestResult>('test_git_connection', { remoteUrl, authType }),
-  exportToGit: (configId: string) =>
-    invoke<GitExportResult>('export_skills_to_git', { configId }),
-  cloneRepo: (remoteUrl: string, branch?: string) =>
-    invoke<GitCloneResult>('clone_git_repo', { remoteUrl, branch }),
+  testConnection: (remoteUrl: string, authType: string, credential?: GitCredentialInput) =>
+    invoke<GitTestResult>('test_git_connection', { remoteUrl, authType, credential: credential ?? null }),
+  exportToGit: (configId: string, opId?: string) =>
+    invoke<GitExportResult>('export_skills_to_git', { configId, opId: opId ?? null }),
+  cloneRepo: (remoteUrl: string, branch?: string, opId?: string) =>
+    invoke<GitCloneResult>('clone_git_repo', { remoteUrl, branch, opId: opId ?? null }),
   importFromRepo: (clonePath: string, skillNames: string[], overwriteConflicts: boolean, sourceUrl?: string) =>
     invoke<GitImportResult>('import_from_git_repo', { clonePath, skillNames, overwriteConflicts, sourceUrl: sourceUrl ?? null }),
-  checkRepoUpdates: (configId?: string) =>
-    invoke<GitRepoUpdateInfo[]>('check_git_repo_updates', { configId: configId ?? null }),
+  checkRepoUpdates: (configId?: string, opId?: string) =>
+    invoke<GitRepoUpdateInfo[]>('check_git_repo_updates', { configId: configId ?? null, opId: opId ?? null }),
+  sync: (configId: string, resolutions?: GitSyncResolution[], opId?: string) =>
+    invoke<GitSyncResultData>('sync_with_git', { configId, resolutions: resolutions ?? null, opId: opId ?? null }),
+  cancel: (opId: string) =>
+    invoke<boolean>('cancel_git_operation', { opId }),
 }
 
 // ── skills.sh ──
@@ -376,6 +592,8 @@ export interface CatalogSkill {
   skill_md_url: string
   commit_sha: string
   installs: number | null
+  registry_id: string | null
+  manifest_url: string | null  // 自托管静态注册源的文件清单
 }
 
 // ── skills.sh 搜索结果类型 ──
@@ -407,11 +625,34 @@ export const skillsShApi = {
     }),
 }
 
+export interface CatalogSnapshotInfoData {
+  registry_id: string
+  source_url: string | null
+  skill_count: number
+  fetched_at: string | null
+  stale: boolean
+  error: string | null
+}
+
+export interface CatalogUpdateResultData {
+  skill_id: string
+  status: 'up_to_date' | 'updated' | 'merged' | 'conflict'
+  from_revision: string | null
+  to_revision: string
+  backup_id: string | null
+  files_written: number
+  deployments_synced: number
+  deployments_skipped: string[]  // 有本地修改、未被新版本覆盖的部署
+  merge: MergeResultData | null  // status 为 conflict 时未写入，需提供 resolutions 重试
+}
+
 export const catalogApi = {
   fetch: (category?: string) =>
     invoke<CatalogSkill[]>('fetch_catalog', { category: category ?? null }),
   search: (query: string, category?: string) =>
     invoke<CatalogSkill[]>('search_catalog', { query, category: category ?? null }),
+  status: () => invoke<CatalogSnapshotInfoData[]>('get_catalog_status'),
+  refresh: () => invoke<CatalogSnapshotInfoData[]>('refresh_catalog'),
   enrichSingle: (skillName: string, sourceRepo: string) =>
     invoke<number | null>('enrich_single_install', { skillName, sourceRepo }),
   enrichBatchByCategory: (categoryKeyword: string) =>
@@ -421,11 +662,108 @@ export const catalogApi = {
     sourcePath: string
     skillName: string
     commitSha: string
+    registryId?: string | null
     deployTargets: DeployTargetParam[]
     forceOverwrite?: boolean
     token?: string
-  }) => invoke<SkillsShInstallResult>('install_from_catalog', params),
+  }) =>
+    invoke<SkillsShInstallResult>('install_from_catalog', {
+      ...params,
+      registryId: params.registryId ?? null,
+    }),
   checkUpdates: () =>
     invoke<RemoteUpdateInfo[]>('check_catalog_updates'),
+  applyUpdate: (params: {
+    skillId: string
+    revision?: string | null
+    resolutions?: MergeResolutionData[]
+    token?: string
+  }) =>
+    invoke<CatalogUpdateResultData>('apply_catalog_update', {
+      skillId: params.skillId,
+      revision: params.revision ?? null,
+      resolutions: params.resolutions ?? null,
+      token: params.token ?? null,
+    }),
 }
 
+
+// ── Skill 注册源 ──
+export type RegistryKind = 'catalog' | 'skills_sh' | 'github' | 'git' | 'local'
+
+export interface RegistryConfigData {
+  id: string
+  name: string
+  kind: RegistryKind
+  url: string
+  options: string | null  // JSON：catalog 的 mirrors、github/git 的 ref、github 的 path
+  enabled: boolean
+  priority: number
+  created_at: string
+  updated_at: string
+}
+
+export interface RegistryInputData {
+  id?: string | null
+  name: string
+  kind: RegistryKind
+  url: string
+  options?: string | null
+  enabled?: boolean
+  priority?: number
+}
+
+export interface RegistrySkillData {
+  registry_id: string
+  skill_id: string          // 由注册源定义，安装时原样传回
+  name: string
+  description: string | null
+  version: string | null
+  source_url: string | null
+  revision: string | null
+  installs: number | null
+  tags: string[]
+}
+
+export interface StaticRegistryResultData {
+  output_dir: string
+  index_path: string
+  skills_exported: number
+  files_exported: number
+}
+
+export const registryApi = {
+  list: () => invoke<RegistryConfigData[]>('list_registries'),
+  save: (registry: RegistryInputData) =>
+    invoke<RegistryConfigData>('save_registry', { registry }),
+  remove: (registryId: string) => invoke<void>('delete_registry', { registryId }),
+  search: (query: string, registryIds?: string[], limit?: number, token?: string) =>
+    invoke<RegistrySkillData[]>('search_registries', {
+      query,
+      registryIds: registryIds ?? null,
+      limit: limit ?? null,
+      token: token ?? null,
+    }),
+  install: (params: {
+    registryId: string
+    skillId: string
+    revision?: string
+    deployTargets: DeployTargetParam[]
+    forceOverwrite?: boolean
+    token?: string
+  }) =>
+    invoke<SkillsShInstallResult>('install_from_registry', {
+      registryId: params.registryId,
+      skillId: params.skillId,
+      revision: params.revision ?? null,
+      deployTargets: params.deployTargets,
+      forceOverwrite: params.forceOverwrite ?? false,
+      token: params.token ?? null,
+    }),
+  generateStatic: (outputDir: string, skillIds?: string[], provider?: string) =>
+    invoke<StaticRegistryResultData>('generate_static_registry', {
+      outputDir,
+      skillIds: skillIds ?? null,
+      provider: provider ?? null,
+    }),
+}
@@ -8,6 +8,7 @@ import {
 import { Button } from '@/components/ui/button'
 import { Card, CardContent, CardHeader, CardTitle } from '@/components/ui/card'
 import { Input } from '@/components/ui/input'
+import { Textarea } from '@/components/ui/textarea'
 import { Label } from '@/components/ui/label'
 import { Switch } from '@/components/ui/switch'
 import { Tabs, TabsList, TabsTrigger } from '@/components/ui/tabs'
@@ -25,6 +26,8 @@ import { ALL_TOOLS } from '@/lib/tools'
 import { toast } from 'sonner'
 import { useTheme } from 'next-themes'
 import { settingsApi, gitApi } from '@/lib/tauri-api'
+import type { AppSettings } from '@/types'
+import type { GitCredentialInput } from '@/lib/tauri-api'
 
 import { ToolIcon } from '@/components/ui/ToolIcon'
 
@@ -79,6 +82,9 @@ export default function Settings() {
   const [gitPlatform, setGitPlatform] = useState('github')
   const [gitUrl, setGitUrl] = useState('https://github.com/congwa/cong_wa_skills')
   const [authType, setAuthType] = useState('ssh')
+  const [gitToken, setGitToken] = useState('')
+  const [sshKeyPath, setSshKeyPath] = useState('')
+  const [sshPassphrase, setSshPassphrase] = useState('')
   const [testing, setTesting] = useState(false)
   const [testResult, setTestResult] = useState<'success' | 'fail' | null>(null)
   const [factoryResetOpen, setFactoryResetOpen] = useState(false)
@@ -143,6 +149,13 @@ export default function Settings() {
     }
   }
 
+  // 未填写时返回 undefined：保存时保留已有凭据，测试时使用已保存的凭据
+  const gitCredential = (): GitCredentialInput | undefined => {
+    if (authType === 'token' && gitToken) return { token: gitToken }
+    if (authType === 'ssh' && sshKeyPath) return { ssh_key_path: sshKeyPath, ssh_passphrase: sshPassphrase }
+    return undefined
+  }
+
   const handleSaveGitConfig = async () => {
     try {
       await settingsApi.saveGitConfig({
@@ -151,7 +164,10 @@ export default function Settings() {
         authType: authType,
         branch: 'main',
         autoExport: 'manual',
+        credential: gitCredential(),
       })
+      setGitToken('')
+      setSshPassphrase('')
       toast.success('Git 配置已保存')
     } catch (e) {
       console.error('save git config error:', e)
@@ -163,7 +179,7 @@ export default function Settings() {
     setTesting(true)
     setTestResult(null)
     try {
-      const result = await gitApi.testConnection(gitUrl, authType)
+      const result = await gitApi.testConnection(gitUrl, authType, gitCredential())
       setTestResult(result.success ? 'success' : 'fail')
       if (result.success) {
         toast.success(result.message)
@@ -303,8 +319,27 @@ export default function Settings() {
                       <RadioGroup value={authType} onValueChange={setAuthType} className="flex gap-4">
                         <div className="flex items-center gap-2"><RadioGroupItem value="ssh" id="s-ssh" /><Label htmlFor="s-ssh">SSH Key</Label></div>
                         <div className="flex items-center gap-2"><RadioGroupItem value="token" id="s-token" /><Label htmlFor="s-token">HTTPS Token</Label></div>
+                        <div className="flex items-center gap-2"><RadioGroupItem value="none" id="s-none" /><Label htmlFor="s-none">无（公开仓库）</Label></div>
                       </RadioGroup>
                     </div>
+                    {authType === 'token' && (
+                      <div className="space-y-1">
+                        <Label>Token</Label>
+                        <Input type="password" value={gitToken} onChange={(e) => setGitToken(e.target.value)} placeholder="留空则保留已保存的 Token" />
+                      </div>
+                    )}
+                    {authType === 'ssh' && (
+                      <div className="grid grid-cols-2 gap-2">
+                        <div className="space-y-1">
+                          <Label>私钥路径</Label>
+                          <Input value={sshKeyPath} onChange={(e) => setSshKeyPath(e.target.value)} placeholder="~/.ssh/id_ed25519（留空使用 ssh-agent）" />
+                        </div>
+                        <div className="space-y-1">
+                          <Label>私钥密码</Label>
+                          <Input type="password" value={sshPassphrase} onChange={(e) => setSshPassphrase(e.target.value)} placeholder="可选" />
+                        </div>
+                      </div>
+                    )}
                     <div className="space-y-1">
                       <Label>导出分支</Label>
                       <Input defaultValue="main" className="w-32" />
@@ -370,10 +405,11 @@ export default function Settings() {
               <CardContent className="space-y-5">
                 <div className="flex items-center justify-between">
                   <div><Label>自动导出频率</Label></div>
-                  <Select value={settings.auto_export_frequency} onValueChange={(v) => updateSettings({ auto_export_frequency: v as 'manual' | 'daily' | 'on-change' })}>
+                  <Select value={settings.auto_export_frequency} onValueChange={(v) => updateSettings({ auto_export_frequency: v as AppSettings['auto_export_frequency'] })}>
                     <SelectTrigger className="w-36"><SelectValue /></SelectTrigger>
                     <SelectContent>
                       <SelectItem value="manual">手动</SelectItem>
+                      <SelectItem value="hourly">每小时</SelectItem>
                       <SelectItem value="daily">每日</SelectItem>
                       <SelectItem value="on-change">变更时</SelectItem>
                     </SelectContent>
@@ -387,6 +423,27 @@ export default function Settings() {
                   <div><Label>文件监听</Label><p className="text-xs text-cream-500 mt-0.5">后台监听项目目录变更</p></div>
                   <Switch checked={settings.file_watch_enabled} onCheckedChange={(v) => updateSettings({ file_watch_enabled: v })} />
                 </div>
+                <div className="flex items-center justify-between">
+                  <div><Label>合并窗口</Label><p className="text-xs text-cream-500 mt-0.5">文件停止变化多少毫秒后再统一处理</p></div>
+                  <Input
+                    type="number"
+                    min={100}
+                    step={100}
+                    value={settings.watcher_quiet_ms}
+                    onChange={(e) => updateSettings({ watcher_quiet_ms: Number(e.target.value) || 500 })}
+                    className="w-24"
+                  />
+                </div>
+                <div className="space-y-1.5">
+                  <div><Label>忽略规则</Label><p className="text-xs text-cream-500 mt-0.5">gitignore 语法，每行一条；导入、监听、校验和 Git 导出都会跳过匹配的文件。Skill 目录下的 .skillignore 可进一步覆盖</p></div>
+                  <Textarea
+                    value={settings.ignore_patterns}
+                    onChange={(e) => updateSettings({ ignore_patterns: e.target.value })}
+                    placeholder={'dist/\n*.log'}
+                    rows={4}
+                    className="font-mono text-xs"
+                  />
+                </div>
               </CardContent>
             </Card>
           )}
@@ -201,24 +201,14 @@ export default function SkillDetail() {
     if (!remoteUpdateInfo) return
     setApplyingUpdate(true)
     try {
-      // 从 catalog 查找最新版本，获取正确的 source_path 和 commit_sha
-      const catalog = await catalogApi.fetch()
-      const catalogSkill = catalog.find(
-        s => s.name.toLowerCase() === remoteUpdateInfo.skill_name.toLowerCase()
-          || s.source_repo === remoteUpdateInfo.owner_repo
-      )
-      if (!catalogSkill) {
-        toast.error(`商城中找不到 ${remoteUpdateInfo.skill_name} 的最新版本`)
+      const result = await catalogApi.applyUpdate({
+        skillId: remoteUpdateInfo.skill_id,
+        revision: remoteUpdateInfo.remote_sha,
+      })
+      if (result.status === 'conflict') {
+        toast.error(`本地修改与上游冲突（${result.merge?.conflict_count ?? 0} 个文件），请先手动合并`)
         return
       }
-      await catalogApi.install({
-        sourceRepo: catalogSkill.source_repo,
-        sourcePath: catalogSkill.source_path,
-        skillName: catalogSkill.name,
-        commitSha: catalogSkill.commit_sha,
-        deployTargets: [],
-        forceOverwrite: true,
-      })
       await fetchSkills()
       setRemoteUpdateInfo((prev) => prev ? { ...prev, has_update: false } : null)
       toast.success('已从商城更新到数据库')
@@ -25,17 +25,36 @@ import { cn, toolNames, sourceLabels } from '@/lib/utils'
 import { ToolIcon } from '@/components/ui/ToolIcon'
 import { gitApi, deploymentsApi, catalogApi, skillsApi } from '@/lib/tauri-api'
 import type { SkillDeployment } from '@/types'
-import type { RemoteUpdateInfo } from '@/lib/tauri-api'
+import type { RemoteUpdateInfo, SkillSearchHitData } from '@/lib/tauri-api'
 import { invoke } from '@tauri-apps/api/core'
 import { toast } from 'sonner'
 
 type FilterTab = 'all' | 'has-update' | 'locally-modified' | 'deploy-issue'
 
+// 全文搜索片段：<mark> 包裹的部分高亮，其余按纯文本渲染
+function SearchSnippet({ text }: { text: string }) {
+  return (
+    <>
+      {text.split(/(<mark>.*?<\/mark>)/g).map((part, i) =>
+        part.startsWith('<mark>') && part.endsWith('</mark>') ? (
+          <mark key={i} className="bg-peach-100 text-peach-700 rounded-sm px-0.5">
+            {part.slice(6, -7)}
+          </mark>
+        ) : (
+          <span key={i}>{part}</span>
+        ),
+      )}
+    </>
+  )
+}
+
 export default function SkillList() {
   const { skills, deployments, fetchSkills, fetchDeployments, checkSkillUpdates, getUpdateInfo } = useSkillStore()
   const navigate = useNavigate()
   const [tab, setTab] = useState<FilterTab>('all')
   const [searchQuery, setSearchQuery] = useState('')
+  // 全文搜索结果（skill_id → 命中），为 null 时退回按名称/描述本地过滤
+  const [searchHits, setSearchHits] = useState<Map<string, SkillSearchHitData> | null>(null)
   const [checkingUpdates, setCheckingUpdates] = useState(false)
   // Watcher 决策状态
   const [watcherActionId, setWatcherActionId] = useState<string | null>(null)
@@ -63,6 +82,27 @@ export default function SkillList() {
     handleCheckUpdates()
   }, []) // eslint-disable-line react-hooks/exhaustive-deps
 
+  useEffect(() => {
+    const q = searchQuery.trim()
+    if (!q) {
+      setSearchHits(null)
+      return
+    }
+    let cancelled = false
+    const timer = setTimeout(async () => {
+      try {
+        const hits = await skillsApi.search(q, 200)
+        if (!cancelled) setSearchHits(new Map(hits.map((h) => [h.skill_id, h])))
+      } catch {
+        if (!cancelled) setSearchHits(null)
+      }
+    }, 250)
+    return () => {
+      cancelled = true
+      clearTimeout(timer)
+    }
+  }, [searchQuery])
+
   const handleCheckUpdates = async () => {
     setCheckingUpdates(true)
     try {
@@ -106,8 +146,10 @@ export default function SkillList() {
     .filter((s) => {
       const matchSearch =
         !searchQuery ||
-        s.name.toLowerCase().includes(searchQuery.toLowerCase()) ||
-        (s.description ?? '').toLowerCase().includes(searchQuery.toLowerCase())
+        (searchHits
+          ? searchHits.has(s.id)
+          : s.name.toLowerCase().includes(searchQuery.toLowerCase()) ||
+            (s.description ?? '').toLowerCase().includes(searchQuery.toLowerCase()))
 
       if (!matchSearch) return false
 
@@ -117,6 +159,9 @@ export default function SkillList() {
       if (tab === 'deploy-issue') return hasDivergeDeploys(s.id)
       return true
     })
+    .sort((a, b) =>
+      searchHits ? (searchHits.get(b.id)?.score ?? 0) - (searchHits.get(a.id)?.score ?? 0) : 0,
+    )
 
   const hasUpdateCount = skills.filter((s) => !!getUpdateInfo(s.id)?.has_update).length
   const deployIssueCount = skills.filter((s) => hasDivergeDeploys(s.id)).length
@@ -184,26 +229,14 @@ export default function SkillList() {
   }
 
   // 应用商城更新（商城 → DB）
-  const handleApplyUpdate = async (_skillId: string, updateInfo: RemoteUpdateInfo) => {
+  const handleApplyUpdate = async (skillId: string, updateInfo: RemoteUpdateInfo) => {
     const id = toast.loading(`正在应用更新: ${updateInfo.skill_name}`)
     try {
-      const catalog = await catalogApi.fetch()
-      const catalogSkill = catalog.find(
-        s => s.name.toLowerCase() === updateInfo.skill_name.toLowerCase()
-          || s.source_repo === updateInfo.owner_repo
-      )
-      if (!catalogSkill) {
-        toast.error(`商城中找不到 ${updateInfo.skill_name} 的最新版本`, { id })
+      const result = await catalogApi.applyUpdate({ skillId, revision: updateInfo.remote_sha })
+      if (result.status === 'conflict') {
+        toast.error(`${updateInfo.skill_name} 本地修改与上游冲突，请先手动合并`, { id })
         return
       }
-      await catalogApi.install({
-        sourceRepo: catalogSkill.source_repo,
-        sourcePath: catalogSkill.source_path,
-        skillName: catalogSkill.name,
-        commitSha: catalogSkill.commit_sha,
-        deployTargets: [],
-        forceOverwrite: true,
-      })
       await fetchSkills()
       await fetchDeployments()
       await checkSkillUpdates()
@@ -376,7 +409,7 @@ export default function SkillList() {
         <div className="relative flex-1 max-w-xs">
           <Search className="absolute left-3 top-1/2 -translate-y-1/2 h-4 w-4 text-cream-400" />
           <Input
-            placeholder="搜索技能名称或描述..."
+            placeholder="搜索技能名称、描述或文件内容..."
             value={searchQuery}
             onChange={(e) => setSearchQuery(e.target.value)}
             className="pl-9 rounded-full border-cream-300 h-9"
@@ -445,6 +478,7 @@ export default function SkillList() {
                 : null
               const otherDeps = skillDeps.filter((d) => d.id !== skill.watcher_trigger_dep_id)
               const isSelectivePushOpen = selectivePushSkillId === skill.id
+              const fileMatch = searchHits?.get(skill.id)?.matches.find((m) => m.path) ?? null
 
               return (
                 <motion.div
@@ -537,6 +571,12 @@ export default function SkillList() {
                         )}
                       </div>
                       <p className="text-xs text-cream-400 truncate mt-0.5">{skill.description}</p>
+                      {fileMatch && (
+                        <p className="text-xs text-cream-500 truncate mt-0.5">
+                          <span className="font-mono text-cream-400 mr-1.5">{fileMatch.path}</span>
+                          <SearchSnippet text={fileMatch.snippet} />
+                        </p>
+                      )}
                     </div>
 
                     {/* 已部署工具小圆点 */}
@@ -298,6 +298,7 @@ export default function SkillsStore() {
         sourcePath: skill.source_path,
         skillName: skill.name,
         commitSha: skill.commit_sha,
+        registryId: skill.registry_id,
         deployTargets,
         forceOverwrite: false,
       })
@@ -322,30 +323,30 @@ export default function SkillsStore() {
   const handleApplyUpdate = async (updateInfo: RemoteUpdateInfo) => {
     const loadingId = toast.loading(`正在更新 ${updateInfo.skill_name}...`)
     try {
-      // 优先从 catalog 找最新版本
-      const catalogSkill = catalogSkills.find(
-        s => s.name.toLowerCase() === updateInfo.skill_name.toLowerCase()
-          || s.source_repo === updateInfo.owner_repo
-      )
-
-      if (!catalogSkill) {
-        toast.error(`无法在商城 catalog 中找到 ${updateInfo.skill_name}`, { id: loadingId })
+      const result = await catalogApi.applyUpdate({
+        skillId: updateInfo.skill_id,
+        revision: updateInfo.remote_sha,
+      })
+      if (result.status === 'conflict') {
+        toast.error(
+          `${updateInfo.skill_name} 本地修改与上游冲突（${result.merge?.conflict_count ?? 0} 个文件），请先手动合并`,
+          { id: loadingId },
+        )
         return
       }
 
-      await catalogApi.install({
-        sourceRepo: catalogSkill.source_repo,
-        sourcePath: catalogSkill.source_path,
-        skillName: catalogSkill.name,
-        commitSha: catalogSkill.commit_sha,
-        deployTargets: [],
-        forceOverwrite: true,
-      })
-
       await fetchSkills()
       const newUpdates = await catalogApi.checkUpdates()
       setRemoteUpdates(newUpdates)
-      toast.success(`${updateInfo.skill_name} 已更新到数据库`, { id: loadingId })
+      toast.success(
+        result.status === 'merged'
+          ? `${updateInfo.skill_name} 已与本地修改合并，并同步 ${result.deployments_synced} 个部署`
+          : `${updateInfo.skill_name} 已更新，并同步 ${result.deployments_synced} 个部署`,
+        { id: loadingId },
+      )
+      if (result.deployments_skipped.length > 0) {
+        toast.warning(`${result.deployments_skipped.length} 个部署有本地修改，未覆盖，请在详情页合并`)
+      }
     } catch (e) {
       toast.error('更新失败: ' + String(e), { id: loadingId })
     }
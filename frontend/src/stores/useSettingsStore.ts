@@ -25,6 +25,8 @@ function parseSettingsFromRows(rows: { key: string; value: string | null }[]): P
   if (result.language) mapped.language = result.language as AppSettings['language']
   if (result.notification_enabled !== undefined) mapped.notifications_enabled = result.notification_enabled as boolean
   if (result.skills_lib_path) mapped.skill_library_path = result.skills_lib_path as string
+  if (typeof result.ignore_patterns === 'string') mapped.ignore_patterns = result.ignore_patterns
+  if (typeof result.watcher_quiet_ms === 'number') mapped.watcher_quiet_ms = result.watcher_quiet_ms
   if (result.update_check_interval) mapped.update_check_frequency = result.update_check_interval as AppSettings['update_check_frequency']
   return mapped
 }
@@ -32,7 +34,7 @@ function parseSettingsFromRows(rows: { key: string; value: string | null }[]): P
 const defaultSettings: AppSettings = {
   language: 'zh-CN', theme: 'light', startup_page: 'projects', notifications_enabled: true,
   skill_library_path: '~/.skills-manager/skills/', auto_export_frequency: 'daily',
-  file_watch_enabled: true, update_check_frequency: 'daily', auto_update: false, history_retention_days: 90,
+  file_watch_enabled: true, watcher_quiet_ms: 500, ignore_patterns: '', update_check_frequency: 'daily', auto_update: false, history_retention_days: 90,
 }
 
 export const useSettingsStore = create<SettingsStore>()((set) => ({
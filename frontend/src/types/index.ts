@@ -68,8 +68,10 @@ export interface AppSettings {
   startup_page: 'last' | 'projects' | 'sync'
   notifications_enabled: boolean
   skill_library_path: string
-  auto_export_frequency: 'manual' | 'daily' | 'on-change'
+  auto_export_frequency: 'manual' | 'hourly' | 'daily' | 'on-change'
   file_watch_enabled: boolean
+  watcher_quiet_ms: number
+  ignore_patterns: string
   update_check_frequency: 'startup' | 'hourly' | 'daily' | 'manual'
   auto_update: boolean
   history_retention_days: number